@@ -0,0 +1,148 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseBuffer};
+
+use crate::signature::{register_signature, ParameterOwnership, SignatureParameter};
+use crate::utils::{is_primitive_type, snake_case, target_type_lifetimes};
+
+/// Generates the `extern "C"` functions requested by the `#[generate_accessors]` attribute : a
+/// `<type_name>_new` constructor, creating the C representation of a default target value, and a
+/// `<type_name>_get_<field>` getter per field.
+pub fn impl_accessors(
+    input: &syn::DeriveInput,
+    target_type: &syn::Path,
+) -> syn::Result<TokenStream> {
+    let attribute = match input
+        .attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("generate_accessors"))
+    {
+        Some(attribute) => attribute,
+        None => return Ok(quote!()),
+    };
+
+    if !input.generics.params.is_empty() || !target_type_lifetimes(target_type).is_empty() {
+        return Err(syn::Error::new_spanned(
+            attribute,
+            "accessors can't be generated for generic types",
+        ));
+    }
+
+    let constructor: syn::Expr = if attribute.tokens.is_empty() {
+        syn::parse_quote!(<#target_type as Default>::default)
+    } else {
+        attribute.parse_args::<AccessorsArgs>()?.new
+    };
+
+    let struct_name = &input.ident;
+    let prefix = snake_case(&struct_name.to_string());
+
+    let constructor_name = syn::Ident::new(&format!("{}_new", prefix), struct_name.span());
+    let constructor_doc = format!(
+        "Creates a new `{}`, to be freed by its destructor. Returns `null` if it can't be created.",
+        struct_name
+    );
+    let constructor_signature = register_signature(
+        &constructor_name,
+        &constructor_doc,
+        &[],
+        Some((quote!(*mut #struct_name), ParameterOwnership::OwnedByCaller)),
+        &[],
+    );
+    let constructor = quote!(
+        #[doc = #constructor_doc]
+        #[no_mangle]
+        pub extern "C" fn #constructor_name() -> *mut #struct_name {
+            std::panic::catch_unwind(|| {
+                <#struct_name as ffi_convert::prelude::CReprOf<#target_type>>::c_repr_of(#constructor())
+            })
+            .ok()
+            .and_then(Result::ok)
+            .map_or(std::ptr::null_mut(), ffi_convert::convert_into_raw_pointer_mut)
+        }
+
+        #constructor_signature
+    );
+
+    let fields = match &input.data {
+        syn::Data::Struct(data_struct) => &data_struct.fields,
+        _ => return Ok(constructor),
+    };
+
+    let getters = fields.iter().filter_map(|field| {
+        let field_name = field.ident.as_ref()?;
+        let field_type = &field.ty;
+        let getter_name = syn::Ident::new(&format!("{}_get_{}", prefix, field_name), field_name.span());
+
+        // the values that are not pointers or primitive types are not copied, they are borrowed
+        let (value_type, value, value_ownership) = match field_type {
+            syn::Type::Ptr(_) => (quote!(#field_type), quote!((*ptr).#field_name), ParameterOwnership::Borrowed),
+            _ if is_primitive_type(field_type) => {
+                (quote!(#field_type), quote!((*ptr).#field_name), ParameterOwnership::Value)
+            }
+            _ => (quote!(*const #field_type), quote!(&(*ptr).#field_name), ParameterOwnership::Borrowed),
+        };
+        let doc = format!(
+            "Writes the `{}` field of a `{}` to `value` (the struct keeps the ownership of its \
+            content). Returns `0` on success, and `-1` if a pointer is `null`.",
+            field_name, struct_name
+        );
+        let signature = register_signature(
+            &getter_name,
+            &doc,
+            &[
+                SignatureParameter::new("ptr", quote!(*const #struct_name), ParameterOwnership::Borrowed),
+                SignatureParameter::output("value", quote!(*mut #value_type), value_ownership),
+            ],
+            Some((quote!(i32), ParameterOwnership::Value)),
+            &[(0, "success"), (-1, "null pointer")],
+        );
+
+        Some(quote!(
+            #[doc = #doc]
+            ///
+            /// # Safety
+            ///
+            /// The pointers must be `null` or valid.
+            #[no_mangle]
+            pub unsafe extern "C" fn #getter_name(ptr: *const #struct_name, value: *mut #value_type) -> i32 {
+                if ptr.is_null() || value.is_null() {
+                    return -1;
+                }
+                *value = #value;
+                0
+            }
+
+            #signature
+        ))
+    });
+
+    Ok(quote!(
+        #constructor
+        #(#getters)*
+    ))
+}
+
+/// Arguments of the `generate_accessors` attribute : `#[generate_accessors(new = path)]`, giving
+/// the function creating the target value used by the constructor.
+struct AccessorsArgs {
+    new: syn::Expr,
+}
+
+impl Parse for AccessorsArgs {
+    fn parse(input: &ParseBuffer) -> Result<Self, syn::parse::Error> {
+        let key: syn::Ident = input.parse()?;
+        if key != "new" {
+            return Err(syn::parse::Error::new(
+                key.span(),
+                "expected `new = <function>` in generate_accessors attribute",
+            ));
+        }
+
+        input.parse::<syn::Token![=]>()?;
+
+        Ok(AccessorsArgs {
+            new: input.parse()?,
+        })
+    }
+}
@@ -1,108 +1,367 @@
-use proc_macro::TokenStream;
-
+use proc_macro2::TokenStream;
 use quote::quote;
 use syn::parse::{Parse, ParseBuffer};
 
-use crate::utils::{parse_struct_fields, parse_target_type, Field, TypeArrayOrTypePath};
+use crate::utils::{
+    check_repr_c, collect_results, parse_abi_version, parse_as_rust_accessors_flag,
+    parse_struct_fields, parse_target_types, parse_validate_fns, strip_generic_arguments,
+    use_prelude_traits, Field, TypeArrayOrTypePath,
+};
 
-pub fn impl_asrust_macro(input: &syn::DeriveInput) -> TokenStream {
+pub fn impl_asrust_macro(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    check_repr_c(input, "AsRust")?;
     let struct_name = &input.ident;
-    let target_type = parse_target_type(&input.attrs);
+    let target_types = parse_target_types(&input.attrs)?;
+    let generate_accessors = parse_as_rust_accessors_flag(&input.attrs);
+    let validate_fns = parse_validate_fns(&input.attrs, "as_rust_validate")?;
+    let zeroed = parse_zeroed_attribute(&input.attrs)?;
 
-    let fields = parse_struct_fields(&input.data)
-        .iter()
-        .filter_map(|field| {
-            let Field {
-                name: field_name,
-                target_name: target_field_name,
-                ref field_type,
-                ..
-            } = field;
-
-            if field.levels_of_indirection > 1 && !field.is_nullable {
-                panic!(
-                    "The CReprOf, AsRust, and CDrop traits cannot be derived automatically: \
-                    The field {} is a pointer field has too many levels of indirection \
-                    ({} in this case). Please implements those traits manually.",
-                    field_name, field.levels_of_indirection
-                )
+    let parsed_fields = parse_struct_fields(input)?;
+
+    let target_fields = |target_type: &syn::Path| {
+        collect_results(parsed_fields.iter().filter_map(|field| {
+            let field_name = field.name;
+            let target_field_name = field.target_name_for(target_type);
+
+            if field.skip.is_some() {
+                // skipped fields only exist on the C side
+                return None;
             }
 
-            let mut conversion = if field.is_string {
-                quote!( {
-                    use ffi_convert::RawBorrow;
-                    unsafe { std::ffi::CStr::raw_borrow(self.#field_name) }?.as_rust()?
-                })
-            } else if field.is_pointer {
-                match field_type {
-                    TypeArrayOrTypePath::TypeArray(type_array) => {
-                        quote!( {
-                        let ref_to_array = unsafe { <#type_array>::raw_borrow(self.#field_name)? };
-                        let converted_array = ref_to_struct.as_rust()?;
-                        converted_array
-                    })
-                    }
-                    TypeArrayOrTypePath::TypePath(type_path) => {
-                        quote!( {
-                        let ref_to_struct = unsafe { #type_path::raw_borrow(self.#field_name)? };
-                        let converted_struct = ref_to_struct.as_rust()?;
-                        converted_struct
-                    })
-                    }
+            let conversion = if let Some(convert) = &field.as_rust_convert {
+                quote!(#convert)
+            } else if field.c_repr_of_convert.is_some() {
+                // ignore field for as_rust if it has a special c_repr_of handling
+                return None;
+            } else {
+                match field_conversion(field) {
+                    Ok(conversion) => conversion,
+                    Err(error) => return Some(Err(error)),
                 }
+            };
 
+            let pointer = if field.is_pointer {
+                quote!(Some(self.#field_name as *const ()))
             } else {
-                quote!(self.#field_name.as_rust()?)
+                quote!(None)
             };
 
-            conversion = if field.is_nullable {
-                quote!(
-                    #target_field_name: if !self.#field_name.is_null() {
-                        Some(#conversion)
-                    } else {
-                        None
-                    }
-                )
+            // the conversion is recorded when called from ffi_convert::explain_as_rust
+            Some(Ok(quote!(
+                #target_field_name: ffi_convert::explain_field(
+                    stringify!(#field_name),
+                    &self.#field_name,
+                    #pointer,
+                    || Ok(#conversion),
+                )?
+            )))
+        }))
+    };
+
+    // the fields of the target type that the C struct doesn't carry
+    let extra_fields = collect_results(input.attrs.iter().filter_map(|attribute| {
+        if attribute.path.is_ident("as_rust_extra_field") {
+            Some(
+                attribute
+                    .parse_args()
+                    .map(|ExtraFieldsArgs { field_name, init }| {
+                        quote! {#field_name: #init}
+                    }),
+            )
+        } else if attribute.path.is_ident("as_rust_default_field") {
+            Some(
+                attribute
+                    .parse_args::<syn::Ident>()
+                    .map(|field_name| quote! {#field_name: Default::default()}),
+            )
+        } else {
+            None
+        }
+    }))?;
+    let fill_defaults = if input
+        .attrs
+        .iter()
+        .any(|attribute| attribute.path.is_ident("as_rust_fill_defaults"))
+    {
+        quote!(..Default::default())
+    } else {
+        quote!()
+    };
+
+    let accessors = if generate_accessors {
+        let accessors = collect_results(
+            parsed_fields
+                .iter()
+                .filter(|field| {
+                    field.skip.is_none()
+                        && field.as_rust_convert.is_none()
+                        && field.c_repr_of_convert.is_none()
+                })
+                .map(field_accessor),
+        )?;
+
+        quote!(
+            #[allow(clippy::needless_question_mark)]
+            impl #struct_name {
+                #(#accessors)*
+            }
+        )
+    } else {
+        quote!()
+    };
+
+    let zeroed_impls = match zeroed {
+        Some(zeroed) => {
+            let checked_fields = parsed_fields.iter().map(|field| &field.name);
+            let option_impls = if zeroed == Zeroed::None {
+                target_types
+                    .iter()
+                    .map(|target_type| {
+                        quote!(
+                            impl ffi_convert::prelude::AsRust<Option<#target_type>> for #struct_name {
+                                fn as_rust(&self) -> Result<Option<#target_type>, ffi_convert::AsRustError> {
+                                    if ffi_convert::ZeroCheck::is_zeroed(self) {
+                                        Ok(None)
+                                    } else {
+                                        ffi_convert::prelude::AsRust::<#target_type>::as_rust(self).map(Some)
+                                    }
+                                }
+                            }
+                        )
+                    })
+                    .collect()
             } else {
-                quote!(
-                    #target_field_name: #conversion
-                )
+                vec![]
             };
-            if field.c_repr_of_convert.is_some() {
-                // ignore field for as_rust if it has a special c_repr_of handling
-                None
-            } else {
-                Some(conversion)
-            }
+            quote!(
+                impl ffi_convert::ZeroCheck for #struct_name {
+                    fn is_zeroed(&self) -> bool {
+                        true #(&& ffi_convert::ZeroCheck::is_zeroed(&self.#checked_fields))*
+                    }
+                }
+
+                #(#option_impls)*
+            )
+        }
+        None => quote!(),
+    };
+
+    let zeroed_default = if zeroed == Some(Zeroed::Default) {
+        quote!(if ffi_convert::ZeroCheck::is_zeroed(self) {
+            return Ok(Default::default());
         })
-        .collect::<Vec<_>>();
+    } else {
+        quote!()
+    };
 
-    let extra_fields = &input
-        .attrs
+    // the other fields may not exist in a struct of another version
+    let version_check = if parse_abi_version(&input.attrs)?.is_some() {
+        quote!(ffi_convert::check_version(self)?;)
+    } else {
+        quote!()
+    };
+
+    let use_traits = use_prelude_traits();
+
+    // the struct is converted to each of its target types, in the same way
+    let as_rust_impls = collect_results(target_types.iter().map(|target_type| {
+        let target_constructor = strip_generic_arguments(target_type);
+        let fields = target_fields(target_type)?;
+        Ok(quote!(
+            #[allow(clippy::needless_question_mark, clippy::needless_update)]
+            impl ffi_convert::prelude::AsRust<#target_type> for #struct_name {
+                fn as_rust(&self) -> Result<#target_type, ffi_convert::AsRustError> {
+                    #use_traits
+                    #zeroed_default
+                    #version_check
+                    let value = #target_constructor {
+                        #(#fields, )*
+                        #(#extra_fields, )*
+                        #fill_defaults
+                    };
+                    #(
+                        #validate_fns(&value)
+                            .map_err(|error| ffi_convert::AsRustError::Other(error.into()))?;
+                    )*
+                    Ok(value)
+                }
+            }
+        ))
+    }))?;
+
+    Ok(quote!(
+        #(#as_rust_impls)*
+
+        #accessors
+
+        #zeroed_impls
+    ))
+}
+
+/// How the structs whose fields are all zero are converted, given with the `as_rust_zeroed` struct
+/// attribute.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Zeroed {
+    /// `#[as_rust_zeroed]` : only `ZeroCheck` is implemented, such structs are converted
+    /// as any other.
+    Check,
+    /// `#[as_rust_zeroed(default)]` : converted to the default value of the target type.
+    Default,
+    /// `#[as_rust_zeroed(none)]` : converted to `None` by an additional `AsRust<Option<_>>` impl.
+    None,
+}
+
+fn parse_zeroed_attribute(attrs: &[syn::Attribute]) -> syn::Result<Option<Zeroed>> {
+    let attribute = match attrs
         .iter()
-        .filter(|attribute| {
-            attribute.path.get_ident().map(|it| it.to_string())
-                == Some("as_rust_extra_field".into())
-        })
-        .map(|it| {
-            let ExtraFieldsArgs { field_name, init } = it
-                .parse_args()
-                .expect("Could not parse args for as_rust_extra_field");
-            quote! {#field_name: #init}
-        })
-        .collect::<Vec<_>>();
-
-    quote!(
-        impl AsRust<#target_type> for #struct_name {
-            fn as_rust(&self) -> Result<#target_type, ffi_convert::AsRustError> {
-                Ok(#target_type {
-                    #(#fields, )*
-                    #(#extra_fields, )*
+        .find(|attribute| attribute.path.is_ident("as_rust_zeroed"))
+    {
+        Some(attribute) => attribute,
+        None => return Ok(None),
+    };
+    if attribute.tokens.is_empty() {
+        return Ok(Some(Zeroed::Check));
+    }
+    let mode: syn::Ident = attribute.parse_args()?;
+    if mode == "default" {
+        Ok(Some(Zeroed::Default))
+    } else if mode == "none" {
+        Ok(Some(Zeroed::None))
+    } else {
+        Err(syn::Error::new_spanned(
+            mode,
+            "expected `#[as_rust_zeroed]`, `#[as_rust_zeroed(default)]` or `#[as_rust_zeroed(none)]`",
+        ))
+    }
+}
+
+/// Generates the expression converting the given field of `self` to its Rust counterpart.
+fn field_conversion(field: &Field) -> syn::Result<TokenStream> {
+    let Field {
+        name: field_name,
+        ref field_type,
+        ..
+    } = field;
+
+    field.check_levels_of_indirection()?;
+
+    if field.is_borrowed_string {
+        return Err(syn::Error::new_spanned(
+            field.name,
+            "borrowed strings can only be converted to Rust by the `AsRustView` derive",
+        ));
+    }
+
+    let inner_type = &field.inner_type;
+    let conversion = if field.is_opaque {
+        field.opaque_as_rust_call()
+    } else if field.is_stringified {
+        field.stringified_as_rust_call()
+    } else if field.is_string {
+        let (_, borrowed_string_type) = field.string_types();
+        field.as_rust_call(
+            quote!(#borrowed_string_type),
+            field.borrow_string_call(),
+            None,
+        )
+    } else if field.is_pointer {
+        match field_type {
+            TypeArrayOrTypePath::TypeArray(type_array) => {
+                let conversion =
+                    field.as_rust_call(quote!(#type_array), quote!(ref_to_array), None);
+                quote!( {
+                    let ref_to_array = unsafe { <#type_array>::raw_borrow(self.#field_name)? };
+                    let converted_array = #conversion;
+                    converted_array
+                })
+            }
+            TypeArrayOrTypePath::TypePath(type_path) => {
+                let conversion =
+                    field.as_rust_call(quote!(#inner_type), quote!(ref_to_struct), None);
+                quote!( {
+                    let ref_to_struct = unsafe { #type_path::raw_borrow(self.#field_name)? };
+                    let converted_struct = #conversion;
+                    converted_struct
                 })
             }
         }
-    )
-    .into()
+    } else {
+        field.as_rust_call(quote!(#inner_type), quote!(self.#field_name), None)
+    };
+
+    let conversion = if field.is_boxed {
+        quote!(Box::new(#conversion))
+    } else {
+        conversion
+    };
+
+    if field.is_nullable {
+        Ok(quote!(
+            if !self.#field_name.is_null() {
+                Some(#conversion)
+            } else {
+                None
+            }
+        ))
+    } else {
+        Ok(conversion)
+    }
+}
+
+/// Generates an `as_rust_<field>` method converting only the given field. As the type of the
+/// field in the target struct is unknown here, the method is generic over it (except for strings).
+fn field_accessor(field: &Field) -> syn::Result<TokenStream> {
+    let method_name = syn::Ident::new(
+        &format!("as_rust_{}", field.target_name),
+        field.target_name.span(),
+    );
+    let doc = format!(
+        "Converts only the `{}` field to its Rust representation.",
+        field.name
+    );
+    let conversion = field_conversion(field)?;
+    let use_traits = use_prelude_traits();
+    let inner_type = &field.inner_type;
+
+    let (generics, output, bounds) = if let Some(target_field_type) = &field.target_field_type {
+        (quote!(), quote!(#target_field_type), quote!())
+    } else if field.is_opaque {
+        (quote!(<T>), quote!(T), quote!(where T: Clone + 'static))
+    } else if field.is_stringified {
+        (
+            quote!(<T>),
+            quote!(T),
+            quote!(where T: ffi_convert::CStringifiable),
+        )
+    } else if field.is_string {
+        (quote!(), quote!(String), quote!())
+    } else {
+        (
+            quote!(<T>),
+            quote!(T),
+            quote!(where #inner_type: ffi_convert::prelude::AsRust<T>),
+        )
+    };
+
+    let output = if field.is_boxed {
+        quote!(Box<#output>)
+    } else {
+        output
+    };
+
+    let output = if field.is_nullable {
+        quote!(Option<#output>)
+    } else {
+        output
+    };
+
+    Ok(quote!(
+        #[doc = #doc]
+        pub fn #method_name #generics(&self) -> Result<#output, ffi_convert::AsRustError> #bounds {
+            #use_traits
+            Ok(#conversion)
+        }
+    ))
 }
 
 struct ExtraFieldsArgs {
@@ -0,0 +1,112 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::utils::{
+    collect_results, parse_abi_version, parse_struct_fields, parse_target_type,
+    strip_generic_arguments, target_type_lifetimes, use_prelude_traits, Field, TypeArrayOrTypePath,
+};
+
+pub fn impl_asrustview_macro(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let struct_name = &input.ident;
+    let target_type = parse_target_type(&input.attrs)?;
+    let target_constructor = strip_generic_arguments(&target_type);
+
+    // the view borrows from the C struct for the first lifetime of the target type
+    let lifetime = target_type_lifetimes(&target_type)
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| syn::parse_quote!('ffi_convert_view));
+
+    let fields = collect_results(parse_struct_fields(input)?.iter().filter_map(|field| {
+        let Field {
+            name: field_name,
+            ref field_type,
+            ..
+        } = field;
+        let target_field_name = field.target_name_for(&target_type);
+
+        if field.skip.is_some() {
+            // skipped fields only exist on the C side
+            return None;
+        }
+
+        if let Err(error) = field.check_levels_of_indirection() {
+            return Some(Err(error));
+        }
+
+        let inner_type = &field.inner_type;
+        let conversion = if field.is_opaque {
+            field.opaque_as_rust_call()
+        } else if field.is_borrowed_string {
+            field.borrow_string_call()
+        } else if field.is_stringified {
+            field.stringified_as_rust_call()
+        } else if field.is_string {
+            let (_, borrowed_string_type) = field.string_types();
+            field.as_rust_call(
+                quote!(#borrowed_string_type),
+                field.borrow_string_call(),
+                Some(&lifetime),
+            )
+        } else if field.is_pointer {
+            match field_type {
+                TypeArrayOrTypePath::TypeArray(type_array) => field.as_rust_call(
+                    quote!(#type_array),
+                    quote!(unsafe { <#type_array>::raw_borrow(self.#field_name) }?),
+                    Some(&lifetime),
+                ),
+                TypeArrayOrTypePath::TypePath(type_path) => field.as_rust_call(
+                    quote!(#inner_type),
+                    quote!(unsafe { #type_path::raw_borrow(self.#field_name) }?),
+                    Some(&lifetime),
+                ),
+            }
+        } else {
+            field.as_rust_call(
+                quote!(#inner_type),
+                quote!(self.#field_name),
+                Some(&lifetime),
+            )
+        };
+
+        let conversion = if field.is_boxed {
+            quote!(Box::new(#conversion))
+        } else {
+            conversion
+        };
+
+        let conversion = if field.is_nullable {
+            quote!(
+                if !self.#field_name.is_null() {
+                    Some(#conversion)
+                } else {
+                    None
+                }
+            )
+        } else {
+            conversion
+        };
+
+        Some(Ok(quote!(#target_field_name: #conversion)))
+    }))?;
+
+    let version_check = if parse_abi_version(&input.attrs)?.is_some() {
+        quote!(ffi_convert::check_version(self)?;)
+    } else {
+        quote!()
+    };
+
+    let use_traits = use_prelude_traits();
+
+    Ok(quote!(
+        impl<#lifetime> ffi_convert::prelude::AsRustView<#lifetime, #target_type> for #struct_name {
+            fn as_rust_view(&#lifetime self) -> Result<#target_type, ffi_convert::AsRustError> {
+                #use_traits
+                #version_check
+                Ok(#target_constructor {
+                    #(#fields, )*
+                })
+            }
+        }
+    ))
+}
@@ -0,0 +1,39 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::utils::{as_rust_fn, parse_struct_fields, parse_target_type};
+
+pub fn impl_cdebug_macro(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let struct_name = &input.ident;
+    let target_type = parse_target_type(&input.attrs)?;
+    let as_rust_fn = as_rust_fn(struct_name, &target_type);
+
+    // when the struct can't be converted, only the addresses of its pointers are shown
+    let pointer_fields = parse_struct_fields(input)?
+        .iter()
+        .filter(|field| field.is_pointer)
+        .map(|field| {
+            let field_name = field.name;
+            let name = field_name.to_string();
+            quote!(.field(#name, &self.#field_name))
+        })
+        .collect::<Vec<_>>();
+    let name = struct_name.to_string();
+
+    Ok(quote!(
+        impl std::fmt::Debug for #struct_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                #as_rust_fn
+
+                match as_rust(self) {
+                    Ok(value) => std::fmt::Debug::fmt(&value, f),
+                    Err(error) => f
+                        .debug_struct(#name)
+                        .field("as_rust_error", &error.to_string())
+                        #(#pointer_fields)*
+                        .finish_non_exhaustive(),
+                }
+            }
+        }
+    ))
+}
@@ -1,82 +1,227 @@
-use crate::utils::{parse_no_drop_impl_flag, parse_struct_fields, Field, TypeArrayOrTypePath};
-use proc_macro::TokenStream;
-use quote::quote;
+use crate::utils::{
+    collect_results, is_primitive_type, parse_abi_version, parse_defensive_drop_flag,
+    parse_no_drop_impl_flag, parse_struct_fields, parse_thread_safe_flag, use_prelude_traits,
+    Field, TypeArrayOrTypePath,
+};
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
 
-pub fn impl_cdrop_macro(input: &syn::DeriveInput) -> TokenStream {
+pub fn impl_cdrop_macro(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
     let struct_name = &input.ident;
     let disable_drop_impl = parse_no_drop_impl_flag(&input.attrs);
+    let defensive_drop = parse_defensive_drop_flag(&input.attrs);
+    let thread_safe = parse_thread_safe_flag(&input.attrs);
 
-    let fields = parse_struct_fields(&input.data);
+    let fields = parse_struct_fields(input)?;
 
-    let do_drop_fields = fields
+    // the pointers are always reset once freed, so that a struct whose `do_drop` was called (e.g.
+    // as a value field of another struct) can still be dropped by rust without a double free
+    let (drop_pointer_fields, release_pointer_fields): (Vec<_>, Vec<_>) = fields
         .iter()
+        // skipped fields and borrowed strings are not owned by the struct
+        .filter(|field| field.is_pointer && field.skip.is_none() && !field.is_borrowed_string)
         .map(|field| {
-            let Field {
-                name: field_name,
-                ref field_type,
-                ..
-            } = field;
+            let field_name = field.name;
+            let drop_field = drop_pointer_field(field, quote!(self.#field_name));
+            let null = if field.is_mut_pointer {
+                quote!(std::ptr::null_mut())
+            } else {
+                quote!(std::ptr::null())
+            };
+            let release_field = quote!(
+                if !self.#field_name.is_null() {
+                    # drop_field;
+                    self.#field_name = #null;
+                }
+            );
 
-            let drop_field = if field.is_string {
+            // with `defensive_drop`, dropping the struct twice is a no-op
+            let drop_field = if defensive_drop || field.is_nullable {
+                release_field.clone()
+            } else {
                 quote!({
-                    use ffi_convert::RawPointerConverter;
-                    unsafe { std::ffi::CString::drop_raw_pointer(self.#field_name) }?
+                    # drop_field;
+                    self.#field_name = #null;
                 })
-            } else if field.is_pointer {
-                match field_type {
-                    TypeArrayOrTypePath::TypeArray(type_array) => {
-                        quote!( unsafe { <#type_array>::drop_raw_pointer(self.#field_name) }? )
-                    }
-                    TypeArrayOrTypePath::TypePath(type_path) => {
-                        quote!( unsafe { #type_path::drop_raw_pointer(self.#field_name) }? )
-                    }
-                }
-            } else {
-                // the other cases will be handled automatically by rust
-                quote!()
             };
+            (drop_field, release_field)
+        })
+        .unzip();
 
-            if field.is_nullable {
-                quote!(
-                    if !self.#field_name.is_null() {
-                       # drop_field
-                    }
-                )
-            } else {
-                drop_field
-            }
+    // the value fields owning resources (containers, nested structs...) are dropped too, for the
+    // structs that are not dropped by rust (e.g. allocated by C code)
+    let drop_value_fields = fields
+        .iter()
+        .filter(|field| {
+            !field.is_pointer
+                && field.skip.is_none()
+                && !field.is_shallow
+                && owns_resources(&field.inner_type)
+        })
+        .map(|field| {
+            let field_name = field.name;
+            quote!(self.#field_name.do_drop()?)
         })
         .collect::<Vec<_>>();
 
+    let use_traits = use_prelude_traits();
+
     let c_drop_impl = quote!(
-        impl CDrop for # struct_name {
+        impl ffi_convert::prelude::CDrop for # struct_name {
             fn do_drop(&mut self) -> Result<(), ffi_convert::CDropError> {
-                use ffi_convert::RawPointerConverter;
-                # ( #do_drop_fields; )*
+                #use_traits
+                # ( #drop_pointer_fields; )*
+                # ( #drop_value_fields; )*
                 Ok(())
             }
         }
     );
 
+    // rust drops the value fields itself once the struct is dropped, only the pointer fields that
+    // were not freed yet by `do_drop` are freed here so that they are not dropped twice
     let drop_impl = quote!(
         impl Drop for # struct_name {
             fn drop(&mut self) {
-                let _ = self.do_drop();
+                let _ = (|| -> Result<(), ffi_convert::CDropError> {
+                    #use_traits
+                    # ( #release_pointer_fields; )*
+                    Ok(())
+                })();
             }
         }
     );
 
-    {
-        if disable_drop_impl {
-            quote! {
-                # c_drop_impl
+    let thread_safe_impls = if thread_safe {
+        impl_thread_safe(struct_name, &fields)?
+    } else {
+        quote!()
+    };
+
+    // implemented here as all the C structs derive CDrop, whichever conversions they derive
+    let versioned_impl = match parse_abi_version(&input.attrs)? {
+        Some(abi_version) => quote!(
+            impl ffi_convert::CVersioned for # struct_name {
+                const ABI_VERSION: u32 = #abi_version;
+
+                fn version(&self) -> u32 {
+                    self.version
+                }
             }
-        } else {
-            quote! {
-                # c_drop_impl
-                # drop_impl
+        ),
+        None => quote!(),
+    };
+
+    let layout_assertions = impl_layout_assertions(input)?;
+
+    if disable_drop_impl {
+        Ok(quote! {
+            # c_drop_impl
+            # thread_safe_impls
+            # versioned_impl
+            # layout_assertions
+        })
+    } else {
+        Ok(quote! {
+            # c_drop_impl
+            # drop_impl
+            # thread_safe_impls
+            # versioned_impl
+            # layout_assertions
+        })
+    }
+}
+
+/// Generates the compile time assertions checking the size and alignment of the struct against
+/// the ones given by the `#[expected_size(N)]` and `#[expected_align(N)]` attributes, so that a
+/// change of the C layout (a reordered field, a type of another width...) doesn't go unnoticed.
+fn impl_layout_assertions(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let struct_name = &input.ident;
+    let checks = [
+        ("expected_size", "size", quote!(std::mem::size_of)),
+        ("expected_align", "alignment", quote!(std::mem::align_of)),
+    ];
+
+    let assertions = collect_results(checks.iter().filter_map(
+        |(attribute_name, what, function)| {
+            let attribute = input
+                .attrs
+                .iter()
+                .find(|attribute| attribute.path.is_ident(attribute_name))?;
+            Some(attribute.parse_args::<syn::LitInt>().and_then(|expected| {
+                let value = expected.base10_parse::<usize>()?;
+                let message = format!(
+                    "the {} of `{}` is not the expected {} bytes, its C layout changed",
+                    what, struct_name, value
+                );
+                Ok(quote_spanned!(expected.span()=>
+                    const _: () = assert!(#function::<#struct_name>() == #value, #message);
+                ))
+            }))
+        },
+    ))?;
+
+    Ok(quote!(#(#assertions)*))
+}
+
+/// Generates the statement freeing the value behind `pointer`, the pointer stored in the given
+/// field, in a function returning a `Result<_, CDropError>`.
+pub fn drop_pointer_field(field: &Field, pointer: TokenStream) -> TokenStream {
+    if field.is_opaque {
+        quote!(unsafe { ffi_convert::opaque_drop(#pointer as *mut _) })
+    } else if field.is_string {
+        let (owned_string_type, _) = field.string_types();
+        quote!(unsafe { #owned_string_type::drop_raw_pointer(#pointer) }?)
+    } else {
+        match &field.field_type {
+            TypeArrayOrTypePath::TypeArray(type_array) => {
+                quote!( unsafe { <#type_array>::drop_raw_pointer(#pointer) }? )
             }
+            TypeArrayOrTypePath::TypePath(type_path) => {
+                quote!( unsafe { #type_path::drop_raw_pointer(#pointer) }? )
+            }
+        }
+    }
+}
+
+/// Implements `Send` and `Sync` for a struct owning the memory its pointer fields point to, when
+/// the types of its values (its value fields, and the values its pointer fields point to) are
+/// `Send` and `Sync`. The strings it owns are thread safe.
+fn impl_thread_safe(struct_name: &syn::Ident, fields: &[Field]) -> syn::Result<TokenStream> {
+    let owned_types = collect_results(fields.iter().filter_map(|field| {
+        if field.skip.is_some() || field.is_opaque || field.is_borrowed_string {
+            Some(Err(syn::Error::new_spanned(
+                field.name,
+                "this field is not owned by the struct, which can't be asserted thread safe",
+            )))
+        } else if field.is_string {
+            // the owned strings are thread safe
+            None
+        } else {
+            Some(Ok(&field.inner_type))
         }
+    }))?;
+
+    // the bounds point to the fields whose types are not thread safe
+    let send_bounds = owned_types
+        .iter()
+        .map(|ty| quote_spanned!(ty.span()=> #ty: Send));
+    let sync_bounds = owned_types
+        .iter()
+        .map(|ty| quote_spanned!(ty.span()=> #ty: Sync));
+
+    Ok(quote!(
+        unsafe impl Send for #struct_name where #(#send_bounds,)* {}
+        unsafe impl Sync for #struct_name where #(#sync_bounds,)* {}
+    ))
+}
+
+/// Whether a value field may own resources that must be released by `do_drop`, which is not the
+/// case of the primitive types (and of the arrays of primitive types).
+fn owns_resources(field_type: &syn::Type) -> bool {
+    match field_type {
+        syn::Type::Array(type_array) => owns_resources(&type_array.elem),
+        syn::Type::Path(_) => !is_primitive_type(field_type),
+        _ => false,
     }
-    .into()
 }
@@ -0,0 +1,24 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::utils::{as_rust_fn, parse_target_type};
+
+pub fn impl_cpartialeq_macro(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let struct_name = &input.ident;
+    let target_type = parse_target_type(&input.attrs)?;
+    let as_rust_fn = as_rust_fn(struct_name, &target_type);
+
+    // a struct that can't be converted is not equal to any other, not even to itself
+    Ok(quote!(
+        impl PartialEq for #struct_name {
+            fn eq(&self, other: &Self) -> bool {
+                #as_rust_fn
+
+                match (as_rust(self), as_rust(other)) {
+                    (Ok(value), Ok(other_value)) => value == other_value,
+                    _ => false,
+                }
+            }
+        }
+    ))
+}
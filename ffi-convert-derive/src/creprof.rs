@@ -1,26 +1,131 @@
-use proc_macro::TokenStream;
-
+use proc_macro2::TokenStream;
 use quote::quote;
 
-use crate::utils::{parse_struct_fields, parse_target_type, Field, TypeArrayOrTypePath};
+use crate::accessors::impl_accessors;
+use crate::cdrop::drop_pointer_field;
+use crate::utils::{
+    check_repr_c, parse_struct_fields, parse_target_try_getter_flag, parse_target_types,
+    parse_validate_fns, target_type_lifetimes, use_prelude_traits, Field, TypeArrayOrTypePath,
+};
 
-pub fn impl_creprof_macro(input: &syn::DeriveInput) -> TokenStream {
+pub fn impl_creprof_macro(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    check_repr_c(input, "CReprOf")?;
     let struct_name = &input.ident;
-    let target_type = parse_target_type(&input.attrs);
+    let target_types = parse_target_types(&input.attrs)?;
+
+    let all_try_getters = parse_target_try_getter_flag(&input.attrs);
+    let validate_fns = parse_validate_fns(&input.attrs, "c_repr_of_validate")?;
+
+    let fields = parse_struct_fields(input)?;
+
+    // the struct is converted from each of its target types, in the same way
+    let c_repr_of_impls = target_types.iter().map(|target_type| {
+        impl_c_repr_of(
+            struct_name,
+            target_type,
+            &fields,
+            all_try_getters,
+            &validate_fns,
+        )
+    });
 
-    let fields = parse_struct_fields(&input.data);
+    let c_repr_of_at_impl = if let [target_type] = &target_types[..] {
+        let lifetimes = target_type_lifetimes(target_type);
+        quote!(
+            impl # struct_name {
+                /// Writes the C representation of `input` in the struct pointed by `dst`, allocated
+                /// by the caller, see `ffi_convert::c_repr_of_at`.
+                ///
+                /// # Safety
+                ///
+                /// `dst` must be null or valid for writes and aligned, the struct it points to is
+                /// overwritten without being dropped.
+                pub unsafe fn c_repr_of_at<#(#lifetimes,)* R: ffi_convert::CResultCode>(
+                    dst: *mut Self,
+                    input: # target_type,
+                ) -> R {
+                    ffi_convert::c_repr_of_at(dst, input)
+                }
+            }
+        )
+    } else {
+        // the method is generic over the target types
+        quote!(
+            impl # struct_name {
+                /// Writes the C representation of `input`, one of the target types, in the struct
+                /// pointed by `dst`, allocated by the caller, see `ffi_convert::c_repr_of_at`.
+                ///
+                /// # Safety
+                ///
+                /// `dst` must be null or valid for writes and aligned, the struct it points to is
+                /// overwritten without being dropped.
+                pub unsafe fn c_repr_of_at<T, R: ffi_convert::CResultCode>(
+                    dst: *mut Self,
+                    input: T,
+                ) -> R
+                where
+                    Self: ffi_convert::prelude::CReprOf<T>,
+                {
+                    ffi_convert::c_repr_of_at(dst, input)
+                }
+            }
+        )
+    };
+    // the accessors are generated for the first target type
+    let accessors = impl_accessors(input, &target_types[0])?;
+
+    Ok(quote!(
+        #(#c_repr_of_impls)*
+        #c_repr_of_at_impl
+        #accessors
+    ))
+}
+
+/// Generates the `CReprOf` impl converting the given target type.
+fn impl_c_repr_of(
+    struct_name: &syn::Ident,
+    target_type: &syn::Path,
+    fields: &[Field],
+    all_try_getters: bool,
+    validate_fns: &[syn::Path],
+) -> TokenStream {
+    let lifetimes = target_type_lifetimes(target_type);
+    let mut getter_fields = vec![];
+    let mut field_statements = vec![];
     let c_repr_of_fields = fields
         .iter()
         .map(|field| {
             let Field {
                 name: field_name,
-                target_name: target_field_name,
                 ref field_type,
                 ..
             } = field;
+            let target_field_name = field.target_name_for(target_type);
 
-            let mut conversion = if field.is_string {
-                quote!(std::ffi::CString::c_repr_of(field)?)
+            let inner_type = &field.inner_type;
+            let mut conversion = if field.is_opaque {
+                // the value is stored as is, behind a type erased pointer
+                quote!(ffi_convert::opaque_into_raw(field) as _)
+            } else if field.is_borrowed_string {
+                // the C string is borrowed from the input, which must outlive the struct
+                quote!(ffi_convert::borrowed_c_str_ptr(field))
+            } else if field.is_stringified {
+                match &field.target_field_type {
+                    Some(target_field_type) => quote!(
+                        <#target_field_type as ffi_convert::CStringifiable>::to_c_string(&field)?
+                    ),
+                    None => quote!(ffi_convert::CStringifiable::to_c_string(&field)?),
+                }
+            } else if let Some(target_field_type) = &field.target_field_type {
+                let c_type = if field.is_string {
+                    field.string_types().0
+                } else {
+                    quote!(#inner_type)
+                };
+                quote!(<#c_type as ffi_convert::prelude::CReprOf<#target_field_type>>::c_repr_of(field)?)
+            } else if field.is_string {
+                let (owned_string_type, _) = field.string_types();
+                quote!(#owned_string_type::c_repr_of(field)?)
             } else {
                 match field_type {
                     TypeArrayOrTypePath::TypeArray(type_array) => {
@@ -32,40 +137,114 @@ pub fn impl_creprof_macro(input: &syn::DeriveInput) -> TokenStream {
                 }
             };
 
-            if field.is_pointer {
+            if field.is_pointer && !field.is_opaque && !field.is_borrowed_string {
                 for _ in 0..field.levels_of_indirection {
                     conversion = quote!(#conversion.into_raw_pointer())
                 }
             }
 
+            if field.is_boxed {
+                // the value is moved out of its box
+                conversion = quote!({ let field = *field; #conversion })
+            }
+
+            // a missing callback is represented by a callback with a null function pointer
+            let null = if field.is_callback {
+                quote!(ffi_convert::CCallback::null())
+            } else {
+                quote!(std::ptr::null() as _)
+            };
+
+            let is_try_getter = all_try_getters || field.is_try_getter;
+            let value = if is_try_getter {
+                quote!(input.#target_field_name().map_err(|error| {
+                    ffi_convert::CReprOfError::Other(error.into())
+                })?)
+            } else {
+                quote!(input.#target_field_name)
+            };
+            let owned_field = if let Some(ordering) = &field.atomic_ordering {
+                // shared atomics are read through a reference
+                quote!(let field = field.load(std::sync::atomic::Ordering::#ordering);)
+            } else if is_try_getter {
+                // the getters can return references
+                quote!(let field = field.to_owned();)
+            } else {
+                quote!()
+            };
+
             conversion = if field.is_nullable {
                 quote!(
-                    #field_name: if let Some(field) = input.#target_field_name {
+                    if let Some(field) = #value {
+                        #owned_field
                         #conversion
                     } else {
-                        std::ptr::null() as _
+                        #null
                     }
                 )
             } else {
-                quote!(#field_name: { let field = input.#target_field_name ; #conversion })
+                quote!({ let field = #value; #owned_field #conversion })
             };
-            if let Some(convert) = &field.c_repr_of_convert {
-                quote!(#field_name: #convert)
+            let value = if let Some(default) = &field.skip {
+                quote!(#default)
+            } else if let Some(convert) = &field.c_repr_of_convert {
+                quote!(#convert)
             } else {
                 conversion
+            };
+            // each field is converted in a variable, so that the fields converted before a failing
+            // one are released
+            let variable =
+                syn::Ident::new(&field_name.to_string(), proc_macro2::Span::mixed_site());
+            let is_owned_pointer =
+                field.is_pointer && field.skip.is_none() && !field.is_borrowed_string;
+            let (statement, field_init) = if is_owned_pointer {
+                let ty = field.ty;
+                let mut release = drop_pointer_field(field, quote!(pointer));
+                if field.is_nullable {
+                    release = quote!(if !pointer.is_null() { #release; });
+                }
+                (
+                    quote!(
+                        let #variable = ffi_convert::ReleaseOnError::new(#value, |pointer: #ty| {
+                            #release;
+                            Ok(())
+                        });
+                    ),
+                    quote!(#field_name: #variable.keep()),
+                )
+            } else {
+                (
+                    quote!(let #variable = #value;),
+                    quote!(#field_name: #variable),
+                )
+            };
+            if is_try_getter && field.skip.is_none() && field.c_repr_of_convert.is_none() {
+                // the getters borrow the input, so they are called before moving fields out of it
+                getter_fields.push(statement);
+            } else {
+                field_statements.push(statement);
             }
+            field_init
         })
         .collect::<Vec<_>>();
 
-    let c_repr_of_impl = quote!(
-        impl CReprOf<# target_type> for # struct_name {
+    let use_traits = use_prelude_traits();
+
+    quote!(
+        impl<#(#lifetimes),*> ffi_convert::prelude::CReprOf<# target_type> for # struct_name {
             fn c_repr_of(input: # target_type) -> Result<Self, ffi_convert::CReprOfError> {
-                use ffi_convert::RawPointerConverter;
+                #use_traits
+                #(
+                    #validate_fns(&input)
+                        .map_err(|error| ffi_convert::CReprOfError::Other(error.into()))?;
+                )*
+                #(#getter_fields)*
+                #(#field_statements)*
                 Ok(Self {
                     # ( # c_repr_of_fields, )*
                 })
             }
         }
-    );
-    c_repr_of_impl.into()
+    )
 }
@@ -0,0 +1,111 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::utils::{
+    collect_results, parse_struct_fields, parse_target_type, target_type_lifetimes, Field,
+    TypeArrayOrTypePath,
+};
+
+pub fn impl_creprofin_macro(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let struct_name = &input.ident;
+    let target_type = parse_target_type(&input.attrs)?;
+    let lifetimes = target_type_lifetimes(&target_type);
+
+    let fields = parse_struct_fields(input)?;
+
+    // all the fields are converted before the struct is built, so that the fields already
+    // converted are not dropped if a conversion fails (their memory belongs to the arena)
+    let conversions = collect_results(fields.iter().map(|field| {
+        let field_name = field.name;
+        let target_field_name = field.target_name_for(&target_type);
+        let variable = syn::Ident::new(&field_name.to_string(), proc_macro2::Span::mixed_site());
+
+        let conversion = if let Some(default) = &field.skip {
+            quote!(std::mem::ManuallyDrop::new(#default))
+        } else {
+            let conversion = field_conversion(field)?;
+            if field.is_nullable {
+                quote!(
+                    if let Some(field) = input.#target_field_name {
+                        #conversion
+                    } else {
+                        std::mem::ManuallyDrop::new(std::ptr::null() as _)
+                    }
+                )
+            } else {
+                quote!({ let field = input.#target_field_name; #conversion })
+            }
+        };
+        Ok(quote!(let #variable = #conversion;))
+    }))?;
+
+    let struct_fields = fields.iter().map(|field| {
+        let field_name = field.name;
+        let variable = syn::Ident::new(&field_name.to_string(), proc_macro2::Span::mixed_site());
+        quote!(#field_name: std::mem::ManuallyDrop::into_inner(#variable))
+    });
+
+    Ok(quote!(
+        impl<#(#lifetimes),*> ffi_convert::CReprOfIn<#target_type> for #struct_name {
+            fn c_repr_of_in(
+                input: #target_type,
+                arena: &ffi_convert::CArena,
+            ) -> Result<std::mem::ManuallyDrop<Self>, ffi_convert::CReprOfError> {
+                #(#conversions)*
+                Ok(std::mem::ManuallyDrop::new(Self {
+                    #(#struct_fields, )*
+                }))
+            }
+        }
+    ))
+}
+
+/// Generates the expression converting `field` (the value of the given field of the input) in the
+/// memory of the arena, returning the C representation in a `ManuallyDrop`.
+fn field_conversion(field: &Field) -> syn::Result<TokenStream> {
+    if field.is_opaque
+        || field.is_callback
+        || field.is_boxed
+        || field.is_stringified
+        || field.is_wide_string
+        || field.is_try_getter
+        || field.atomic_ordering.is_some()
+        || field.c_repr_of_convert.is_some()
+        || field.target_field_type.is_some()
+    {
+        return Err(syn::Error::new_spanned(
+            field.name,
+            "this field can't be converted by the `CReprOfIn` derive, which only supports \
+            primitive values, strings, pointers and containers",
+        ));
+    }
+    if field.is_borrowed_string {
+        return Ok(quote!(std::mem::ManuallyDrop::new(
+            ffi_convert::borrowed_c_str_ptr(field)
+        )));
+    }
+    if field.is_string {
+        return Ok(quote!(std::mem::ManuallyDrop::new(
+            arena.c_string(field.into())?
+        )));
+    }
+
+    let inner_type = &field.inner_type;
+    let value = quote!(
+        <#inner_type as ffi_convert::CReprOfIn<_>>::c_repr_of_in(field, arena)?
+    );
+    if !field.is_pointer {
+        return Ok(value);
+    }
+    if field.levels_of_indirection > 1
+        || matches!(field.field_type, TypeArrayOrTypePath::TypeArray(_))
+    {
+        return Err(syn::Error::new_spanned(
+            field.name,
+            "only pointers to structs can be converted by the `CReprOfIn` derive",
+        ));
+    }
+    Ok(quote!(std::mem::ManuallyDrop::new(
+        arena.alloc_raw(std::mem::ManuallyDrop::into_inner(#value))? as _
+    )))
+}
@@ -0,0 +1,127 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+pub fn impl_cschema_macro(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let struct_name = &input.ident;
+
+    let fields = match &input.data {
+        syn::Data::Struct(data_struct) => &data_struct.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                struct_name,
+                "CSchema can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut field_schemas = vec![];
+    let hashed_fields = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let member = match &field.ident {
+                Some(ident) => syn::Member::Named(ident.clone()),
+                None => syn::Member::Unnamed(index.into()),
+            };
+            let name = match &member {
+                syn::Member::Named(ident) => ident.to_string(),
+                syn::Member::Unnamed(index) => index.index.to_string(),
+            };
+            // the spacing of the tokens is not meaningful
+            let field_type = &field.ty;
+            let type_name = quote!(#field_type)
+                .to_string()
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>();
+
+            let readable_type_name = readable_type_name(field_type);
+            let ownership = field_ownership(field)?;
+            field_schemas.push(quote!(
+                ffi_convert::FieldSchema {
+                    name: #name,
+                    type_name: #readable_type_name,
+                    offset: std::mem::offset_of!(#struct_name, #member),
+                    ownership: ffi_convert::Ownership::#ownership,
+                }
+            ));
+
+            Ok(quote!(
+                .write_str(#name)
+                .write_str(#type_name)
+                .write_usize(std::mem::offset_of!(#struct_name, #member))
+            ))
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let struct_name_str = struct_name.to_string();
+
+    Ok(quote!(
+        impl ffi_convert::prelude::CSchema for #struct_name {
+            const SCHEMA_HASH: u64 = ffi_convert::SchemaHasher::new()
+                .write_str(#struct_name_str)
+                .write_usize(std::mem::size_of::<#struct_name>())
+                .write_usize(std::mem::align_of::<#struct_name>())
+                #(#hashed_fields)*
+                .finish();
+
+            const FIELDS: &'static [ffi_convert::FieldSchema] = &[#(#field_schemas),*];
+        }
+    ))
+}
+
+/// Returns the `Ownership` variant of a field : given by its `#[ownership(...)]` attribute, or
+/// deduced from its type and attributes.
+fn field_ownership(field: &syn::Field) -> syn::Result<syn::Ident> {
+    let has_attribute = |name: &str| field.attrs.iter().any(|attr| attr.path.is_ident(name));
+
+    if let Some(attr) = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("ownership"))
+    {
+        let ownership: syn::Ident = attr.parse_args()?;
+        let variant = match ownership.to_string().as_str() {
+            "value" => "Value",
+            "owned" => "Owned",
+            "borrowed" => "Borrowed",
+            "c_allocated" => "CAllocated",
+            "callback" => "Callback",
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ownership,
+                    "expected one of `value`, `owned`, `borrowed`, `c_allocated`, `callback`",
+                ))
+            }
+        };
+        return Ok(syn::Ident::new(variant, ownership.span()));
+    }
+
+    let is_callback = has_attribute("callback")
+        || matches!(&field.ty, syn::Type::Path(type_path)
+            if type_path.path.segments.last().is_some_and(|segment| segment.ident == "CCallback"));
+    let variant = match &field.ty {
+        _ if is_callback => "Callback",
+        // skipped fields are not owned by the struct
+        syn::Type::Ptr(_) if has_attribute("skip") => "Borrowed",
+        syn::Type::Ptr(_) => "Owned",
+        _ => "Value",
+    };
+    Ok(syn::Ident::new(variant, proc_macro2::Span::call_site()))
+}
+
+/// Formats a type as it would be written, the tokens being only separated by a space when
+/// needed (e.g. `*const libc::c_char`).
+fn readable_type_name(field_type: &syn::Type) -> String {
+    let tokens = quote!(#field_type).to_string();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut name = String::with_capacity(tokens.len());
+    let mut chars = tokens.chars().peekable();
+    while let Some(c) = chars.next() {
+        let is_needed_space = name.ends_with(is_word) && chars.peek().copied().is_some_and(is_word);
+        if c != ' ' || is_needed_space {
+            name.push(c);
+        }
+    }
+    name
+}
@@ -0,0 +1,68 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::utils::{parse_struct_fields, use_prelude_traits, Field, TypeArrayOrTypePath};
+
+pub fn impl_cvalidate_macro(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let struct_name = &input.ident;
+
+    let validate_fields = parse_struct_fields(input)?
+        .iter()
+        .filter(|field| {
+            // those fields are not (or not directly) used by as_rust
+            field.skip.is_none()
+                && field.as_rust_convert.is_none()
+                && field.c_repr_of_convert.is_none()
+        })
+        .map(|field| {
+            let Field {
+                name: field_name,
+                ref field_type,
+                ..
+            } = field;
+
+            let validation = if field.is_opaque {
+                // the type of the value is only checked by as_rust
+                quote!(if self.#field_name.is_null() {
+                    return Err(ffi_convert::UnexpectedNullPointerError.into());
+                })
+            } else if field.is_string {
+                let borrowed_string = field.borrow_string_call();
+                quote!(#borrowed_string.validate_c()?)
+            } else if field.is_pointer {
+                match field_type {
+                    TypeArrayOrTypePath::TypeArray(type_array) => {
+                        quote!(unsafe { <#type_array>::raw_borrow(self.#field_name) }?.validate_c()?)
+                    }
+                    TypeArrayOrTypePath::TypePath(type_path) => {
+                        quote!(unsafe { #type_path::raw_borrow(self.#field_name) }?.validate_c()?)
+                    }
+                }
+            } else {
+                quote!(self.#field_name.validate_c()?)
+            };
+
+            if field.is_nullable {
+                quote!(
+                    if !self.#field_name.is_null() {
+                        #validation
+                    }
+                )
+            } else {
+                validation
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let use_traits = use_prelude_traits();
+
+    Ok(quote!(
+        impl ffi_convert::prelude::CValidate for #struct_name {
+            fn validate_c(&self) -> Result<(), ffi_convert::AsRustError> {
+                #use_traits
+                #( #validate_fields; )*
+                Ok(())
+            }
+        }
+    ))
+}
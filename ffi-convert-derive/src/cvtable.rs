@@ -0,0 +1,103 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::utils::{collect_results, parse_impls, use_prelude_traits};
+
+pub fn impl_cvtable_macro(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let struct_name = &input.ident;
+
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                struct_name,
+                "CVTable can only be derived for structs with named fields",
+            ))
+        }
+    };
+
+    // entries declared as `Option<fn(...)>` can be null on the C side, they are checked unless
+    // they are `#[nullable]`
+    let checked_entries = collect_results(fields.iter().map(|field| {
+        let is_nullable = field
+            .attrs
+            .iter()
+            .any(|attr| attr.path.is_ident("nullable"));
+        match (&field.ty, is_nullable) {
+            (syn::Type::BareFn(_), true) => Err(syn::Error::new_spanned(
+                &field.ty,
+                "a function pointer can't be null, use `Option<fn(...)>` for nullable entries",
+            )),
+            (syn::Type::Path(type_path), false)
+                if type_path
+                    .path
+                    .segments
+                    .last()
+                    .is_some_and(|segment| segment.ident == "Option") =>
+            {
+                Ok(field.ident.as_ref())
+            }
+            _ => Ok(None),
+        }
+    }))?
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+
+    let impls = parse_impls(&input.attrs, &["CValidate", "CReprOf", "AsRust", "CDrop"])?;
+
+    let c_validate_impl = quote!(
+        impl ffi_convert::prelude::CValidate for #struct_name {
+            fn validate_c(&self) -> Result<(), ffi_convert::AsRustError> {
+                #(
+                    if self.#checked_entries.is_none() {
+                        return Err(ffi_convert::UnexpectedNullPointerError.into());
+                    }
+                )*
+                Ok(())
+            }
+        }
+    );
+
+    let c_repr_of_impl = quote!(
+        impl ffi_convert::prelude::CReprOf<#struct_name> for #struct_name {
+            fn c_repr_of(input: #struct_name) -> Result<Self, ffi_convert::CReprOfError> {
+                Ok(input)
+            }
+        }
+    );
+
+    let use_traits = use_prelude_traits();
+
+    let as_rust_impl = quote!(
+        impl ffi_convert::prelude::AsRust<#struct_name> for #struct_name {
+            fn as_rust(&self) -> Result<#struct_name, ffi_convert::AsRustError> {
+                #use_traits
+                self.validate_c()?;
+                Ok(*self)
+            }
+        }
+    );
+
+    let c_drop_impl = quote!(
+        impl ffi_convert::prelude::CDrop for #struct_name {
+            fn do_drop(&mut self) -> Result<(), ffi_convert::CDropError> {
+                Ok(())
+            }
+        }
+    );
+
+    Ok(vec![
+        ("CValidate", c_validate_impl),
+        ("CReprOf", c_repr_of_impl),
+        ("AsRust", as_rust_impl),
+        ("CDrop", c_drop_impl),
+    ]
+    .into_iter()
+    .filter(|(name, _)| impls.iter().any(|it| it == name))
+    .map(|(_, implementation)| implementation)
+    .collect())
+}
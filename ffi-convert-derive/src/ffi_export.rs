@@ -0,0 +1,325 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseBuffer};
+
+use crate::signature::{
+    register_signature, ParameterOwnership, SignatureParameter, C_STATUS_CODES,
+};
+use crate::utils::{is_primitive_type, use_prelude_traits};
+
+/// Generates an `extern "C"` shim for the given function, converting its arguments from their C
+/// representation and its output to its C representation.
+///
+/// The shim takes the C representation of each argument (given with the `#[c_type(...)]` argument
+/// attribute, or inferred for strings and primitive types), followed by a pointer where the C
+/// representation of the output is written, and returns a `CStatus`.
+pub fn impl_ffi_export_macro(
+    args: ExportArgs,
+    mut function: syn::ItemFn,
+) -> syn::Result<TokenStream> {
+    let function_name = &function.sig.ident;
+    let shim_name = args
+        .name
+        .unwrap_or_else(|| format_ident!("ffi_{}", function_name));
+
+    if !function.sig.generics.params.is_empty() || function.sig.asyncness.is_some() {
+        return Err(syn::Error::new_spanned(
+            &function.sig,
+            "only non generic and non async functions can be exported",
+        ));
+    }
+
+    let mut c_params = vec![];
+    let mut signature_params = vec![];
+    let mut owned_pointers = vec![];
+    let mut conversions = vec![];
+    let mut call_args = vec![];
+    for input in function.sig.inputs.iter_mut() {
+        let param = match input {
+            syn::FnArg::Typed(param) => param,
+            syn::FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new_spanned(
+                    receiver,
+                    "methods can't be exported, only free functions",
+                ))
+            }
+        };
+        let name = match &*param.pat {
+            syn::Pat::Ident(pat_ident) => pat_ident.ident.clone(),
+            pat => {
+                return Err(syn::Error::new_spanned(
+                    pat,
+                    "the arguments of exported functions must be identifiers",
+                ))
+            }
+        };
+
+        // the `c_type` attribute is consumed here, it is not valid on the original function
+        let c_type = take_c_type_attribute(&mut param.attrs)?;
+
+        let (owned_type, is_borrowed) = match &*param.ty {
+            syn::Type::Reference(reference) if reference.mutability.is_some() => {
+                return Err(syn::Error::new_spanned(
+                    reference,
+                    "mutable references can't be exported, the arguments are converted copies",
+                ))
+            }
+            syn::Type::Reference(reference) => (owned_type(&reference.elem), true),
+            ty => (ty.clone(), false),
+        };
+        let c_type = match c_type {
+            Some(c_type) => c_type,
+            None => default_c_type(&owned_type).ok_or_else(|| {
+                syn::Error::new_spanned(
+                    &param.ty,
+                    "the C type of this argument must be given with `#[c_type(...)]`",
+                )
+            })?,
+        };
+
+        let is_value = is_primitive_type(&c_type);
+        let conversion = match (&c_type, is_borrowed) {
+            (_, false) if is_value => quote!(#name.as_rust()?),
+            (syn::Type::Ptr(pointer), true) => {
+                let pointee = &pointer.elem;
+                if is_c_char(pointee) {
+                    quote!(<std::ffi::CStr>::raw_borrow(#name)?.as_rust()?)
+                } else {
+                    quote!(<#pointee>::raw_borrow(#name)?.as_rust()?)
+                }
+            }
+            // the value belongs to the caller, it must not be dropped here
+            (_, true) => quote!(std::mem::ManuallyDrop::new(#name).as_rust()?),
+            // the argument passed by value belongs to the function, it is released once converted
+            (c_type, false) => {
+                let c_value = match c_type {
+                    syn::Type::Ptr(_) => quote!(#name?),
+                    _ => quote!(#name),
+                };
+                quote!({
+                    let mut c_value = #c_value;
+                    let value = c_value.as_rust();
+                    c_value.do_drop()?;
+                    value?
+                })
+            }
+        };
+        // the pointers passed by value are all taken back before any conversion, so that they
+        // are released even if the conversion of a previous argument fails
+        if let (syn::Type::Ptr(pointer), false) = (&c_type, is_borrowed) {
+            let pointee = &pointer.elem;
+            let pointee = if is_c_char(pointee) {
+                quote!(std::ffi::CString)
+            } else {
+                quote!(#pointee)
+            };
+            owned_pointers.push(quote!(let #name = <#pointee>::from_raw_pointer(#name);));
+        }
+        conversions.push(quote!(let #name: #owned_type = #conversion;));
+        call_args.push(if is_borrowed {
+            quote!(&#name)
+        } else {
+            quote!(#name)
+        });
+        c_params.push(quote!(#name: #c_type));
+        // the arguments borrowed by the function are converted copies, they still belong to the
+        // caller, while the ones it takes by value are released
+        let ownership = if is_value {
+            ParameterOwnership::Value
+        } else if is_borrowed {
+            ParameterOwnership::Borrowed
+        } else {
+            ParameterOwnership::Released
+        };
+        signature_params.push(SignatureParameter::new(&name, &c_type, ownership));
+    }
+
+    let (output_type, is_result) = match &function.sig.output {
+        syn::ReturnType::Default => (None, false),
+        syn::ReturnType::Type(_, ty) => match result_ok_type(ty) {
+            Some(ok_type) => (Some(ok_type), true),
+            None => (Some((**ty).clone()), false),
+        },
+    };
+    let output_type =
+        output_type.filter(|ty| !matches!(ty, syn::Type::Tuple(tuple) if tuple.elems.is_empty()));
+
+    let call = if is_result {
+        quote!(#function_name(#(#call_args),*).map_err(|error| error.to_string())?)
+    } else {
+        quote!(#function_name(#(#call_args),*))
+    };
+
+    let output = match output_type {
+        None => quote!(#call;),
+        Some(output_type) => {
+            let c_output_type = match args.output {
+                Some(c_output_type) => c_output_type,
+                None => default_c_type(&output_type).ok_or_else(|| {
+                    syn::Error::new_spanned(
+                        &function.sig.output,
+                        "the C type of the output must be given with `#[ffi_export(output = ...)]`",
+                    )
+                })?,
+            };
+            let conversion = match &c_output_type {
+                syn::Type::Ptr(pointer) => {
+                    let pointee = &pointer.elem;
+                    let c_value = if is_c_char(pointee) {
+                        quote!(<std::ffi::CString>::c_repr_of(result)?)
+                    } else {
+                        quote!(<#pointee as ffi_convert::prelude::CReprOf<_>>::c_repr_of(result)?)
+                    };
+                    if pointer.mutability.is_some() {
+                        quote!(#c_value.into_raw_pointer_mut() as _)
+                    } else {
+                        quote!(#c_value.into_raw_pointer() as _)
+                    }
+                }
+                _ => {
+                    quote!(<#c_output_type as ffi_convert::prelude::CReprOf<_>>::c_repr_of(result)?)
+                }
+            };
+            c_params.push(quote!(output: *mut #c_output_type));
+            let ownership = if is_primitive_type(&c_output_type) {
+                ParameterOwnership::Value
+            } else {
+                ParameterOwnership::OwnedByCaller
+            };
+            signature_params.push(SignatureParameter::output(
+                "output",
+                quote!(*mut #c_output_type),
+                ownership,
+            ));
+            quote!(
+                if output.is_null() {
+                    return Err(ffi_convert::UnexpectedNullPointerError.into());
+                }
+                let result = #call;
+                *output = #conversion;
+            )
+        }
+    };
+
+    let doc = format!(
+        "Calls `{}` from C, returns `CStatus::Ko` and records the last error if it fails.",
+        function_name
+    );
+    let use_traits = use_prelude_traits();
+    let signature = register_signature(
+        &shim_name,
+        &doc,
+        &signature_params,
+        Some((quote!(CStatus), ParameterOwnership::Value)),
+        C_STATUS_CODES,
+    );
+
+    Ok(quote!(
+        #function
+
+        #[doc = #doc]
+        ///
+        /// # Safety
+        ///
+        /// The pointers must be valid, the arguments passed by value (and the values their
+        /// pointers point to) are released, the output is owned by the caller.
+        #[no_mangle]
+        pub unsafe extern "C" fn #shim_name(#(#c_params),*) -> ffi_convert::CStatus {
+            ffi_convert::ffi_guard(|| -> Result<(), Box<dyn std::error::Error>> {
+                #use_traits
+                #(#owned_pointers)*
+                #(#conversions)*
+                #output
+                Ok(())
+            })
+        }
+
+        #signature
+    ))
+}
+
+/// Arguments of the `ffi_export` attribute : `#[ffi_export(name = ffi_fn, output = *const CType)]`,
+/// both being optional.
+#[derive(Default)]
+pub struct ExportArgs {
+    name: Option<syn::Ident>,
+    output: Option<syn::Type>,
+}
+
+impl Parse for ExportArgs {
+    fn parse(input: &ParseBuffer) -> Result<Self, syn::parse::Error> {
+        let mut args = ExportArgs::default();
+        while !input.is_empty() {
+            let key: syn::Ident = input.parse()?;
+            input.parse::<syn::Token![=]>()?;
+            if key == "name" {
+                args.name = Some(input.parse()?);
+            } else if key == "output" {
+                args.output = Some(input.parse()?);
+            } else {
+                return Err(syn::parse::Error::new(
+                    key.span(),
+                    "expected `name = <ident>` or `output = <type>` in ffi_export attribute",
+                ));
+            }
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+        Ok(args)
+    }
+}
+
+fn take_c_type_attribute(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Option<syn::Type>> {
+    let index = attrs.iter().position(|attr| attr.path.is_ident("c_type"));
+    index
+        .map(|index| attrs.remove(index).parse_args())
+        .transpose()
+}
+
+/// The owned type converted from C for a borrowed argument : a `String` for a `&str`, a `Vec<T>`
+/// for a `&[T]`, or the borrowed type itself.
+fn owned_type(borrowed: &syn::Type) -> syn::Type {
+    match borrowed {
+        syn::Type::Path(type_path) if type_path.path.is_ident("str") => syn::parse_quote!(String),
+        syn::Type::Slice(slice) => {
+            let element = &slice.elem;
+            syn::parse_quote!(Vec<#element>)
+        }
+        ty => ty.clone(),
+    }
+}
+
+/// The C type of the strings and of the primitive types, which don't need to be given.
+fn default_c_type(ty: &syn::Type) -> Option<syn::Type> {
+    match ty {
+        syn::Type::Path(type_path) if type_path.path.is_ident("String") => {
+            Some(syn::parse_quote!(*const libc::c_char))
+        }
+        ty if is_primitive_type(ty) => Some(ty.clone()),
+        _ => None,
+    }
+}
+
+/// The type of the value of a `Result`, if the type is a `Result`.
+fn result_ok_type(ty: &syn::Type) -> Option<syn::Type> {
+    let segment = match ty {
+        syn::Type::Path(type_path) => type_path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Result" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(arguments) => match arguments.args.first()? {
+            syn::GenericArgument::Type(ok_type) => Some(ok_type.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_c_char(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Path(type_path)
+        if type_path.path.segments.last().is_some_and(|segment| segment.ident == "c_char"))
+}
@@ -1,51 +1,255 @@
-//! This crate provides ffi_convert derive macros for CReprOf, AsRust and CDrop traits.
+//! This crate provides ffi_convert derive macros for CReprOf, CReprOfIn, AsRust, AsRustView, CDrop, CValidate and CSchema traits.
+//! It also provides the CVTable derive macro, to use tables of function pointers as fields, and the
+//! CDebug and CPartialEq derive macros, showing and comparing the Rust values of C structs.
 
 extern crate proc_macro;
 
+mod accessors;
 mod asrust;
+mod asrustview;
+mod cdebug;
 mod cdrop;
+mod cpartialeq;
 mod creprof;
+mod creprofin;
+mod cschema;
+mod cvalidate;
+mod cvtable;
+mod ffi_export;
 mod rawpointerconverter;
+mod signature;
+mod transparent;
 mod utils;
 
 use asrust::impl_asrust_macro;
+use asrustview::impl_asrustview_macro;
+use cdebug::impl_cdebug_macro;
 use cdrop::impl_cdrop_macro;
+use cpartialeq::impl_cpartialeq_macro;
 use creprof::impl_creprof_macro;
+use creprofin::impl_creprofin_macro;
+use cschema::impl_cschema_macro;
+use cvalidate::impl_cvalidate_macro;
+use cvtable::impl_cvtable_macro;
+use ffi_export::impl_ffi_export_macro;
 use proc_macro::TokenStream;
 use rawpointerconverter::impl_rawpointerconverter_macro;
+use transparent::impl_transparent_macro;
 
 #[proc_macro_derive(
     CReprOf,
-    attributes(target_type, nullable, c_repr_of_convert, target_name)
+    attributes(
+        allow_non_repr_c,
+        target_type,
+        target_types,
+        abi_version,
+        target_try_getter,
+        c_repr_of_validate,
+        generate_accessors,
+        nullable,
+        c_repr_of_convert,
+        target_name,
+        target_field_type,
+        atomic_ordering,
+        boxed,
+        callback,
+        opaque,
+        skip,
+        string_types,
+        wide_string,
+        stringify,
+        borrowed_string
+    )
 )]
 pub fn creprof_derive(token_stream: TokenStream) -> TokenStream {
-    let ast = syn::parse(token_stream).unwrap();
+    let ast = syn::parse_macro_input!(token_stream as syn::DeriveInput);
     impl_creprof_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(
+    CReprOfIn,
+    attributes(
+        target_type,
+        target_types,
+        nullable,
+        target_name,
+        skip,
+        string_types,
+        borrowed_string
+    )
+)]
+pub fn creprofin_derive(token_stream: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(token_stream as syn::DeriveInput);
+    impl_creprofin_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
 #[proc_macro_derive(
     AsRust,
     attributes(
+        allow_non_repr_c,
         target_type,
+        target_types,
+        abi_version,
         nullable,
         as_rust_extra_field,
+        as_rust_default_field,
+        as_rust_fill_defaults,
         as_rust_ignore,
-        target_name
+        as_rust_convert,
+        as_rust_accessors,
+        as_rust_validate,
+        as_rust_zeroed,
+        target_name,
+        target_field_type,
+        boxed,
+        callback,
+        opaque,
+        skip,
+        string_types,
+        wide_string,
+        stringify,
+        string_policy,
+        borrowed_string
     )
 )]
 pub fn asrust_derive(token_stream: TokenStream) -> TokenStream {
-    let ast = syn::parse(token_stream).unwrap();
+    let ast = syn::parse_macro_input!(token_stream as syn::DeriveInput);
     impl_asrust_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
-#[proc_macro_derive(CDrop, attributes(no_drop_impl, nullable))]
+#[proc_macro_derive(
+    AsRustView,
+    attributes(
+        target_type,
+        target_types,
+        abi_version,
+        nullable,
+        target_name,
+        target_field_type,
+        boxed,
+        callback,
+        opaque,
+        skip,
+        string_types,
+        wide_string,
+        stringify,
+        string_policy,
+        borrowed_string
+    )
+)]
+pub fn asrustview_derive(token_stream: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(token_stream as syn::DeriveInput);
+    impl_asrustview_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(
+    CDrop,
+    attributes(
+        abi_version,
+        expected_size,
+        expected_align,
+        no_drop_impl,
+        defensive_drop,
+        thread_safe,
+        nullable,
+        opaque,
+        callback,
+        shallow,
+        skip,
+        string_types,
+        wide_string,
+        borrowed_string
+    )
+)]
 pub fn cdrop_derive(token_stream: TokenStream) -> TokenStream {
-    let ast = syn::parse(token_stream).unwrap();
+    let ast = syn::parse_macro_input!(token_stream as syn::DeriveInput);
     impl_cdrop_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(
+    CValidate,
+    attributes(
+        nullable,
+        opaque,
+        callback,
+        skip,
+        string_types,
+        wide_string,
+        string_policy,
+        as_rust_convert,
+        c_repr_of_convert
+    )
+)]
+pub fn cvalidate_derive(token_stream: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(token_stream as syn::DeriveInput);
+    impl_cvalidate_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(CDebug, attributes(target_type, target_types))]
+pub fn cdebug_derive(token_stream: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(token_stream as syn::DeriveInput);
+    impl_cdebug_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(CPartialEq, attributes(target_type, target_types))]
+pub fn cpartialeq_derive(token_stream: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(token_stream as syn::DeriveInput);
+    impl_cpartialeq_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
-#[proc_macro_derive(RawPointerConverter)]
+#[proc_macro_derive(CVTable, attributes(nullable, impls))]
+pub fn cvtable_derive(token_stream: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(token_stream as syn::DeriveInput);
+    impl_cvtable_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(CSchema, attributes(ownership, skip, callback))]
+pub fn cschema_derive(token_stream: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(token_stream as syn::DeriveInput);
+    impl_cschema_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(CReprOfTransparent, attributes(target_type, impls))]
+pub fn transparent_derive(token_stream: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(token_stream as syn::DeriveInput);
+    impl_transparent_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(RawPointerConverter, attributes(generate_destructor))]
 pub fn rawpointerconverter_derive(token_stream: TokenStream) -> TokenStream {
-    let ast = syn::parse(token_stream).unwrap();
+    let ast = syn::parse_macro_input!(token_stream as syn::DeriveInput);
     impl_rawpointerconverter_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_attribute]
+pub fn ffi_export(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(args as ffi_export::ExportArgs);
+    let function = syn::parse_macro_input!(item as syn::ItemFn);
+    impl_ffi_export_macro(args, function)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
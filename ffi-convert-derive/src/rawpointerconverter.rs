@@ -1,27 +1,95 @@
-use proc_macro::TokenStream;
+use proc_macro2::TokenStream;
 use quote::quote;
 
-pub fn impl_rawpointerconverter_macro(input: &syn::DeriveInput) -> TokenStream {
+use crate::signature::{register_signature, ParameterOwnership, SignatureParameter};
+use crate::utils::snake_case;
+
+pub fn impl_rawpointerconverter_macro(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
     let struct_name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let struct_type = quote!(# struct_name # type_generics);
+
+    let destructor = parse_destructor_name(input)?.map(|destructor_name| {
+        let doc = format!(
+            "Drops a `{}` created by Rust, returns `0` on success, `-1` if the pointer is `null` \
+            and `-2` if dropping it panicked.",
+            struct_name
+        );
+        let signature = register_signature(
+            &destructor_name,
+            &doc,
+            &[SignatureParameter::new(
+                "ptr",
+                quote!(*mut #struct_name),
+                ParameterOwnership::Released,
+            )],
+            Some((quote!(i32), ParameterOwnership::Value)),
+            &[(0, "success"), (-1, "null pointer"), (-2, "panic")],
+        );
+        quote!(
+            #[doc = #doc]
+            ///
+            /// # Safety
+            ///
+            /// See `ffi_convert::destroy_raw_pointer`.
+            #[no_mangle]
+            pub unsafe extern "C" fn #destructor_name(ptr: *mut #struct_name) -> i32 {
+                ffi_convert::destroy_raw_pointer(ptr)
+            }
 
-    quote!(
-        impl RawPointerConverter<# struct_name> for # struct_name {
-            fn into_raw_pointer(self) -> *const # struct_name {
+            #signature
+        )
+    });
+
+    Ok(quote!(
+        impl # impl_generics ffi_convert::prelude::RawPointerConverter<# struct_type> for # struct_type # where_clause {
+            fn into_raw_pointer(self) -> *const # struct_type {
                 ffi_convert::convert_into_raw_pointer(self)
             }
 
-            fn into_raw_pointer_mut(self) -> *mut # struct_name {
+            fn into_raw_pointer_mut(self) -> *mut # struct_type {
                 ffi_convert::convert_into_raw_pointer_mut(self)
             }
 
-            unsafe fn from_raw_pointer_mut(input: *mut # struct_name) -> Result<# struct_name, ffi_convert::UnexpectedNullPointerError> {
+            unsafe fn from_raw_pointer_mut(input: *mut # struct_type) -> Result<# struct_type, ffi_convert::UnexpectedNullPointerError> {
                 ffi_convert::take_back_from_raw_pointer_mut(input)
             }
 
-            unsafe fn from_raw_pointer(input: *const # struct_name) -> Result<# struct_name, ffi_convert::UnexpectedNullPointerError> {
+            unsafe fn from_raw_pointer(input: *const # struct_type) -> Result<# struct_type, ffi_convert::UnexpectedNullPointerError> {
                 ffi_convert::take_back_from_raw_pointer(input)
             }
 
         }
-    ).into()
+
+        #destructor
+    ))
+}
+
+/// Parses the `#[generate_destructor]` attribute, which can give the name of the destructor
+/// (`#[generate_destructor(ffi_pizza_destroy)]`), named `<type_name>_destroy` by default.
+fn parse_destructor_name(input: &syn::DeriveInput) -> syn::Result<Option<syn::Ident>> {
+    let attribute = match input
+        .attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("generate_destructor"))
+    {
+        Some(attribute) => attribute,
+        None => return Ok(None),
+    };
+
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            attribute,
+            "destructors can't be generated for generic types",
+        ));
+    }
+
+    if attribute.tokens.is_empty() {
+        Ok(Some(syn::Ident::new(
+            &format!("{}_destroy", snake_case(&input.ident.to_string())),
+            input.ident.span(),
+        )))
+    } else {
+        attribute.parse_args().map(Some)
+    }
 }
@@ -0,0 +1,126 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+
+/// A parameter of a generated `extern "C"` function, described in its registered signature.
+pub struct SignatureParameter {
+    pub name: String,
+    pub ty: String,
+    pub is_output: bool,
+    pub ownership: ParameterOwnership,
+}
+
+impl SignatureParameter {
+    pub fn new(name: impl ToString, ty: impl ToTokens, ownership: ParameterOwnership) -> Self {
+        Self {
+            name: name.to_string(),
+            ty: type_string(ty),
+            is_output: false,
+            ownership,
+        }
+    }
+
+    /// A pointer where the function writes a value, whose ownership is given.
+    pub fn output(name: impl ToString, ty: impl ToTokens, ownership: ParameterOwnership) -> Self {
+        Self {
+            is_output: true,
+            ..Self::new(name, ty, ownership)
+        }
+    }
+}
+
+/// The variants of `ffi_convert::ParameterOwnership`.
+#[derive(Clone, Copy)]
+pub enum ParameterOwnership {
+    Value,
+    Borrowed,
+    OwnedByCaller,
+    Released,
+}
+
+impl ToTokens for ParameterOwnership {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(match self {
+            ParameterOwnership::Value => quote!(ffi_convert::ParameterOwnership::Value),
+            ParameterOwnership::Borrowed => quote!(ffi_convert::ParameterOwnership::Borrowed),
+            ParameterOwnership::OwnedByCaller => {
+                quote!(ffi_convert::ParameterOwnership::OwnedByCaller)
+            }
+            ParameterOwnership::Released => quote!(ffi_convert::ParameterOwnership::Released),
+        })
+    }
+}
+
+/// The codes returned by the functions returning a `CStatus`.
+pub const C_STATUS_CODES: &[(i64, &str)] = &[(0, "success"), (1, "failure, see the last error")];
+
+/// Generates the registration of the signature of a generated `extern "C"` function, which is
+/// only kept with the `signatures` feature of `ffi_convert`.
+pub fn register_signature(
+    name: &syn::Ident,
+    doc: &str,
+    parameters: &[SignatureParameter],
+    return_type: Option<(TokenStream, ParameterOwnership)>,
+    return_codes: &[(i64, &str)],
+) -> TokenStream {
+    let name = name.to_string();
+    let parameters = parameters.iter().map(|parameter| {
+        let SignatureParameter {
+            name,
+            ty,
+            ownership,
+            ..
+        } = parameter;
+        let direction = if parameter.is_output {
+            quote!(ffi_convert::Direction::Out)
+        } else {
+            quote!(ffi_convert::Direction::In)
+        };
+        quote!(ffi_convert::CParameter {
+            name: #name,
+            ty: #ty,
+            direction: #direction,
+            ownership: #ownership,
+        })
+    });
+    let (return_type, return_ownership) = match return_type {
+        Some((return_type, ownership)) => (type_string(return_type), ownership),
+        None => ("()".to_string(), ParameterOwnership::Value),
+    };
+    let return_codes = return_codes.iter().map(|(value, meaning)| {
+        quote!(ffi_convert::CReturnCode {
+            value: #value,
+            meaning: #meaning,
+        })
+    });
+
+    quote!(
+        ffi_convert::register_signature!(ffi_convert::CFunctionSignature {
+            name: #name,
+            doc: #doc,
+            parameters: &[#(#parameters),*],
+            return_type: #return_type,
+            return_ownership: #return_ownership,
+            return_codes: &[#(#return_codes),*],
+        });
+    )
+}
+
+/// Formats a type as it would be written, without the spaces added between its tokens.
+fn type_string(ty: impl ToTokens) -> String {
+    let tokens = ty.into_token_stream().to_string();
+    let mut formatted = String::with_capacity(tokens.len());
+    let mut previous = ' ';
+    let mut chars = tokens.chars().peekable();
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied().unwrap_or(' ');
+        // the spaces are only kept between words (such as `*const T`) and after separators
+        let is_glued = matches!(next, ':' | '<' | '>' | ',' | ';' | ']')
+            || matches!(previous, ':' | '<' | '*' | '&' | '[');
+        if c == ' ' && is_glued {
+            continue;
+        }
+        formatted.push(c);
+        previous = c;
+    }
+    formatted
+}
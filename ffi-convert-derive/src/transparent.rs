@@ -0,0 +1,95 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::utils::{
+    parse_impls, strip_generic_arguments, target_type_lifetimes, use_prelude_traits,
+};
+
+pub fn impl_transparent_macro(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let struct_name = &input.ident;
+
+    let is_transparent = input.attrs.iter().any(|attr| {
+        attr.path.is_ident("repr")
+            && attr
+                .parse_args::<syn::Ident>()
+                .is_ok_and(|repr| repr == "transparent")
+    });
+    if !is_transparent {
+        return Err(syn::Error::new_spanned(
+            struct_name,
+            "CReprOfTransparent can only be derived for `#[repr(transparent)]` structs",
+        ));
+    }
+
+    let field = match &input.data {
+        syn::Data::Struct(syn::DataStruct { fields, .. }) if fields.len() == 1 => {
+            fields.iter().next().unwrap()
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(
+                struct_name,
+                "CReprOfTransparent can only be derived for structs with a single field",
+            ))
+        }
+    };
+    let inner_type = &field.ty;
+    let member = match &field.ident {
+        Some(ident) => syn::Member::Named(ident.clone()),
+        None => syn::Member::Unnamed(0.into()),
+    };
+
+    // without a target type, the struct is its own C representation (e.g. a strongly typed id)
+    let target_type: syn::Path = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("target_type"))
+        .map(|attr| attr.parse_args())
+        .transpose()?
+        .unwrap_or_else(|| struct_name.clone().into());
+    let target_constructor = strip_generic_arguments(&target_type);
+    let lifetimes = target_type_lifetimes(&target_type);
+    let impls = parse_impls(&input.attrs, &["CReprOf", "AsRust", "CDrop"])?;
+
+    let use_traits = use_prelude_traits();
+
+    let c_repr_of_impl = quote!(
+        impl<#(#lifetimes),*> ffi_convert::prelude::CReprOf<#target_type> for #struct_name {
+            fn c_repr_of(input: #target_type) -> Result<Self, ffi_convert::CReprOfError> {
+                #use_traits
+                Ok(Self {
+                    #member: <#inner_type>::c_repr_of(input.#member)?,
+                })
+            }
+        }
+    );
+
+    let as_rust_impl = quote!(
+        impl ffi_convert::prelude::AsRust<#target_type> for #struct_name {
+            fn as_rust(&self) -> Result<#target_type, ffi_convert::AsRustError> {
+                #use_traits
+                Ok(#target_constructor {
+                    #member: self.#member.as_rust()?,
+                })
+            }
+        }
+    );
+
+    let c_drop_impl = quote!(
+        impl ffi_convert::prelude::CDrop for #struct_name {
+            fn do_drop(&mut self) -> Result<(), ffi_convert::CDropError> {
+                #use_traits
+                self.#member.do_drop()
+            }
+        }
+    );
+
+    Ok(vec![
+        ("CReprOf", c_repr_of_impl),
+        ("AsRust", as_rust_impl),
+        ("CDrop", c_drop_impl),
+    ]
+    .into_iter()
+    .filter(|(name, _)| impls.iter().any(|it| it == name))
+    .map(|(_, implementation)| implementation)
+    .collect())
+}
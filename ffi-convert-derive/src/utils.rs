@@ -1,12 +1,185 @@
-pub fn parse_target_type(attrs: &[syn::Attribute]) -> syn::Path {
-    let target_type_attribute = attrs
+use syn::parse::{Parse, ParseBuffer};
+use syn::punctuated::Punctuated;
+
+/// Parses the Rust types the struct is converted from/to, given by `#[target_type(...)]` attributes
+/// (which can be repeated) or a `#[target_types(...)]` attribute listing them.
+pub fn parse_target_types(attrs: &[syn::Attribute]) -> syn::Result<Vec<syn::Path>> {
+    let mut target_types = vec![];
+    for attribute in attrs {
+        if attribute.path.is_ident("target_type") {
+            target_types.push(attribute.parse_args()?);
+        } else if attribute.path.is_ident("target_types") {
+            let paths = attribute
+                .parse_args_with(Punctuated::<syn::Path, syn::Token![,]>::parse_terminated)?;
+            target_types.extend(paths);
+        }
+    }
+
+    if target_types.is_empty() {
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "missing `#[target_type(...)]` attribute, giving the Rust type to convert from/to",
+        ));
+    }
+    Ok(target_types)
+}
+
+/// Checks that the struct has a C layout (`#[repr(C)]` or `#[repr(transparent)]`), as the
+/// conversions of a struct with the Rust layout would silently break the C ABI, unless it has the
+/// `#[allow_non_repr_c]` attribute.
+pub fn check_repr_c(input: &syn::DeriveInput, derive_name: &str) -> syn::Result<()> {
+    let has_c_layout = input.attrs.iter().any(|attribute| {
+        attribute.path.is_ident("repr")
+            && attribute
+                .parse_args_with(Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated)
+                .is_ok_and(|reprs| {
+                    reprs.iter().any(|repr| {
+                        repr.path().is_ident("C") || repr.path().is_ident("transparent")
+                    })
+                })
+    });
+    let is_allowed = input
+        .attrs
+        .iter()
+        .any(|attribute| attribute.path.is_ident("allow_non_repr_c"));
+
+    if has_c_layout || is_allowed {
+        Ok(())
+    } else {
+        Err(syn::Error::new_spanned(
+            &input.ident,
+            format!(
+                "{} can only be derived for `#[repr(C)]` or `#[repr(transparent)]` structs, add \
+                `#[allow_non_repr_c]` if the struct is not passed to C",
+                derive_name
+            ),
+        ))
+    }
+}
+
+/// Parses the first target type of the struct, for the derives supporting a single one.
+pub fn parse_target_type(attrs: &[syn::Attribute]) -> syn::Result<syn::Path> {
+    parse_target_types(attrs).map(|target_types| target_types[0].clone())
+}
+
+/// Generates a local `as_rust` function converting the struct to its target type, declaring the
+/// lifetimes of the target type.
+pub fn as_rust_fn(struct_name: &syn::Ident, target_type: &syn::Path) -> proc_macro2::TokenStream {
+    let lifetimes = target_type_lifetimes(target_type);
+    quote::quote!(
+        fn as_rust<#(#lifetimes),*>(
+            value: &#struct_name,
+        ) -> Result<#target_type, ffi_convert::AsRustError> {
+            ffi_convert::prelude::AsRust::as_rust(value)
+        }
+    )
+}
+
+/// Extracts the lifetimes used as generic arguments of the target type, so that they can be
+/// declared on the generated impl (for instance `'a` for `#[target_type(Foo<'a>)]`).
+pub fn target_type_lifetimes(target_type: &syn::Path) -> Vec<syn::Lifetime> {
+    target_type
+        .segments
         .iter()
-        .find(|attribute| {
-            attribute.path.get_ident().map(|it| it.to_string()) == Some("target_type".into())
+        .filter_map(|segment| match &segment.arguments {
+            syn::PathArguments::AngleBracketed(arguments) => Some(arguments.args.iter()),
+            _ => None,
         })
-        .expect("Can't derive CReprOf without target_type helper attribute.");
+        .flatten()
+        .filter_map(|argument| match argument {
+            syn::GenericArgument::Lifetime(lifetime) => Some(lifetime.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Removes the generic arguments of a path, so that it can be used to build a struct in expression
+/// position (`Foo<'a, T> { .. }` is not valid, `Foo { .. }` is and will infer the arguments).
+pub fn strip_generic_arguments(path: &syn::Path) -> syn::Path {
+    let mut path = path.clone();
+    for segment in path.segments.iter_mut() {
+        segment.arguments = syn::PathArguments::None;
+    }
+    path
+}
+
+/// Brings the traits of `ffi_convert` in scope of the generated functions, so that they don't
+/// depend on the user's imports. The traits are imported anonymously, not to shadow the user's
+/// items.
+pub fn use_prelude_traits() -> proc_macro2::TokenStream {
+    quote::quote!(
+        use ffi_convert::prelude::{
+            AsRust as _, AsRustView as _, CDrop as _, CReprOf as _, CValidate as _, RawBorrow as _,
+            RawBorrowMut as _, RawPointerConverter as _,
+        };
+    )
+}
+
+/// Whether a type is a primitive type (or a nullable function pointer), which owns no resources.
+pub fn is_primitive_type(ty: &syn::Type) -> bool {
+    const PRIMITIVE_TYPES: &[&str] = &[
+        "bool",
+        "char",
+        "f32",
+        "f64",
+        "i8",
+        "i16",
+        "i32",
+        "i64",
+        "i128",
+        "isize",
+        "u8",
+        "u16",
+        "u32",
+        "u64",
+        "u128",
+        "usize",
+        "c_char",
+        "c_schar",
+        "c_uchar",
+        "c_short",
+        "c_ushort",
+        "c_int",
+        "c_uint",
+        "c_long",
+        "c_ulong",
+        "c_longlong",
+        "c_ulonglong",
+        "c_float",
+        "c_double",
+        // nullable function pointers
+        "Option",
+    ];
+
+    match ty {
+        syn::Type::Path(type_path) => {
+            type_path.path.segments.last().is_some_and(|segment| {
+                PRIMITIVE_TYPES.contains(&segment.ident.to_string().as_str())
+            })
+        }
+        _ => false,
+    }
+}
 
-    target_type_attribute.parse_args().unwrap()
+/// Converts a type name to snake case, e.g. `CIpAddr` to `c_ip_addr`.
+pub fn snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut snake_case = String::new();
+    for (index, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && index > 0 {
+            let previous = chars[index - 1];
+            let next_is_lowercase = chars.get(index + 1).is_some_and(|next| next.is_lowercase());
+            // a word starts after a lowercase letter, or at the last capital of an acronym
+            if previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_is_lowercase)
+            {
+                snake_case.push('_');
+            }
+        }
+        snake_case.extend(c.to_lowercase());
+    }
+    snake_case
 }
 
 pub fn parse_no_drop_impl_flag(attrs: &[syn::Attribute]) -> bool {
@@ -15,14 +188,161 @@ pub fn parse_no_drop_impl_flag(attrs: &[syn::Attribute]) -> bool {
     })
 }
 
-pub fn parse_struct_fields(data: &syn::Data) -> Vec<Field> {
-    match &data {
-        syn::Data::Struct(data_struct) => data_struct
-            .fields
+pub fn parse_defensive_drop_flag(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attribute| {
+        attribute.path.get_ident().map(|it| it.to_string()) == Some("defensive_drop".to_string())
+    })
+}
+
+/// Parses the `thread_safe` struct attribute of the `CDrop` derive, asserting that the struct owns
+/// the memory its pointer fields point to.
+pub fn parse_thread_safe_flag(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attribute| {
+        attribute.path.get_ident().map(|it| it.to_string()) == Some("thread_safe".to_string())
+    })
+}
+
+pub fn parse_as_rust_accessors_flag(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attribute| {
+        attribute.path.get_ident().map(|it| it.to_string()) == Some("as_rust_accessors".to_string())
+    })
+}
+
+/// Parses the struct attributes with the given name giving the path of a validation function
+/// (e.g. `#[c_repr_of_validate(path::to_fn)]`), in declaration order.
+pub fn parse_validate_fns(attrs: &[syn::Attribute], name: &str) -> syn::Result<Vec<syn::Path>> {
+    collect_results(
+        attrs
             .iter()
-            .map(parse_field)
-            .collect::<Vec<Field>>(),
-        _ => panic!("CReprOf / AsRust can only be derived for structs"),
+            .filter(|attribute| {
+                attribute.path.get_ident().map(|it| it.to_string()) == Some(name.into())
+            })
+            .map(|attribute| attribute.parse_args()),
+    )
+}
+
+/// Parses the `target_try_getter` attribute, on the struct (for all the fields) or on a field :
+/// the field is read with a getter of the target type returning a `Result`.
+pub fn parse_target_try_getter_flag(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attribute| {
+        attribute.path.get_ident().map(|it| it.to_string()) == Some("target_try_getter".to_string())
+    })
+}
+
+/// Parses the `impls` struct attribute of the derives implementing several traits at once, listing
+/// the traits to implement (among `available`) so that the others can be implemented manually.
+/// All of them are implemented without this attribute.
+///
+/// `#[impls(CReprOf, CDrop)]`
+pub fn parse_impls(attrs: &[syn::Attribute], available: &[&str]) -> syn::Result<Vec<String>> {
+    let mut impls = vec![];
+    let mut has_attribute = false;
+    for attribute in attrs.iter().filter(|attribute| {
+        attribute.path.get_ident().map(|it| it.to_string()) == Some("impls".into())
+    }) {
+        has_attribute = true;
+        for name in
+            attribute.parse_args_with(Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated)?
+        {
+            if !available.contains(&name.to_string().as_str()) {
+                return Err(syn::Error::new_spanned(
+                    &name,
+                    format!(
+                        "this derive can't implement `{}`, expected one of {}",
+                        name,
+                        available.join(", ")
+                    ),
+                ));
+            }
+            impls.push(name.to_string());
+        }
+    }
+    if has_attribute {
+        Ok(impls)
+    } else {
+        Ok(available.iter().map(|it| it.to_string()).collect())
+    }
+}
+
+/// Parses the `string_types` struct attribute, listing additional type names (on top of `c_char`)
+/// that should be considered as C chars, so that pointers to them are handled as strings.
+///
+/// `#[string_types(char_t, gchar)]`
+pub fn parse_string_types(attrs: &[syn::Attribute]) -> syn::Result<Vec<syn::Ident>> {
+    let mut string_types = vec![];
+    for attribute in attrs.iter().filter(|attribute| {
+        attribute.path.get_ident().map(|it| it.to_string()) == Some("string_types".into())
+    }) {
+        string_types.extend(
+            attribute
+                .parse_args_with(Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated)?,
+        );
+    }
+    Ok(string_types)
+}
+
+pub fn parse_struct_fields(input: &syn::DeriveInput) -> syn::Result<Vec<Field<'_>>> {
+    let string_types = parse_string_types(&input.attrs)?;
+
+    let mut fields = match &input.data {
+        syn::Data::Struct(data_struct) => collect_results(
+            data_struct
+                .fields
+                .iter()
+                .map(|field| parse_field(field, &string_types)),
+        )?,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "this trait can only be derived for structs",
+            ))
+        }
+    };
+
+    if let Some(abi_version) = parse_abi_version(&input.attrs)? {
+        // the version field only exists on the C side, where it is set to the version of the layout
+        let version_field = fields
+            .first_mut()
+            .filter(|field| field.name == "version" && *field.ty == syn::parse_quote!(u32))
+            .ok_or_else(|| {
+                syn::Error::new_spanned(
+                    &abi_version,
+                    "a struct with an `#[abi_version(...)]` attribute must start with a \
+                    `version: u32` field",
+                )
+            })?;
+        version_field.skip = Some(syn::parse_quote!(#abi_version));
+    }
+
+    Ok(fields)
+}
+
+/// Parses the version of the layout of the struct, given by the `#[abi_version(...)]` attribute.
+pub fn parse_abi_version(attrs: &[syn::Attribute]) -> syn::Result<Option<syn::LitInt>> {
+    attrs
+        .iter()
+        .find(|attribute| attribute.path.is_ident("abi_version"))
+        .map(|attribute| attribute.parse_args())
+        .transpose()
+}
+
+/// Collects the given results, combining all the errors (so that they are all reported at once)
+/// if there is at least one.
+pub fn collect_results<T>(
+    results: impl IntoIterator<Item = syn::Result<T>>,
+) -> syn::Result<Vec<T>> {
+    let mut values = vec![];
+    let mut error: Option<syn::Error> = None;
+    for result in results {
+        match (result, &mut error) {
+            (Ok(value), _) => values.push(value),
+            (Err(new_error), Some(error)) => error.combine(new_error),
+            (Err(new_error), None) => error = Some(new_error),
+        }
+    }
+    match error {
+        Some(error) => Err(error),
+        None => Ok(values),
     }
 }
 
@@ -35,27 +355,163 @@ pub enum TypeArrayOrTypePath {
 pub struct Field<'a> {
     pub name: &'a syn::Ident,
     pub target_name: syn::Ident,
+    /// The names given with `#[target_name(name, for = Type)]`, by the name of the target type
+    pub scoped_target_names: Vec<(syn::Ident, syn::Ident)>,
+    /// The type of the field, as declared
+    pub ty: &'a syn::Type,
     pub field_type: TypeArrayOrTypePath,
-    pub type_params: Option<syn::AngleBracketedGenericArguments>,
+    pub inner_type: syn::Type,
     pub is_nullable: bool,
     pub is_string: bool,
+    pub is_wide_string: bool,
+    pub string_policy: Option<proc_macro2::TokenStream>,
+    pub is_stringified: bool,
+    pub is_borrowed_string: bool,
     pub is_pointer: bool,
+    pub is_mut_pointer: bool,
     pub c_repr_of_convert: Option<syn::Expr>,
+    pub as_rust_convert: Option<syn::Expr>,
+    pub skip: Option<syn::Expr>,
+    pub target_field_type: Option<syn::Type>,
+    pub atomic_ordering: Option<syn::Ident>,
+    pub is_boxed: bool,
+    pub is_opaque: bool,
+    pub is_callback: bool,
+    pub is_shallow: bool,
+    pub is_try_getter: bool,
     pub levels_of_indirection: u32,
 }
 
-pub fn parse_field(field: &syn::Field) -> Field {
-    let name = field.ident.as_ref().expect("Field should have an ident");
+impl Field<'_> {
+    /// The name of the field in the given target type, which can be scoped to this target with
+    /// `#[target_name(name, for = Type)]`.
+    pub fn target_name_for(&self, target_type: &syn::Path) -> &syn::Ident {
+        let target_type_name = target_type.segments.last().map(|segment| &segment.ident);
+        self.scoped_target_names
+            .iter()
+            .find(|(scope, _)| Some(scope) == target_type_name)
+            .map(|(_, name)| name)
+            .unwrap_or(&self.target_name)
+    }
+
+    /// Returns the owned and borrowed types used to convert a string field : `CString` and `CStr`
+    /// for `c_char` strings, and their UTF-16 counterparts for `#[wide_string]` fields.
+    pub fn string_types(&self) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+        if self.is_wide_string {
+            (
+                quote::quote!(ffi_convert::widestring::U16CString),
+                quote::quote!(ffi_convert::widestring::U16CStr),
+            )
+        } else {
+            (
+                quote::quote!(std::ffi::CString),
+                quote::quote!(std::ffi::CStr),
+            )
+        }
+    }
+
+    /// Generates the expression borrowing the string behind a string field of `self`, checked
+    /// against its `#[string_policy(...)]` if any.
+    pub fn borrow_string_call(&self) -> proc_macro2::TokenStream {
+        let field_name = self.name;
+        match &self.string_policy {
+            Some(policy) => quote::quote!(unsafe { #policy.borrow(self.#field_name) }?),
+            None => {
+                let (_, borrowed_string_type) = self.string_types();
+                quote::quote!(unsafe { #borrowed_string_type::raw_borrow(self.#field_name) }?)
+            }
+        }
+    }
+
+    /// Generates the call reading the number written in a `#[stringify]` field of `self`.
+    pub fn stringified_as_rust_call(&self) -> proc_macro2::TokenStream {
+        let borrowed_string = self.borrow_string_call();
+        match &self.target_field_type {
+            Some(target_field_type) => quote::quote!(
+                <#target_field_type as ffi_convert::CStringifiable>::from_c_str(#borrowed_string)?
+            ),
+            None => quote::quote!(ffi_convert::CStringifiable::from_c_str(#borrowed_string)?),
+        }
+    }
+
+    /// Generates the call converting `value` (an expression of type `c_type`) to its Rust
+    /// representation, with `AsRust` or with `AsRustView` if a view lifetime is given, through the
+    /// `#[target_field_type(...)]` of the field if any.
+    pub fn as_rust_call(
+        &self,
+        c_type: proc_macro2::TokenStream,
+        value: proc_macro2::TokenStream,
+        view_lifetime: Option<&syn::Lifetime>,
+    ) -> proc_macro2::TokenStream {
+        match (&self.target_field_type, view_lifetime) {
+            (Some(target_field_type), None) => {
+                quote::quote!(<#c_type as ffi_convert::prelude::AsRust<#target_field_type>>::as_rust(&#value)?)
+            }
+            (Some(target_field_type), Some(lifetime)) => quote::quote!(
+                <#c_type as ffi_convert::prelude::AsRustView<#lifetime, #target_field_type>>::as_rust_view(&#value)?
+            ),
+            (None, None) => quote::quote!(#value.as_rust()?),
+            (None, Some(_)) => quote::quote!(#value.as_rust_view()?),
+        }
+    }
+
+    /// Generates the call cloning the value behind an `#[opaque]` field, whose type is given by the
+    /// `#[target_field_type(...)]` of the field if any, or inferred from the target struct.
+    pub fn opaque_as_rust_call(&self) -> proc_macro2::TokenStream {
+        let field_name = self.name;
+        match &self.target_field_type {
+            Some(target_field_type) => quote::quote!(
+                unsafe { ffi_convert::opaque_as_rust::<#target_field_type>(self.#field_name) }?
+            ),
+            None => quote::quote!(unsafe { ffi_convert::opaque_as_rust(self.#field_name) }?),
+        }
+    }
+
+    /// Fails if the field is a pointer with several levels of indirection (which can't be
+    /// converted automatically), unless it's nullable.
+    pub fn check_levels_of_indirection(&self) -> syn::Result<()> {
+        if self.levels_of_indirection > 1 && !self.is_nullable {
+            Err(syn::Error::new_spanned(
+                self.name,
+                format!(
+                    "this field is a pointer with too many levels of indirection ({} in this \
+                    case), please implement the traits manually",
+                    self.levels_of_indirection
+                ),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
 
-    let target_name = field
+pub fn parse_field<'a>(
+    field: &'a syn::Field,
+    string_types: &[syn::Ident],
+) -> syn::Result<Field<'a>> {
+    let name = field.ident.as_ref().ok_or_else(|| {
+        syn::Error::new_spanned(
+            field,
+            "tuple structs are not supported, fields must be named",
+        )
+    })?;
+
+    let mut target_name = name.clone();
+    let mut scoped_target_names = vec![];
+    for attribute in field
         .attrs
         .iter()
-        .find(|attr| attr.path.get_ident().map(|it| it.to_string()) == Some("target_name".into()))
-        .map(|attr| {
-            attr.parse_args()
-                .expect("Could not parse attributes of c_repr_of_convert")
-        })
-        .unwrap_or_else(|| name.clone());
+        .filter(|attr| attr.path.is_ident("target_name"))
+    {
+        let TargetNameArgs {
+            name: field_target_name,
+            target_type,
+        } = attribute.parse_args()?;
+        match target_type {
+            Some(target_type) => scoped_target_names.push((target_type, field_target_name)),
+            None => target_name = field_target_name,
+        }
+    }
 
     let mut inner_field_type: syn::Type = field.ty.clone();
     let mut levels_of_indirection: u32 = 0;
@@ -65,11 +521,18 @@ pub fn parse_field(field: &syn::Field) -> Field {
         levels_of_indirection += 1;
     }
 
-    let (field_type, type_params) = match inner_field_type {
-        syn::Type::Path(type_path) => generic_path_to_concrete_type_path(type_path),
-        syn::Type::Array(type_array) => (TypeArrayOrTypePath::TypeArray(type_array), None),
-        _ => panic!("Field type used in this struct is not supported by the proc macro"),
-    };
+    let inner_type = inner_field_type.clone();
+
+    let field_type =
+        match inner_field_type {
+            syn::Type::Path(type_path) => generic_path_to_concrete_type_path(type_path).0,
+            syn::Type::Array(type_array) => TypeArrayOrTypePath::TypeArray(type_array),
+            _ => return Err(syn::Error::new_spanned(
+                &field.ty,
+                "this field type is not supported, expected a type path, an array, or a pointer \
+                to one of them",
+            )),
+        };
 
     let is_nullable = field
         .attrs
@@ -82,18 +545,65 @@ pub fn parse_field(field: &syn::Field) -> Field {
         .find(|attr| {
             attr.path.get_ident().map(|it| it.to_string()) == Some("c_repr_of_convert".into())
         })
+        .map(|attr| attr.parse_args())
+        .transpose()?;
+
+    let as_rust_convert = field
+        .attrs
+        .iter()
+        .find(|attr| {
+            attr.path.get_ident().map(|it| it.to_string()) == Some("as_rust_convert".into())
+        })
+        .map(|attr| attr.parse_args())
+        .transpose()?;
+
+    let skip = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path.get_ident().map(|it| it.to_string()) == Some("skip".into()))
+        .map(|attr| {
+            if attr.tokens.is_empty() {
+                Ok(syn::parse_quote!(Default::default()))
+            } else {
+                attr.parse_args().map(|SkipArgs { default }| default)
+            }
+        })
+        .transpose()?;
+
+    let target_field_type = field
+        .attrs
+        .iter()
+        .find(|attr| {
+            attr.path.get_ident().map(|it| it.to_string()) == Some("target_field_type".into())
+        })
+        .map(|attr| attr.parse_args())
+        .transpose()?;
+
+    let atomic_ordering = field
+        .attrs
+        .iter()
+        .find(|attr| {
+            attr.path.get_ident().map(|it| it.to_string()) == Some("atomic_ordering".into())
+        })
         .map(|attr| {
+            if matches!(field.ty, syn::Type::Ptr(_)) {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "atomic fields must be converted to primitive values, not pointers",
+                ));
+            }
             attr.parse_args()
-                .expect("Could not parse attributes of c_repr_of_convert")
-        });
+        })
+        .transpose()?;
 
     let is_string = match &field.ty {
         syn::Type::Ptr(ptr_t) => {
             match &*ptr_t.elem {
                 syn::Type::Path(path_t) => {
-                    // We are trying to detect the c_char identifier in the last segment
+                    // We are trying to detect the c_char identifier (or one of the
+                    // user-provided string types) in the last segment
                     if let Some(segment) = path_t.path.segments.last() {
-                        &segment.ident.to_string() == "c_char"
+                        segment.ident == "c_char" || string_types.contains(&segment.ident)
                     } else {
                         false
                     }
@@ -104,18 +614,226 @@ pub fn parse_field(field: &syn::Field) -> Field {
         _ => false,
     };
 
+    let is_wide_string = field
+        .attrs
+        .iter()
+        .any(|attr| attr.path.get_ident().map(|it| it.to_string()) == Some("wide_string".into()));
+
+    let string_policy = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path.get_ident().map(|it| it.to_string()) == Some("string_policy".into()))
+        .map(|attr| {
+            if !is_string || is_wide_string {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "a string policy can only be given for `c_char` string fields",
+                ));
+            }
+            attr.parse_args::<StringPolicyArgs>().map(
+                |StringPolicyArgs {
+                     max_len,
+                     reject_control_chars,
+                 }| {
+                    let max_len = match max_len {
+                        Some(max_len) => quote::quote!(Some(#max_len)),
+                        None => quote::quote!(None),
+                    };
+                    quote::quote!(ffi_convert::CStrPolicy {
+                        max_len: #max_len,
+                        reject_control_chars: #reject_control_chars,
+                    })
+                },
+            )
+        })
+        .transpose()?;
+
+    let stringify = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path.get_ident().map(|it| it.to_string()) == Some("stringify".into()));
+    if let (Some(stringify), false) = (stringify, is_string && !is_wide_string) {
+        return Err(syn::Error::new_spanned(
+            stringify,
+            "only `c_char` string fields can hold stringified numbers",
+        ));
+    }
+
+    let borrowed_string = field.attrs.iter().find(|attr| {
+        attr.path.get_ident().map(|it| it.to_string()) == Some("borrowed_string".into())
+    });
+    if let (Some(borrowed_string), false) = (
+        borrowed_string,
+        is_string && !is_wide_string && stringify.is_none(),
+    ) {
+        return Err(syn::Error::new_spanned(
+            borrowed_string,
+            "only `c_char` string fields can borrow their string",
+        ));
+    }
+
     let is_pointer = matches!(&field.ty, syn::Type::Ptr(_));
+    let is_mut_pointer = matches!(&field.ty, syn::Type::Ptr(ptr_t) if ptr_t.mutability.is_some());
+
+    let boxed = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path.get_ident().map(|it| it.to_string()) == Some("boxed".into()));
+    let opaque = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path.get_ident().map(|it| it.to_string()) == Some("opaque".into()));
+    if let (Some(opaque), false) = (opaque, is_pointer && levels_of_indirection == 1) {
+        return Err(syn::Error::new_spanned(
+            opaque,
+            "opaque fields must be `*mut c_void` pointers, use `COpaque<T>` for other fields",
+        ));
+    }
 
-    Field {
+    let callback = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path.get_ident().map(|it| it.to_string()) == Some("callback".into()));
+    if let (Some(callback), true) = (callback, is_pointer) {
+        return Err(syn::Error::new_spanned(
+            callback,
+            "callback fields must be `CCallback` values, not pointers",
+        ));
+    }
+
+    let shallow = field
+        .attrs
+        .iter()
+        .find(|attr| attr.path.get_ident().map(|it| it.to_string()) == Some("shallow".into()));
+    if let (Some(shallow), true) = (shallow, is_pointer) {
+        return Err(syn::Error::new_spanned(
+            shallow,
+            "only value fields can be `shallow`, use `skip` for the pointers not owned by the struct",
+        ));
+    }
+
+    if let (Some(boxed), true) = (boxed, is_string || is_wide_string) {
+        return Err(syn::Error::new_spanned(
+            boxed,
+            "string fields can target a `Box<str>` without the `boxed` attribute",
+        ));
+    }
+
+    Ok(Field {
         name,
         target_name,
+        scoped_target_names,
+        ty: &field.ty,
         field_type,
+        inner_type,
         is_nullable,
-        is_string,
+        is_string: is_string || is_wide_string,
+        is_wide_string,
+        string_policy,
+        is_stringified: stringify.is_some(),
+        is_borrowed_string: borrowed_string.is_some(),
         is_pointer,
+        is_mut_pointer,
         c_repr_of_convert,
+        as_rust_convert,
+        skip,
+        target_field_type,
+        atomic_ordering,
+        is_boxed: boxed.is_some(),
+        is_opaque: opaque.is_some(),
+        is_callback: callback.is_some(),
+        is_shallow: shallow.is_some(),
+        is_try_getter: parse_target_try_getter_flag(&field.attrs),
         levels_of_indirection,
-        type_params,
+    })
+}
+
+/// Arguments of the `string_policy` field attribute :
+/// `#[string_policy(max_len = 64, reject_control_chars)]`, both being optional.
+struct StringPolicyArgs {
+    max_len: Option<syn::LitInt>,
+    reject_control_chars: bool,
+}
+
+impl Parse for StringPolicyArgs {
+    fn parse(input: &ParseBuffer) -> Result<Self, syn::parse::Error> {
+        let mut args = StringPolicyArgs {
+            max_len: None,
+            reject_control_chars: false,
+        };
+        while !input.is_empty() {
+            let key: syn::Ident = input.parse()?;
+            if key == "max_len" {
+                input.parse::<syn::Token![=]>()?;
+                args.max_len = Some(input.parse()?);
+            } else if key == "reject_control_chars" {
+                args.reject_control_chars = true;
+            } else {
+                return Err(syn::parse::Error::new(
+                    key.span(),
+                    "expected `max_len = <integer>` or `reject_control_chars` in string_policy \
+                    attribute",
+                ));
+            }
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// Arguments of the `target_name` field attribute : `#[target_name(name)]`, or
+/// `#[target_name(name, for = Type)]` when the name is only used for one of the target types.
+struct TargetNameArgs {
+    name: syn::Ident,
+    target_type: Option<syn::Ident>,
+}
+
+impl Parse for TargetNameArgs {
+    fn parse(input: &ParseBuffer) -> Result<Self, syn::parse::Error> {
+        let name = input.parse()?;
+        let target_type = if input.is_empty() {
+            None
+        } else {
+            input.parse::<syn::Token![,]>()?;
+            input.parse::<syn::Token![for]>()?;
+            input.parse::<syn::Token![=]>()?;
+            Some(input.parse()?)
+        };
+        Ok(TargetNameArgs { name, target_type })
+    }
+}
+
+/// Arguments of the `skip` field attribute : `#[skip(default = expr)]`.
+///
+/// The expression can also be given as a string literal (`#[skip(default = "std::ptr::null()")]`),
+/// in which case it is parsed from the content of the literal.
+struct SkipArgs {
+    default: syn::Expr,
+}
+
+impl Parse for SkipArgs {
+    fn parse(input: &ParseBuffer) -> Result<Self, syn::parse::Error> {
+        let key: syn::Ident = input.parse()?;
+        if key != "default" {
+            return Err(syn::parse::Error::new(
+                key.span(),
+                "expected `default = <expr>` in skip attribute",
+            ));
+        }
+
+        input.parse::<syn::Token![=]>()?;
+
+        let default = match input.parse()? {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(lit_str),
+                ..
+            }) => lit_str.parse()?,
+            expr => expr,
+        };
+
+        Ok(SkipArgs { default })
     }
 }
 
@@ -210,11 +928,15 @@ mod tests {
     fn test_field_parsing_1() {
         let fields = syn::parse_str::<syn::FieldsNamed>("{ field : *const mod1::CDummy }").unwrap();
 
-        let parsed_fields = fields.named.iter().map(parse_field).collect::<Vec<Field>>();
+        let parsed_fields = fields
+            .named
+            .iter()
+            .map(|f| parse_field(f, &[]).unwrap())
+            .collect::<Vec<Field>>();
 
-        assert_eq!(parsed_fields[0].is_string, false);
-        assert_eq!(parsed_fields[0].is_pointer, true);
-        assert_eq!(parsed_fields[0].is_nullable, false);
+        assert!(!parsed_fields[0].is_string);
+        assert!(parsed_fields[0].is_pointer);
+        assert!(!parsed_fields[0].is_nullable);
 
         if let TypeArrayOrTypePath::TypePath(type_path) = &parsed_fields[0].field_type {
             assert_eq!(type_path.path.segments.len(), 2);
@@ -223,6 +945,158 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_nested_generic_field_parsing() {
+        let fields = syn::parse_str::<syn::FieldsNamed>(
+            "{\
+                field1: *const CArray<CArray<f32>>, \
+                field2: ffi_convert::CArray<ffi_convert::CArray<CDummy>>\
+            }",
+        )
+        .unwrap();
+
+        let parsed_fields = fields
+            .named
+            .iter()
+            .map(|f| parse_field(f, &[]).unwrap())
+            .collect::<Vec<Field>>();
+
+        assert!(parsed_fields[0].is_pointer);
+        assert!(!parsed_fields[1].is_pointer);
+        assert_eq!(
+            parsed_fields[0].field_type,
+            TypeArrayOrTypePath::TypePath(syn::parse_str::<TypePath>("CArray").unwrap())
+        );
+        assert_eq!(
+            parsed_fields[1].field_type,
+            TypeArrayOrTypePath::TypePath(
+                syn::parse_str::<TypePath>("ffi_convert::CArray").unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_target_type_lifetimes_extraction() {
+        let target_type = syn::parse_str::<syn::Path>("mod1::Foo<'a, Bar, 'b>").unwrap();
+
+        let lifetimes = target_type_lifetimes(&target_type);
+
+        assert_eq!(
+            lifetimes,
+            vec![
+                syn::parse_str::<syn::Lifetime>("'a").unwrap(),
+                syn::parse_str::<syn::Lifetime>("'b").unwrap()
+            ]
+        );
+        assert!(target_type_lifetimes(&syn::parse_str("Foo<Bar>").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_string_types_field_parsing() {
+        let fields = syn::parse_str::<syn::FieldsNamed>(
+            "{\
+                field1: *const libc::c_char, \
+                field2: *const sys::char_t, \
+                field3: *const CDummy\
+            }",
+        )
+        .unwrap();
+        let string_types = vec![syn::parse_str::<syn::Ident>("char_t").unwrap()];
+
+        let parsed_fields = fields
+            .named
+            .iter()
+            .map(|f| parse_field(f, &string_types).unwrap())
+            .collect::<Vec<Field>>();
+
+        assert!(parsed_fields[0].is_string);
+        assert!(parsed_fields[1].is_string);
+        assert!(!parsed_fields[2].is_string);
+    }
+
+    #[test]
+    fn test_wide_string_field_parsing() {
+        let fields = syn::parse_str::<syn::FieldsNamed>(
+            "{\
+                #[wide_string] field1: *const u16, \
+                field2: *const u16, \
+                field3: *const libc::c_char\
+            }",
+        )
+        .unwrap();
+
+        let parsed_fields = fields
+            .named
+            .iter()
+            .map(|f| parse_field(f, &[]).unwrap())
+            .collect::<Vec<Field>>();
+
+        assert!(parsed_fields[0].is_string);
+        assert!(parsed_fields[0].is_wide_string);
+        assert!(!parsed_fields[1].is_string);
+        assert!(!parsed_fields[1].is_wide_string);
+        assert!(parsed_fields[2].is_string);
+        assert!(!parsed_fields[2].is_wide_string);
+    }
+
+    #[test]
+    fn test_skip_field_parsing() {
+        let fields = syn::parse_str::<syn::FieldsNamed>(
+            "{\
+                #[skip] field1: [u8; 4], \
+                #[skip(default = std::ptr::null())] field2: *const CDummy, \
+                #[skip(default = \"std::ptr::null()\")] field3: *const CDummy, \
+                field4: *const CDummy\
+            }",
+        )
+        .unwrap();
+
+        let parsed_fields = fields
+            .named
+            .iter()
+            .map(|f| parse_field(f, &[]).unwrap())
+            .collect::<Vec<Field>>();
+
+        let default: syn::Expr = syn::parse_quote!(Default::default());
+        let null: syn::Expr = syn::parse_quote!(std::ptr::null());
+
+        assert_eq!(parsed_fields[0].skip, Some(default));
+        assert_eq!(parsed_fields[1].skip, Some(null.clone()));
+        assert_eq!(parsed_fields[2].skip, Some(null));
+        assert!(parsed_fields[3].skip.is_none());
+    }
+
+    #[test]
+    fn test_snake_case() {
+        assert_eq!(snake_case("CPizza"), "c_pizza");
+        assert_eq!(snake_case("CIpAddr"), "c_ip_addr");
+        assert_eq!(snake_case("HTTPServer"), "http_server");
+        assert_eq!(snake_case("CUuid"), "c_uuid");
+        assert_eq!(snake_case("pizza"), "pizza");
+    }
+
+    #[test]
+    fn test_mut_pointer_field_parsing() {
+        let fields = syn::parse_str::<syn::FieldsNamed>(
+            "{\
+                field1: *mut CDummy, \
+                field2: *const *mut CDummy, \
+                field3: CDummy\
+            }",
+        )
+        .unwrap();
+
+        let parsed_fields = fields
+            .named
+            .iter()
+            .map(|f| parse_field(f, &[]).unwrap())
+            .collect::<Vec<Field>>();
+
+        assert!(parsed_fields[0].is_mut_pointer);
+        assert!(!parsed_fields[1].is_mut_pointer);
+        assert!(!parsed_fields[2].is_mut_pointer);
+    }
+
     #[test]
     fn test_field_parsing_2() {
         let fields = syn::parse_str::<syn::FieldsNamed>(
@@ -236,17 +1110,14 @@ mod tests {
         let parsed_fields = fields
             .named
             .iter()
-            .map(|f| {
-                println!("f : {:?}", f);
-                f
-            })
-            .map(parse_field)
+            .inspect(|f| println!("f : {:?}", f))
+            .map(|f| parse_field(f, &[]).unwrap())
             .collect::<Vec<Field>>();
 
-        assert_eq!(parsed_fields[0].is_pointer, true);
-        assert_eq!(parsed_fields[1].is_pointer, true);
-        assert_eq!(parsed_fields[0].is_string, false);
-        assert_eq!(parsed_fields[1].is_string, false);
+        assert!(parsed_fields[0].is_pointer);
+        assert!(parsed_fields[1].is_pointer);
+        assert!(!parsed_fields[0].is_string);
+        assert!(!parsed_fields[1].is_string);
 
         let field_type0 =
             if let TypeArrayOrTypePath::TypePath(type_path) = &parsed_fields[0].field_type {
@@ -281,17 +1152,14 @@ mod tests {
         let parsed_fields = fields
             .named
             .iter()
-            .map(|f| {
-                println!("f : {:?}", f);
-                f
-            })
-            .map(parse_field)
+            .inspect(|f| println!("f : {:?}", f))
+            .map(|f| parse_field(f, &[]).unwrap())
             .collect::<Vec<Field>>();
 
-        assert_eq!(parsed_fields[0].is_pointer, true);
-        assert_eq!(parsed_fields[1].is_pointer, true);
-        assert_eq!(parsed_fields[0].is_string, false);
-        assert_eq!(parsed_fields[1].is_string, false);
+        assert!(parsed_fields[0].is_pointer);
+        assert!(parsed_fields[1].is_pointer);
+        assert!(!parsed_fields[0].is_string);
+        assert!(!parsed_fields[1].is_string);
 
         let field_type0 =
             if let TypeArrayOrTypePath::TypePath(type_path) = &parsed_fields[0].field_type {
@@ -1,6 +1,13 @@
 use anyhow::{bail, Result};
 use ffi_convert::*;
+use std::borrow::Cow;
+use std::ffi::{CStr, OsString};
+use std::num::{NonZeroU64, NonZeroU8};
 use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[macro_export]
 macro_rules! generate_round_trip_rust_c_rust {
@@ -28,9 +35,174 @@ where
         bail!("The value is not the same before and after the roundtrip");
     }
 
+    #[cfg(feature = "ub-checks")]
+    release_as_c_code(intermediate, &value)?;
+
+    Ok(())
+}
+
+/// Reads a C value through a pointer and releases it with `do_drop`, as C code owning it does, so
+/// that Miri or AddressSanitizer check these paths too : rust doesn't drop it afterwards, a leak
+/// means that `do_drop` doesn't release everything the value owns.
+#[cfg(feature = "ub-checks")]
+fn release_as_c_code<T, U>(value: T, expected: &U) -> Result<()>
+where
+    T: AsRust<U> + CDrop,
+    U: PartialEq,
+{
+    let pointer = Box::into_raw(Box::new(std::mem::ManuallyDrop::new(value)));
+    unsafe {
+        if (**pointer).as_rust()? != *expected {
+            bail!("The value is not the same when read through a pointer");
+        }
+        (**pointer).do_drop()?;
+        // the allocation is freed, not the value which was released
+        drop(Box::from_raw(pointer));
+    }
+    Ok(())
+}
+
+#[macro_export]
+macro_rules! generate_round_trip_c_rust_c {
+    ($func_name:ident, $rust_struct:ty, $c_struct:ty, $builder:block) => {
+        #[test]
+        fn $func_name() {
+            use $crate::round_trip_test_c_rust_c;
+            let item = $builder;
+            round_trip_test_c_rust_c::<$c_struct, $rust_struct>(item)
+                .expect("Round trip test failed!");
+        }
+    };
+}
+
+/// Converts a C value built by C code (with raw allocations) to Rust and back, checks that the
+/// two C trees are structurally equal, and releases the C value with `do_drop` as C code does.
+pub fn round_trip_test_c_rust_c<T, U>(value: T) -> Result<()>
+where
+    T: AsRust<U> + CReprOf<U> + CDeepEq,
+{
+    // rust doesn't drop the value, which is released by `do_drop` only
+    let mut value = std::mem::ManuallyDrop::new(value);
+    let intermediate: U = value.as_rust()?;
+    let value_roundtrip: T = T::c_repr_of(intermediate)?;
+
+    if !value.deep_eq(&value_roundtrip) {
+        bail!("The C value is not the same before and after the roundtrip");
+    }
+
+    value.do_drop()?;
+    Ok(())
+}
+
+#[macro_export]
+macro_rules! generate_deterministic_rust_c {
+    ($func_name:ident, $rust_struct:ty, $c_struct:ty, $builder:block) => {
+        #[test]
+        fn $func_name() {
+            use $crate::deterministic_test_rust_c;
+            let item = $builder;
+            deterministic_test_rust_c::<$c_struct, $rust_struct>(item)
+                .expect("Deterministic conversion test failed!");
+        }
+    };
+}
+
+/// Converts two equal values to their C representation, and checks that the two C trees are
+/// structurally equal : C consumers comparing snapshots of the converted values rely on it.
+pub fn deterministic_test_rust_c<T, U>(value: U) -> Result<()>
+where
+    T: CReprOf<U> + CDeepEq,
+    U: Clone,
+{
+    let first = T::c_repr_of(value.clone())?;
+    let second = T::c_repr_of(value)?;
+
+    if !first.deep_eq(&second) {
+        bail!("The two C representations of the same value are not the same");
+    }
+
     Ok(())
 }
 
+/// Structural equality of C values : the values are compared bit for bit, except for pointers
+/// which are compared by the values they point to (as two conversions allocate at different
+/// addresses).
+pub trait CDeepEq {
+    fn deep_eq(&self, other: &Self) -> bool;
+}
+
+macro_rules! impl_c_deep_eq_bitwise {
+    ($($typ:ty),*) => {
+        $(
+            impl CDeepEq for $typ {
+                fn deep_eq(&self, other: &Self) -> bool {
+                    self == other
+                }
+            }
+        )*
+    };
+}
+
+impl_c_deep_eq_bitwise!(bool, u8, i32, i64, usize);
+
+impl CDeepEq for f32 {
+    fn deep_eq(&self, other: &Self) -> bool {
+        // unlike ==, NaN is equal to itself and 0.0 differs from -0.0
+        self.to_bits() == other.to_bits()
+    }
+}
+
+impl<T: CDeepEq, const N: usize> CDeepEq for [T; N] {
+    fn deep_eq(&self, other: &Self) -> bool {
+        self.iter().zip(other).all(|(a, b)| a.deep_eq(b))
+    }
+}
+
+impl<T: CDeepEq> CDeepEq for CRange<T> {
+    fn deep_eq(&self, other: &Self) -> bool {
+        self.start.deep_eq(&other.start) && self.end.deep_eq(&other.end)
+    }
+}
+
+impl<T: CDeepEq> CDeepEq for COption<T> {
+    fn deep_eq(&self, other: &Self) -> bool {
+        // the value of a none option is unspecified
+        self.is_some == other.is_some && (self.is_some == 0 || self.value.deep_eq(&other.value))
+    }
+}
+
+impl<T: CDeepEq> CDeepEq for CArray<T> {
+    fn deep_eq(&self, other: &Self) -> bool {
+        self.size == other.size
+            && (0..self.size)
+                .all(|i| unsafe { (*self.data_ptr.add(i)).deep_eq(&*other.data_ptr.add(i)) })
+    }
+}
+
+impl CDeepEq for CStringArray {
+    fn deep_eq(&self, other: &Self) -> bool {
+        self.size == other.size
+            && (0..self.size)
+                .all(|i| unsafe { c_str_deep_eq(*self.data.add(i), *other.data.add(i)) })
+    }
+}
+
+/// Compares the values pointed by two (possibly null) pointers.
+fn ptr_deep_eq<T: CDeepEq>(a: *const T, b: *const T) -> bool {
+    match (a.is_null(), b.is_null()) {
+        (false, false) => unsafe { (*a).deep_eq(&*b) },
+        (a_is_null, b_is_null) => a_is_null == b_is_null,
+    }
+}
+
+/// Compares two (possibly null) C strings.
+fn c_str_deep_eq(a: *const libc::c_char, b: *const libc::c_char) -> bool {
+    match (a.is_null(), b.is_null()) {
+        (false, false) => unsafe { std::ffi::CStr::from_ptr(a) == std::ffi::CStr::from_ptr(b) },
+        (a_is_null, b_is_null) => a_is_null == b_is_null,
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Pancake {
     pub name: String,
@@ -49,11 +221,14 @@ pub struct Pancake {
     pub flattened_range: Range<i64>,
     pub field_with_specific_rust_name: String,
     pub pancake_data: Option<Vec<u8>>,
+    pub is_vegan: bool,
+    pub cooking_time: Option<f32>,
 }
 
 #[repr(C)]
-#[derive(CReprOf, AsRust, CDrop, RawPointerConverter)]
+#[derive(CReprOf, AsRust, CDrop, CValidate, CSchema, CDebug, CPartialEq, RawPointerConverter)]
 #[target_type(Pancake)]
+#[as_rust_accessors]
 #[as_rust_extra_field(some_futile_info = None)]
 #[as_rust_extra_field(flattened_range = self.flattened_range_start..self.flattened_range_end)]
 pub struct CPancake {
@@ -81,6 +256,15 @@ pub struct CPancake {
     pub field_with_specific_c_name: *const libc::c_char,
     #[nullable]
     pancake_data: *const CArray<u8>,
+    #[target_name(is_vegan)]
+    #[c_repr_of_convert(input.is_vegan as u8)]
+    #[as_rust_convert(self.flags & 1 != 0)]
+    flags: u8,
+    cooking_time: COption<f32>,
+    #[skip(default = std::ptr::null_mut())]
+    user_data: *mut libc::c_void,
+    #[skip]
+    reserved: [u8; 4],
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -89,24 +273,70 @@ pub struct Sauce {
 }
 
 #[repr(C)]
-#[derive(CReprOf, AsRust, CDrop, RawPointerConverter)]
+#[derive(CReprOf, AsRust, CDrop, CValidate, CSchema, RawPointerConverter)]
 #[target_type(Sauce)]
 pub struct CSauce {
     volume: f32,
 }
 
+impl CDeepEq for CPancake {
+    fn deep_eq(&self, other: &Self) -> bool {
+        c_str_deep_eq(self.name, other.name)
+            && c_str_deep_eq(self.description, other.description)
+            && self.start.deep_eq(&other.start)
+            && ptr_deep_eq(self.end, other.end)
+            && self.float_array.deep_eq(&other.float_array)
+            && self.dummy.deep_eq(&other.dummy)
+            && ptr_deep_eq(self.sauce, other.sauce)
+            && ptr_deep_eq(self.toppings, other.toppings)
+            && ptr_deep_eq(self.layers, other.layers)
+            && self.base_layers.deep_eq(&other.base_layers)
+            && self.is_delicious.deep_eq(&other.is_delicious)
+            && self.range.deep_eq(&other.range)
+            && self
+                .flattened_range_start
+                .deep_eq(&other.flattened_range_start)
+            && self.flattened_range_end.deep_eq(&other.flattened_range_end)
+            && c_str_deep_eq(
+                self.field_with_specific_c_name,
+                other.field_with_specific_c_name,
+            )
+            && ptr_deep_eq(self.pancake_data, other.pancake_data)
+            && self.flags.deep_eq(&other.flags)
+            && self.cooking_time.deep_eq(&other.cooking_time)
+            && self.user_data == other.user_data
+            && self.reserved.deep_eq(&other.reserved)
+    }
+}
+
+impl CDeepEq for CSauce {
+    fn deep_eq(&self, other: &Self) -> bool {
+        self.volume.deep_eq(&other.volume)
+    }
+}
+
+export_schema_hash!(CPancake, ffi_convert_tests_pancake_schema_hash);
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Topping {
     pub amount: i32,
 }
 
 #[repr(C)]
-#[derive(CReprOf, AsRust, CDrop, RawPointerConverter)]
+#[derive(CReprOf, AsRust, CDrop, CValidate, CSchema, RawPointerConverter)]
 #[target_type(Topping)]
 pub struct CTopping {
     amount: i32,
 }
 
+declare_c_array!(CToppingArray, CTopping);
+
+impl CDeepEq for CTopping {
+    fn deep_eq(&self, other: &Self) -> bool {
+        self.amount.deep_eq(&other.amount)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Layer {
     pub number: i32,
@@ -114,7 +344,7 @@ pub struct Layer {
 }
 
 #[repr(C)]
-#[derive(CReprOf, AsRust, CDrop, RawPointerConverter)]
+#[derive(CReprOf, AsRust, CDrop, CValidate, CDebug, CPartialEq, RawPointerConverter)]
 #[target_type(Layer)]
 pub struct CLayer {
     number: i32,
@@ -122,6 +352,12 @@ pub struct CLayer {
     subtitle: *const libc::c_char,
 }
 
+impl CDeepEq for CLayer {
+    fn deep_eq(&self, other: &Self) -> bool {
+        self.number.deep_eq(&other.number) && c_str_deep_eq(self.subtitle, other.subtitle)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Dummy {
     pub count: i32,
@@ -129,114 +365,5015 @@ pub struct Dummy {
 }
 
 #[repr(C)]
-#[derive(CReprOf, AsRust, CDrop, RawPointerConverter)]
+#[derive(CReprOf, AsRust, CDrop, CValidate, RawPointerConverter)]
 #[target_type(Dummy)]
 pub struct CDummy {
     count: i32,
     describe: *const libc::c_char,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl CDeepEq for CDummy {
+    fn deep_eq(&self, other: &Self) -> bool {
+        self.count.deep_eq(&other.count) && c_str_deep_eq(self.describe, other.describe)
+    }
+}
 
-    generate_round_trip_rust_c_rust!(round_trip_sauce, Sauce, CSauce, { Sauce { volume: 4.2 } });
+#[derive(Clone, Debug, PartialEq)]
+pub struct Setlist {
+    pub durations: Option<Vec<f32>>,
+}
 
-    generate_round_trip_rust_c_rust!(round_trip_topping, Topping, CTopping, {
-        Topping { amount: 2 }
-    });
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Setlist)]
+pub struct CSetlist {
+    durations: COption<CArray<f32>>,
+}
 
-    generate_round_trip_rust_c_rust!(round_trip_dummy, Dummy, CDummy, {
-        Dummy {
-            count: 2,
-            describe: "yo".to_string(),
+pub mod sys {
+    #[allow(non_camel_case_types)]
+    pub type char_t = libc::c_char;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Label {
+    pub text: String,
+    pub tooltip: Option<String>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, CValidate, RawPointerConverter)]
+#[target_type(Label)]
+#[string_types(char_t)]
+#[generate_destructor]
+pub struct CLabel {
+    text: *const sys::char_t,
+    #[nullable]
+    tooltip: *const sys::char_t,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, CValidate, RawPointerConverter)]
+#[target_type(Label)]
+#[as_rust_accessors]
+pub struct CWideLabel {
+    #[wide_string]
+    text: *const u16,
+    #[nullable]
+    #[wide_string]
+    tooltip: *const u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Document {
+    pub path: PathBuf,
+    pub name: OsString,
+    pub backup_path: Option<PathBuf>,
+}
+
+pub struct DocumentView<'a> {
+    pub path: &'a Path,
+    pub name: OsString,
+    pub backup_path: Option<&'a Path>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Document)]
+pub struct CDocument {
+    path: *const libc::c_char,
+    name: *const libc::c_char,
+    #[nullable]
+    backup_path: *const libc::c_char,
+}
+
+impl<'a> AsRustView<'a, DocumentView<'a>> for CDocument {
+    fn as_rust_view(&'a self) -> Result<DocumentView<'a>, AsRustError> {
+        Ok(DocumentView {
+            path: unsafe { std::ffi::CStr::raw_borrow(self.path) }?.as_rust_view()?,
+            name: unsafe { std::ffi::CStr::raw_borrow(self.name) }?.as_rust()?,
+            backup_path: if self.backup_path.is_null() {
+                None
+            } else {
+                Some(unsafe { std::ffi::CStr::raw_borrow(self.backup_path) }?.as_rust_view()?)
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NetworkInterface {
+    pub mac: [u8; 6],
+    pub netmasks: [[u8; 4]; 2],
+    pub labels: [Label; 2],
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, CValidate)]
+#[target_type(NetworkInterface)]
+pub struct CNetworkInterface {
+    mac: [u8; 6],
+    netmasks: [[u8; 4]; 2],
+    labels: [CLabel; 2],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Samples<'a> {
+    pub rate: u32,
+    pub values: &'a [f32],
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRustView, CDrop)]
+#[target_type(Samples<'a>)]
+pub struct CSamples {
+    rate: u32,
+    values: CSliceView<f32>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MenuView<'a> {
+    pub title: &'a str,
+    pub subtitle: Option<Cow<'a, str>>,
+    pub prices: &'a [f32],
+    pub dummy: Dummy,
+}
+
+#[repr(C)]
+#[derive(AsRustView, CDrop)]
+#[target_type(MenuView<'a>)]
+pub struct CMenu {
+    title: *const libc::c_char,
+    #[nullable]
+    subtitle: *const libc::c_char,
+    prices: CArray<f32>,
+    dummy: CDummy,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Order {
+    pub id: uuid::Uuid,
+    pub related_orders: Vec<uuid::Uuid>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, CValidate)]
+#[target_type(Order)]
+pub struct COrder {
+    id: CUuid,
+    related_orders: *const CArray<CUuid>,
+}
+
+/// A strongly typed id, used as is on the C side
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CReprOfTransparent)]
+pub struct CustomerId(pub u64);
+
+/// A percentage, whose `AsRust` implementation checks the value coming from C
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CReprOfTransparent)]
+#[impls(CReprOf, CDrop)]
+pub struct Percentage(pub u8);
+
+impl AsRust<Percentage> for Percentage {
+    fn as_rust(&self) -> Result<Percentage, AsRustError> {
+        if self.0 > 100 {
+            return Err(AsRustError::Other("a percentage can't exceed 100".into()));
         }
-    });
+        Ok(*self)
+    }
+}
 
-    generate_round_trip_rust_c_rust!(round_trip_layer, Layer, CLayer, {
-        Layer {
-            number: 1,
-            subtitle: Some(String::from("first layer")),
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderId(pub uuid::Uuid);
+
+#[repr(transparent)]
+#[derive(CReprOfTransparent, RawPointerConverter)]
+#[target_type(OrderId)]
+pub struct COrderId(CUuid);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Customer {
+    pub id: CustomerId,
+    pub name: String,
+    pub last_order: Option<OrderId>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Customer)]
+pub struct CCustomer {
+    id: CustomerId,
+    name: *const libc::c_char,
+    #[nullable]
+    last_order: *const COrderId,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flavor {
+    Plain,
+    Sugar,
+    Chocolate,
+}
+
+impl CReprOf<Flavor> for i32 {
+    fn c_repr_of(input: Flavor) -> Result<Self, CReprOfError> {
+        Ok(input as i32)
+    }
+}
+
+impl AsRust<Flavor> for i32 {
+    fn as_rust(&self) -> Result<Flavor, AsRustError> {
+        match self {
+            0 => Ok(Flavor::Plain),
+            1 => Ok(Flavor::Sugar),
+            2 => Ok(Flavor::Chocolate),
+            _ => Err(AsRustError::Other(
+                format!("invalid flavor {}", self).into(),
+            )),
         }
-    });
+    }
+}
 
-    generate_round_trip_rust_c_rust!(round_trip_pancake, Pancake, CPancake, {
-        Pancake {
-            name: String::from("Here is your pancake"),
-            description: Some("I'm delicious ! ".to_string()),
-            start: 0.0,
-            end: Some(2.0),
-            float_array: [1.0, 2.0, 3.0, 4.0],
-            dummy: Dummy {
-                count: 2,
-                describe: "yo".to_string(),
-            },
-            sauce: Some(Sauce { volume: 32.23 }),
-            toppings: vec![Topping { amount: 2 }, Topping { amount: 3 }],
-            layers: Some(vec![Layer {
-                number: 1,
-                subtitle: Some(String::from("first layer")),
-            }]),
-            base_layers: [
-                Layer {
-                    number: 0,
-                    subtitle: Some(String::from("flour")),
-                },
-                Layer {
-                    number: 1,
-                    subtitle: Some(String::from("dough")),
-                },
-                Layer {
-                    number: 2,
-                    subtitle: Some(String::from("tomato")),
-                },
-            ],
-            is_delicious: true,
-            range: Range { start: 20, end: 30 },
-            some_futile_info: None,
-            flattened_range: Range { start: 42, end: 64 },
-            field_with_specific_rust_name: "renamed field".to_string(),
-            pancake_data: Some(vec![1, 2, 3]),
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Crepe {
+    pub flavor: Flavor,
+    pub weight: usize,
+    pub diameter: Option<usize>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[as_rust_accessors]
+#[target_type(Crepe)]
+pub struct CCrepe {
+    #[target_field_type(Flavor)]
+    flavor: i32,
+    #[target_field_type(usize)]
+    weight: u64,
+    #[nullable]
+    #[target_field_type(usize)]
+    diameter: *const u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Session {
+    pub id: NonZeroU64,
+    pub parent_id: Option<NonZeroU64>,
+    pub retries: Option<NonZeroU8>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Session)]
+pub struct CSession {
+    id: u64,
+    parent_id: u64,
+    retries: u8,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, CVTable)]
+pub struct CKitchenVTable {
+    pub cook: Option<extern "C" fn(pancakes: u32) -> u32>,
+    #[nullable]
+    pub clean: Option<extern "C" fn()>,
+    pub log: extern "C" fn(level: i32),
+}
+
+/// A vtable whose entries must all be set, or all be null
+#[repr(C)]
+#[derive(Clone, Copy, Debug, CVTable)]
+#[impls(CReprOf, AsRust, CDrop)]
+pub struct CTimerVTable {
+    pub start: Option<extern "C" fn()>,
+    pub stop: Option<extern "C" fn()>,
+}
+
+impl CValidate for CTimerVTable {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        if self.start.is_some() != self.stop.is_some() {
+            return Err(AsRustError::Other(
+                "a timer can't be only started or stopped".into(),
+            ));
         }
-    });
+        Ok(())
+    }
+}
 
-    generate_round_trip_rust_c_rust!(round_trip_pancake_2, Pancake, CPancake, {
-        Pancake {
-            name: String::from("Here is your pancake"),
-            description: Some("I'm delicious ! ".to_string()),
+#[derive(Clone, Debug)]
+pub struct Kitchen {
+    pub name: String,
+    pub vtable: CKitchenVTable,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, CValidate)]
+#[target_type(Kitchen)]
+pub struct CKitchen {
+    name: *const libc::c_char,
+    vtable: CKitchenVTable,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shelf {
+    pub labels: Vec<String>,
+    pub tags: Option<Vec<String>>,
+    pub slots: Option<Range<i32>>,
+    pub spare: Option<f32>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, CValidate)]
+#[target_type(Shelf)]
+#[as_rust_accessors]
+pub struct CShelf {
+    labels: *const CStringArray,
+    #[nullable]
+    tags: *const CStringArray,
+    #[nullable]
+    slots: *const CRange<i32>,
+    spare: *const COption<f32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecipeStep {
+    pub label: Box<str>,
+    pub note: Option<Box<str>>,
+    pub duration: Box<Duration>,
+    pub then: Option<Box<RecipeStep>>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, RawPointerConverter)]
+#[target_type(RecipeStep)]
+#[as_rust_accessors]
+pub struct CRecipeStep {
+    label: *const libc::c_char,
+    #[nullable]
+    note: *const libc::c_char,
+    #[boxed]
+    duration: CDuration,
+    #[nullable]
+    #[boxed]
+    then: *const CRecipeStep,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Excerpt {
+    pub lines: Range<i32>,
+    pub bytes: Range<usize>,
+    pub highlight: Option<Range<usize>>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, CValidate)]
+#[target_type(Excerpt)]
+pub struct CExcerpt {
+    lines: CRange<i32>,
+    bytes: CSpan<u32>,
+    #[nullable]
+    highlight: *const CSpan,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Model {
+    pub weights: Vec<f32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inference {
+    pub model: Arc<Model>,
+    pub input: Vec<f32>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Inference)]
+pub struct CInference {
+    model: CArc<Model>,
+    input: CArray<f32>,
+}
+
+export_c_arc!(Model, ffi_model_clone, ffi_model_drop);
+
+export_allocator!(ffi_convert_alloc, ffi_convert_free);
+
+export_arena!(ffi_convert_arena_create, ffi_convert_arena_destroy);
+
+/// Collects the messages it receives, compared by identity
+#[derive(Clone, Debug, Default)]
+pub struct Listener {
+    pub received: Arc<Mutex<Vec<String>>>,
+}
+
+impl PartialEq for Listener {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.received, &other.received)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subscription {
+    pub topic: String,
+    pub listener: Listener,
+    pub model: Arc<Model>,
+    pub fallback: Option<Listener>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, CValidate)]
+#[target_type(Subscription)]
+#[as_rust_accessors]
+pub struct CSubscription {
+    topic: *const libc::c_char,
+    listener: COpaque<Listener>,
+    #[opaque]
+    model: *mut libc::c_void,
+    #[nullable]
+    #[opaque]
+    fallback: *const libc::c_void,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sensor {
+    pub name: String,
+    pub unit: Option<String>,
+    pub reading: f32,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, CValidate, RawPointerConverter)]
+#[target_type(Sensor)]
+pub struct CSensor {
+    name: CFixedString<16>,
+    #[nullable]
+    unit: *const CFixedString<8>,
+    reading: f32,
+}
+
+pub mod accounts {
+    /// A validated account, whose fields can only be read with getters
+    pub struct Account {
+        pub tags: Vec<String>,
+        name: String,
+        email: Option<String>,
+        closed: bool,
+    }
+
+    impl Account {
+        pub fn new(tags: Vec<String>, name: &str, email: Option<&str>) -> Result<Self, String> {
+            if name.is_empty() {
+                return Err("the name of an account can't be empty".to_string());
+            }
+            Ok(Self {
+                tags,
+                name: name.to_string(),
+                email: email.map(str::to_string),
+                closed: false,
+            })
+        }
+
+        pub fn close(&mut self) {
+            self.closed = true;
+        }
+
+        pub fn name(&self) -> Result<&str, &'static str> {
+            Ok(&self.name)
+        }
+
+        pub fn email(&self) -> Result<Option<&str>, &'static str> {
+            if self.closed {
+                return Err("the email of a closed account is not available");
+            }
+            Ok(self.email.as_deref())
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(CReprOf, CDrop)]
+#[target_type(accounts::Account)]
+pub struct CAccount {
+    tags: *const CStringArray,
+    #[target_try_getter]
+    name: *const libc::c_char,
+    #[target_try_getter]
+    #[nullable]
+    email: *const libc::c_char,
+}
+
+/// Only the validated fields of an account, all read with their getters
+#[repr(C)]
+#[derive(CReprOf, CDrop)]
+#[target_type(accounts::Account)]
+#[target_try_getter]
+pub struct CAccountContact {
+    name: *const libc::c_char,
+    #[nullable]
+    email: *const libc::c_char,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Booking {
+    pub guest: String,
+    pub nights: Range<i32>,
+}
+
+pub mod bookings {
+    pub fn check_guest(booking: &super::Booking) -> Result<(), String> {
+        if booking.guest.is_empty() {
+            return Err("a booking needs a guest".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn check_nights(booking: &super::Booking) -> Result<(), &'static str> {
+        if booking.nights.is_empty() {
+            return Err("a booking needs at least a night");
+        }
+        Ok(())
+    }
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Booking)]
+#[c_repr_of_validate(bookings::check_guest)]
+#[c_repr_of_validate(bookings::check_nights)]
+#[as_rust_validate(bookings::check_nights)]
+pub struct CBooking {
+    guest: *const libc::c_char,
+    nights: CRange<i32>,
+}
+
+pub struct Button {
+    pub label: String,
+    pub on_click: Box<dyn Fn(u32) -> bool>,
+    pub on_hover: Option<Box<dyn Fn()>>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, CValidate)]
+#[target_type(Button)]
+pub struct CButton {
+    label: *const libc::c_char,
+    on_click: CCallback<(u32,), bool>,
+    #[callback]
+    #[nullable]
+    on_hover: CCallback<()>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transcript {
+    pub code_points: Vec<char>,
+    pub raw_text: String,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Transcript)]
+pub struct CTranscript {
+    code_points: CArray<u32>,
+    raw_text: CByteArray,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ShelfLabelsView<'a> {
+    pub labels: Vec<&'a str>,
+    pub tags: Option<Vec<&'a str>>,
+}
+
+#[repr(C)]
+#[derive(AsRustView, CDrop)]
+#[target_type(ShelfLabelsView<'a>)]
+pub struct CShelfLabels {
+    labels: *const CStringArray,
+    #[nullable]
+    tags: *const CStringArray,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vector {
+    pub lanes: [f32; 8],
+}
+
+/// An over-aligned struct, as used for SIMD data
+#[repr(C, align(32))]
+#[derive(CReprOf, AsRust, CDrop, CSchema, RawPointerConverter)]
+#[target_type(Vector)]
+pub struct CVector {
+    lanes: [f32; 8],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trajectory {
+    pub origin: Vector,
+    pub points: Vec<Vector>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Trajectory)]
+pub struct CTrajectory {
+    origin: *const CVector,
+    points: *const CArray<CVector>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recording {
+    pub frames: Vec<Vec<f32>>,
+    pub labels: Vec<Vec<String>>,
+    pub markers: Option<Vec<Vec<Range<i32>>>>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, CValidate)]
+#[target_type(Recording)]
+#[as_rust_accessors]
+pub struct CRecording {
+    frames: CArray<CArray<f32>>,
+    labels: *const CArray<CStringArray>,
+    #[nullable]
+    markers: *const CArray<CArray<CRange<i32>>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recipe {
+    pub base: Arc<Sauce>,
+    pub topping: Arc<Sauce>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Recipe)]
+pub struct CRecipe {
+    base: CShared<CSauce>,
+    topping: CShared<CSauce>,
+}
+
+#[derive(Debug)]
+pub struct Node {
+    pub value: i32,
+    pub next: Mutex<Option<Arc<Node>>>,
+}
+
+impl Clone for Node {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value,
+            next: Mutex::new(self.next.lock().unwrap().clone()),
+        }
+    }
+}
+
+#[repr(C)]
+pub struct CNode {
+    value: i32,
+    /// pointer owned by a `CShared<CNode>`
+    next: *const CNode,
+}
+
+impl CReprOf<Node> for CNode {
+    fn c_repr_of(input: Node) -> Result<Self, CReprOfError> {
+        let next = match input.next.lock().unwrap().clone() {
+            Some(next) => std::mem::ManuallyDrop::new(CShared::c_repr_of(next)?).ptr,
+            None => std::ptr::null(),
+        };
+        Ok(Self {
+            value: input.value,
+            next,
+        })
+    }
+}
+
+impl CDrop for CNode {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        if !self.next.is_null() {
+            drop(CShared { ptr: self.next });
+            self.next = std::ptr::null();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CNode {
+    fn drop(&mut self) {
+        let _ = self.do_drop();
+    }
+}
+
+pub struct Catalog {
+    pub names: Vec<String>,
+    pub models: Vec<Arc<Model>>,
+    pub default_model: Arc<Model>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Catalog)]
+#[no_drop_impl]
+pub struct CCatalog {
+    names: CStringArray,
+    models: CArray<CArc<Model>>,
+    #[shallow]
+    default_model: CArc<Model>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Banner {
+    pub label: Label,
+    pub priority: u32,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Banner)]
+#[no_drop_impl]
+pub struct CBanner {
+    label: CLabel,
+    priority: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Playlist {
+    pub titles: Vec<String>,
+}
+
+/// For a JNA binding, mapping the sizes to Java `int`s
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Playlist)]
+pub struct CPlaylist {
+    titles: CStringArrayInt,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Journal {
+    pub entries: Vec<Vector>,
+}
+
+/// C code appends the entries in place, up to the capacity
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Journal)]
+pub struct CJournal {
+    entries: CDynArray<CVector>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Note {
+    pub title: String,
+    pub comment: Option<String>,
+    pub layer: Layer,
+    pub author: Listener,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Note)]
+#[defensive_drop]
+pub struct CNote {
+    title: *const libc::c_char,
+    #[nullable]
+    comment: *const libc::c_char,
+    layer: *const CLayer,
+    #[opaque]
+    author: *mut libc::c_void,
+}
+
+pub struct Counters {
+    pub hits: AtomicU32,
+    pub enabled: AtomicBool,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Counters)]
+pub struct CCounters {
+    hits: u32,
+    enabled: bool,
+}
+
+/// Counters shared with the threads updating them
+pub struct SharedCounters {
+    pub hits: Arc<AtomicUsize>,
+    pub enabled: Arc<AtomicBool>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, CDrop)]
+#[target_type(SharedCounters)]
+pub struct CSharedCounters {
+    #[atomic_ordering(Acquire)]
+    hits: u32,
+    #[atomic_ordering(Relaxed)]
+    enabled: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Thermostat {
+    pub room: String,
+    pub target: f32,
+    pub schedule: Vec<u8>,
+}
+
+impl Thermostat {
+    pub fn living_room() -> Self {
+        Self {
+            room: "living room".to_string(),
+            target: 19.5,
+            schedule: vec![7, 22],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, RawPointerConverter)]
+#[target_type(Thermostat)]
+#[generate_accessors]
+#[generate_destructor]
+pub struct CThermostat {
+    room: *const libc::c_char,
+    target: f32,
+    schedule: CArray<u8>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, CDrop, RawPointerConverter)]
+#[target_type(Thermostat)]
+#[generate_accessors(new = Thermostat::living_room)]
+pub struct CLivingRoomThermostat {
+    room: *const libc::c_char,
+    target: f32,
+    schedule: CArray<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Packet {
+    pub header: bytes::BytesMut,
+    pub payload: bytes::Bytes,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Packet)]
+pub struct CPacket {
+    header: CByteArray,
+    payload: CByteArray,
+}
+
+/// Panics when it is dropped
+#[derive(RawPointerConverter)]
+#[generate_destructor(ffi_exploding_destroy)]
+pub struct CExploding;
+
+impl Drop for CExploding {
+    fn drop(&mut self) {
+        panic!("dropped");
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Command {
+    pub verb: String,
+    pub argument: Option<String>,
+}
+
+/// Received from an untrusted process
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, CValidate)]
+#[target_type(Command)]
+pub struct CCommand {
+    #[string_policy(max_len = 4, reject_control_chars)]
+    verb: *const libc::c_char,
+    #[nullable]
+    #[string_policy(max_len = 16)]
+    argument: *const libc::c_char,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogRecord<'a> {
+    pub source: &'a CStr,
+    pub message: Option<&'a CStr>,
+    pub level: u8,
+}
+
+/// Built for each log record, pointing to strings kept alive by the caller
+#[repr(C)]
+#[derive(CReprOf, AsRustView, CDrop)]
+#[target_type(LogRecord<'a>)]
+pub struct CLogRecord {
+    #[borrowed_string]
+    source: *const libc::c_char,
+    #[borrowed_string]
+    #[nullable]
+    message: *const libc::c_char,
+    level: u8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track {
+    pub title: String,
+    pub samples: Vec<i16>,
+    pub tags: Vec<String>,
+    pub cover: Option<Topping>,
+}
+
+/// Converted by parallel batches
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Track)]
+#[thread_safe]
+pub struct CTrack {
+    title: *const libc::c_char,
+    samples: SyncCArray<i16>,
+    tags: SyncCStringArray,
+    #[nullable]
+    cover: *const CTopping,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeerConnected {
+    pub peer: String,
+    pub channels: Vec<String>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(PeerConnected)]
+pub struct CPeerConnected {
+    peer: *const libc::c_char,
+    channels: CStringArray,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Payload {
+    pub channel: String,
+    pub data: Vec<u8>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Payload)]
+pub struct CPayload {
+    channel: *const libc::c_char,
+    data: CArray<u8>,
+}
+
+/// The messages of an event bus, a heartbeat being a plain timestamp
+#[repr(C)]
+pub union CBusMessage {
+    peer_connected: std::mem::ManuallyDrop<CPeerConnected>,
+    payload: std::mem::ManuallyDrop<CPayload>,
+    heartbeat: u64,
+}
+
+impl CTaggedUnion for CBusMessage {
+    fn registry() -> &'static CVariantRegistry<Self> {
+        static REGISTRY: std::sync::OnceLock<CVariantRegistry<CBusMessage>> =
+            std::sync::OnceLock::new();
+        REGISTRY.get_or_init(|| {
+            CVariantRegistry::new()
+                .register::<PeerConnected, CPeerConnected>(1)
+                .register::<Payload, CPayload>(2)
+                .register::<u64, u64>(3)
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Book {
+    pub title: String,
+    pub pages: u32,
+}
+
+#[repr(C)]
+#[derive(CReprOf, CReprOfIn, AsRust, CDrop, RawPointerConverter)]
+#[target_type(Book)]
+pub struct CBook {
+    title: *const libc::c_char,
+    pages: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bookcase {
+    pub label: String,
+    pub note: Option<String>,
+    pub books: Vec<Book>,
+    pub tags: Vec<String>,
+    pub favorite: Option<Book>,
+}
+
+/// Converted in large numbers, with the memory of an arena
+#[repr(C)]
+#[derive(CReprOf, CReprOfIn, AsRust, CDrop)]
+#[target_type(Bookcase)]
+pub struct CBookcase {
+    label: *const libc::c_char,
+    #[nullable]
+    note: *const libc::c_char,
+    books: CArray<CBook>,
+    tags: CStringArray,
+    #[nullable]
+    favorite: *const CBook,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Measure {
+    pub value: f64,
+    pub count: u64,
+    pub offset: Option<f32>,
+}
+
+/// For a C API taking numbers as strings
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop, CValidate)]
+#[target_type(Measure)]
+#[as_rust_accessors]
+pub struct CMeasure {
+    #[stringify]
+    value: *const libc::c_char,
+    #[stringify]
+    count: *const libc::c_char,
+    #[stringify]
+    #[nullable]
+    offset: *const libc::c_char,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Margins {
+    pub label: String,
+    pub width: u32,
+}
+
+/// Zeroed by C callers which don't set margins
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Margins)]
+#[as_rust_zeroed(default)]
+pub struct CMargins {
+    label: *const libc::c_char,
+    width: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proxy {
+    pub host: String,
+    pub port: u16,
+}
+
+/// Zeroed by C callers which don't use a proxy
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Proxy)]
+#[as_rust_zeroed(none)]
+pub struct CProxy {
+    host: *const libc::c_char,
+    port: u16,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Inset {
+    pub enabled: bool,
+    pub width: u32,
+}
+
+/// Has padding between its fields
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Inset)]
+#[as_rust_zeroed]
+pub struct CInset {
+    enabled: bool,
+    width: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Frame {
+    pub inset: Inset,
+    pub title: String,
+    pub weights: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Frame)]
+#[as_rust_zeroed(default)]
+pub struct CFrame {
+    inset: CInset,
+    title: *const libc::c_char,
+    weights: [f32; 2],
+}
+
+/// A reading as received from a sensor
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReadingRequest {
+    pub sensor: String,
+    pub value: f64,
+    pub unit: Option<String>,
+}
+
+/// A reading as stored, once received
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredReading {
+    pub sensor_id: String,
+    pub value: f64,
+    pub unit_symbol: Option<String>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(ReadingRequest)]
+#[target_type(StoredReading)]
+pub struct CReading {
+    #[target_name(sensor_id, for = StoredReading)]
+    sensor: *const libc::c_char,
+    value: f64,
+    #[nullable]
+    #[target_name(unit_symbol, for = StoredReading)]
+    unit: *const libc::c_char,
+}
+
+/// An article, which gained fields that the C structs below don't carry
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Article {
+    pub title: String,
+    pub pages: u32,
+    pub tags: Vec<String>,
+    pub author: Option<String>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Article)]
+#[as_rust_default_field(tags)]
+#[as_rust_extra_field(author = Some("unknown".to_string()))]
+pub struct CArticle {
+    title: *const libc::c_char,
+    pages: u32,
+}
+
+/// The first version of `CArticle`, without pages
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Article)]
+#[as_rust_fill_defaults]
+pub struct CArticleV1 {
+    title: *const libc::c_char,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Speaker {
+    pub name: String,
+    pub volume: u32,
+}
+
+/// The second version of the layout of speakers, which gained a volume
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Speaker)]
+#[abi_version(2)]
+#[cfg_attr(target_pointer_width = "64", expected_size(24), expected_align(8))]
+pub struct CSpeaker {
+    pub version: u32,
+    name: *const libc::c_char,
+    pub volume: u32,
+}
+
+export_c_types! {
+    /// The layers of a pancake, defined here for the generated headers
+    pub struct CLayerArray = CArray<CLayer>;
+    pub struct CRoomNames = CStringArray;
+    pub struct CTemperatureRange = CRange<f32>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cake {
+    pub layers: Vec<Layer>,
+    pub rooms: Vec<String>,
+    pub baking_temperature: Range<f32>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Cake)]
+pub struct CCake {
+    layers: *const CLayerArray,
+    rooms: CRoomNames,
+    baking_temperature: CTemperatureRange,
+}
+
+/// Exported to C as `ffi_reprogram_thermostat`
+#[ffi_export(output = *const CThermostat)]
+pub fn reprogram_thermostat(
+    #[c_type(*const CThermostat)] thermostat: &Thermostat,
+    room: &str,
+    #[c_type(CArray<u8>)] schedule: Vec<u8>,
+) -> Result<Thermostat, String> {
+    if room.is_empty() {
+        return Err("the room can't be empty".to_string());
+    }
+    Ok(Thermostat {
+        room: room.to_string(),
+        target: thermostat.target,
+        schedule,
+    })
+}
+
+/// Exported to C as `ffi_average`, panics if there are no values
+#[ffi_export(name = ffi_average)]
+pub fn scaled_average(#[c_type(*const CArray<f32>)] values: &[f32], scale: f32) -> f32 {
+    assert!(!values.is_empty(), "no values");
+    scale * values.iter().sum::<f32>() / values.len() as f32
+}
+
+/// Exported to C as `ffi_model_size`, borrows the model
+#[ffi_export]
+pub fn model_size(#[c_type(*const CArc<Model>)] model: &Arc<Model>) -> u32 {
+    model.weights.len() as u32
+}
+
+/// Exported to C as `ffi_release_model`, takes the model
+#[ffi_export]
+pub fn release_model(#[c_type(CArc<Model>)] model: Arc<Model>) -> u32 {
+    model.weights.len() as u32
+}
+
+/// Exported to C as `ffi_release_boxed_model`, takes the model and the box holding it
+#[ffi_export]
+pub fn release_boxed_model(#[c_type(*const CArc<Model>)] model: Arc<Model>) -> u32 {
+    model.weights.len() as u32
+}
+
+/// Exported to C as `ffi_label_length`, takes the label
+#[ffi_export]
+pub fn label_length(label: String) -> u32 {
+    label.len() as u32
+}
+
+generate_error_handling!(
+    ffi_get_last_error,
+    ffi_destroy_error,
+    FFI_RESULT {
+        FFI_RESULT_OK,
+        FFI_RESULT_KO,
+        FFI_RESULT_NULL_POINTER = NullPointer,
+        FFI_RESULT_PANIC = Panic,
+        FFI_RESULT_BELOW_ABSOLUTE_ZERO = User(1),
+    }
+);
+
+/// Parses a temperature, the errors being available with `ffi_get_last_error`
+///
+/// # Safety
+///
+/// The pointers must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_parse_temperature(
+    input: *const libc::c_char,
+    temperature: *mut f32,
+) -> FFI_RESULT {
+    wrap!(parse_temperature(input, temperature))
+}
+
+unsafe fn parse_temperature(
+    input: *const libc::c_char,
+    temperature: *mut f32,
+) -> Result<(), CodedError> {
+    use anyhow::Context;
+
+    let input: String = std::ffi::CStr::raw_borrow(input)?.as_rust()?;
+    let temperature = temperature.as_mut().ok_or(UnexpectedNullPointerError)?;
+    *temperature = input
+        .parse()
+        .with_context(|| format!("invalid temperature {:?}", input))
+        .map_err(|error| CodedError::new(ErrorKind::Other, error))?;
+    if *temperature < -273.15 {
+        return Err(CodedError::new(
+            ErrorKind::User(1),
+            format!("{} is below the absolute zero", temperature),
+        ));
+    }
+    Ok(())
+}
+
+/// Looks up the name of a day of the week, panicking on invalid days
+///
+/// # Safety
+///
+/// `name` must be `null` or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ffi_day_name(day: usize, name: *mut *const libc::c_char) -> FFI_RESULT {
+    catch_panic! {
+        let names = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+        assert!(day < names.len(), "invalid day {}", day);
+        if name.is_null() {
+            bail!("no output");
+        }
+        *name = std::ffi::CString::c_repr_of(names[day].to_string())?.into_raw_pointer();
+        Ok(())
+    }
+}
+
+/// The derives don't depend on the traits imported by the user
+pub mod without_imports {
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Parcel {
+        pub reference: String,
+        pub weights: Vec<u32>,
+        pub label: Option<super::Label>,
+    }
+
+    #[repr(C)]
+    #[derive(
+        ffi_convert::CReprOf,
+        ffi_convert::AsRust,
+        ffi_convert::CDrop,
+        ffi_convert::CValidate,
+        ffi_convert::CSchema,
+        ffi_convert::RawPointerConverter,
+    )]
+    #[target_type(Parcel)]
+    #[as_rust_accessors]
+    pub struct CParcel {
+        reference: *const libc::c_char,
+        weights: ffi_convert::CArray<u32>,
+        #[nullable]
+        label: *const super::CLabel,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::{CStr, CString};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    generate_round_trip_rust_c_rust!(round_trip_sauce, Sauce, CSauce, { Sauce { volume: 4.2 } });
+
+    generate_round_trip_rust_c_rust!(round_trip_exported_types, Cake, CCake, {
+        Cake {
+            layers: vec![Layer {
+                number: 1,
+                subtitle: Some("sponge".to_string()),
+            }],
+            rooms: vec!["Kitchen".to_string()],
+            baking_temperature: 160.0..180.0,
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_topping, Topping, CTopping, {
+        Topping { amount: 2 }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_declared_array, Vec<Topping>, CToppingArray, {
+        vec![Topping { amount: 2 }, Topping { amount: 3 }]
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_dummy, Dummy, CDummy, {
+        Dummy {
+            count: 2,
+            describe: "yo".to_string(),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_layer, Layer, CLayer, {
+        Layer {
+            number: 1,
+            subtitle: Some(String::from("first layer")),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_label, Label, CLabel, {
+        Label {
+            text: "Order".to_string(),
+            tooltip: Some("Order a pancake".to_string()),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_setlist, Setlist, CSetlist, {
+        Setlist {
+            durations: Some(vec![180.0, 240.5]),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_customer, Customer, CCustomer, {
+        Customer {
+            id: CustomerId(42),
+            name: "Jane".to_string(),
+            last_order: Some(OrderId(uuid::Uuid::from_u128(
+                0x936da01f9abd4d9d80c702af85c822a8,
+            ))),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_crepe, Crepe, CCrepe, {
+        Crepe {
+            flavor: Flavor::Chocolate,
+            weight: 120,
+            diameter: Some(30),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_session, Session, CSession, {
+        Session {
+            id: NonZeroU64::new(42).unwrap(),
+            parent_id: None,
+            retries: NonZeroU8::new(3),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_shelf, Shelf, CShelf, {
+        Shelf {
+            labels: vec!["flour".to_string(), "sugar".to_string()],
+            tags: Some(vec!["pantry".to_string()]),
+            slots: Some(Range { start: 2, end: 8 }),
+            spare: Some(0.5),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_recipe_step, RecipeStep, CRecipeStep, {
+        RecipeStep {
+            label: "mix".into(),
+            note: None,
+            duration: Box::new(Duration::from_secs(60)),
+            then: Some(Box::new(RecipeStep {
+                label: "rest".into(),
+                note: Some("covered".into()),
+                duration: Box::new(Duration::from_secs(1800)),
+                then: None,
+            })),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_excerpt, Excerpt, CExcerpt, {
+        Excerpt {
+            lines: 3..7,
+            bytes: 120..480,
+            highlight: Some(200..210),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_inference, Inference, CInference, {
+        Inference {
+            model: Arc::new(Model {
+                weights: vec![0.25; 16],
+            }),
+            input: vec![1.0, 2.0],
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_subscription, Subscription, CSubscription, {
+        Subscription {
+            topic: "orders".to_string(),
+            listener: Listener::default(),
+            model: Arc::new(Model {
+                weights: vec![0.25; 16],
+            }),
+            fallback: Some(Listener::default()),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_booking, Booking, CBooking, {
+        Booking {
+            guest: "Ada".to_string(),
+            nights: 3..5,
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_sensor, Sensor, CSensor, {
+        Sensor {
+            name: "thermometer".to_string(),
+            unit: Some("°C".to_string()),
+            reading: 21.5,
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_transcript, Transcript, CTranscript, {
+        Transcript {
+            code_points: "crêpe 🥞".chars().collect(),
+            raw_text: "crêpe\0suzette".to_string(),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_trajectory, Trajectory, CTrajectory, {
+        Trajectory {
+            origin: Vector { lanes: [0.0; 8] },
+            points: (0..5)
+                .map(|i| Vector {
+                    lanes: [i as f32; 8],
+                })
+                .collect(),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_recording, Recording, CRecording, {
+        Recording {
+            frames: vec![vec![0.1, 0.2], vec![], vec![0.3]],
+            labels: vec![
+                vec!["crêpe".to_string()],
+                vec!["blini".to_string(), "pancake".to_string()],
+            ],
+            markers: Some(vec![vec![0..2, 4..8], vec![]]),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_wide_label, Label, CWideLabel, {
+        Label {
+            text: "Commander".to_string(),
+            tooltip: Some("Commander une crêpe 🥞".to_string()),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_document, Document, CDocument, {
+        Document {
+            path: PathBuf::from("/tmp/pancakes/recipe.txt"),
+            name: OsString::from("recipe.txt"),
+            backup_path: Some(PathBuf::from("/tmp/pancakes/recipe.txt.bak")),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(
+        round_trip_network_interface,
+        NetworkInterface,
+        CNetworkInterface,
+        {
+            NetworkInterface {
+                mac: [0x00, 0x0e, 0x58, 0x12, 0x34, 0x56],
+                netmasks: [[255, 255, 255, 0], [255, 255, 0, 0]],
+                labels: [
+                    Label {
+                        text: "eth0".to_string(),
+                        tooltip: None,
+                    },
+                    Label {
+                        text: "wired".to_string(),
+                        tooltip: Some("Wired connection".to_string()),
+                    },
+                ],
+            }
+        }
+    );
+
+    generate_round_trip_rust_c_rust!(round_trip_ipv4_addr, IpAddr, CIpAddr, {
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_ipv6_addr, IpAddr, CIpAddr, {
+        IpAddr::V6(Ipv6Addr::new(
+            0xfe80, 0, 0, 0, 0x0200, 0x5eff, 0xfe00, 0x5301,
+        ))
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_socket_addr_v4, SocketAddr, CSocketAddr, {
+        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 1400))
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_socket_addr_v6, SocketAddr, CSocketAddr, {
+        SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::LOCALHOST, 1400, 12, 3))
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_result_ok, Result<Topping, Dummy>, CResult<CTopping, CDummy>, {
+        Ok(Topping { amount: 2 })
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_result_err, Result<Topping, Dummy>, CResult<CTopping, CDummy>, {
+        Err(Dummy {
+            count: 2,
+            describe: "yo".to_string(),
+        })
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_duration, Duration, CDuration, {
+        Duration::new(42, 123_456_789)
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_timestamp, SystemTime, CTimestamp, {
+        UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_789)
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_timestamp_before_epoch, SystemTime, CTimestamp, {
+        UNIX_EPOCH - Duration::new(1_500, 250_000_000)
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_order, Order, COrder, {
+        Order {
+            id: uuid::Uuid::from_u128(0x936da01f9abd4d9d80c702af85c822a8),
+            related_orders: vec![uuid::Uuid::from_u128(42), uuid::Uuid::nil()],
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_recipe, Recipe, CRecipe, {
+        Recipe {
+            base: Arc::new(Sauce { volume: 4.2 }),
+            topping: Arc::new(Sauce { volume: 2.4 }),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_pancake, Pancake, CPancake, {
+        Pancake {
+            name: String::from("Here is your pancake"),
+            description: Some("I'm delicious ! ".to_string()),
+            start: 0.0,
+            end: Some(2.0),
+            float_array: [1.0, 2.0, 3.0, 4.0],
+            dummy: Dummy {
+                count: 2,
+                describe: "yo".to_string(),
+            },
+            sauce: Some(Sauce { volume: 32.23 }),
+            toppings: vec![Topping { amount: 2 }, Topping { amount: 3 }],
+            layers: Some(vec![Layer {
+                number: 1,
+                subtitle: Some(String::from("first layer")),
+            }]),
+            base_layers: [
+                Layer {
+                    number: 0,
+                    subtitle: Some(String::from("flour")),
+                },
+                Layer {
+                    number: 1,
+                    subtitle: Some(String::from("dough")),
+                },
+                Layer {
+                    number: 2,
+                    subtitle: Some(String::from("tomato")),
+                },
+            ],
+            is_delicious: true,
+            range: Range { start: 20, end: 30 },
+            some_futile_info: None,
+            flattened_range: Range { start: 42, end: 64 },
+            field_with_specific_rust_name: "renamed field".to_string(),
+            pancake_data: Some(vec![1, 2, 3]),
+            is_vegan: true,
+            cooking_time: Some(3.5),
+        }
+    });
+
+    generate_round_trip_rust_c_rust!(round_trip_pancake_2, Pancake, CPancake, {
+        Pancake {
+            name: String::from("Here is your pancake"),
+            description: Some("I'm delicious ! ".to_string()),
+            start: 0.0,
+            end: None,
+            float_array: [8.0, -1.0, f32::INFINITY, -0.0],
+            dummy: Dummy {
+                count: 2,
+                describe: "yo".to_string(),
+            },
+            sauce: None,
+            toppings: vec![],
+            layers: Some(vec![]),
+            base_layers: [
+                Layer {
+                    number: 0,
+                    subtitle: Some(String::from("flour")),
+                },
+                Layer {
+                    number: 1,
+                    subtitle: Some(String::from("dough")),
+                },
+                Layer {
+                    number: 2,
+                    subtitle: Some(String::from("cream")),
+                },
+            ],
+            is_delicious: true,
+            range: Range {
+                start: 50,
+                end: 100,
+            },
+            some_futile_info: None,
+            flattened_range: Range { start: 42, end: 64 },
+            field_with_specific_rust_name: "renamed field".to_string(),
+            pancake_data: None,
+            is_vegan: false,
+            cooking_time: None,
+        }
+    });
+
+    generate_deterministic_rust_c!(deterministic_pancake, Pancake, CPancake, {
+        Pancake {
+            name: String::from("Here is your pancake"),
+            description: None,
+            start: f32::NAN,
+            end: Some(2.0),
+            float_array: [1.0, 2.0, 3.0, -0.0],
+            dummy: Dummy {
+                count: 2,
+                describe: "yo".to_string(),
+            },
+            sauce: Some(Sauce { volume: 32.23 }),
+            toppings: vec![Topping { amount: 2 }, Topping { amount: 3 }],
+            layers: None,
+            base_layers: [
+                Layer {
+                    number: 0,
+                    subtitle: None,
+                },
+                Layer {
+                    number: 1,
+                    subtitle: Some(String::from("dough")),
+                },
+                Layer {
+                    number: 2,
+                    subtitle: Some(String::from("tomato")),
+                },
+            ],
+            is_delicious: true,
+            range: Range { start: 20, end: 30 },
+            some_futile_info: None,
+            flattened_range: Range { start: 42, end: 64 },
+            field_with_specific_rust_name: "renamed field".to_string(),
+            pancake_data: Some(vec![1, 2, 3]),
+            is_vegan: true,
+            cooking_time: None,
+        }
+    });
+
+    /// A string allocated as C code does, owned by the struct it is stored in.
+    fn raw_c_string(s: &str) -> *const libc::c_char {
+        CString::new(s).unwrap().into_raw()
+    }
+
+    /// An array allocated as C code does, owned by the struct it is stored in.
+    fn raw_c_array<T>(elements: Vec<T>) -> CArray<T> {
+        let size = elements.len();
+        CArray {
+            data_ptr: Box::into_raw(elements.into_boxed_slice()) as *const T,
+            size,
+        }
+    }
+
+    generate_round_trip_c_rust_c!(round_trip_c_layer, Layer, CLayer, {
+        CLayer {
+            number: 3,
+            subtitle: raw_c_string("cream"),
+        }
+    });
+
+    generate_round_trip_c_rust_c!(round_trip_c_layer_without_subtitle, Layer, CLayer, {
+        CLayer {
+            number: 0,
+            subtitle: std::ptr::null(),
+        }
+    });
+
+    generate_round_trip_c_rust_c!(round_trip_c_layers, Vec<Layer>, CArray<CLayer>, {
+        raw_c_array(vec![
+            CLayer {
+                number: 1,
+                subtitle: raw_c_string("dough"),
+            },
+            CLayer {
+                number: 2,
+                subtitle: std::ptr::null(),
+            },
+        ])
+    });
+
+    generate_round_trip_c_rust_c!(round_trip_c_string_array, Vec<String>, CStringArray, {
+        let strings = vec![raw_c_string("Diavola"), raw_c_string("")];
+        CStringArray {
+            size: strings.len(),
+            data: Box::into_raw(strings.into_boxed_slice()) as *const *const libc::c_char,
+        }
+    });
+
+    generate_round_trip_c_rust_c!(round_trip_c_empty_array, Vec<Topping>, CArray<CTopping>, {
+        CArray {
+            data_ptr: std::ptr::null(),
+            size: 0,
+        }
+    });
+
+    #[test]
+    fn round_trips_from_c_detect_lossy_conversions() {
+        // the C value is not valid UTF-8, the conversion fails before comparing anything
+        let invalid = CString::new(vec![0xff, 0xfe]).unwrap().into_raw();
+        assert!(round_trip_test_c_rust_c::<CLayer, Layer>(CLayer {
+            number: 1,
+            subtitle: invalid,
+        })
+        .is_err());
+        drop(unsafe { CString::from_raw(invalid) });
+
+        // the C value is lost when converted to Rust
+        #[derive(Clone)]
+        pub struct Position {
+            pub x: i32,
+        }
+
+        #[repr(C)]
+        #[derive(CReprOf, AsRust, CDrop)]
+        #[target_type(Position)]
+        pub struct CPosition {
+            x: i32,
+            #[skip]
+            z: i32,
+        }
+
+        impl CDeepEq for CPosition {
+            fn deep_eq(&self, other: &Self) -> bool {
+                self.x.deep_eq(&other.x) && self.z.deep_eq(&other.z)
+            }
+        }
+
+        assert!(round_trip_test_c_rust_c::<CPosition, Position>(CPosition { x: 1, z: 2 }).is_err());
+        assert!(round_trip_test_c_rust_c::<CPosition, Position>(CPosition { x: 1, z: 0 }).is_ok());
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: LeakCheckAllocator = LeakCheckAllocator;
+
+    // the property tests are not run under Miri, proptest persisting its failures in files, which
+    // Miri can't access
+
+    /// Strings which may contain nul bytes, which have no C representation.
+    #[cfg(not(miri))]
+    fn strings_with_nul_bytes() -> impl proptest::strategy::Strategy<Value = String> {
+        "[a-z\\x00é]{0,6}"
+    }
+
+    #[cfg(not(miri))]
+    fn layers() -> impl proptest::strategy::Strategy<Value = Layer> {
+        use proptest::prelude::*;
+
+        (any::<i32>(), proptest::option::of(strings_with_nul_bytes()))
+            .prop_map(|(number, subtitle)| Layer { number, subtitle })
+    }
+
+    #[cfg(not(miri))]
+    generate_roundtrip_proptest!(proptest_primitive_arrays, Vec<i32>, CArray<i32>);
+
+    #[cfg(not(miri))]
+    generate_roundtrip_proptest!(proptest_layer, Layer, CLayer, layers());
+
+    #[cfg(not(miri))]
+    generate_roundtrip_proptest!(
+        proptest_layers,
+        Vec<Layer>,
+        CArray<CLayer>,
+        proptest::collection::vec(layers(), 0..8)
+    );
+
+    #[cfg(not(miri))]
+    generate_roundtrip_proptest!(
+        proptest_string_arrays,
+        Vec<String>,
+        CStringArray,
+        proptest::collection::vec(strings_with_nul_bytes(), 0..8)
+    );
+
+    #[test]
+    fn proptest_leaks_are_detected() {
+        thread_local! {
+            /// The strings leaked by the conversions, freed at the end of the test
+            static LEAKED: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+        }
+
+        /// Leaks a string each time it is converted to Rust
+        pub struct CLeaky(u32);
+
+        impl CReprOf<u32> for CLeaky {
+            fn c_repr_of(input: u32) -> Result<Self, CReprOfError> {
+                Ok(Self(input))
+            }
+        }
+
+        impl CDrop for CLeaky {
+            fn do_drop(&mut self) -> Result<(), CDropError> {
+                Ok(())
+            }
+        }
+
+        impl AsRust<u32> for CLeaky {
+            fn as_rust(&self) -> Result<u32, AsRustError> {
+                LEAKED.with(|leaked| leaked.borrow_mut().push(self.0.to_string()));
+                Ok(self.0)
+            }
+        }
+
+        assert!(check_round_trip::<CLeaky, u32>(10).is_err());
+        assert!(check_round_trip::<u32, u32>(10).is_ok());
+        LEAKED.with(|leaked| leaked.borrow_mut().clear());
+    }
+
+    #[test]
+    fn debug_shows_the_rust_values() {
+        let c_layer = CLayer::c_repr_of(Layer {
+            number: 1,
+            subtitle: Some("dough".to_string()),
+        })
+        .unwrap();
+        assert_eq!(
+            format!("{:?}", c_layer),
+            r#"Layer { number: 1, subtitle: Some("dough") }"#
+        );
+        assert_eq!(
+            format!("{:#?}", c_layer),
+            format!(
+                "{:#?}",
+                Layer {
+                    number: 1,
+                    subtitle: Some("dough".to_string()),
+                }
+            )
+        );
+
+        // the addresses of the pointers are shown when the struct can't be converted
+        let c_layer = std::mem::ManuallyDrop::new(CLayer {
+            number: 1,
+            subtitle: b"\xff\0".as_ptr() as *const libc::c_char,
+        });
+        assert_eq!(
+            format!("{:?}", *c_layer),
+            format!(
+                "CLayer {{ as_rust_error: {:?}, subtitle: {:?}, .. }}",
+                c_layer.as_rust().unwrap_err().to_string(),
+                c_layer.subtitle
+            )
+        );
+    }
+
+    #[test]
+    fn eq_compares_the_rust_values() {
+        let layer = |subtitle: &str| {
+            CLayer::c_repr_of(Layer {
+                number: 1,
+                subtitle: Some(subtitle.to_string()),
+            })
+            .unwrap()
+        };
+        assert_eq!(layer("dough"), layer("dough"));
+        assert_ne!(layer("dough"), layer("cream"));
+        assert_eq!(
+            CArray::from(vec![layer("dough"), layer("cream")])
+                .as_slice()
+                .unwrap(),
+            &[layer("dough"), layer("cream")]
+        );
+
+        // a struct that can't be converted is not equal to itself
+        let invalid = std::mem::ManuallyDrop::new(CLayer {
+            number: 1,
+            subtitle: b"\xff\0".as_ptr() as *const libc::c_char,
+        });
+        let same = &*invalid;
+        assert_ne!(*invalid, *same);
+
+        let pancake = Pancake {
+            name: String::from("Here is your pancake"),
+            description: Some("I'm delicious ! ".to_string()),
+            start: 0.0,
+            end: Some(2.0),
+            float_array: [1.0, 2.0, 3.0, 4.0],
+            dummy: Dummy {
+                count: 2,
+                describe: "yo".to_string(),
+            },
+            sauce: None,
+            toppings: vec![Topping { amount: 2 }],
+            layers: Some(vec![]),
+            base_layers: [
+                Layer {
+                    number: 0,
+                    subtitle: None,
+                },
+                Layer {
+                    number: 1,
+                    subtitle: Some(String::from("dough")),
+                },
+                Layer {
+                    number: 2,
+                    subtitle: Some(String::from("cream")),
+                },
+            ],
+            is_delicious: true,
+            range: Range { start: 20, end: 30 },
+            some_futile_info: None,
+            flattened_range: Range { start: 42, end: 64 },
+            field_with_specific_rust_name: "renamed field".to_string(),
+            pancake_data: None,
+            is_vegan: false,
+            cooking_time: Some(10.0),
+        };
+        assert_eq!(
+            CPancake::c_repr_of(pancake.clone()).unwrap(),
+            CPancake::c_repr_of(pancake).unwrap()
+        );
+    }
+
+    #[test]
+    fn borrowed_values_conversions() {
+        let layers = vec![
+            Layer {
+                number: 1,
+                subtitle: Some("dough".to_string()),
+            },
+            Layer {
+                number: 2,
+                subtitle: None,
+            },
+        ];
+        let c_layers = CArray::<CLayer>::c_repr_of_ref(&layers).unwrap();
+        assert_eq!(c_layers.as_rust().unwrap(), layers);
+
+        let c_layer = CLayer::c_repr_of_ref(&layers[0]).unwrap();
+        assert_eq!(c_layer, CLayer::c_repr_of(layers[0].clone()).unwrap());
+
+        // the errors are the same as for the owned values
+        assert!(CStringArray::c_repr_of_ref(&vec!["nul\0".to_string()]).is_err());
+    }
+
+    #[test]
+    fn several_target_types() {
+        let request = ReadingRequest {
+            sensor: "kitchen".to_string(),
+            value: 21.5,
+            unit: Some("°C".to_string()),
+        };
+        let stored = StoredReading {
+            sensor_id: "kitchen".to_string(),
+            value: 21.5,
+            unit_symbol: Some("°C".to_string()),
+        };
+
+        let c_reading = CReading::c_repr_of(request.clone()).unwrap();
+        assert_eq!(
+            AsRust::<StoredReading>::as_rust(&c_reading).unwrap(),
+            stored
+        );
+
+        let c_reading = CReading::c_repr_of(stored).unwrap();
+        assert_eq!(
+            AsRust::<ReadingRequest>::as_rust(&c_reading).unwrap(),
+            request
+        );
+
+        // the generated c_repr_of_at method accepts all the target types
+        let mut c_reading = std::mem::MaybeUninit::<CReading>::uninit();
+        let status: CStatus = unsafe { CReading::c_repr_of_at(c_reading.as_mut_ptr(), request) };
+        assert_eq!(status, CStatus::Ok);
+        let c_reading = unsafe { c_reading.assume_init() };
+        assert_eq!(
+            AsRust::<StoredReading>::as_rust(&c_reading)
+                .unwrap()
+                .sensor_id,
+            "kitchen"
+        );
+    }
+
+    #[test]
+    fn missing_c_fields_are_defaulted() {
+        let article = Article {
+            title: "Pancakes of the world".to_string(),
+            pages: 4,
+            tags: vec!["vegetarian".to_string()],
+            author: Some("Marie".to_string()),
+        };
+
+        let c_article = CArticle::c_repr_of(article.clone()).unwrap();
+        assert_eq!(
+            c_article.as_rust().unwrap(),
+            Article {
+                title: "Pancakes of the world".to_string(),
+                pages: 4,
+                tags: vec![],
+                author: Some("unknown".to_string()),
+            }
+        );
+
+        let c_article = CArticleV1::c_repr_of(article).unwrap();
+        assert_eq!(
+            c_article.as_rust().unwrap(),
+            Article {
+                title: "Pancakes of the world".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn versioned_structs() {
+        let speaker = Speaker {
+            name: "kitchen".to_string(),
+            volume: 30,
+        };
+        let mut c_speaker = CSpeaker::c_repr_of(speaker.clone()).unwrap();
+        assert_eq!(c_speaker.version, 2);
+        assert_eq!(CSpeaker::ABI_VERSION, 2);
+        assert_eq!(c_speaker.as_rust().unwrap(), speaker);
+
+        // a struct of the first version, written by an older C caller
+        c_speaker.version = 1;
+        assert!(matches!(
+            check_version(&c_speaker),
+            Err(AsRustError::VersionMismatch {
+                expected: 2,
+                found: 1
+            })
+        ));
+        assert!(matches!(
+            AsRust::<Speaker>::as_rust(&c_speaker),
+            Err(AsRustError::VersionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    // the header is checked by a C compiler, which Miri can't run
+    #[cfg_attr(miri, ignore)]
+    fn header_declares_the_layouts() {
+        use std::mem::{align_of, offset_of, size_of};
+
+        fn assertions<T>(c_type: &str, fields: &[(&str, usize)]) -> String {
+            let mut assertions = format!(
+                "_Static_assert(sizeof({0}) == {1} && _Alignof({0}) == {2}, \"{0}\");\n",
+                c_type,
+                size_of::<T>(),
+                align_of::<T>()
+            );
+            for (field, offset) in fields {
+                assertions.push_str(&format!(
+                    "_Static_assert(offsetof({0}, {1}) == {2}, \"{0}.{1}\");\n",
+                    c_type, field, offset
+                ));
+            }
+            assertions
+        }
+
+        let program = [
+            "#include \"ffi_convert.h\"\n".to_string(),
+            "FFI_CONVERT_ARRAY(CToppingArray, int32_t);\n".to_string(),
+            "FFI_CONVERT_RANGE(CRangeI64, int64_t);\n".to_string(),
+            "FFI_CONVERT_SPAN(CSpanU16, uint16_t);\n".to_string(),
+            "FFI_CONVERT_OPTION(COptionF64, double);\n".to_string(),
+            "FFI_CONVERT_RESULT(CResultDuration, CDuration, int32_t);\n".to_string(),
+            "FFI_CONVERT_FIXED_STRING(CName, 12);\n".to_string(),
+            assertions::<CStatus>("CStatus", &[]),
+            assertions::<CStringArray>(
+                "CStringArray",
+                &[
+                    ("data", offset_of!(CStringArray, data)),
+                    ("size", offset_of!(CStringArray, size)),
+                ],
+            ),
+            assertions::<CStringArrayInt>(
+                "CStringArrayInt",
+                &[("size", offset_of!(CStringArrayInt, size))],
+            ),
+            assertions::<CDuration>("CDuration", &[("nanos", offset_of!(CDuration, nanos))]),
+            assertions::<CTimestamp>("CTimestamp", &[("nanos", offset_of!(CTimestamp, nanos))]),
+            assertions::<CIpAddr>("CIpAddr", &[("addr", offset_of!(CIpAddr, addr))]),
+            assertions::<CSocketAddr>(
+                "CSocketAddr",
+                &[
+                    ("port", offset_of!(CSocketAddr, port)),
+                    ("flowinfo", offset_of!(CSocketAddr, flowinfo)),
+                    ("scope_id", offset_of!(CSocketAddr, scope_id)),
+                ],
+            ),
+            assertions::<CUuid>("CUuid", &[]),
+            assertions::<CArray<i32>>("CToppingArray", &[("size", offset_of!(CArray<i32>, size))]),
+            assertions::<CRange<i64>>("CRangeI64", &[("end", offset_of!(CRange<i64>, end))]),
+            assertions::<CSpan<u16>>("CSpanU16", &[("length", offset_of!(CSpan<u16>, length))]),
+            assertions::<COption<f64>>("COptionF64", &[("value", offset_of!(COption<f64>, value))]),
+            assertions::<CResult<CDuration, i32>>(
+                "CResultDuration",
+                &[("err", offset_of!(CResult<CDuration, i32>, err))],
+            ),
+            assertions::<CFixedString<12>>("CName", &[]),
+        ]
+        .concat();
+
+        let directory =
+            std::env::temp_dir().join(format!("ffi_convert_header_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        ffi_convert::header::write_header(directory.join("ffi_convert.h")).unwrap();
+        std::fs::write(directory.join("layouts.c"), program).unwrap();
+
+        let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+        let output = std::process::Command::new(compiler)
+            .args(["-std=c11", "-fsyntax-only", "layouts.c"])
+            .current_dir(&directory)
+            .output()
+            .expect("a C compiler is needed to check the header");
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    #[test]
+    fn nondeterministic_conversions_are_detected() {
+        use std::sync::atomic::{AtomicI32, Ordering};
+
+        static NEXT_TICKET: AtomicI32 = AtomicI32::new(0);
+
+        #[derive(Clone)]
+        pub struct Order {
+            pub toppings: Vec<Topping>,
+        }
+
+        #[repr(C)]
+        #[derive(CReprOf, CDrop)]
+        #[target_type(Order)]
+        pub struct COrder {
+            toppings: CArray<CTopping>,
+            #[c_repr_of_convert(NEXT_TICKET.fetch_add(1, Ordering::SeqCst))]
+            ticket: i32,
+        }
+
+        impl CDeepEq for COrder {
+            fn deep_eq(&self, other: &Self) -> bool {
+                self.toppings.deep_eq(&other.toppings) && self.ticket.deep_eq(&other.ticket)
+            }
+        }
+
+        let order = Order {
+            toppings: vec![Topping { amount: 1 }],
+        };
+        assert!(deterministic_test_rust_c::<COrder, Order>(order).is_err());
+        assert!(
+            deterministic_test_rust_c::<CArray<CTopping>, Vec<Topping>>(vec![
+                Topping { amount: 1 },
+                Topping { amount: 2 },
+            ])
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn borrowed_slice_view() {
+        let values = vec![1.0, 2.0, 3.0];
+        let c_samples = CSamples::c_repr_of(Samples {
+            rate: 16000,
+            values: &values,
+        })
+        .expect("could not convert samples");
+
+        assert_eq!(c_samples.rate, 16000);
+        assert_eq!(c_samples.values.data_ptr, values.as_ptr());
+        let values_view: &[f32] = c_samples.values.as_rust_view().unwrap();
+        assert_eq!(values_view, &values[..]);
+        assert_eq!(
+            c_samples.as_rust_view().unwrap(),
+            Samples {
+                rate: 16000,
+                values: &values,
+            }
+        );
+
+        let owned: Vec<f32> = c_samples.values.as_rust().unwrap();
+        assert_eq!(owned, values);
+
+        let view = CSliceView::from(&values[1..]);
+        assert_eq!(view.size, 2);
+        let view_values: &[f32] = view.as_rust_view().unwrap();
+        assert_eq!(view_values, &values[1..]);
+
+        let empty = CSamples::c_repr_of(Samples {
+            rate: 16000,
+            values: &[],
+        })
+        .expect("could not convert samples");
+        let empty_values: &[f32] = empty.values.as_rust_view().unwrap();
+        assert!(empty_values.is_empty());
+    }
+
+    #[test]
+    fn as_rust_field_accessors() {
+        let c_pancake = CPancake::c_repr_of(Pancake {
+            name: String::from("Here is your pancake"),
+            description: None,
             start: 0.0,
-            end: None,
-            float_array: [8.0, -1.0, f32::INFINITY, -0.0],
+            end: Some(2.0),
+            float_array: [1.0, 2.0, 3.0, 4.0],
             dummy: Dummy {
                 count: 2,
                 describe: "yo".to_string(),
             },
-            sauce: None,
-            toppings: vec![],
-            layers: Some(vec![]),
-            base_layers: [
-                Layer {
-                    number: 0,
-                    subtitle: Some(String::from("flour")),
+            sauce: None,
+            toppings: vec![Topping { amount: 2 }, Topping { amount: 3 }],
+            layers: None,
+            base_layers: [
+                Layer {
+                    number: 0,
+                    subtitle: None,
+                },
+                Layer {
+                    number: 1,
+                    subtitle: None,
+                },
+                Layer {
+                    number: 2,
+                    subtitle: None,
+                },
+            ],
+            is_delicious: true,
+            range: Range { start: 20, end: 30 },
+            some_futile_info: None,
+            flattened_range: Range { start: 42, end: 64 },
+            field_with_specific_rust_name: "renamed field".to_string(),
+            pancake_data: None,
+            is_vegan: true,
+            cooking_time: None,
+        })
+        .expect("could not convert pancake");
+
+        assert_eq!(c_pancake.as_rust_name().unwrap(), "Here is your pancake");
+        assert_eq!(c_pancake.as_rust_description().unwrap(), None);
+        assert_eq!(c_pancake.as_rust_end::<f32>().unwrap(), Some(2.0));
+        assert_eq!(
+            c_pancake.as_rust_toppings::<Vec<Topping>>().unwrap(),
+            vec![Topping { amount: 2 }, Topping { amount: 3 }]
+        );
+        assert_eq!(
+            c_pancake.as_rust_range::<Range<usize>>().unwrap(),
+            Range { start: 20, end: 30 }
+        );
+        assert_eq!(
+            c_pancake.as_rust_field_with_specific_rust_name().unwrap(),
+            "renamed field"
+        );
+    }
+
+    #[test]
+    fn target_field_types() {
+        let c_crepe = CCrepe::c_repr_of(Crepe {
+            flavor: Flavor::Sugar,
+            weight: 90,
+            diameter: None,
+        })
+        .expect("could not convert crepe");
+
+        assert_eq!(c_crepe.flavor, 1);
+        assert_eq!(c_crepe.as_rust_flavor().unwrap(), Flavor::Sugar);
+        assert_eq!(c_crepe.as_rust_weight().unwrap(), 90);
+        assert_eq!(c_crepe.as_rust_diameter().unwrap(), None);
+
+        let invalid_crepe = CCrepe {
+            flavor: 42,
+            weight: 90,
+            diameter: std::ptr::null(),
+        };
+        let crepe: Result<Crepe, _> = invalid_crepe.as_rust();
+        assert!(crepe.is_err());
+
+        // values that don't fit in the C type are rejected instead of being truncated
+        let too_big = CCrepe::c_repr_of(Crepe {
+            flavor: Flavor::Plain,
+            weight: 90,
+            diameter: Some(usize::MAX),
+        });
+        assert!(matches!(too_big, Err(CReprOfError::IntegerOverflow(_))));
+    }
+
+    #[test]
+    fn vtables() {
+        extern "C" fn cook(pancakes: u32) -> u32 {
+            pancakes * 3
+        }
+        extern "C" fn log(_level: i32) {}
+
+        let vtable = CKitchenVTable {
+            cook: Some(cook),
+            clean: None,
+            log,
+        };
+        let c_kitchen = CKitchen::c_repr_of(Kitchen {
+            name: "Chez Paul".to_string(),
+            vtable,
+        })
+        .unwrap();
+        assert!(c_kitchen.validate_c().is_ok());
+
+        let kitchen: Kitchen = c_kitchen.as_rust().unwrap();
+        assert_eq!(kitchen.name, "Chez Paul");
+        assert_eq!((kitchen.vtable.cook.unwrap())(2), 6);
+        assert!(kitchen.vtable.clean.is_none());
+
+        let incomplete = CKitchen::c_repr_of(Kitchen {
+            name: "Chez Paul".to_string(),
+            vtable: CKitchenVTable {
+                cook: None,
+                ..vtable
+            },
+        })
+        .unwrap();
+        assert!(matches!(
+            incomplete.validate_c(),
+            Err(AsRustError::NullPointer(_))
+        ));
+        assert!(AsRust::<Kitchen>::as_rust(&incomplete).is_err());
+    }
+
+    #[test]
+    fn non_zero_integers() {
+        let c_session = CSession::c_repr_of(Session {
+            id: NonZeroU64::new(7).unwrap(),
+            parent_id: NonZeroU64::new(1),
+            retries: None,
+        })
+        .unwrap();
+        assert_eq!(c_session.id, 7);
+        assert_eq!(c_session.parent_id, 1);
+        assert_eq!(c_session.retries, 0);
+
+        let invalid = CSession {
+            id: 0,
+            parent_id: 0,
+            retries: 0,
+        };
+        assert!(matches!(
+            AsRust::<Session>::as_rust(&invalid),
+            Err(AsRustError::UnexpectedZero)
+        ));
+    }
+
+    #[test]
+    fn over_aligned_structs() {
+        assert_eq!(std::mem::align_of::<CVector>(), 32);
+
+        let trajectory = Trajectory {
+            origin: Vector { lanes: [1.0; 8] },
+            points: vec![Vector { lanes: [2.0; 8] }, Vector { lanes: [3.0; 8] }],
+        };
+        let c_trajectory = CTrajectory::c_repr_of(trajectory).unwrap();
+        assert!(c_trajectory.origin.is_aligned());
+        let points = unsafe { &*c_trajectory.points };
+        assert!(points.data_ptr.is_aligned());
+
+        // e.g. an array allocated by C code with a 16 bytes aligned malloc
+        let buffer = vec![0u8; 3 * std::mem::size_of::<CVector>()];
+        let offset = buffer.as_ptr().align_offset(32) + 16;
+        let misaligned = std::mem::ManuallyDrop::new(CArray::<CVector> {
+            data_ptr: buffer[offset..].as_ptr() as *const CVector,
+            size: 1,
+        });
+        assert!(matches!(
+            misaligned.as_slice(),
+            Err(AsRustError::MisalignedPointer { alignment: 32 })
+        ));
+        assert!(matches!(
+            AsRust::<Vec<Vector>>::as_rust(&*misaligned),
+            Err(AsRustError::MisalignedPointer { alignment: 32 })
+        ));
+    }
+
+    #[test]
+    fn checked_integer_conversions() {
+        assert_eq!(<i32 as AsRust<usize>>::as_rust(&42).unwrap(), 42);
+        assert!(matches!(
+            <i32 as AsRust<usize>>::as_rust(&-1),
+            Err(AsRustError::IntegerOverflow(_))
+        ));
+        assert!(matches!(
+            <i32 as CReprOf<usize>>::c_repr_of(usize::MAX),
+            Err(CReprOfError::IntegerOverflow(_))
+        ));
+        assert!(matches!(
+            <i32 as CReprOf<isize>>::c_repr_of(isize::MIN),
+            Err(CReprOfError::IntegerOverflow(_))
+        ));
+
+        // arrays of a different primitive type are converted element by element
+        let c_array = CArray::<i32>::c_repr_of(vec![4usize, 2]).unwrap();
+        assert_eq!(c_array.as_slice().unwrap(), &[4, 2]);
+        assert_eq!(AsRust::<Vec<usize>>::as_rust(&c_array).unwrap(), vec![4, 2]);
+
+        let saturating = ConversionContext {
+            saturate_integers: true,
+            ..Default::default()
+        };
+        assert_eq!(as_rust_with::<usize, i32>(&-1, &saturating).unwrap(), 0);
+        assert_eq!(
+            c_repr_of_with::<i32, isize>(isize::MIN, &saturating).unwrap(),
+            i32::MIN
+        );
+        assert_eq!(
+            c_repr_of_with::<i64, usize>(usize::MAX, &saturating).unwrap(),
+            i64::MAX
+        );
+    }
+
+    #[test]
+    fn array_errors_report_the_failing_element() {
+        let error = CStringArray::c_repr_of(vec!["Diavola".to_string(), "Marga\0rita".to_string()])
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            CReprOfError::InElement { index: 1, ref source }
+                if matches!(**source, CReprOfError::StringContainsNullBit(_))
+        ));
+        assert!(error.to_string().starts_with("element 1: "));
+
+        let c_array = CArray::<i32>::c_repr_of(vec![4, -1, 2]).unwrap();
+        assert!(matches!(
+            AsRust::<Vec<usize>>::as_rust(&c_array),
+            Err(AsRustError::InElement { index: 1, ref source })
+                if matches!(**source, AsRustError::IntegerOverflow(_))
+        ));
+    }
+
+    #[test]
+    fn array_errors_release_the_converted_elements() {
+        use std::sync::atomic::Ordering;
+
+        static RELEASED: AtomicUsize = AtomicUsize::new(0);
+
+        /// A channel number, which can't be 0
+        struct CChannel(u32);
+
+        impl CReprOf<u32> for CChannel {
+            fn c_repr_of(input: u32) -> Result<Self, CReprOfError> {
+                match input {
+                    0 => Err(CReprOfError::Other("no channel 0".into())),
+                    channel => Ok(Self(channel)),
+                }
+            }
+        }
+
+        impl CDrop for CChannel {
+            fn do_drop(&mut self) -> Result<(), CDropError> {
+                RELEASED.fetch_add(self.0 as usize, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        impl Drop for CChannel {
+            fn drop(&mut self) {
+                let _ = self.do_drop();
+            }
+        }
+
+        // the first error is returned, the elements converted before it are released
+        assert!(matches!(
+            CArray::<CChannel>::c_repr_of(vec![1, 2, 0, 4, 0]),
+            Err(CReprOfError::InElement { index: 2, .. })
+        ));
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 1 + 2);
+
+        let nested = vec![vec![8], vec![16, 0]];
+        assert!(matches!(
+            CArray::<CArray<CChannel>>::c_repr_of(nested),
+            Err(CReprOfError::InElement { index: 1, .. })
+        ));
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 1 + 2 + 8 + 16);
+    }
+
+    #[test]
+    fn struct_errors_release_the_converted_fields() {
+        use std::sync::atomic::Ordering;
+
+        static RELEASED: AtomicUsize = AtomicUsize::new(0);
+
+        /// A channel number, which can't be 0
+        #[derive(RawPointerConverter)]
+        pub struct CChannel(u32);
+
+        impl CReprOf<u32> for CChannel {
+            fn c_repr_of(input: u32) -> Result<Self, CReprOfError> {
+                match input {
+                    0 => Err(CReprOfError::Other("no channel 0".into())),
+                    channel => Ok(Self(channel)),
+                }
+            }
+        }
+
+        impl CDrop for CChannel {
+            fn do_drop(&mut self) -> Result<(), CDropError> {
+                RELEASED.fetch_add(self.0 as usize, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        impl Drop for CChannel {
+            fn drop(&mut self) {
+                let _ = self.do_drop();
+            }
+        }
+
+        pub struct Tuning {
+            pub name: String,
+            pub main: u32,
+            pub backup: Option<u32>,
+            pub fallback: u32,
+        }
+
+        #[repr(C)]
+        #[derive(CReprOf, CDrop)]
+        #[target_type(Tuning)]
+        pub struct CTuning {
+            name: *const libc::c_char,
+            main: *const CChannel,
+            #[nullable]
+            backup: *const CChannel,
+            fallback: CChannel,
+        }
+
+        let tuning = |main, backup, fallback| Tuning {
+            name: "Radio".to_string(),
+            main,
+            backup,
+            fallback,
+        };
+
+        // the pointer fields converted before the failing field are released
+        assert!(CTuning::c_repr_of(tuning(1, Some(2), 0)).is_err());
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 1 + 2);
+        assert!(CTuning::c_repr_of(tuning(4, None, 0)).is_err());
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 1 + 2 + 4);
+        assert!(CTuning::c_repr_of(tuning(8, Some(0), 16)).is_err());
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 1 + 2 + 4 + 8);
+
+        // once built, the struct owns its fields
+        let c_tuning = CTuning::c_repr_of(tuning(32, Some(64), 128)).unwrap();
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 1 + 2 + 4 + 8);
+        drop(c_tuning);
+        assert_eq!(
+            RELEASED.load(Ordering::SeqCst),
+            1 + 2 + 4 + 8 + 32 + 64 + 128
+        );
+    }
+
+    #[test]
+    fn mutable_array_slices() {
+        let mut c_matrix =
+            CArray::<CArray<f32>>::c_repr_of(vec![vec![1.0, 2.0], vec![3.0]]).unwrap();
+        for row in c_matrix.as_mut_slice().unwrap() {
+            row.as_mut_slice()
+                .unwrap()
+                .iter_mut()
+                .for_each(|x| *x *= 2.0);
+        }
+        assert_eq!(c_matrix.as_rust().unwrap(), vec![vec![2.0, 4.0], vec![6.0]]);
+        assert!(CArray::<f32>::c_repr_of(vec![])
+            .unwrap()
+            .as_mut_slice()
+            .unwrap()
+            .is_empty());
+
+        let c_names = CStringArray::c_repr_of(vec!["Rock".to_string()]).unwrap();
+        assert_eq!(c_names.as_slice().unwrap().len(), 1);
+        let null_names = std::mem::ManuallyDrop::new(CStringArray {
+            data: std::ptr::null(),
+            size: 2,
+        });
+        assert!(matches!(
+            null_names.as_slice(),
+            Err(AsRustError::NullPointer(_))
+        ));
+    }
+
+    #[test]
+    fn int_sized_string_arrays() {
+        let playlist = Playlist {
+            titles: vec!["Intro".to_string(), "Outro".to_string()],
+        };
+        let c_playlist = CPlaylist::c_repr_of(playlist.clone()).unwrap();
+        assert_eq!(c_playlist.titles.size, 2 as libc::c_int);
+        assert_eq!(c_playlist.titles.get(1).unwrap().to_str().unwrap(), "Outro");
+        assert_eq!(c_playlist.as_rust().unwrap(), playlist);
+
+        let negative = std::mem::ManuallyDrop::new(CStringArrayInt {
+            data: c_playlist.titles.data,
+            size: -1,
+        });
+        assert!(matches!(
+            AsRust::<Vec<String>>::as_rust(&*negative),
+            Err(AsRustError::IntegerOverflow(_))
+        ));
+        assert!(matches!(
+            negative.iter(),
+            Err(AsRustError::IntegerOverflow(_))
+        ));
+        assert_eq!(c_playlist.titles.iter().unwrap().count(), 2);
+    }
+
+    #[test]
+    fn dyn_arrays() {
+        let mut entries = Vec::with_capacity(4);
+        entries.push(Vector { lanes: [1.0; 8] });
+        let mut c_journal = CJournal::c_repr_of(Journal { entries }).unwrap();
+        assert_eq!(c_journal.entries.size, 1);
+        assert_eq!(c_journal.entries.capacity, 4);
+
+        // what C code appending an entry does
+        let entry = CVector::c_repr_of(Vector { lanes: [2.0; 8] }).unwrap();
+        unsafe { c_journal.entries.data.add(1).write(entry) };
+        c_journal.entries.size += 1;
+        assert_eq!(
+            c_journal.as_rust().unwrap().entries,
+            vec![Vector { lanes: [1.0; 8] }, Vector { lanes: [2.0; 8] }]
+        );
+
+        let samples = CDynArray::<u16>::c_repr_of(vec![7, 8, 9]).unwrap();
+        let data = samples.data;
+        let samples = samples.into_vec().unwrap();
+        assert_eq!(samples, vec![7, 8, 9]);
+        // the buffer is reclaimed without copying it
+        assert_eq!(samples.as_ptr(), data as *const u16);
+
+        let mut overflowing = CDynArray::<u16>::c_repr_of(vec![1]).unwrap();
+        overflowing.size = 2;
+        assert!(matches!(
+            overflowing.as_slice(),
+            Err(AsRustError::IndexOutOfBounds { index: 2, size: 1 })
+        ));
+        assert!(overflowing.do_drop().is_err());
+        overflowing.size = 1;
+    }
+
+    #[test]
+    fn c_sizes() {
+        assert_eq!(CSize::c_repr_of(42usize).unwrap(), CSize(42));
+        assert_eq!(AsRust::<u64>::as_rust(&CSize(42)).unwrap(), 42);
+
+        let large = CSize(u32::MAX as libc::size_t + 1);
+        assert!(matches!(
+            AsRust::<u32>::as_rust(&large),
+            Err(AsRustError::IntegerOverflow(_))
+        ));
+        let saturate = ConversionContext {
+            saturate_integers: true,
+            ..Default::default()
+        };
+        assert_eq!(as_rust_with::<u32, _>(&large, &saturate).unwrap(), u32::MAX);
+    }
+
+    #[test]
+    fn borrowed_strings() {
+        let source = CString::new("network").unwrap();
+        let message = CString::new("link up").unwrap();
+        for level in 0..3 {
+            let c_record = CLogRecord::c_repr_of(LogRecord {
+                source: &source,
+                message: Some(&message),
+                level,
+            })
+            .unwrap();
+            // the strings are not copied, nor freed with the record
+            assert_eq!(c_record.source, source.as_ptr());
+            assert_eq!(c_record.message, message.as_ptr());
+            assert_eq!(
+                c_record.as_rust_view().unwrap(),
+                LogRecord {
+                    source: &source,
+                    message: Some(&message),
+                    level,
+                }
+            );
+        }
+        assert_eq!(source.to_str().unwrap(), "network");
+
+        let c_record = CLogRecord::c_repr_of(LogRecord {
+            source: &source,
+            message: None,
+            level: 0,
+        })
+        .unwrap();
+        assert!(c_record.message.is_null());
+    }
+
+    #[test]
+    fn selected_derived_impls() {
+        assert_eq!(
+            Percentage::c_repr_of(Percentage(42))
+                .unwrap()
+                .as_rust()
+                .unwrap(),
+            Percentage(42)
+        );
+        assert!(Percentage(120).as_rust().is_err());
+
+        extern "C" fn noop() {}
+        let vtable = CTimerVTable {
+            start: Some(noop),
+            stop: None,
+        };
+        assert!(vtable.validate_c().is_err());
+        assert!(vtable.as_rust().is_err());
+        let vtable = CTimerVTable {
+            start: None,
+            stop: None,
+        };
+        assert!(vtable.as_rust().is_ok());
+    }
+
+    #[test]
+    fn opaque_arcs() {
+        let model = Arc::new(Model {
+            weights: vec![0.5; 64],
+        });
+
+        let c_inferences = (0..3)
+            .map(|i| {
+                CInference::c_repr_of(Inference {
+                    model: model.clone(),
+                    input: vec![i as f32],
+                })
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        // the model is shared, not copied
+        assert!(c_inferences
+            .iter()
+            .all(|c_inference| c_inference.model.ptr == Arc::as_ptr(&model)));
+        assert_eq!(Arc::strong_count(&model), 4);
+
+        // a C consumer keeps its own reference, after the C structs are dropped
+        let handle = unsafe { ffi_model_clone(c_inferences[0].model.ptr) };
+        let inference = c_inferences[1].as_rust().unwrap();
+        assert!(Arc::ptr_eq(&inference.model, &model));
+        drop(c_inferences);
+        assert_eq!(Arc::strong_count(&model), 3);
+
+        drop(inference);
+        unsafe { ffi_model_drop(handle) };
+        assert_eq!(Arc::strong_count(&model), 1);
+
+        let null = CArc::<Model> {
+            ptr: std::ptr::null(),
+        };
+        assert!(matches!(
+            AsRust::<Arc<Model>>::as_rust(&null),
+            Err(AsRustError::NullPointer(_))
+        ));
+    }
+
+    #[test]
+    fn callbacks() {
+        let clicks = Arc::new(Mutex::new(vec![]));
+        let recorded_clicks = clicks.clone();
+        let c_button = CButton::c_repr_of(Button {
+            label: "Order".to_string(),
+            on_click: Box::new(move |count| {
+                recorded_clicks.lock().unwrap().push(count);
+                count < 2
+            }),
+            on_hover: None,
+        })
+        .unwrap();
+        assert!(c_button.on_hover.is_null());
+        assert!(c_button.validate_c().is_ok());
+
+        // what C code would do with the callback
+        let on_click = c_button.on_click.function.unwrap();
+        assert!(unsafe { on_click(c_button.on_click.user_data, 1) });
+        assert!(!c_button.on_click.call((2,)).unwrap());
+
+        // the closures hold their own reference on the user data
+        let button = c_button.as_rust().unwrap();
+        drop(c_button);
+        assert!(button.on_hover.is_none());
+        assert!((button.on_click)(0));
+        assert_eq!(*clicks.lock().unwrap(), vec![1, 2, 0]);
+        assert_eq!(Arc::strong_count(&clicks), 2);
+        drop(button);
+        assert_eq!(Arc::strong_count(&clicks), 1);
+    }
+
+    #[test]
+    fn callbacks_created_in_c() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static HOVERS: AtomicUsize = AtomicUsize::new(0);
+        static REFERENCES: AtomicUsize = AtomicUsize::new(1);
+
+        unsafe extern "C" fn hover(user_data: *mut libc::c_void) {
+            (*(user_data as *const AtomicUsize)).fetch_add(1, Ordering::SeqCst);
+        }
+        unsafe extern "C" fn click(_: *mut libc::c_void, count: u32) -> bool {
+            count > 0
+        }
+        unsafe extern "C" fn retain(user_data: *mut libc::c_void) -> *mut libc::c_void {
+            REFERENCES.fetch_add(1, Ordering::SeqCst);
+            user_data
+        }
+        unsafe extern "C" fn release(_: *mut libc::c_void) {
+            REFERENCES.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        let c_hover = CCallback::<()> {
+            function: Some(hover),
+            user_data: &HOVERS as *const AtomicUsize as *mut libc::c_void,
+            retain: Some(retain),
+            release: Some(release),
+        };
+        let on_hover: Box<dyn Fn()> = c_hover.as_rust().unwrap();
+        on_hover();
+        on_hover();
+        assert_eq!(HOVERS.load(Ordering::SeqCst), 2);
+        assert_eq!(REFERENCES.load(Ordering::SeqCst), 2);
+        drop(on_hover);
+        drop(c_hover);
+        assert_eq!(REFERENCES.load(Ordering::SeqCst), 0);
+
+        // unmanaged user data is shared as is
+        let c_click = CCallback::<(u32,), bool> {
+            function: Some(click),
+            user_data: std::ptr::null_mut(),
+            retain: None,
+            release: None,
+        };
+        let on_click: Box<dyn Fn(u32) -> bool> = c_click.as_rust().unwrap();
+        assert!(on_click(3));
+
+        // user data that would be released by the callback can't be shared without retain
+        let c_click = CCallback::<(u32,), bool> {
+            release: Some(release),
+            ..c_click
+        };
+        assert!(matches!(
+            AsRust::<Box<dyn Fn(u32) -> bool>>::as_rust(&c_click),
+            Err(AsRustError::Other(_))
+        ));
+
+        let c_click = CCallback::<(u32,), bool>::null();
+        assert!(matches!(
+            c_click.validate_c(),
+            Err(AsRustError::NullPointer(_))
+        ));
+        assert!(matches!(
+            c_click.call((1,)),
+            Err(AsRustError::NullPointer(_))
+        ));
+    }
+
+    #[test]
+    fn opaque_values() {
+        let listener = Listener::default();
+        let model = Arc::new(Model {
+            weights: vec![0.5; 64],
+        });
+
+        let c_subscription = CSubscription::c_repr_of(Subscription {
+            topic: "orders".to_string(),
+            listener: listener.clone(),
+            model: model.clone(),
+            fallback: None,
+        })
+        .unwrap();
+        assert!(c_subscription.fallback.is_null());
+        assert!(c_subscription.validate_c().is_ok());
+
+        // the values are not copied, a C consumer only passes them back
+        c_subscription
+            .listener
+            .get()
+            .unwrap()
+            .received
+            .lock()
+            .unwrap()
+            .push("order #1".to_string());
+        assert_eq!(*listener.received.lock().unwrap(), vec!["order #1"]);
+        let shared: Arc<Model> = c_subscription.as_rust_model().unwrap();
+        assert!(Arc::ptr_eq(&shared, &model));
+        drop(shared);
+
+        // a value of another type is rejected instead of being misread
+        assert!(matches!(
+            c_subscription.as_rust_model::<Listener>(),
+            Err(AsRustError::Other(_))
+        ));
+
+        // the values are freed with the C struct, whatever their type
+        assert_eq!(Arc::strong_count(&model), 2);
+        drop(c_subscription);
+        assert_eq!(Arc::strong_count(&model), 1);
+        assert_eq!(Arc::strong_count(&listener.received), 1);
+
+        let c_listener = COpaque::c_repr_of(listener.clone()).unwrap();
+        assert_eq!(c_listener.into_inner().unwrap(), listener);
+        assert_eq!(Arc::strong_count(&listener.received), 1);
+    }
+
+    #[test]
+    fn spans() {
+        let c_excerpt = CExcerpt::c_repr_of(Excerpt {
+            lines: 1..2,
+            bytes: 16..48,
+            highlight: None,
+        })
+        .unwrap();
+        assert_eq!(
+            c_excerpt.bytes,
+            CSpan {
+                start: 16,
+                length: 32
+            }
+        );
+        assert!(c_excerpt.validate_c().is_ok());
+
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = 8..4;
+        assert!(matches!(
+            CSpan::<usize>::c_repr_of(reversed),
+            Err(CReprOfError::Other(_))
+        ));
+        assert!(matches!(
+            CSpan::<u32>::c_repr_of(0..usize::MAX),
+            Err(CReprOfError::IntegerOverflow(_))
+        ));
+
+        let overflowing = CSpan {
+            start: usize::MAX,
+            length: 1,
+        };
+        assert!(overflowing.validate_c().is_err());
+        assert!(matches!(
+            AsRust::<Range<usize>>::as_rust(&overflowing),
+            Err(AsRustError::Other(_))
+        ));
+    }
+
+    #[test]
+    fn boxed_fields() {
+        let step = RecipeStep {
+            label: "fold".into(),
+            note: None,
+            duration: Box::new(Duration::from_millis(1500)),
+            then: Some(Box::new(RecipeStep {
+                label: "serve".into(),
+                note: None,
+                duration: Box::new(Duration::ZERO),
+                then: None,
+            })),
+        };
+        let c_step = CRecipeStep::c_repr_of(step.clone()).unwrap();
+        assert_eq!(c_step.as_rust_label().unwrap(), "fold");
+        assert_eq!(
+            c_step.as_rust_duration::<Duration>().unwrap(),
+            Box::new(Duration::from_millis(1500))
+        );
+
+        let then = c_step.as_rust_then::<RecipeStep>().unwrap().unwrap();
+        assert_eq!(then.label, "serve".into());
+        assert!(then.then.is_none());
+        assert_eq!(c_step.as_rust().unwrap(), step);
+
+        let label: Box<str> = CStr::from_bytes_with_nul(b"fold\0")
+            .unwrap()
+            .as_rust()
+            .unwrap();
+        assert_eq!(&*label, "fold");
+    }
+
+    #[test]
+    fn code_points_and_byte_strings() {
+        let c_chars = CArray::<u32>::c_repr_of("é🥞".chars().collect::<Vec<_>>()).unwrap();
+        assert_eq!(c_chars.as_slice().unwrap(), &[0xe9, 0x1f95e]);
+
+        // surrogates are not valid chars
+        let c_chars = CArray::<u32>::c_repr_of(vec![0x61u32, 0xd800]).unwrap();
+        assert!(matches!(
+            AsRust::<Vec<char>>::as_rust(&c_chars),
+            Err(AsRustError::InElement { index: 1, ref source })
+                if matches!(**source, AsRustError::InvalidChar(_))
+        ));
+
+        let c_bytes = CByteArray::c_repr_of("blini".to_string()).unwrap();
+        assert_eq!(c_bytes.as_slice().unwrap(), b"blini");
+        assert_eq!(AsRustView::<&str>::as_rust_view(&c_bytes).unwrap(), "blini");
+
+        let c_bytes = CByteArray::from(b"cr\xeape".to_vec());
+        assert!(matches!(
+            AsRust::<String>::as_rust(&c_bytes),
+            Err(AsRustError::Utf8Error(_))
+        ));
+        let lossy = ConversionContext {
+            utf8_policy: Utf8Policy::Lossy,
+            ..Default::default()
+        };
+        assert_eq!(
+            as_rust_with::<String, _>(&c_bytes, &lossy).unwrap(),
+            "cr\u{FFFD}pe"
+        );
+    }
+
+    #[test]
+    fn fixed_size_strings() {
+        let c_name = CFixedString::<8>::c_repr_of("crêpe".to_string()).unwrap();
+        assert_eq!(c_name.to_bytes(), "crêpe".as_bytes());
+        assert_eq!(c_name.0[6], 0);
+        assert!(c_name.validate_c().is_ok());
+
+        // room is left for the nul terminator
+        assert!(CFixedString::<8>::c_repr_of("galette".to_string()).is_ok());
+        assert!(matches!(
+            CFixedString::<8>::c_repr_of("pancakes".to_string()),
+            Err(CReprOfError::StringTooLong {
+                length: 8,
+                capacity: 7
+            })
+        ));
+        assert!(matches!(
+            CFixedString::<8>::c_repr_of("cr\0pe".to_string()),
+            Err(CReprOfError::StringContainsNullBit(_))
+        ));
+
+        // truncated on a character boundary
+        let truncating = ConversionContext {
+            truncate_strings: true,
+            ..Default::default()
+        };
+        let c_name: CFixedString<8> =
+            c_repr_of_with("crêpe suzette".to_string(), &truncating).unwrap();
+        assert_eq!(AsRust::<String>::as_rust(&c_name).unwrap(), "crêpe ");
+        let c_name: CFixedString<4> = c_repr_of_with("crêpe".to_string(), &truncating).unwrap();
+        assert_eq!(AsRust::<String>::as_rust(&c_name).unwrap(), "cr");
+
+        // a buffer filled by C code may have no nul terminator
+        let c_name = CFixedString([b'b' as libc::c_char, b'l' as libc::c_char]);
+        assert_eq!(AsRust::<String>::as_rust(&c_name).unwrap(), "bl");
+
+        let c_name = CFixedString([0xea_u8 as libc::c_char, 0]);
+        assert!(matches!(
+            c_name.validate_c(),
+            Err(AsRustError::Utf8Error(_))
+        ));
+        let lossy = ConversionContext {
+            utf8_policy: Utf8Policy::Lossy,
+            ..Default::default()
+        };
+        assert_eq!(
+            as_rust_with::<String, _>(&c_name, &lossy).unwrap(),
+            "\u{FFFD}"
+        );
+
+        let c_sensor = CSensor::c_repr_of(Sensor {
+            name: "hygrometer".to_string(),
+            unit: None,
+            reading: 0.4,
+        })
+        .unwrap();
+        assert!(c_sensor.unit.is_null());
+        assert_eq!(c_sensor.as_rust().unwrap().name, "hygrometer");
+    }
+
+    #[test]
+    fn fallible_getters() {
+        let tags = vec!["premium".to_string()];
+        let mut account =
+            accounts::Account::new(tags.clone(), "ada", Some("ada@example.com")).unwrap();
+
+        let c_account = CAccount::c_repr_of(account_clone(&account)).unwrap();
+        assert_eq!(
+            unsafe { CStringArray::raw_borrow(c_account.tags) }
+                .unwrap()
+                .as_rust()
+                .unwrap(),
+            tags
+        );
+        assert_eq!(
+            unsafe { CStr::raw_borrow(c_account.name) }
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "ada"
+        );
+        assert_eq!(
+            unsafe { CStr::raw_borrow(c_account.email) }
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "ada@example.com"
+        );
+
+        let c_account =
+            CAccount::c_repr_of(accounts::Account::new(vec![], "grace", None).unwrap()).unwrap();
+        assert!(c_account.email.is_null());
+        let c_contact = CAccountContact::c_repr_of(account_clone(&account)).unwrap();
+        assert!(!c_contact.email.is_null());
+
+        account.close();
+        assert!(matches!(
+            CAccount::c_repr_of(account),
+            Err(CReprOfError::Other(error))
+                if error.to_string() == "the email of a closed account is not available"
+        ));
+
+        fn account_clone(account: &accounts::Account) -> accounts::Account {
+            accounts::Account::new(
+                account.tags.clone(),
+                account.name().unwrap(),
+                account.email().unwrap(),
+            )
+            .unwrap()
+        }
+    }
+
+    #[test]
+    fn validation_hooks() {
+        let booking = |guest: &str, nights| Booking {
+            guest: guest.to_string(),
+            nights,
+        };
+        let is_rejected = |result: Result<CBooking, _>, expected: &str| matches!(result, Err(CReprOfError::Other(error)) if error.to_string() == expected);
+
+        assert!(is_rejected(
+            CBooking::c_repr_of(booking("", 3..5)),
+            "a booking needs a guest"
+        ));
+        assert!(is_rejected(
+            CBooking::c_repr_of(booking("Ada", 5..5)),
+            "a booking needs at least a night"
+        ));
+        // the validation functions are called in order
+        assert!(is_rejected(
+            CBooking::c_repr_of(booking("", 5..5)),
+            "a booking needs a guest"
+        ));
+
+        // C code can break the invariants, they are checked again by as_rust
+        let mut c_booking = CBooking::c_repr_of(booking("Ada", 3..5)).unwrap();
+        c_booking.nights.end = 2;
+        assert!(matches!(
+            c_booking.as_rust(),
+            Err(AsRustError::Other(error)) if error.to_string() == "a booking needs at least a night"
+        ));
+    }
+
+    #[test]
+    fn shrinking_arrays() {
+        let model = Arc::new(Model {
+            weights: vec![0.5; 4],
+        });
+        let mut c_models = CArray::<CArc<Model>>::from_iter(vec![model.clone(); 5]).unwrap();
+        assert_eq!(Arc::strong_count(&model), 6);
+
+        // the removed elements are dropped
+        c_models.truncate(3);
+        assert_eq!(c_models.size, 3);
+        assert_eq!(Arc::strong_count(&model), 4);
+        c_models.truncate(10);
+        assert_eq!(c_models.size, 3);
+
+        let mut index = 0;
+        c_models.retain(|_| {
+            index += 1;
+            index != 2
+        });
+        assert_eq!(c_models.size, 2);
+        assert_eq!(Arc::strong_count(&model), 3);
+        assert!(c_models
+            .iter()
+            .unwrap()
+            .all(|c_model| c_model.ptr == Arc::as_ptr(&model)));
+
+        c_models.retain(|_| false);
+        assert!(c_models.data_ptr.is_null());
+        assert_eq!(c_models.size, 0);
+        assert_eq!(Arc::strong_count(&model), 1);
+
+        let mut c_layers = CArray::<CLayer>::c_repr_of(vec![
+            Layer {
+                number: 1,
+                subtitle: Some("flour".to_string()),
+            },
+            Layer {
+                number: 2,
+                subtitle: None,
+            },
+        ])
+        .unwrap();
+        c_layers.retain(|c_layer| !c_layer.subtitle.is_null());
+        assert_eq!(
+            c_layers.as_rust().unwrap(),
+            vec![Layer {
+                number: 1,
+                subtitle: Some("flour".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn value_fields_are_dropped() {
+        let model = Arc::new(Model {
+            weights: vec![0.5; 4],
+        });
+        let mut c_catalog = CCatalog::c_repr_of(Catalog {
+            names: vec!["small".to_string(), "large".to_string()],
+            models: vec![model.clone(), model.clone()],
+            default_model: model.clone(),
+        })
+        .unwrap();
+        assert_eq!(Arc::strong_count(&model), 4);
+
+        c_catalog.do_drop().unwrap();
+        assert!(c_catalog.names.data.is_null());
+        assert!(c_catalog.models.data_ptr.is_null());
+        // the shallow field is left to rust
+        assert_eq!(Arc::strong_count(&model), 2);
+
+        // the fields already dropped are not dropped twice
+        c_catalog.do_drop().unwrap();
+        drop(c_catalog);
+        assert_eq!(Arc::strong_count(&model), 1);
+    }
+
+    #[test]
+    fn nested_value_structs_are_not_dropped_twice() {
+        let mut c_banner = CBanner::c_repr_of(Banner {
+            label: Label {
+                text: "Today's special".to_string(),
+                tooltip: Some("Pancakes with maple syrup".to_string()),
+            },
+            priority: 1,
+        })
+        .unwrap();
+
+        c_banner.do_drop().unwrap();
+        assert!(c_banner.label.text.is_null());
+        assert!(c_banner.label.tooltip.is_null());
+
+        // rust drops the nested label, whose strings were already freed
+        drop(c_banner);
+    }
+
+    #[test]
+    fn defensive_drop() {
+        let author = Listener::default();
+        let mut c_note = CNote::c_repr_of(Note {
+            title: "pancakes".to_string(),
+            comment: Some("with less sugar".to_string()),
+            layer: Layer {
+                number: 1,
+                subtitle: Some("flour".to_string()),
+            },
+            author: author.clone(),
+        })
+        .unwrap();
+        assert_eq!(Arc::strong_count(&author.received), 2);
+
+        c_note.do_drop().unwrap();
+        assert!(c_note.title.is_null());
+        assert!(c_note.comment.is_null());
+        assert!(c_note.layer.is_null());
+        assert!(c_note.author.is_null());
+        assert_eq!(Arc::strong_count(&author.received), 1);
+
+        // dropping the struct again is a no-op
+        c_note.do_drop().unwrap();
+        drop(c_note);
+    }
+
+    #[test]
+    fn derives_without_imports() {
+        use without_imports::{CParcel, Parcel};
+
+        let parcel = Parcel {
+            reference: "FR-1234".to_string(),
+            weights: vec![250, 500],
+            label: Some(Label {
+                text: "fragile".to_string(),
+                tooltip: None,
+            }),
+        };
+        let c_parcel = CParcel::c_repr_of(parcel.clone()).unwrap();
+        c_parcel.validate_c().unwrap();
+        assert_eq!(c_parcel.as_rust().unwrap(), parcel);
+        assert_eq!(
+            c_parcel.as_rust_weights::<Vec<u32>>().unwrap(),
+            parcel.weights
+        );
+    }
+
+    #[test]
+    fn atomics() {
+        use std::sync::atomic::Ordering;
+
+        let c_counters = CCounters::c_repr_of(Counters {
+            hits: AtomicU32::new(12),
+            enabled: AtomicBool::new(true),
+        })
+        .unwrap();
+        assert_eq!(c_counters.hits, 12);
+        assert!(c_counters.enabled);
+        let counters: Counters = c_counters.as_rust().unwrap();
+        assert_eq!(counters.hits.load(Ordering::SeqCst), 12);
+        assert!(counters.enabled.load(Ordering::SeqCst));
+
+        let hits = Arc::new(AtomicUsize::new(3));
+        let enabled = Arc::new(AtomicBool::new(false));
+        let shared_counters = SharedCounters {
+            hits: hits.clone(),
+            enabled: enabled.clone(),
+        };
+        hits.fetch_add(2, Ordering::Release);
+        let c_counters = CSharedCounters::c_repr_of(shared_counters).unwrap();
+        assert_eq!(c_counters.hits, 5);
+        assert!(!c_counters.enabled);
+
+        // the value is checked like a non atomic integer
+        hits.store(usize::MAX, Ordering::Release);
+        assert!(matches!(
+            CSharedCounters::c_repr_of(SharedCounters { hits, enabled }),
+            Err(CReprOfError::IntegerOverflow(_))
+        ));
+    }
+
+    #[test]
+    fn generated_destructors() {
+        let c_label = CLabel::c_repr_of(Label {
+            text: "fragile".to_string(),
+            tooltip: Some("handle with care".to_string()),
+        })
+        .unwrap();
+        assert_eq!(
+            unsafe { c_label_destroy(c_label.into_raw_pointer_mut()) },
+            0
+        );
+        assert_eq!(unsafe { c_label_destroy(std::ptr::null_mut()) }, -1);
+
+        // the panic doesn't unwind into the caller
+        assert_eq!(
+            unsafe { ffi_exploding_destroy(CExploding.into_raw_pointer_mut()) },
+            -2
+        );
+    }
+
+    #[test]
+    fn generated_accessors() {
+        unsafe {
+            let c_thermostat = c_thermostat_new();
+            assert_eq!((*c_thermostat).as_rust().unwrap(), Thermostat::default());
+            assert_eq!(c_thermostat_destroy(c_thermostat), 0);
+
+            let c_thermostat = c_living_room_thermostat_new();
+            let mut room = std::ptr::null();
+            assert_eq!(
+                c_living_room_thermostat_get_room(c_thermostat, &mut room),
+                0
+            );
+            assert_eq!(CStr::from_ptr(room).to_str().unwrap(), "living room");
+            let mut target = 0.0;
+            assert_eq!(
+                c_living_room_thermostat_get_target(c_thermostat, &mut target),
+                0
+            );
+            assert_eq!(target, 19.5);
+            let mut schedule = std::ptr::null();
+            assert_eq!(
+                c_living_room_thermostat_get_schedule(c_thermostat, &mut schedule),
+                0
+            );
+            let schedule: Vec<u8> = (*schedule).as_rust().unwrap();
+            assert_eq!(schedule, vec![7, 22]);
+
+            assert_eq!(
+                c_living_room_thermostat_get_target(std::ptr::null(), &mut target),
+                -1
+            );
+            drop(CLivingRoomThermostat::from_raw_pointer_mut(c_thermostat));
+        }
+    }
+
+    #[test]
+    fn string_policies() {
+        let command = Command {
+            verb: "stop".to_string(),
+            argument: Some("now\n".to_string()),
+        };
+        let c_command = CCommand::c_repr_of(command.clone()).unwrap();
+        assert!(c_command.validate_c().is_ok());
+        assert_eq!(c_command.as_rust().unwrap(), command);
+
+        let c_command = CCommand::c_repr_of(Command {
+            verb: "st\top".to_string(),
+            argument: None,
+        })
+        .unwrap();
+        assert!(matches!(
+            c_command.validate_c(),
+            Err(AsRustError::ControlCharacter { position: 2 })
+        ));
+        assert!(matches!(
+            c_command.as_rust(),
+            Err(AsRustError::ControlCharacter { position: 2 })
+        ));
+
+        // the bytes after the maximum length are not read
+        let unterminated = [b'a' as libc::c_char; 5];
+        let c_command = std::mem::ManuallyDrop::new(CCommand {
+            verb: unterminated.as_ptr(),
+            argument: std::ptr::null(),
+        });
+        assert!(matches!(
+            c_command.as_rust(),
+            Err(AsRustError::StringTooLong { max_len: 4 })
+        ));
+    }
+
+    #[test]
+    fn error_handling() {
+        unsafe fn last_error() -> String {
+            let mut error = std::ptr::null();
+            assert_eq!(ffi_get_last_error(&mut error), FFI_RESULT::FFI_RESULT_OK);
+            let message = CStr::from_ptr(error).to_str().unwrap().to_string();
+            assert_eq!(ffi_destroy_error(error), FFI_RESULT::FFI_RESULT_OK);
+            message
+        }
+
+        unsafe {
+            let mut temperature = 0.0;
+            let input = CString::new("21.5").unwrap();
+            assert_eq!(
+                ffi_parse_temperature(input.as_ptr(), &mut temperature),
+                FFI_RESULT::FFI_RESULT_OK
+            );
+            assert_eq!(temperature, 21.5);
+            assert_eq!(last_error(), "");
+
+            let input = CString::new("hot").unwrap();
+            assert_eq!(
+                ffi_parse_temperature(input.as_ptr(), &mut temperature),
+                FFI_RESULT::FFI_RESULT_KO
+            );
+            assert_eq!(
+                last_error(),
+                "invalid temperature \"hot\"\nCaused by: invalid float literal"
+            );
+
+            let input = CString::new("-300").unwrap();
+            assert_eq!(
+                ffi_parse_temperature(input.as_ptr(), &mut temperature),
+                FFI_RESULT::FFI_RESULT_BELOW_ABSOLUTE_ZERO
+            );
+            assert_eq!(last_error(), "-300 is below the absolute zero");
+
+            assert_eq!(
+                ffi_parse_temperature(input.as_ptr(), std::ptr::null_mut()),
+                FFI_RESULT::FFI_RESULT_NULL_POINTER
+            );
+            assert_eq!(
+                last_error(),
+                "Could not use raw pointer: unexpected null pointer"
+            );
+
+            assert_eq!(
+                ffi_get_last_error(std::ptr::null_mut()),
+                FFI_RESULT::FFI_RESULT_NULL_POINTER
+            );
+        }
+    }
+
+    #[test]
+    fn caught_panics() {
+        unsafe {
+            let mut name = std::ptr::null();
+            assert_eq!(ffi_day_name(4, &mut name), FFI_RESULT::FFI_RESULT_OK);
+            assert_eq!(
+                CString::from_raw_pointer(name).unwrap().to_str().unwrap(),
+                "friday"
+            );
+
+            assert_eq!(
+                ffi_day_name(4, std::ptr::null_mut()),
+                FFI_RESULT::FFI_RESULT_KO
+            );
+            assert_eq!(error_store::take_last_error().unwrap(), "no output");
+
+            assert_eq!(ffi_day_name(7, &mut name), FFI_RESULT::FFI_RESULT_PANIC);
+            assert_eq!(
+                error_store::take_last_error().unwrap(),
+                "panic: invalid day 7"
+            );
+        }
+    }
+
+    #[test]
+    fn zeroed_structs() {
+        let zeroed_margins: CMargins = unsafe { std::mem::zeroed() };
+        assert!(zeroed_margins.is_zeroed());
+        assert_eq!(zeroed_margins.as_rust().unwrap(), Margins::default());
+        std::mem::forget(zeroed_margins);
+
+        let margins = Margins {
+            label: "".to_string(),
+            width: 0,
+        };
+        let c_margins = CMargins::c_repr_of(margins.clone()).unwrap();
+        assert!(!c_margins.is_zeroed());
+        assert_eq!(c_margins.as_rust().unwrap(), margins);
+
+        let zeroed_proxy: CProxy = unsafe { std::mem::zeroed() };
+        assert!(zeroed_proxy.is_zeroed());
+        assert_eq!(
+            AsRust::<Option<Proxy>>::as_rust(&zeroed_proxy).unwrap(),
+            None
+        );
+        assert!(AsRust::<Proxy>::as_rust(&zeroed_proxy).is_err());
+
+        let proxy = Proxy {
+            host: "localhost".to_string(),
+            port: 3128,
+        };
+        let c_proxy = CProxy::c_repr_of(proxy.clone()).unwrap();
+        assert_eq!(
+            AsRust::<Option<Proxy>>::as_rust(&c_proxy).unwrap(),
+            Some(proxy)
+        );
+        // the fields which are not zeroed are checked as usual
+        let partial_proxy = std::mem::ManuallyDrop::new(CProxy {
+            host: std::ptr::null(),
+            port: 8080,
+        });
+        assert!(AsRust::<Option<Proxy>>::as_rust(&*partial_proxy).is_err());
+        std::mem::forget(zeroed_proxy);
+    }
+
+    #[test]
+    fn zeroed_structs_with_padding() {
+        // the padding of the nested struct is left uninitialized, it must not be read
+        let zeroed_frame = std::mem::ManuallyDrop::new(CFrame {
+            inset: CInset {
+                enabled: false,
+                width: 0,
+            },
+            title: std::ptr::null(),
+            weights: [0.0; 2],
+        });
+        assert!(zeroed_frame.is_zeroed());
+        assert_eq!(zeroed_frame.as_rust().unwrap(), Frame::default());
+
+        let frame = Frame {
+            inset: Inset {
+                enabled: true,
+                width: 0,
+            },
+            title: "".to_string(),
+            weights: [0.0, -0.0],
+        };
+        let c_frame = CFrame::c_repr_of(frame.clone()).unwrap();
+        assert!(!c_frame.inset.is_zeroed());
+        assert!(!c_frame.weights.is_zeroed());
+        assert!(!c_frame.is_zeroed());
+        assert_eq!(c_frame.as_rust().unwrap(), frame);
+    }
+
+    #[test]
+    fn exported_allocator() {
+        unsafe {
+            // a string allocated by C code, then owned by Rust
+            let buffer = ffi_convert_alloc(6) as *mut u8;
+            buffer.copy_from_nonoverlapping(b"hello\0".as_ptr(), 6);
+            let string = CString::from_raw_pointer(buffer as *const libc::c_char).unwrap();
+            assert_eq!(string.to_str().unwrap(), "hello");
+
+            // a string allocated by Rust, then freed by C code
+            let string: *const libc::c_char = CString::new("bye").unwrap().into_raw_pointer();
+            ffi_convert_free(string as *mut libc::c_void, 4);
+
+            assert!(!ffi_convert_alloc(0).is_null());
+            ffi_convert_free(ffi_convert_alloc(0), 0);
+            ffi_convert_free(std::ptr::null_mut(), 8);
+        }
+    }
+
+    #[test]
+    fn arena_sessions() {
+        let arena = ffi_convert_arena_create();
+        let session = unsafe { CArena::raw_borrow(arena) }.unwrap();
+
+        let proxy: *const CProxy = session
+            .c_repr_of(Proxy {
+                host: "localhost".to_string(),
+                port: 3128,
+            })
+            .unwrap();
+        let host = session.c_string("example.com".to_string()).unwrap();
+        let marker = std::rc::Rc::new(());
+        session.alloc(marker.clone());
+        // the string is copied in the memory of the arena
+        assert_eq!(session.len(), 2);
+        unsafe {
+            assert_eq!((*proxy).port, 3128);
+            assert_eq!(CStr::from_ptr(host).to_str().unwrap(), "example.com");
+        }
+
+        assert_eq!(unsafe { ffi_convert_arena_destroy(arena) }, 0);
+        // all the values were dropped with the arena
+        assert_eq!(std::rc::Rc::strong_count(&marker), 1);
+        assert_eq!(
+            unsafe { ffi_convert_arena_destroy(std::ptr::null_mut()) },
+            -1
+        );
+    }
+
+    #[test]
+    fn heterogeneous_arrays() {
+        let messages: Vec<Box<dyn AnyConvertible>> = vec![
+            Box::new(PeerConnected {
+                peer: "kitchen".to_string(),
+                channels: vec!["volume".to_string(), "queue".to_string()],
+            }),
+            Box::new(1_700_000_000_u64),
+            Box::new(Payload {
+                channel: "volume".to_string(),
+                data: vec![0, 42],
+            }),
+        ];
+
+        let c_messages = CArray::<CTaggedValue<CBusMessage>>::c_repr_of(messages).unwrap();
+        let tags = c_messages.iter().unwrap().map(|message| message.tag);
+        assert_eq!(tags.collect::<Vec<_>>(), vec![1, 3, 2]);
+        let heartbeat = c_messages.get(1).unwrap();
+        assert_eq!(
+            unsafe { heartbeat.value.assume_init_ref().heartbeat },
+            1_700_000_000
+        );
+
+        let messages: Vec<Box<dyn AnyConvertible>> = c_messages.as_rust().unwrap();
+        assert_eq!(
+            messages[0].downcast_ref(),
+            Some(&PeerConnected {
+                peer: "kitchen".to_string(),
+                channels: vec!["volume".to_string(), "queue".to_string()],
+            })
+        );
+        assert_eq!(messages[1].downcast_ref(), Some(&1_700_000_000_u64));
+        assert!(messages[2].is::<Payload>());
+        let payload = messages.into_iter().nth(2).unwrap().downcast::<Payload>();
+        assert_eq!(payload.unwrap().data, vec![0, 42]);
+
+        assert_eq!(CBusMessage::registry().tag_of::<Payload>(), Some(2));
+        assert_eq!(CBusMessage::registry().tag_of::<String>(), None);
+    }
+
+    #[test]
+    fn heterogeneous_arrays_errors() {
+        let messages: Vec<Box<dyn AnyConvertible>> = vec![Box::new(3_u64), Box::new(3_u32)];
+        assert!(matches!(
+            CArray::<CTaggedValue<CBusMessage>>::c_repr_of(messages),
+            Err(CReprOfError::InElement { index: 1, source })
+                if matches!(*source, CReprOfError::UnregisteredType { type_name: "u32" })
+        ));
+
+        let mut c_message = CTaggedValue::<CBusMessage>::c_repr_of(Box::new(Payload {
+            channel: "queue".to_string(),
+            data: vec![],
+        }))
+        .unwrap();
+        c_message.tag = 7;
+        assert!(matches!(
+            c_message.as_rust(),
+            Err(AsRustError::UnknownTag { tag: 7 })
+        ));
+        c_message.tag = 2;
+
+        // the variant is released once, even if the value is dropped again
+        c_message.do_drop().unwrap();
+        assert_eq!(c_message.tag, CVariantRegistry::<CBusMessage>::RELEASED_TAG);
+        c_message.do_drop().unwrap();
+    }
+
+    #[test]
+    fn conversions_into_caller_structs() {
+        unsafe extern "C" fn get_book(pages: u32, book: *mut CBook) -> CStatus {
+            let title = if pages == 0 { "\0" } else { "Dune" };
+            CBook::c_repr_of_at(
+                book,
+                Book {
+                    title: title.to_string(),
+                    pages,
                 },
-                Layer {
-                    number: 1,
-                    subtitle: Some(String::from("dough")),
+            )
+        }
+
+        // the struct is allocated by the caller, on its stack
+        let mut book = std::mem::MaybeUninit::<CBook>::uninit();
+        assert_eq!(unsafe { get_book(412, book.as_mut_ptr()) }, CStatus::Ok);
+        let book = unsafe { book.assume_init() };
+        assert_eq!(
+            book.as_rust().unwrap(),
+            Book {
+                title: "Dune".to_string(),
+                pages: 412
+            }
+        );
+
+        let mut book = std::mem::MaybeUninit::<CBook>::uninit();
+        assert_eq!(unsafe { get_book(0, book.as_mut_ptr()) }, CStatus::Ko);
+        assert!(error_store::take_last_error().unwrap().contains("nul"));
+        assert_eq!(unsafe { get_book(412, std::ptr::null_mut()) }, CStatus::Ko);
+    }
+
+    #[test]
+    fn generated_function_signatures() {
+        let reprogram = registered_signature("ffi_reprogram_thermostat").unwrap();
+        assert_eq!(
+            reprogram.parameters,
+            &[
+                CParameter {
+                    name: "thermostat",
+                    ty: "*const CThermostat",
+                    direction: Direction::In,
+                    ownership: ParameterOwnership::Borrowed,
                 },
-                Layer {
-                    number: 2,
-                    subtitle: Some(String::from("cream")),
+                CParameter {
+                    name: "room",
+                    ty: "*const libc::c_char",
+                    direction: Direction::In,
+                    ownership: ParameterOwnership::Borrowed,
                 },
-            ],
-            is_delicious: true,
-            range: Range {
-                start: 50,
-                end: 100,
+                CParameter {
+                    name: "schedule",
+                    ty: "CArray<u8>",
+                    direction: Direction::In,
+                    ownership: ParameterOwnership::Released,
+                },
+                CParameter {
+                    name: "output",
+                    ty: "*mut *const CThermostat",
+                    direction: Direction::Out,
+                    ownership: ParameterOwnership::OwnedByCaller,
+                },
+            ]
+        );
+        assert_eq!(reprogram.return_type, "CStatus");
+        assert_eq!(reprogram.return_codes.len(), 2);
+        assert_eq!(
+            registered_signature("ffi_model_size").unwrap().parameters[0].ownership,
+            ParameterOwnership::Borrowed
+        );
+        assert_eq!(
+            registered_signature("ffi_release_boxed_model")
+                .unwrap()
+                .parameters[0]
+                .ownership,
+            ParameterOwnership::Released
+        );
+
+        let destroy = registered_signature("c_thermostat_destroy").unwrap();
+        assert_eq!(
+            destroy.parameters[0].ownership,
+            ParameterOwnership::Released
+        );
+        let new = registered_signature("c_thermostat_new").unwrap();
+        assert_eq!(new.return_ownership, ParameterOwnership::OwnedByCaller);
+        assert_eq!(
+            registered_signature("c_thermostat_get_target")
+                .unwrap()
+                .doxygen_comment(),
+            "/**
+ * Writes the `target` field of a `CThermostat` to `value` (the struct keeps the ownership of its content). Returns `0` on success, and `-1` if a pointer is `null`.
+ *
+ * @param[in] ptr `*const CThermostat`, borrowed, its owner keeps the ownership
+ * @param[out] value `*mut f32`, passed by value
+ * @return `i32` : `0` (success), `-1` (null pointer)
+ */"
+        );
+
+        let get_last_error = registered_signature("ffi_get_last_error").unwrap();
+        assert_eq!(get_last_error.return_type, "FFI_RESULT");
+        assert_eq!(
+            get_last_error.return_codes[4],
+            CReturnCode {
+                value: 4,
+                meaning: "FFI_RESULT_BELOW_ABSOLUTE_ZERO"
+            }
+        );
+        let clone = registered_signature("ffi_model_clone").unwrap();
+        assert_eq!(clone.return_type, "*const Model");
+
+        // the functions written by hand are not registered
+        assert!(registered_signature("ffi_parse_temperature").is_none());
+    }
+
+    #[test]
+    fn conversions_in_arenas() {
+        let bookcases = (0..100)
+            .map(|index| Bookcase {
+                label: format!("shelf {}", index),
+                note: (index % 2 == 0).then(|| "even".to_string()),
+                books: (0..index)
+                    .map(|pages| Book {
+                        title: format!("book {}", pages),
+                        pages,
+                    })
+                    .collect(),
+                tags: vec!["wood".to_string(); index as usize % 3],
+                favorite: Some(Book {
+                    title: "favorite".to_string(),
+                    pages: index,
+                }),
+            })
+            .collect::<Vec<_>>();
+
+        let arena = CArena::new();
+        let c_bookcases: &CArray<CBookcase> = arena.c_repr_of_in(bookcases.clone()).unwrap();
+        assert_eq!(
+            AsRust::<Vec<Bookcase>>::as_rust(c_bookcases).unwrap(),
+            bookcases
+        );
+        // nothing is dropped one by one, all the memory is freed with the arena
+        assert!(arena.is_empty());
+
+        let mut invalid = bookcases[3].clone();
+        invalid.books[1].title = "nul\0byte".to_string();
+        assert!(matches!(
+            arena.c_repr_of_in::<CBookcase, _>(invalid),
+            Err(CReprOfError::InElement { index: 1, .. })
+        ));
+
+        /// A C value converted from `()`, so that arrays of them too large to be allocated can be
+        /// requested without allocating the input
+        pub struct CBlock(#[allow(dead_code)] [u8; 1 << 16]);
+
+        impl CReprOfIn<()> for CBlock {
+            fn c_repr_of_in(
+                _: (),
+                _: &CArena,
+            ) -> Result<std::mem::ManuallyDrop<Self>, CReprOfError> {
+                unreachable!("the array is not allocated")
+            }
+        }
+
+        assert!(matches!(
+            arena.c_repr_of_in::<CArray<CBlock>, _>(vec![(); 1 << 48]),
+            Err(CReprOfError::AllocationTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn shared_error_store() {
+        error_store::set_last_error("line 1\0line 2");
+        unsafe {
+            let mut error = std::ptr::null();
+            error_store::last_error_to_c(&mut error).unwrap();
+            assert_eq!(
+                CStr::from_ptr(error).to_str().unwrap(),
+                "line 1\u{FFFD}line 2"
+            );
+            error_store::destroy_c_error(error).unwrap();
+
+            assert!(error_store::last_error_to_c(std::ptr::null_mut()).is_err());
+        }
+        assert_eq!(error_store::take_last_error(), None);
+    }
+
+    #[test]
+    fn stringified_numbers() {
+        unsafe fn text(ptr: *const libc::c_char) -> &'static str {
+            CStr::from_ptr(ptr).to_str().unwrap()
+        }
+
+        let measure = Measure {
+            value: 1234567.125,
+            count: u64::MAX,
+            offset: Some(-0.1),
+        };
+        let c_measure = CMeasure::c_repr_of(measure.clone()).unwrap();
+        unsafe {
+            assert_eq!(text(c_measure.value), "1234567.125");
+            assert_eq!(text(c_measure.count), "18446744073709551615");
+            assert_eq!(text(c_measure.offset), "-0.1");
+        }
+        assert_eq!(c_measure.as_rust().unwrap(), measure);
+        assert_eq!(c_measure.as_rust_count::<u64>().unwrap(), u64::MAX);
+
+        let c_measure = CMeasure::c_repr_of(Measure {
+            value: f64::NEG_INFINITY,
+            count: 0,
+            offset: None,
+        })
+        .unwrap();
+        assert!(c_measure.offset.is_null());
+        assert_eq!(c_measure.as_rust().unwrap().value, f64::NEG_INFINITY);
+
+        let value = CString::new("1,5").unwrap();
+        let c_measure = std::mem::ManuallyDrop::new(CMeasure {
+            value: value.as_ptr(),
+            count: value.as_ptr(),
+            offset: std::ptr::null(),
+        });
+        assert_eq!(
+            c_measure.as_rust().unwrap_err().to_string(),
+            "An error occurred during conversion to Rust: `1,5` is not a valid `f64`: invalid \
+            float literal"
+        );
+    }
+
+    #[test]
+    fn exported_functions() {
+        unsafe {
+            let kitchen = CString::new("kitchen").unwrap();
+            let empty = CString::new("").unwrap();
+            let c_thermostat = CThermostat::c_repr_of(Thermostat::living_room()).unwrap();
+            let c_schedule = CArray::c_repr_of(vec![6, 23]).unwrap();
+            let mut reprogrammed = std::ptr::null();
+            // the array passed by value is released by the function
+            assert_eq!(
+                ffi_reprogram_thermostat(
+                    &c_thermostat,
+                    kitchen.as_ptr(),
+                    c_schedule,
+                    &mut reprogrammed
+                ),
+                CStatus::Ok
+            );
+            assert_eq!(
+                CThermostat::from_raw_pointer(reprogrammed)
+                    .unwrap()
+                    .as_rust()
+                    .unwrap(),
+                Thermostat {
+                    room: "kitchen".to_string(),
+                    target: 19.5,
+                    schedule: vec![6, 23],
+                }
+            );
+
+            let c_schedule = CArray::c_repr_of(Vec::<u8>::new()).unwrap();
+            assert_eq!(
+                ffi_reprogram_thermostat(
+                    &c_thermostat,
+                    empty.as_ptr(),
+                    c_schedule,
+                    &mut reprogrammed
+                ),
+                CStatus::Ko
+            );
+            assert_eq!(
+                error_store::take_last_error().unwrap(),
+                "the room can't be empty"
+            );
+
+            let c_schedule = CArray::c_repr_of(Vec::<u8>::new()).unwrap();
+            assert_eq!(
+                ffi_reprogram_thermostat(
+                    &c_thermostat,
+                    std::ptr::null(),
+                    c_schedule,
+                    &mut reprogrammed
+                ),
+                CStatus::Ko
+            );
+            assert!(error_store::take_last_error().is_some());
+
+            let c_values = CArray::c_repr_of(vec![1.0, 2.0]).unwrap();
+            let mut average = 0.0;
+            assert_eq!(ffi_average(&c_values, 2.0, &mut average), CStatus::Ok);
+            assert_eq!(average, 3.0);
+            assert_eq!(
+                ffi_average(&c_values, 2.0, std::ptr::null_mut()),
+                CStatus::Ko
+            );
+            assert!(error_store::take_last_error().is_some());
+
+            // the panic doesn't unwind into the caller
+            let c_values = CArray::<f32>::c_repr_of(vec![]).unwrap();
+            assert_eq!(ffi_average(&c_values, 2.0, &mut average), CStatus::Ko);
+            assert_eq!(error_store::take_last_error().unwrap(), "panic: no values");
+        }
+    }
+
+    #[test]
+    fn exported_functions_ownership() {
+        let model = Arc::new(Model {
+            weights: vec![0.5; 4],
+        });
+        let mut size = 0;
+        unsafe {
+            // the argument taken by reference is not released
+            let c_model = CArc::c_repr_of(model.clone()).unwrap();
+            assert_eq!(ffi_model_size(&c_model, &mut size), CStatus::Ok);
+            assert_eq!(size, 4);
+            assert_eq!(Arc::strong_count(&model), 2);
+            drop(c_model);
+            assert_eq!(Arc::strong_count(&model), 1);
+
+            // the arguments taken by value are released
+            let c_model = CArc::c_repr_of(model.clone()).unwrap();
+            assert_eq!(ffi_release_model(c_model, &mut size), CStatus::Ok);
+            assert_eq!(Arc::strong_count(&model), 1);
+
+            let c_model = CArc::c_repr_of(model.clone()).unwrap().into_raw_pointer();
+            assert_eq!(ffi_release_boxed_model(c_model, &mut size), CStatus::Ok);
+            assert_eq!(Arc::strong_count(&model), 1);
+
+            // even if the function is not called
+            let c_model = CArc::c_repr_of(model.clone()).unwrap().into_raw_pointer();
+            assert_eq!(
+                ffi_release_boxed_model(c_model, std::ptr::null_mut()),
+                CStatus::Ko
+            );
+            assert!(error_store::take_last_error().is_some());
+            assert_eq!(Arc::strong_count(&model), 1);
+
+            let label = CString::c_repr_of("pancakes".to_string()).unwrap();
+            assert_eq!(
+                ffi_label_length(label.into_raw_pointer(), &mut size),
+                CStatus::Ok
+            );
+            assert_eq!(size, 8);
+        }
+    }
+
+    #[test]
+    fn bool_arrays() {
+        let values = vec![
+            true, false, false, true, true, false, true, false, true, true,
+        ];
+
+        let bytes = CBoolArray::<BytePerBool>::c_repr_of(values.clone()).unwrap();
+        assert_eq!(bytes.as_bytes().unwrap(), &[1, 0, 0, 1, 1, 0, 1, 0, 1, 1]);
+        assert_eq!(bytes.as_rust().unwrap(), values);
+
+        let bits = CBoolArray::<BitPacked>::c_repr_of(values.clone()).unwrap();
+        assert_eq!(bits.as_bytes().unwrap(), &[0b0101_1001, 0b0000_0011]);
+        assert_eq!(bits.as_rust().unwrap(), values);
+        assert!(bits.get(9).unwrap());
+        assert!(matches!(
+            bits.get(10),
+            Err(AsRustError::IndexOutOfBounds {
+                index: 10,
+                size: 10
+            })
+        ));
+
+        let empty = CBoolArray::<BitPacked>::c_repr_of(vec![]).unwrap();
+        assert!(empty.data_ptr.is_null());
+        assert_eq!(empty.as_rust().unwrap(), Vec::<bool>::new());
+
+        // any non zero byte is true, as in C
+        let buffer = [0u8, 2, 255];
+        let mut from_c =
+            std::mem::ManuallyDrop::new(CBoolArray::<BytePerBool>::c_repr_of(vec![]).unwrap());
+        from_c.data_ptr = buffer.as_ptr();
+        from_c.size = 3;
+        assert_eq!(from_c.as_rust().unwrap(), vec![false, true, true]);
+
+        let mut dangling = CBoolArray::<BitPacked>::c_repr_of(vec![]).unwrap();
+        dangling.size = 3;
+        assert!(dangling.validate_c().is_err());
+    }
+
+    #[test]
+    fn bytes_buffers() {
+        use bytes::{Bytes, BytesMut};
+
+        let payload = Bytes::from(vec![1, 2, 3, 4]);
+        let payload_ptr = payload.as_ptr();
+        let packet = Packet {
+            header: BytesMut::from(&b"v1"[..]),
+            payload,
+        };
+        let c_packet = CPacket::c_repr_of(packet.clone()).unwrap();
+        assert_eq!(c_packet.as_rust().unwrap(), packet);
+
+        // the clone kept in `packet` shares the buffer, which is copied
+        assert_ne!(c_packet.payload.data_ptr, payload_ptr);
+        drop(c_packet);
+        let c_packet = CPacket::c_repr_of(packet).unwrap();
+        assert_eq!(c_packet.payload.data_ptr, payload_ptr);
+    }
+
+    #[test]
+    fn optional_arrays_are_released() {
+        let mut c_setlist = CSetlist::c_repr_of(Setlist {
+            durations: Some(vec![180.0]),
+        })
+        .unwrap();
+        c_setlist.do_drop().unwrap();
+        assert_eq!(c_setlist.durations.is_some, 0);
+        assert!(c_setlist.durations.value.data_ptr.is_null());
+        // releasing it again does nothing
+        c_setlist.do_drop().unwrap();
+
+        let c_setlist = CSetlist::c_repr_of(Setlist { durations: None }).unwrap();
+        assert!(c_setlist.durations.value.data_ptr.is_null());
+        assert_eq!(c_setlist.as_rust().unwrap(), Setlist { durations: None });
+    }
+
+    #[test]
+    fn optional_string_lists() {
+        for tags in [None, Some(vec![]), Some(vec!["pantry".to_string()])] {
+            let shelf = Shelf {
+                labels: vec!["flour".to_string()],
+                tags: tags.clone(),
+                slots: None,
+                spare: None,
+            };
+            let c_shelf = CShelf::c_repr_of(shelf.clone()).unwrap();
+            assert_eq!(c_shelf.tags.is_null(), tags.is_none());
+            assert!(c_shelf.validate_c().is_ok());
+            assert_eq!(c_shelf.as_rust_tags::<Vec<String>>().unwrap(), tags);
+            assert_eq!(c_shelf.as_rust().unwrap(), shelf);
+        }
+
+        let c_labels = CShelfLabels {
+            labels: CStringArray::from_iter(["flour", "sugar"])
+                .unwrap()
+                .into_raw_pointer(),
+            tags: std::ptr::null(),
+        };
+        assert_eq!(
+            c_labels.as_rust_view().unwrap(),
+            ShelfLabelsView {
+                labels: vec!["flour", "sugar"],
+                tags: None,
+            }
+        );
+
+        let c_labels = CShelfLabels {
+            labels: CStringArray::from_iter(["flour"])
+                .unwrap()
+                .into_raw_pointer(),
+            tags: CStringArray::from_iter(["pantry"])
+                .unwrap()
+                .into_raw_pointer(),
+        };
+        assert_eq!(c_labels.as_rust_view().unwrap().tags, Some(vec!["pantry"]));
+    }
+
+    #[test]
+    fn arrays_from_iterators() {
+        let rows = ["crêpe", "blini"].iter().map(|name| name.to_uppercase());
+        let c_names = CStringArray::from_iter(rows).unwrap();
+        assert_eq!(
+            AsRust::<Vec<String>>::as_rust(&c_names).unwrap(),
+            vec!["CRÊPE".to_string(), "BLINI".to_string()]
+        );
+
+        let c_bytes = CStringArray::from_iter(vec![b"\xff".to_vec(), vec![]]).unwrap();
+        assert_eq!(c_bytes.get(0).unwrap().to_bytes(), b"\xff");
+        assert!(c_bytes.get(1).unwrap().to_bytes().is_empty());
+
+        assert_eq!(
+            CStringArray::from_iter(Vec::<String>::new()).unwrap().size,
+            0
+        );
+        assert!(matches!(
+            CStringArray::from_iter(["a", "b", "c\0"]),
+            Err(CReprOfError::InElement { index: 2, .. })
+        ));
+
+        let c_toppings =
+            CArray::<CTopping>::from_iter((1..=3).map(|amount| Topping { amount })).unwrap();
+        assert_eq!(
+            AsRust::<Vec<Topping>>::as_rust(&c_toppings).unwrap(),
+            vec![
+                Topping { amount: 1 },
+                Topping { amount: 2 },
+                Topping { amount: 3 }
+            ]
+        );
+        assert!(matches!(
+            CArray::<CString>::from_iter(["ok".to_string(), "\0".to_string()]),
+            Err(CReprOfError::InElement { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn primitive_arrays_reuse_their_buffer() {
+        fn check<T: Copy + std::fmt::Debug + PartialEq + 'static>(values: Vec<T>)
+        where
+            CArray<T>: CReprOf<Vec<T>> + AsRust<Vec<T>>,
+        {
+            let expected = values.clone();
+            let buffer = values.as_ptr();
+            let c_values = CArray::<T>::c_repr_of(values).unwrap();
+            assert_eq!(c_values.data_ptr, buffer);
+            assert_eq!(c_values.as_rust().unwrap(), expected);
+        }
+
+        check(vec![1_u8, 2, 3]);
+        check(vec![-1_i16, 2]);
+        check(vec![1_u64 << 40, 7]);
+        check(vec![-(1_i64 << 40)]);
+        check(vec![usize::MAX, 0]);
+        check(vec![0.25_f32, 0.5]);
+        check(vec![0.1_f64]);
+        check(vec![true, false, true]);
+
+        // the array fields of the derived structs too
+        let input = vec![0.5_f32, 1.5];
+        let buffer = input.as_ptr();
+        let c_inference = CInference::c_repr_of(Inference {
+            model: Arc::new(Model { weights: vec![] }),
+            input,
+        })
+        .unwrap();
+        assert_eq!(c_inference.input.data_ptr, buffer);
+
+        // the elements which are not primitive types are converted one by one, even when they
+        // borrow data
+        let values = [1.0, 2.0];
+        let c_samples = CArray::<CSamples>::c_repr_of(vec![Samples {
+            rate: 8000,
+            values: &values,
+        }])
+        .unwrap();
+        assert_eq!(c_samples.as_slice().unwrap()[0].rate, 8000);
+    }
+
+    #[test]
+    fn conversion_timeouts() {
+        let frames = vec![vec![0.5_f32; 16]; 64];
+        let c_frames = CArray::<CArray<f32>>::c_repr_of(frames.clone()).unwrap();
+
+        // the deadline is not reported as the error of an element
+        assert!(matches!(
+            as_rust_with_timeout::<Vec<Vec<f32>>, _>(&c_frames, Duration::ZERO),
+            Err(AsRustError::DeadlineExceeded(DeadlineExceededError { timeout }))
+                if timeout == Duration::ZERO
+        ));
+        assert!(matches!(
+            c_repr_of_with_timeout::<CArray<CArray<f32>>, _>(frames.clone(), Duration::ZERO),
+            Err(CReprOfError::DeadlineExceeded(_))
+        ));
+        assert_eq!(
+            as_rust_with_timeout::<Vec<Vec<f32>>, _>(&c_frames, Duration::from_secs(60)).unwrap(),
+            frames
+        );
+
+        // a nested call can't extend the deadline of the enclosing one
+        struct Frames<'a>(&'a CArray<CArray<f32>>);
+        impl AsRust<Vec<Vec<f32>>> for Frames<'_> {
+            fn as_rust(&self) -> Result<Vec<Vec<f32>>, AsRustError> {
+                as_rust_with_timeout(self.0, Duration::from_secs(60))
+            }
+        }
+        assert!(matches!(
+            as_rust_with_timeout::<Vec<Vec<f32>>, _>(&Frames(&c_frames), Duration::ZERO),
+            Err(AsRustError::DeadlineExceeded(_))
+        ));
+
+        let context = ConversionContext {
+            timeout: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        assert!(matches!(
+            as_rust_with::<Vec<Vec<f32>>, _>(&c_frames, &context),
+            Err(AsRustError::DeadlineExceeded(_))
+        ));
+        // the deadline only applies to the call
+        assert!(check_deadline().is_ok());
+        assert_eq!(c_frames.as_rust().unwrap(), frames);
+    }
+
+    #[test]
+    fn nested_arrays() {
+        let c_frames = CArray::<CArray<f32>>::c_repr_of(vec![vec![0.5, 1.5], vec![]]).unwrap();
+        assert_eq!(c_frames.get(0).unwrap().as_slice().unwrap(), &[0.5, 1.5]);
+        assert!(c_frames.get(1).unwrap().as_slice().unwrap().is_empty());
+
+        let c_recording = CRecording::c_repr_of(Recording {
+            frames: vec![vec![0.1], vec![0.2, 0.3]],
+            labels: vec![vec!["blini".to_string()]],
+            markers: None,
+        })
+        .unwrap();
+        assert!(c_recording.validate_c().is_ok());
+        assert_eq!(
+            c_recording.as_rust_frames::<Vec<Vec<f32>>>().unwrap(),
+            vec![vec![0.1], vec![0.2, 0.3]]
+        );
+        assert_eq!(
+            c_recording.as_rust_labels::<Vec<Vec<String>>>().unwrap(),
+            vec![vec!["blini".to_string()]]
+        );
+
+        // the error of an inner element is reported with the index at each level
+        let error = CArray::<CStringArray>::c_repr_of(vec![vec![], vec!["a".into(), "\0".into()]])
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "element 1: element 1: A string contains a nul bit"
+        );
+    }
+
+    #[test]
+    fn borrowed_view() {
+        let c_menu = CMenu {
+            title: CString::new("Pancakes").unwrap().into_raw_pointer(),
+            subtitle: std::ptr::null(),
+            prices: CArray::c_repr_of(vec![4.5, 6.0]).unwrap(),
+            dummy: CDummy::c_repr_of(Dummy {
+                count: 2,
+                describe: "yo".to_string(),
+            })
+            .unwrap(),
+        };
+
+        let view = c_menu.as_rust_view().expect("could not view menu");
+
+        assert_eq!(view.title.as_ptr(), c_menu.title as *const u8);
+        assert_eq!(view.prices.as_ptr(), c_menu.prices.data_ptr);
+        assert_eq!(
+            view,
+            MenuView {
+                title: "Pancakes",
+                subtitle: None,
+                prices: &[4.5, 6.0],
+                dummy: Dummy {
+                    count: 2,
+                    describe: "yo".to_string(),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn validation() {
+        let c_layer = CLayer::c_repr_of(Layer {
+            number: 1,
+            subtitle: Some(String::from("first layer")),
+        })
+        .unwrap();
+        assert!(c_layer.validate_c().is_ok());
+
+        let c_topping_array = CArray::<CTopping>::c_repr_of(vec![Topping { amount: 2 }]).unwrap();
+        assert!(c_topping_array.validate_c().is_ok());
+
+        let invalid_utf8 = CDummy {
+            count: 2,
+            describe: CString::new(vec![0xc3, 0x28]).unwrap().into_raw_pointer(),
+        };
+        assert!(matches!(
+            invalid_utf8.validate_c(),
+            Err(AsRustError::Utf8Error(_))
+        ));
+
+        let null_string = CLayer {
+            number: 1,
+            subtitle: std::ptr::null(),
+        };
+        assert!(null_string.validate_c().is_ok());
+
+        let null_string = CDummy {
+            count: 2,
+            describe: std::ptr::null(),
+        };
+        assert!(matches!(
+            null_string.validate_c(),
+            Err(AsRustError::NullPointer(_))
+        ));
+
+        let null_array = CArray::<CTopping> {
+            data_ptr: std::ptr::null(),
+            size: 2,
+        };
+        assert!(null_array.validate_c().is_err());
+        std::mem::forget(null_array);
+
+        let invalid_family = CIpAddr {
+            family: 5,
+            addr: [0; 16],
+        };
+        assert!(invalid_family.validate_c().is_err());
+    }
+
+    #[test]
+    fn explained_conversion() {
+        let c_dummy = CDummy::c_repr_of(Dummy {
+            count: 2,
+            describe: "yo".to_string(),
+        })
+        .unwrap();
+        let (dummy, trace): (Result<Dummy, _>, _) = explain_as_rust(&c_dummy);
+        assert!(dummy.is_ok());
+        let lines = trace.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("ffi_convert_tests::CDummy ("));
+        assert!(lines[1].starts_with("  count: i32 (4 bytes at "));
+        assert!(lines[2].starts_with("  describe: *const "));
+        assert!(!lines[2].ends_with("-> null"));
+
+        let c_layers = CArray::<CLayer>::c_repr_of(vec![
+            Layer {
+                number: 1,
+                subtitle: None,
             },
-            some_futile_info: None,
-            flattened_range: Range { start: 42, end: 64 },
-            field_with_specific_rust_name: "renamed field".to_string(),
-            pancake_data: None,
+            Layer {
+                number: 2,
+                subtitle: Some(String::from("second layer")),
+            },
+        ])
+        .unwrap();
+        let (layers, trace): (Result<Vec<Layer>, _>, _) = explain_as_rust(&c_layers);
+        assert!(layers.is_ok());
+        assert!(trace.lines().any(|line| line.ends_with("-> null")));
+
+        let invalid_utf8 = CDummy {
+            count: 2,
+            describe: CString::new(vec![0xc3, 0x28]).unwrap().into_raw_pointer(),
+        };
+        let (dummy, trace): (Result<Dummy, _>, _) = explain_as_rust(&invalid_utf8);
+        assert!(matches!(dummy, Err(AsRustError::Utf8Error(_))));
+        let lines = trace.lines().collect::<Vec<_>>();
+        assert!(!lines[1].contains("error"));
+        assert!(lines[2].contains("=> error: could not convert string as it is not UTF-8"));
+        assert!(lines[3].starts_with("error: "));
+    }
+
+    #[test]
+    fn schema_hashes() {
+        assert_eq!(
+            ffi_convert_tests_pancake_schema_hash(),
+            CPancake::SCHEMA_HASH
+        );
+        assert_ne!(CPancake::SCHEMA_HASH, CSauce::SCHEMA_HASH);
+
+        // same layout as CSauce, but a different field name
+        #[repr(C)]
+        #[derive(CSchema)]
+        struct CSauceRenamed {
+            amount: f32,
         }
-    });
+        assert_ne!(CSauceRenamed::SCHEMA_HASH, CSauce::SCHEMA_HASH);
+
+        // same field name as CTopping, but a different type
+        #[repr(C)]
+        #[derive(CSchema)]
+        struct CTopping {
+            amount: i64,
+        }
+        assert_ne!(CTopping::SCHEMA_HASH, super::CTopping::SCHEMA_HASH);
+
+        mod v1 {
+            use ffi_convert::CSchema;
+
+            #[repr(C)]
+            #[derive(CSchema)]
+            pub struct CFields {
+                a: u8,
+                b: u32,
+            }
+        }
+        mod v2 {
+            use ffi_convert::CSchema;
+
+            #[repr(C)]
+            #[derive(CSchema)]
+            pub struct CFields {
+                b: u32,
+                a: u8,
+            }
+        }
+        assert_ne!(v1::CFields::SCHEMA_HASH, v2::CFields::SCHEMA_HASH);
+        assert_eq!(
+            v2::CFields::SCHEMA_HASH,
+            SchemaHasher::new()
+                .write_str("CFields")
+                .write_usize(8)
+                .write_usize(4)
+                .write_str("b")
+                .write_str("u32")
+                .write_usize(0)
+                .write_str("a")
+                .write_str("u8")
+                .write_usize(4)
+                .finish()
+        );
+    }
+
+    #[test]
+    fn schema_ownership() {
+        let field = |name| {
+            CPancake::FIELDS
+                .iter()
+                .find(|field| field.name == name)
+                .unwrap()
+        };
+        assert_eq!(CPancake::FIELDS.len(), 20);
+        assert_eq!(field("name").ownership, Ownership::Owned);
+        assert_eq!(field("name").type_name, "*const libc::c_char");
+        assert_eq!(field("toppings").type_name, "*const CArray<CTopping>");
+        assert_eq!(field("start").ownership, Ownership::Value);
+        assert_eq!(field("user_data").ownership, Ownership::Borrowed);
+        assert_eq!(
+            field("cooking_time").offset,
+            std::mem::offset_of!(CPancake, cooking_time)
+        );
+
+        #[repr(C)]
+        #[derive(CSchema)]
+        struct CListener {
+            on_event: CCallback<(u32,)>,
+            #[ownership(c_allocated)]
+            host_context: *mut libc::c_void,
+            #[ownership(borrowed)]
+            name: *const libc::c_char,
+        }
+        let ownerships = CListener::FIELDS
+            .iter()
+            .map(|field| field.ownership)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ownerships,
+            vec![
+                Ownership::Callback,
+                Ownership::CAllocated,
+                Ownership::Borrowed
+            ]
+        );
+
+        // fields added to the schema don't change the hash
+        assert_eq!(
+            CListener::SCHEMA_HASH,
+            SchemaHasher::new()
+                .write_str("CListener")
+                .write_usize(std::mem::size_of::<CListener>())
+                .write_usize(8)
+                .write_str("on_event")
+                .write_str("CCallback<(u32,)>")
+                .write_usize(0)
+                .write_str("host_context")
+                .write_str("*mutlibc::c_void")
+                .write_usize(std::mem::offset_of!(CListener, host_context))
+                .write_str("name")
+                .write_str("*constlibc::c_char")
+                .write_usize(std::mem::offset_of!(CListener, name))
+                .finish()
+        );
+
+        assert_eq!(
+            schema_json::<CListener>(),
+            format!(
+                concat!(
+                    r#"{{"name":"CListener","size":48,"align":8,"hash":{},"fields":["#,
+                    r#"{{"name":"on_event","type":"CCallback<(u32,)>","offset":0,"ownership":"callback"}},"#,
+                    r#"{{"name":"host_context","type":"*mut libc::c_void","offset":32,"ownership":"c_allocated"}},"#,
+                    r#"{{"name":"name","type":"*const libc::c_char","offset":40,"ownership":"borrowed"}}]}}"#,
+                ),
+                CListener::SCHEMA_HASH
+            )
+        );
+    }
+
+    #[test]
+    fn batch_conversion() {
+        let labels = (0..100)
+            .map(|i| Label {
+                text: format!("label {}", i),
+                tooltip: if i % 2 == 0 {
+                    None
+                } else {
+                    Some(i.to_string())
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let c_labels: CArray<CLabel> = c_repr_of_batch(labels.clone()).unwrap();
+        assert_eq!(c_labels.size, 100);
+        let converted: Vec<Label> = as_rust_batch(&c_labels).unwrap();
+        assert_eq!(converted, labels);
+
+        let empty: CArray<CLabel> = c_repr_of_batch(Vec::<Label>::new()).unwrap();
+        assert!(empty.data_ptr.is_null());
+        assert_eq!(as_rust_batch::<Label, _>(&empty).unwrap(), vec![]);
+
+        let mut invalid_labels = labels;
+        invalid_labels[50].text = "nul\0byte".to_string();
+        assert!(matches!(
+            c_repr_of_batch::<CLabel, _>(invalid_labels),
+            Err(CReprOfError::StringContainsNullBit(_))
+        ));
+    }
+
+    #[test]
+    fn batch_conversion_in_arena() {
+        let bookcases = (0..100)
+            .map(|index| Bookcase {
+                label: format!("shelf {}", index),
+                note: None,
+                books: vec![Book {
+                    title: format!("book {}", index),
+                    pages: index,
+                }],
+                tags: vec![],
+                favorite: None,
+            })
+            .collect::<Vec<_>>();
+
+        let arena = CArena::new();
+        let c_bookcases: &CArray<CBookcase> =
+            c_repr_of_batch_in(&arena, bookcases.clone()).unwrap();
+        assert_eq!(c_bookcases.size, 100);
+        let converted: Vec<Bookcase> = as_rust_batch(c_bookcases).unwrap();
+        assert_eq!(converted, bookcases);
+
+        let mut invalid_bookcases = bookcases;
+        invalid_bookcases[50].label = "nul\0byte".to_string();
+        assert!(matches!(
+            c_repr_of_batch_in::<CBookcase, _>(&arena, invalid_bookcases),
+            Err(CReprOfError::InElement { index: 50, .. })
+        ));
+    }
+
+    #[test]
+    // the epoch based collector of the rayon thread pool is not accepted by Stacked Borrows
+    #[cfg_attr(miri, ignore)]
+    fn parallel_batch_conversion() {
+        let toppings = (0..10_000)
+            .map(|amount| Topping { amount })
+            .collect::<Vec<_>>();
+
+        let c_toppings: CArray<CTopping> = par_c_repr_of_batch(toppings.clone()).unwrap();
+        assert_eq!(c_toppings.size, 10_000);
+        let converted: Vec<Topping> = par_as_rust_batch(&c_toppings).unwrap();
+        assert_eq!(converted, toppings);
+    }
+
+    #[test]
+    // the epoch based collector of the rayon thread pool is not accepted by Stacked Borrows
+    #[cfg_attr(miri, ignore)]
+    fn parallel_thread_safe_batch_conversion() {
+        let tracks = (0..1000)
+            .map(|index| Track {
+                title: format!("track {}", index),
+                samples: vec![index as i16; 4],
+                tags: vec!["live".to_string()],
+                cover: Some(Topping { amount: index }),
+            })
+            .collect::<Vec<_>>();
+
+        let c_tracks: CArray<CTrack> = par_c_repr_of_batch(tracks.clone()).unwrap();
+        let converted: Vec<Track> = par_as_rust_batch(&c_tracks).unwrap();
+        assert_eq!(converted, tracks);
+    }
+
+    #[test]
+    fn wide_strings() {
+        let c_label = CWideLabel::c_repr_of(Label {
+            text: "é".to_string(),
+            tooltip: None,
+        })
+        .unwrap();
+        assert_eq!(unsafe { *c_label.text }, 0xe9);
+        assert_eq!(unsafe { *c_label.text.add(1) }, 0);
+        assert!(c_label.tooltip.is_null());
+        assert!(c_label.validate_c().is_ok());
+
+        assert_eq!(c_label.as_rust_text().unwrap(), "é");
+
+        assert!(CWideLabel::c_repr_of(Label {
+            text: "nul\0char".to_string(),
+            tooltip: None,
+        })
+        .is_err());
+
+        // unpaired surrogate
+        let invalid_utf16 = [0xd800, 0];
+        let invalid_label = CWideLabel {
+            text: invalid_utf16.as_ptr(),
+            tooltip: std::ptr::null(),
+        };
+        assert!(invalid_label.validate_c().is_err());
+        assert!(AsRust::<Label>::as_rust(&invalid_label).is_err());
+        std::mem::forget(invalid_label);
+    }
+
+    #[test]
+    fn memory_budget() {
+        let layers = vec![
+            Layer {
+                number: 1,
+                subtitle: Some("a".repeat(100)),
+            },
+            Layer {
+                number: 2,
+                subtitle: None,
+            },
+        ];
+        let needed = 2 * std::mem::size_of::<CLayer>() + 101;
+
+        let c_layers: CArray<CLayer> = c_repr_of_with_budget(layers.clone(), needed).unwrap();
+        assert_eq!(c_layers.size, 2);
+
+        let error = c_repr_of_with_budget::<CArray<CLayer>, _>(layers.clone(), needed - 1)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            CReprOfError::InElement { index: 0, ref source } if matches!(
+                **source,
+                CReprOfError::MemoryBudgetExceeded {
+                    requested: 101,
+                    available: 100,
+                }
+            )
+        ));
+
+        // nested budgets are limited by the outer one
+        struct Names(Vec<String>);
+        struct CNames(CStringArray);
+        impl CDrop for CNames {
+            fn do_drop(&mut self) -> Result<(), CDropError> {
+                Ok(())
+            }
+        }
+        impl CReprOf<Names> for CNames {
+            fn c_repr_of(input: Names) -> Result<Self, CReprOfError> {
+                Ok(Self(c_repr_of_with_budget(input.0, 1000)?))
+            }
+        }
+        let names = || Names(vec!["a".repeat(50)]);
+        let needed = std::mem::size_of::<*const libc::c_char>() + 51;
+        let c_names = c_repr_of_with_budget::<CNames, _>(names(), needed).unwrap();
+        assert_eq!(c_names.0.size, 1);
+        assert!(c_repr_of_with_budget::<CNames, _>(names(), needed - 1).is_err());
+
+        // no budget outside of c_repr_of_with_budget
+        assert!(reserve_memory(usize::MAX).is_ok());
+    }
+
+    #[test]
+    fn conversion_context() {
+        let lenient = ConversionContext {
+            utf8_policy: Utf8Policy::Lossy,
+            replace_nul_characters: true,
+            saturate_integers: true,
+            truncate_strings: true,
+            memory_budget: None,
+            timeout: None,
+        };
+
+        let dummy = || Dummy {
+            count: 1,
+            describe: "yo\0lo".to_string(),
+        };
+        assert!(CDummy::c_repr_of(dummy()).is_err());
+        let c_dummy: CDummy = c_repr_of_with(dummy(), &lenient).unwrap();
+        let converted: Dummy = c_dummy.as_rust().unwrap();
+        assert_eq!(converted.describe, "yo\u{FFFD}lo");
+
+        let invalid = CString::new(b"cr\xeape".to_vec()).unwrap();
+        let c_dummy = CDummy {
+            count: 1,
+            describe: invalid.as_ptr(),
+        };
+        assert!(matches!(
+            AsRust::<Dummy>::as_rust(&c_dummy),
+            Err(AsRustError::Utf8Error(_))
+        ));
+        let converted: Dummy = as_rust_with(&c_dummy, &lenient).unwrap();
+        assert_eq!(converted.describe, "cr\u{FFFD}pe");
+        std::mem::forget(c_dummy);
+
+        let crepe = || Crepe {
+            flavor: Flavor::Plain,
+            weight: 90,
+            diameter: Some(usize::MAX),
+        };
+        assert!(CCrepe::c_repr_of(crepe()).is_err());
+        let c_crepe: CCrepe = c_repr_of_with(crepe(), &lenient).unwrap();
+        assert_eq!(c_crepe.as_rust_diameter().unwrap(), Some(u32::MAX as usize));
+
+        let with_budget = |budget| ConversionContext {
+            memory_budget: Some(budget),
+            ..Default::default()
+        };
+        let yolo = || Dummy {
+            count: 1,
+            describe: "yolo".to_string(),
+        };
+        assert!(c_repr_of_with::<CDummy, _>(yolo(), &with_budget(5)).is_ok());
+        assert!(matches!(
+            c_repr_of_with::<CDummy, _>(yolo(), &with_budget(4)),
+            Err(CReprOfError::MemoryBudgetExceeded { .. })
+        ));
+
+        // the options only apply during the call
+        assert_eq!(current_context(), ConversionContext::default());
+    }
+
+    #[test]
+    fn borrowed_path_view() {
+        let c_document = CDocument::c_repr_of(Document {
+            path: PathBuf::from("/tmp/crêpes.txt"),
+            name: OsString::from("crêpes.txt"),
+            backup_path: None,
+        })
+        .unwrap();
+
+        let view: DocumentView = c_document.as_rust_view().unwrap();
+        assert_eq!(view.path, Path::new("/tmp/crêpes.txt"));
+        assert_eq!(view.name, OsString::from("crêpes.txt"));
+        assert!(view.backup_path.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let document = Document {
+            path: PathBuf::from(OsString::from_vec(vec![b'/', 0xff, 0xfe])),
+            name: OsString::from_vec(vec![0xff, 0xfe]),
+            backup_path: None,
+        };
+
+        let c_document = CDocument::c_repr_of(document.clone()).unwrap();
+        let converted: Document = c_document.as_rust().unwrap();
+        assert_eq!(converted, document);
+    }
+
+    #[test]
+    fn formatted_c_strings() {
+        let layer = Layer {
+            number: 2,
+            subtitle: None,
+        };
+        let message = format_to_c!("invalid layer: {:?}", layer);
+        assert_eq!(
+            unsafe { CStr::from_ptr(message) }.to_str().unwrap(),
+            "invalid layer: Layer { number: 2, subtitle: None }"
+        );
+        unsafe { CString::drop_raw_pointer(message) }.unwrap();
+
+        let message = format_to_c!("{}", "nul\0byte\0");
+        assert_eq!(
+            unsafe { CStr::from_ptr(message) }.to_str().unwrap(),
+            "nul\u{FFFD}byte\u{FFFD}"
+        );
+        unsafe { CString::drop_raw_pointer(message) }.unwrap();
+
+        let message = format_to_c!("");
+        assert_eq!(unsafe { *message }, 0);
+        unsafe { CString::drop_raw_pointer(message) }.unwrap();
+    }
+
+    #[test]
+    fn timestamp_before_epoch() {
+        let c_timestamp = CTimestamp::c_repr_of(UNIX_EPOCH - Duration::from_millis(1_500)).unwrap();
+
+        assert_eq!(
+            c_timestamp,
+            CTimestamp {
+                secs: -2,
+                nanos: 500_000_000
+            }
+        );
+        assert!(CDuration {
+            secs: 1,
+            nanos: 1_000_000_000
+        }
+        .validate_c()
+        .is_err());
+    }
+
+    #[test]
+    fn shared_subtrees_are_converted_once() {
+        let sauce = Arc::new(Sauce { volume: 4.2 });
+        let recipe = Recipe {
+            base: sauce.clone(),
+            topping: sauce.clone(),
+        };
+
+        let c_recipe = CRecipe::c_repr_of(recipe.clone()).unwrap();
+        assert_ne!(c_recipe.base.ptr, c_recipe.topping.ptr);
+
+        let c_recipe: CRecipe = c_repr_of_shared(recipe.clone()).unwrap();
+        assert_eq!(c_recipe.base.ptr, c_recipe.topping.ptr);
+        assert_eq!(c_recipe.as_rust().unwrap(), recipe);
+
+        let other_c_recipe: CRecipe = c_repr_of_shared(recipe).unwrap();
+        assert_ne!(other_c_recipe.base.ptr, c_recipe.base.ptr);
+
+        drop(c_recipe);
+        assert_eq!(other_c_recipe.as_rust().unwrap().base.volume, sauce.volume);
+    }
+
+    #[test]
+    fn cycles_are_detected() {
+        let first = Arc::new(Node {
+            value: 1,
+            next: Mutex::new(None),
+        });
+        let second = Arc::new(Node {
+            value: 2,
+            next: Mutex::new(Some(first.clone())),
+        });
+
+        let c_node = CNode::c_repr_of((*second).clone()).unwrap();
+        assert!(!c_node.next.is_null());
+        drop(c_node);
+
+        *first.next.lock().unwrap() = Some(second.clone());
+
+        assert!(matches!(
+            CNode::c_repr_of((*second).clone()),
+            Err(CReprOfError::CycleDetected)
+        ));
+        assert!(matches!(
+            c_repr_of_shared::<CNode, _>((*first).clone()),
+            Err(CReprOfError::CycleDetected)
+        ));
+
+        // break the cycle so that the nodes are freed
+        first.next.lock().unwrap().take();
+    }
 }
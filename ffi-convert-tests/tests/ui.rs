@@ -0,0 +1,8 @@
+// the derive errors are checked by compiling, which Miri can't do
+#![cfg(not(miri))]
+
+#[test]
+fn derive_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
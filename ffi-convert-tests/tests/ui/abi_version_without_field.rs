@@ -0,0 +1,16 @@
+use ffi_convert::{AsRust, CDrop, CReprOf};
+
+pub struct Sauce {
+    pub volume: f32,
+}
+
+#[repr(C)]
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Sauce)]
+#[abi_version(2)]
+pub struct CSauce {
+    pub volume: f32,
+    pub version: u32,
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+use ffi_convert::{CDrop, CReprOf, CReprOfIn};
+use std::sync::Arc;
+
+pub struct Model {
+    pub weights: Vec<f32>,
+}
+
+pub struct Session {
+    pub model: Arc<Model>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, CReprOfIn, CDrop)]
+#[target_type(Session)]
+pub struct CSession {
+    #[opaque]
+    pub model: *mut libc::c_void,
+}
+
+fn main() {}
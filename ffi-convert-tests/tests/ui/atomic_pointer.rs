@@ -0,0 +1,17 @@
+use ffi_convert::{CDrop, CReprOf};
+use std::sync::atomic::AtomicU32;
+use std::sync::Arc;
+
+pub struct Stats {
+    pub hits: Arc<AtomicU32>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, CDrop)]
+#[target_type(Stats)]
+pub struct CStats {
+    #[atomic_ordering(Acquire)]
+    pub hits: *const u32,
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use ffi_convert::{AsRust, CDrop};
+use std::ffi::CStr;
+
+pub struct Label<'a> {
+    pub text: &'a CStr,
+}
+
+#[repr(C)]
+#[derive(AsRust, CDrop)]
+#[target_type(Label<'a>)]
+pub struct CLabel {
+    #[borrowed_string]
+    text: *const libc::c_char,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use ffi_convert::CReprOf;
+
+pub struct Pizza {
+    pub name: Box<str>,
+}
+
+#[repr(C)]
+#[derive(CReprOf)]
+#[target_type(Pizza)]
+pub struct CPizza {
+    #[boxed]
+    pub name: *const libc::c_char,
+}
+
+fn main() {}
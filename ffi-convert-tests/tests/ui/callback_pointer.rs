@@ -0,0 +1,15 @@
+use ffi_convert::{CCallback, CReprOf};
+
+pub struct Button {
+    pub on_click: Box<dyn Fn(u32)>,
+}
+
+#[repr(C)]
+#[derive(CReprOf)]
+#[target_type(Button)]
+pub struct CButton {
+    #[callback]
+    pub on_click: *const CCallback<(u32,)>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use ffi_convert::CDrop;
+
+#[repr(C)]
+#[derive(CDrop)]
+pub enum CSauce {
+    Tomato,
+    Cream,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use ffi_convert::RawPointerConverter;
+
+#[derive(RawPointerConverter)]
+#[generate_destructor]
+pub struct CBox<T> {
+    pub value: T,
+}
+
+fn main() {}
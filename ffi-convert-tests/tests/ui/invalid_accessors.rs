@@ -0,0 +1,16 @@
+use ffi_convert::{CDrop, CReprOf};
+
+#[derive(Default)]
+pub struct Thermostat {
+    pub target: f32,
+}
+
+#[repr(C)]
+#[derive(CReprOf, CDrop)]
+#[target_type(Thermostat)]
+#[generate_accessors(default = Thermostat::default)]
+pub struct CThermostat {
+    pub target: f32,
+}
+
+fn main() {}
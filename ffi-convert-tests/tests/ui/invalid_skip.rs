@@ -0,0 +1,16 @@
+use ffi_convert::CReprOf;
+
+pub struct Pizza {
+    pub weight: f32,
+}
+
+#[repr(C)]
+#[derive(CReprOf)]
+#[target_type(Pizza)]
+pub struct CPizza {
+    pub weight: f32,
+    #[skip(value = 0)]
+    pub padding: u32,
+}
+
+fn main() {}
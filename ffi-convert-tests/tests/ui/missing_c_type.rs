@@ -0,0 +1,12 @@
+use ffi_convert::ffi_export;
+
+pub struct Thermostat {
+    pub target: f32,
+}
+
+#[ffi_export]
+pub fn target(thermostat: &Thermostat) -> f32 {
+    thermostat.target
+}
+
+fn main() {}
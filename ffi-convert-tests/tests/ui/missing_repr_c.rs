@@ -0,0 +1,13 @@
+use ffi_convert::{AsRust, CDrop, CReprOf};
+
+pub struct Sauce {
+    pub volume: f32,
+}
+
+#[derive(CReprOf, AsRust, CDrop)]
+#[target_type(Sauce)]
+pub struct CSauce {
+    pub volume: f32,
+}
+
+fn main() {}
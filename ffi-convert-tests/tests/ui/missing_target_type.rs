@@ -0,0 +1,9 @@
+use ffi_convert::CReprOf;
+
+#[repr(C)]
+#[derive(CReprOf)]
+pub struct CSauce {
+    pub volume: f32,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use ffi_convert::CVTable;
+
+#[repr(C)]
+#[derive(Clone, Copy, CVTable)]
+pub struct CKitchenVTable {
+    #[nullable]
+    pub cook: extern "C" fn(pancakes: u32) -> u32,
+}
+
+fn main() {}
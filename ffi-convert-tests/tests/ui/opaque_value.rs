@@ -0,0 +1,15 @@
+use ffi_convert::CReprOf;
+
+pub struct Job {
+    pub id: u32,
+}
+
+#[repr(C)]
+#[derive(CReprOf)]
+#[target_type(Job)]
+pub struct CJob {
+    #[opaque]
+    pub id: u32,
+}
+
+fn main() {}
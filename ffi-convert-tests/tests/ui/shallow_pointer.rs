@@ -0,0 +1,10 @@
+use ffi_convert::{CDrop, CStringArray};
+
+#[repr(C)]
+#[derive(CDrop)]
+pub struct CShelf {
+    #[shallow]
+    pub labels: *const CStringArray,
+}
+
+fn main() {}
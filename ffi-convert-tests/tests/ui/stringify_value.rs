@@ -0,0 +1,15 @@
+use ffi_convert::{CDrop, CReprOf};
+
+pub struct Setting {
+    pub gain: f64,
+}
+
+#[repr(C)]
+#[derive(CReprOf, CDrop)]
+#[target_type(Setting)]
+pub struct CSetting {
+    #[stringify]
+    gain: f64,
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use ffi_convert::{CArray, CDrop, CReprOf};
+
+pub struct Frame {
+    pub name: String,
+    pub samples: Vec<i16>,
+}
+
+#[repr(C)]
+#[derive(CReprOf, CDrop)]
+#[target_type(Frame)]
+#[thread_safe]
+pub struct CFrame {
+    name: *const libc::c_char,
+    samples: CArray<i16>,
+}
+
+fn main() {}
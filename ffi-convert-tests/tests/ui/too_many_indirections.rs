@@ -0,0 +1,14 @@
+use ffi_convert::AsRust;
+
+pub struct Pizza {
+    pub toppings: Vec<String>,
+}
+
+#[repr(C)]
+#[derive(AsRust)]
+#[target_type(Pizza)]
+pub struct CPizza {
+    pub toppings: *const *const libc::c_char,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use ffi_convert::CReprOfTransparent;
+
+#[derive(CReprOfTransparent)]
+pub struct CustomerId {
+    pub id: u64,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use ffi_convert::CDrop;
+
+#[repr(C)]
+#[derive(CDrop)]
+pub struct CSauce(f32);
+
+fn main() {}
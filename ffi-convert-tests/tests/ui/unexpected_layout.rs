@@ -0,0 +1,12 @@
+use ffi_convert::CDrop;
+
+#[repr(C)]
+#[derive(CDrop)]
+#[expected_size(8)]
+#[expected_align(4)]
+pub struct CSauce {
+    pub volume: f32,
+    pub weight: f64,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use ffi_convert::CReprOfTransparent;
+
+#[repr(transparent)]
+#[derive(CReprOfTransparent)]
+#[impls(CReprOf, CValidate)]
+pub struct PizzaId(pub u64);
+
+fn main() {}
@@ -0,0 +1,10 @@
+use ffi_convert::CSchema;
+
+#[repr(C)]
+#[derive(CSchema)]
+pub struct CJob {
+    #[ownership(shared)]
+    pub context: *mut libc::c_void,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use ffi_convert::CDrop;
+
+#[repr(C)]
+#[derive(CDrop)]
+pub struct CSauce {
+    pub volume: (f32, f32),
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use ffi_convert::{AsRust, CDrop};
+
+pub struct Command {
+    pub verb: String,
+}
+
+#[repr(C)]
+#[derive(AsRust, CDrop)]
+#[target_type(Command)]
+pub struct CCommand {
+    #[wide_string]
+    #[string_policy(max_len = 4)]
+    verb: *const u16,
+}
+
+fn main() {}
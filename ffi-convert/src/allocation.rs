@@ -0,0 +1,119 @@
+//! The allocation functions exported to C by the [`export_allocator!`](crate::export_allocator)
+//! macro, for the buffers allocated by C code and then owned by Rust (or the other way around) : as
+//! they use the allocator of Rust, a buffer can be allocated on one side and freed on the other.
+//!
+//! The buffers are byte buffers (aligned on a single byte), that Rust can own as the data of a
+//! [`CByteArray`](crate::CByteArray) of the same size, or as a C string whose size includes the
+//! nul terminator.
+
+use std::alloc::Layout;
+use std::ptr::{self, NonNull};
+
+/// Allocates a buffer of `size` bytes with the allocator of Rust, to be freed with
+/// [`free_buffer`] or owned by Rust. Returns `null` if the allocation fails, and a dangling
+/// (non `null`) pointer if `size` is 0.
+pub fn alloc_buffer(size: usize) -> *mut libc::c_void {
+    if size == 0 {
+        return NonNull::<u8>::dangling().as_ptr() as _;
+    }
+    match Layout::array::<u8>(size) {
+        Ok(layout) => unsafe { std::alloc::alloc(layout) as _ },
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Frees a buffer allocated by [`alloc_buffer`] or by Rust, `size` being its size in bytes.
+/// Nothing is done if `buffer` is `null` or if `size` is 0.
+///
+/// # Safety
+///
+/// `buffer` must have been allocated with the given size by [`alloc_buffer`] (or as a byte
+/// buffer by Rust), and not be freed yet.
+pub unsafe fn free_buffer(buffer: *mut libc::c_void, size: usize) {
+    if buffer.is_null() || size == 0 {
+        return;
+    }
+    // the layout is valid, as the buffer was allocated with it
+    std::alloc::dealloc(buffer as _, Layout::array::<u8>(size).unwrap_unchecked())
+}
+
+/// Generates two `extern "C"` functions calling [`alloc_buffer`] and [`free_buffer`], so that C
+/// code can allocate the buffers it gives to Rust and free the ones it gets from Rust. They are
+/// only exported by the library invoking this macro, so that the libraries depending on
+/// `ffi_convert` don't export clashing symbols.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{export_allocator, AsRust, CByteArray};
+///
+/// export_allocator!(ffi_alloc, ffi_free);
+///
+/// // what a C caller does to fill a buffer given to Rust
+/// let data = ffi_alloc(4) as *mut u8;
+/// unsafe { data.copy_from_nonoverlapping([1, 2, 3, 4].as_ptr(), 4) };
+///
+/// // the array owns the buffer, which it frees when dropped
+/// let array = CByteArray {
+///     data_ptr: data,
+///     size: 4,
+/// };
+/// assert_eq!(AsRust::<Vec<u8>>::as_rust(&array).unwrap(), vec![1, 2, 3, 4]);
+/// ```
+#[macro_export]
+macro_rules! export_allocator {
+    ($alloc_fn_name:ident, $free_fn_name:ident) => {
+        /// Allocates a buffer of `size` bytes with the allocator of Rust, returns `null` if the
+        /// allocation fails.
+        #[no_mangle]
+        pub extern "C" fn $alloc_fn_name(size: usize) -> *mut std::ffi::c_void {
+            $crate::alloc_buffer(size)
+        }
+
+        /// Frees a buffer of `size` bytes allocated with the allocator of Rust.
+        ///
+        /// # Safety
+        ///
+        /// See `ffi_convert::free_buffer`.
+        #[no_mangle]
+        pub unsafe extern "C" fn $free_fn_name(buffer: *mut std::ffi::c_void, size: usize) {
+            $crate::free_buffer(buffer, size)
+        }
+
+        $crate::register_signature!($crate::CFunctionSignature {
+            name: stringify!($alloc_fn_name),
+            doc: "Allocates a buffer of `size` bytes with the allocator of Rust, returns `null` if the allocation fails.",
+            parameters: &[$crate::CParameter {
+                name: "size",
+                ty: "usize",
+                direction: $crate::Direction::In,
+                ownership: $crate::ParameterOwnership::Value,
+            }],
+            return_type: "*mut std::ffi::c_void",
+            return_ownership: $crate::ParameterOwnership::OwnedByCaller,
+            return_codes: &[],
+        });
+
+        $crate::register_signature!($crate::CFunctionSignature {
+            name: stringify!($free_fn_name),
+            doc: "Frees a buffer of `size` bytes allocated with the allocator of Rust.",
+            parameters: &[
+                $crate::CParameter {
+                    name: "buffer",
+                    ty: "*mut std::ffi::c_void",
+                    direction: $crate::Direction::In,
+                    ownership: $crate::ParameterOwnership::Released,
+                },
+                $crate::CParameter {
+                    name: "size",
+                    ty: "usize",
+                    direction: $crate::Direction::In,
+                    ownership: $crate::ParameterOwnership::Value,
+                },
+            ],
+            return_type: "()",
+            return_ownership: $crate::ParameterOwnership::Value,
+            return_codes: &[],
+        });
+    };
+}
@@ -0,0 +1,194 @@
+//! This module contains the [`CArc`] type, an opaque handle on an `Arc` shared with C without
+//! copying it, and the [`c_arc_clone`] and [`c_arc_drop`] functions managing its reference count.
+
+use ffi_convert_derive::RawPointerConverter;
+
+use std::ptr;
+use std::sync::Arc;
+
+use crate as ffi_convert;
+use crate::conversions::*;
+
+/// A utility type to hand a value shared through an `Arc` to C consumers, as an opaque handle.
+///
+/// Contrary to [`CShared`](crate::CShared), the value is not converted to its C representation :
+/// the handle points to the Rust value itself, so that creating a `CArc` from an `Arc` (or an
+/// `Arc` from a `CArc`) never copies it. C code can't read the value, it can only pass the handle
+/// back to Rust functions.
+///
+/// The handle holds a strong reference on the value : each C consumer keeping the handle should
+/// take its own reference with [`c_arc_clone`] and release it with [`c_arc_drop`] (which can be
+/// exported to C with [`export_c_arc`](crate::export_c_arc)). The value is freed when the last
+/// reference, on the Rust or on the C side, is released.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, CArc, CReprOf};
+/// use std::sync::Arc;
+///
+/// pub struct Model {
+///     pub weights: Vec<f32>,
+/// }
+///
+/// let model = Arc::new(Model { weights: vec![0.5; 1024] });
+///
+/// let handle = CArc::c_repr_of(model.clone()).unwrap();
+/// assert_eq!(Arc::strong_count(&model), 2);
+///
+/// let shared: Arc<Model> = handle.as_rust().unwrap();
+/// assert!(Arc::ptr_eq(&shared, &model));
+///
+/// drop(handle);
+/// drop(shared);
+/// assert_eq!(Arc::strong_count(&model), 1);
+/// ```
+#[repr(transparent)]
+#[derive(Debug, RawPointerConverter)]
+pub struct CArc<T> {
+    /// Pointer to the shared value, created with `Arc::into_raw`
+    pub ptr: *const T,
+}
+
+impl<T> Clone for CArc<T> {
+    fn clone(&self) -> Self {
+        Self {
+            ptr: unsafe { c_arc_clone(self.ptr) },
+        }
+    }
+}
+
+impl<T> AsRust<Arc<T>> for CArc<T> {
+    fn as_rust(&self) -> Result<Arc<T>, AsRustError> {
+        if self.ptr.is_null() {
+            return Err(UnexpectedNullPointerError.into());
+        }
+        Ok(unsafe { Arc::from_raw(c_arc_clone(self.ptr)) })
+    }
+}
+
+impl<T> CReprOf<Arc<T>> for CArc<T> {
+    fn c_repr_of(input: Arc<T>) -> Result<Self, CReprOfError> {
+        Ok(Self {
+            ptr: Arc::into_raw(input),
+        })
+    }
+}
+
+impl<T> CDrop for CArc<T> {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        unsafe { c_arc_drop(self.ptr) };
+        self.ptr = ptr::null();
+        Ok(())
+    }
+}
+
+impl<T> Drop for CArc<T> {
+    fn drop(&mut self) {
+        let _ = self.do_drop();
+    }
+}
+
+/// Takes a new reference on the value pointed by a [`CArc`] handle, and returns the handle
+/// (which can then be released once more with [`c_arc_drop`]). Does nothing if the handle is
+/// `null`.
+///
+/// # Safety
+///
+/// The handle must be `null` or the `ptr` of a live [`CArc<T>`].
+pub unsafe fn c_arc_clone<T>(handle: *const T) -> *const T {
+    if !handle.is_null() {
+        Arc::increment_strong_count(handle);
+    }
+    handle
+}
+
+/// Releases a reference on the value pointed by a [`CArc`] handle, the value being freed when its
+/// last reference is released. Does nothing if the handle is `null`.
+///
+/// # Safety
+///
+/// The handle must be `null` or the `ptr` of a live [`CArc<T>`], and must not be used anymore by
+/// the caller once its reference has been released.
+pub unsafe fn c_arc_drop<T>(handle: *const T) {
+    if !handle.is_null() {
+        Arc::decrement_strong_count(handle);
+    }
+}
+
+/// Generates two `extern "C"` functions calling [`c_arc_clone`] and [`c_arc_drop`] for the given
+/// type, so that C consumers can manage the references they hold on [`CArc`] handles.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{export_c_arc, CArc, CReprOf};
+/// use std::sync::Arc;
+///
+/// pub struct Model {
+///     pub weights: Vec<f32>,
+/// }
+///
+/// export_c_arc!(Model, ffi_model_clone, ffi_model_drop);
+///
+/// let model = Arc::new(Model { weights: vec![0.5; 1024] });
+/// let handle = CArc::c_repr_of(model.clone()).unwrap();
+///
+/// // what a C consumer keeping the handle would do
+/// let consumer_handle = unsafe { ffi_model_clone(handle.ptr) };
+/// assert_eq!(Arc::strong_count(&model), 3);
+/// unsafe { ffi_model_drop(consumer_handle) };
+/// assert_eq!(Arc::strong_count(&model), 2);
+/// ```
+#[macro_export]
+macro_rules! export_c_arc {
+    ($typ:ty, $clone_fn_name:ident, $drop_fn_name:ident) => {
+        /// Takes a new reference on the value pointed by the handle.
+        ///
+        /// # Safety
+        ///
+        /// See `ffi_convert::c_arc_clone`.
+        #[no_mangle]
+        pub unsafe extern "C" fn $clone_fn_name(handle: *const $typ) -> *const $typ {
+            $crate::c_arc_clone(handle)
+        }
+
+        /// Releases a reference on the value pointed by the handle.
+        ///
+        /// # Safety
+        ///
+        /// See `ffi_convert::c_arc_drop`.
+        #[no_mangle]
+        pub unsafe extern "C" fn $drop_fn_name(handle: *const $typ) {
+            $crate::c_arc_drop(handle)
+        }
+
+        $crate::register_signature!($crate::CFunctionSignature {
+            name: stringify!($clone_fn_name),
+            doc: "Takes a new reference on the value pointed by the handle.",
+            parameters: &[$crate::CParameter {
+                name: "handle",
+                ty: concat!("*const ", stringify!($typ)),
+                direction: $crate::Direction::In,
+                ownership: $crate::ParameterOwnership::Borrowed,
+            }],
+            return_type: concat!("*const ", stringify!($typ)),
+            return_ownership: $crate::ParameterOwnership::OwnedByCaller,
+            return_codes: &[],
+        });
+
+        $crate::register_signature!($crate::CFunctionSignature {
+            name: stringify!($drop_fn_name),
+            doc: "Releases a reference on the value pointed by the handle.",
+            parameters: &[$crate::CParameter {
+                name: "handle",
+                ty: concat!("*const ", stringify!($typ)),
+                direction: $crate::Direction::In,
+                ownership: $crate::ParameterOwnership::Released,
+            }],
+            return_type: "()",
+            return_ownership: $crate::ParameterOwnership::Value,
+            return_codes: &[],
+        });
+    };
+}
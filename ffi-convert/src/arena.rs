@@ -0,0 +1,363 @@
+//! This module contains the [`CArena`] type, owning the C representations of the values returned
+//! to C during a session of several calls, all freed at once when the arena is destroyed, and the
+//! [`CReprOfIn`] trait converting values with the memory of an arena.
+
+use ffi_convert_derive::RawPointerConverter;
+
+use std::alloc::{Layout, LayoutError};
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::mem::ManuallyDrop;
+use std::ptr::{self, NonNull};
+
+use crate as ffi_convert;
+use crate::budget::reserve_memory;
+use crate::conversions::*;
+use crate::deadline::check_deadline;
+use crate::types::{CArray, CStringArray};
+
+/// An arena owning C values, handed to C code as an opaque pointer : C code creates it with the
+/// create function exported by [`export_arena!`](crate::export_arena) and passes it to the
+/// exported functions, which allocate their outputs in it instead of transferring their ownership
+/// to C code. All the values are freed by a single call to the destroy function exported with it,
+/// instead of one destructor per output.
+///
+/// The pointers returned by the arena are valid until it is destroyed. An arena must not be used
+/// by several threads at once.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{export_arena, CArena, CStringArray, RawBorrow};
+/// use std::ffi::CStr;
+///
+/// export_arena!(ffi_arena_create, ffi_arena_destroy);
+///
+/// /// Exported to C, the names being owned by the arena
+/// pub unsafe extern "C" fn list_names(
+///     arena: *const CArena,
+///     names: *mut *const CStringArray,
+/// ) -> i32 {
+///     let arena = CArena::raw_borrow(arena).unwrap();
+///     *names = arena.c_repr_of(vec!["Diavola".to_string(), "Regina".to_string()]).unwrap();
+///     0
+/// }
+///
+/// // what C code handling a request does
+/// let arena = ffi_arena_create();
+/// let mut names = std::ptr::null();
+/// unsafe { list_names(arena, &mut names) };
+/// let second = unsafe { (*names).get(1).unwrap() };
+/// assert_eq!(second.to_str().unwrap(), "Regina");
+/// assert_eq!(unsafe { ffi_arena_destroy(arena) }, 0);
+/// ```
+#[derive(Default, RawPointerConverter)]
+pub struct CArena {
+    values: RefCell<Vec<ArenaValue>>,
+    chunks: RefCell<Chunks>,
+}
+
+impl CArena {
+    /// Creates an empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves a value into the arena, returning a pointer to it valid until the arena is dropped.
+    pub fn alloc<T: 'static>(&self, value: T) -> *const T {
+        let pointer = Box::into_raw(Box::new(value));
+        self.values.borrow_mut().push(ArenaValue(unsafe {
+            NonNull::new_unchecked(pointer as *mut dyn Any)
+        }));
+        pointer
+    }
+
+    /// Converts a value to its C representation, owned by the arena.
+    pub fn c_repr_of<T: CReprOf<U> + 'static, U>(
+        &self,
+        input: U,
+    ) -> Result<*const T, CReprOfError> {
+        Ok(self.alloc(T::c_repr_of(input)?))
+    }
+
+    /// Converts a value to its C representation with [`CReprOfIn`], in the memory of the arena.
+    /// The value is not dropped with the arena, all the memory of its C representation being
+    /// freed at once instead.
+    pub fn c_repr_of_in<T: CReprOfIn<U>, U>(&self, input: U) -> Result<&T, CReprOfError> {
+        let value = ManuallyDrop::into_inner(T::c_repr_of_in(input, self)?);
+        Ok(unsafe { &*self.alloc_raw(value)? })
+    }
+
+    /// Copies a string to a C string in the memory of the arena.
+    pub fn c_string(&self, input: String) -> Result<*const libc::c_char, CReprOfError> {
+        if input.as_bytes().contains(&0) {
+            // replaced or rejected depending on the current context
+            return self.copy_c_str(CString::c_repr_of(input)?.as_bytes_with_nul());
+        }
+        let mut bytes = input.into_bytes();
+        bytes.push(0);
+        self.copy_c_str(&bytes)
+    }
+
+    fn copy_c_str(&self, bytes_with_nul: &[u8]) -> Result<*const libc::c_char, CReprOfError> {
+        reserve_memory(bytes_with_nul.len())?;
+        let data = self.alloc_uninit::<u8>(bytes_with_nul.len())?;
+        unsafe { ptr::copy_nonoverlapping(bytes_with_nul.as_ptr(), data, bytes_with_nul.len()) };
+        Ok(data as *const libc::c_char)
+    }
+
+    /// Moves a value in the memory of the arena, returning a pointer to it valid until the arena
+    /// is dropped. Unlike with [`CArena::alloc`], the value is never dropped : this is meant for
+    /// the C representations whose memory belongs to the arena (see [`CReprOfIn`]).
+    pub fn alloc_raw<T>(&self, value: T) -> Result<*mut T, CReprOfError> {
+        let pointer = self.alloc_uninit::<T>(1)?;
+        unsafe { pointer.write(value) };
+        Ok(pointer)
+    }
+
+    /// Allocates memory for `len` values of type `T` in the arena, failing with
+    /// [`CReprOfError::AllocationTooLarge`] if their size overflows.
+    fn alloc_uninit<T>(&self, len: usize) -> Result<*mut T, CReprOfError> {
+        let layout = Layout::array::<T>(len)?;
+        Ok(self.chunks.borrow_mut().alloc(layout)?.as_ptr() as *mut T)
+    }
+
+    /// Number of values owned by the arena with [`CArena::alloc`] and [`CArena::c_repr_of`],
+    /// which are dropped with it.
+    pub fn len(&self) -> usize {
+        self.values.borrow().len()
+    }
+
+    /// Whether the arena owns no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Generates two `extern "C"` functions creating and destroying a [`CArena`], so that C code can
+/// manage the arenas it passes to the exported functions. The destroy function returns `0` on
+/// success, `-1` if the arena is `null` and `-2` if freeing a value panicked, see
+/// [`destroy_raw_pointer`]. They are only exported by the library invoking this macro, so that
+/// the libraries depending on `ffi_convert` don't export clashing symbols.
+///
+/// See [`CArena`] for an example.
+#[macro_export]
+macro_rules! export_arena {
+    ($create_fn_name:ident, $destroy_fn_name:ident) => {
+        /// Creates an arena, to be destroyed with
+        #[doc = concat!("`", stringify!($destroy_fn_name), "`.")]
+        #[no_mangle]
+        pub extern "C" fn $create_fn_name() -> *mut $crate::CArena {
+            $crate::RawPointerConverter::into_raw_pointer_mut($crate::CArena::new())
+        }
+
+        /// Destroys an arena, freeing all the values it owns. Returns `0` on success, `-1` if
+        /// `arena` is `null` and `-2` if freeing a value panicked.
+        ///
+        /// # Safety
+        ///
+        #[doc = concat!("`arena` must be `null` or have been created with `", stringify!($create_fn_name), "`,")]
+        /// and neither it nor the values it owns must be used anymore.
+        #[no_mangle]
+        pub unsafe extern "C" fn $destroy_fn_name(arena: *mut $crate::CArena) -> i32 {
+            $crate::destroy_raw_pointer(arena)
+        }
+
+        $crate::register_signature!($crate::CFunctionSignature {
+            name: stringify!($create_fn_name),
+            doc: concat!("Creates an arena, to be destroyed with `", stringify!($destroy_fn_name), "`."),
+            parameters: &[],
+            return_type: "*mut CArena",
+            return_ownership: $crate::ParameterOwnership::OwnedByCaller,
+            return_codes: &[],
+        });
+
+        $crate::register_signature!($crate::CFunctionSignature {
+            name: stringify!($destroy_fn_name),
+            doc: "Destroys an arena, freeing all the values it owns.",
+            parameters: &[$crate::CParameter {
+                name: "arena",
+                ty: "*mut CArena",
+                direction: $crate::Direction::In,
+                ownership: $crate::ParameterOwnership::Released,
+            }],
+            return_type: "i32",
+            return_ownership: $crate::ParameterOwnership::Value,
+            return_codes: &[
+                $crate::CReturnCode { value: 0, meaning: "success" },
+                $crate::CReturnCode { value: -1, meaning: "the arena is null" },
+                $crate::CReturnCode { value: -2, meaning: "freeing a value panicked" },
+            ],
+        });
+    };
+}
+
+/// A value owned by an arena, kept as a raw pointer rather than as a `Box` so that moving it
+/// doesn't invalidate the pointer handed out by [`CArena::alloc`].
+struct ArenaValue(NonNull<dyn Any>);
+
+impl Drop for ArenaValue {
+    fn drop(&mut self) {
+        drop(unsafe { Box::from_raw(self.0.as_ptr()) });
+    }
+}
+
+/// The memory of an arena, allocated in chunks in which the values are placed one after the
+/// other, so that converting a value doesn't allocate memory for each of its fields.
+#[derive(Default)]
+struct Chunks {
+    /// The chunks and their layout, the last one being the current one
+    chunks: Vec<(NonNull<u8>, Layout)>,
+    /// Number of bytes used in the current chunk
+    used: usize,
+}
+
+impl Chunks {
+    const CHUNK_SIZE: usize = 4096;
+    const CHUNK_ALIGN: usize = 16;
+
+    fn alloc(&mut self, layout: Layout) -> Result<NonNull<u8>, LayoutError> {
+        if layout.size() == 0 {
+            // a dangling pointer with the right alignment, as for empty boxes
+            return Ok(unsafe {
+                NonNull::new_unchecked(ptr::null_mut::<u8>().wrapping_add(layout.align()))
+            });
+        }
+        if let Some((chunk, chunk_layout)) = self.chunks.last() {
+            let start = chunk.as_ptr() as usize + self.used;
+            let offset = start.next_multiple_of(layout.align()) - chunk.as_ptr() as usize;
+            if offset + layout.size() <= chunk_layout.size() {
+                self.used = offset + layout.size();
+                return Ok(unsafe { NonNull::new_unchecked(chunk.as_ptr().add(offset)) });
+            }
+        }
+        let chunk_layout = Layout::from_size_align(
+            layout.size().max(Self::CHUNK_SIZE),
+            layout.align().max(Self::CHUNK_ALIGN),
+        )?;
+        let chunk = NonNull::new(unsafe { std::alloc::alloc(chunk_layout) })
+            .unwrap_or_else(|| std::alloc::handle_alloc_error(chunk_layout));
+        self.chunks.push((chunk, chunk_layout));
+        self.used = layout.size();
+        Ok(chunk)
+    }
+}
+
+impl Drop for Chunks {
+    fn drop(&mut self) {
+        for (chunk, layout) in self.chunks.drain(..) {
+            unsafe { std::alloc::dealloc(chunk.as_ptr(), layout) };
+        }
+    }
+}
+
+/// Trait showing that a C-like struct can be created from a Rust value with the memory of a
+/// [`CArena`] : its strings, arrays and nested structs are copied into the memory of the arena
+/// instead of being allocated one by one, and freed all at once when the arena is dropped, which
+/// is much faster for large values.
+///
+/// The C representation is returned in a `ManuallyDrop`, as it must not be dropped (its memory
+/// belonging to the arena) : it is meant to be moved into the arena, as done by
+/// [`CArena::c_repr_of_in`]. This trait can be derived, for the structs whose fields are
+/// primitive values, strings, pointers to other structs implementing it, [`CArray`]s and
+/// [`CStringArray`]s.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{CArena, CArray, CReprOfIn};
+///
+/// pub struct Point {
+///     pub label: String,
+///     pub coordinates: Vec<f32>,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOfIn)]
+/// #[target_type(Point)]
+/// pub struct CPoint {
+///     pub label: *const libc::c_char,
+///     pub coordinates: CArray<f32>,
+/// }
+///
+/// let arena = CArena::new();
+/// let points = (0..1000)
+///     .map(|i| Point { label: format!("point {}", i), coordinates: vec![i as f32, 0.0] })
+///     .collect::<Vec<_>>();
+/// let c_points: &CArray<CPoint> = arena.c_repr_of_in(points).unwrap();
+///
+/// assert_eq!(c_points.get(999).unwrap().coordinates.as_slice().unwrap(), &[999.0, 0.0]);
+/// // all the points are freed with the arena
+/// drop(arena);
+/// ```
+pub trait CReprOfIn<T>: Sized {
+    fn c_repr_of_in(input: T, arena: &CArena) -> Result<ManuallyDrop<Self>, CReprOfError>;
+}
+
+macro_rules! impl_c_repr_of_in_for {
+    ($typ:ty) => {
+        impl CReprOfIn<$typ> for $typ {
+            fn c_repr_of_in(input: $typ, _: &CArena) -> Result<ManuallyDrop<$typ>, CReprOfError> {
+                Ok(ManuallyDrop::new(input))
+            }
+        }
+    };
+}
+
+impl_c_repr_of_in_for!(usize);
+impl_c_repr_of_in_for!(i8);
+impl_c_repr_of_in_for!(u8);
+impl_c_repr_of_in_for!(i16);
+impl_c_repr_of_in_for!(u16);
+impl_c_repr_of_in_for!(i32);
+impl_c_repr_of_in_for!(u32);
+impl_c_repr_of_in_for!(i64);
+impl_c_repr_of_in_for!(u64);
+impl_c_repr_of_in_for!(f32);
+impl_c_repr_of_in_for!(f64);
+impl_c_repr_of_in_for!(bool);
+
+impl<U: CReprOfIn<V>, V> CReprOfIn<Vec<V>> for CArray<U> {
+    fn c_repr_of_in(input: Vec<V>, arena: &CArena) -> Result<ManuallyDrop<Self>, CReprOfError> {
+        if input.is_empty() {
+            return Ok(ManuallyDrop::new(CArray::from(Vec::new())));
+        }
+        reserve_memory(input.len().saturating_mul(std::mem::size_of::<U>()))?;
+        let size = input.len();
+        let data_ptr = arena.alloc_uninit::<U>(size)?;
+        for (index, value) in input.into_iter().enumerate() {
+            check_deadline()?;
+            let value = U::c_repr_of_in(value, arena).map_err(|e| e.in_element(index))?;
+            unsafe { data_ptr.add(index).write(ManuallyDrop::into_inner(value)) };
+        }
+        Ok(ManuallyDrop::new(CArray {
+            data_ptr: data_ptr as *const U,
+            size,
+        }))
+    }
+}
+
+impl CReprOfIn<Vec<String>> for CStringArray {
+    fn c_repr_of_in(
+        input: Vec<String>,
+        arena: &CArena,
+    ) -> Result<ManuallyDrop<Self>, CReprOfError> {
+        reserve_memory(
+            input
+                .len()
+                .saturating_mul(std::mem::size_of::<*const libc::c_char>()),
+        )?;
+        let size = input.len();
+        let data = arena.alloc_uninit::<*const libc::c_char>(size)?;
+        for (index, string) in input.into_iter().enumerate() {
+            check_deadline()?;
+            let string = arena.c_string(string).map_err(|e| e.in_element(index))?;
+            unsafe { data.add(index).write(string) };
+        }
+        Ok(ManuallyDrop::new(CStringArray {
+            data: data as *const _,
+            size,
+        }))
+    }
+}
@@ -0,0 +1,110 @@
+//! This module contains functions to convert batches of structs to and from a [`CArray`].
+//!
+//! The converted structs of a batch are stored in a single allocation, owned by the [`CArray`]
+//! and freed at once when it is dropped. If the conversion of an element fails, the elements
+//! already converted are dropped before returning the error.
+//!
+//! With the `rayon` feature, [`par_c_repr_of_batch`] and [`par_as_rust_batch`] convert the
+//! elements in parallel. [`c_repr_of_batch_in`] converts a batch in the memory of a [`CArena`],
+//! freed with it.
+
+use crate::arena::{CArena, CReprOfIn};
+use crate::conversions::*;
+use crate::types::CArray;
+
+/// Converts a batch of values to a [`CArray`] of their C representations.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{as_rust_batch, c_repr_of_batch, AsRust, CArray, CDrop, CReprOf};
+///
+/// #[derive(Debug, PartialEq)]
+/// pub struct Topping {
+///     pub amount: i32,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(Topping)]
+/// pub struct CTopping {
+///     pub amount: i32,
+/// }
+///
+/// let toppings = (0..1000).map(|amount| Topping { amount }).collect::<Vec<_>>();
+///
+/// let c_toppings: CArray<CTopping> = c_repr_of_batch(toppings).unwrap();
+/// assert_eq!(c_toppings.size, 1000);
+///
+/// let toppings: Vec<Topping> = as_rust_batch(&c_toppings).unwrap();
+/// assert_eq!(toppings[42], Topping { amount: 42 });
+/// ```
+pub fn c_repr_of_batch<T: CReprOf<U>, U>(input: Vec<U>) -> Result<CArray<T>, CReprOfError> {
+    let converted = input
+        .into_iter()
+        .map(T::c_repr_of)
+        .collect::<Result<Vec<T>, CReprOfError>>()?;
+    Ok(CArray::from(converted))
+}
+
+/// Converts a batch of values to a [`CArray`] of their C representations with [`CReprOfIn`], in
+/// the memory of an arena. The array and its elements are not dropped one by one, they are all
+/// freed with the arena.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{as_rust_batch, c_repr_of_batch_in, CArena, CArray};
+///
+/// let arena = CArena::new();
+/// let batch = (0..1000).map(|i| vec![i as f32; 4]).collect::<Vec<_>>();
+///
+/// let c_batch: &CArray<CArray<f32>> = c_repr_of_batch_in(&arena, batch).unwrap();
+/// let batch: Vec<Vec<f32>> = as_rust_batch(c_batch).unwrap();
+/// assert_eq!(batch[42], vec![42.0; 4]);
+/// ```
+pub fn c_repr_of_batch_in<T: CReprOfIn<U>, U>(
+    arena: &CArena,
+    input: Vec<U>,
+) -> Result<&CArray<T>, CReprOfError> {
+    arena.c_repr_of_in(input)
+}
+
+/// Converts a [`CArray`] of C representations back to a batch of Rust values.
+pub fn as_rust_batch<T, U: AsRust<T>>(input: &CArray<U>) -> Result<Vec<T>, AsRustError> {
+    input.as_slice()?.iter().map(U::as_rust).collect()
+}
+
+/// Converts a batch of values to a [`CArray`] of their C representations, in parallel. Only
+/// available with the `rayon` feature.
+///
+/// As C representations usually contain raw pointers, `T` has to be marked as `Send` when it only
+/// contains pointers to memory it owns, with the `#[thread_safe]` attribute of the `CDrop` derive
+/// (see [`SyncCArray`](crate::SyncCArray)).
+#[cfg(feature = "rayon")]
+pub fn par_c_repr_of_batch<T: CReprOf<U> + Send, U: Send>(
+    input: Vec<U>,
+) -> Result<CArray<T>, CReprOfError> {
+    use rayon::prelude::*;
+
+    let converted = input
+        .into_par_iter()
+        .map(T::c_repr_of)
+        .collect::<Result<Vec<T>, CReprOfError>>()?;
+    Ok(CArray::from(converted))
+}
+
+/// Converts a [`CArray`] of C representations back to a batch of Rust values, in parallel. Only
+/// available with the `rayon` feature.
+///
+/// As C representations usually contain raw pointers, `U` has to be marked as `Sync` when it only
+/// contains pointers to memory that isn't mutated during the conversion, with the `#[thread_safe]`
+/// attribute of the `CDrop` derive for instance.
+#[cfg(feature = "rayon")]
+pub fn par_as_rust_batch<T: Send, U: AsRust<T> + Sync>(
+    input: &CArray<U>,
+) -> Result<Vec<T>, AsRustError> {
+    use rayon::prelude::*;
+
+    input.as_slice()?.par_iter().map(U::as_rust).collect()
+}
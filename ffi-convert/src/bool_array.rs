@@ -0,0 +1,192 @@
+//! This module contains the [`CBoolArray`] type, an array of booleans stored with one byte per
+//! boolean or packed in bits.
+
+use ffi_convert_derive::RawPointerConverter;
+
+use std::marker::PhantomData;
+use std::ptr;
+
+use crate as ffi_convert;
+use crate::budget::reserve_memory;
+use crate::conversions::*;
+
+/// The layout of the booleans of a [`CBoolArray`] : [`BytePerBool`] or [`BitPacked`].
+pub trait CBoolLayout {
+    /// Number of bytes used to store `size` booleans
+    fn byte_len(size: usize) -> usize;
+    /// Reads the boolean at the given index
+    fn get(bytes: &[u8], index: usize) -> bool;
+    /// Stores the booleans in bytes
+    fn pack(values: &[bool]) -> Vec<u8>;
+}
+
+/// Each boolean is stored in a byte, `0` being `false` and any other value `true` (as in C).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BytePerBool;
+
+impl CBoolLayout for BytePerBool {
+    fn byte_len(size: usize) -> usize {
+        size
+    }
+
+    fn get(bytes: &[u8], index: usize) -> bool {
+        bytes[index] != 0
+    }
+
+    fn pack(values: &[bool]) -> Vec<u8> {
+        values.iter().map(|&value| value as u8).collect()
+    }
+}
+
+/// The booleans are packed in bits, the boolean at index `i` being the bit `i % 8` (starting from
+/// the least significant bit) of the byte `i / 8`. The unused bits of the last byte are zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BitPacked;
+
+impl CBoolLayout for BitPacked {
+    fn byte_len(size: usize) -> usize {
+        size.div_ceil(8)
+    }
+
+    fn get(bytes: &[u8], index: usize) -> bool {
+        bytes[index / 8] & (1 << (index % 8)) != 0
+    }
+
+    fn pack(values: &[bool]) -> Vec<u8> {
+        let mut bytes = vec![0; Self::byte_len(values.len())];
+        for (index, _) in values.iter().enumerate().filter(|(_, &value)| value) {
+            bytes[index / 8] |= 1 << (index % 8);
+        }
+        bytes
+    }
+}
+
+/// A utility type to represent a `Vec<bool>`, with one byte per boolean (`CBoolArray`, or
+/// `CBoolArray<BytePerBool>`) or packed in bits (`CBoolArray<BitPacked>`, 8 times smaller, for
+/// large masks). See [`CBoolLayout`] for the layouts.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, BitPacked, CBoolArray, CDrop, CReprOf};
+///
+/// pub struct Frame {
+///     pub voiced: Vec<bool>,
+///     pub mask: Vec<bool>,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(Frame)]
+/// pub struct CFrame {
+///     pub voiced: CBoolArray,
+///     pub mask: CBoolArray<BitPacked>,
+/// }
+///
+/// let mask = (0..1024).map(|i| i % 3 == 0).collect::<Vec<_>>();
+/// let c_frame = CFrame::c_repr_of(Frame { voiced: vec![true, false], mask: mask.clone() }).unwrap();
+/// assert_eq!(c_frame.voiced.size, 2);
+/// assert_eq!(c_frame.mask.as_bytes().unwrap().len(), 128);
+///
+/// let frame = c_frame.as_rust().unwrap();
+/// assert_eq!(frame.voiced, vec![true, false]);
+/// assert_eq!(frame.mask, mask);
+/// ```
+#[repr(C)]
+#[derive(Debug, RawPointerConverter)]
+pub struct CBoolArray<L: CBoolLayout = BytePerBool> {
+    /// Pointer to the first byte of the array
+    pub data_ptr: *const u8,
+    /// Number of booleans in the array
+    pub size: usize,
+    _layout: PhantomData<L>,
+}
+
+impl<L: CBoolLayout> CBoolArray<L> {
+    /// Returns a borrowed slice of the bytes storing the booleans.
+    ///
+    /// An error is returned if the array is not empty and its data pointer is `null`.
+    pub fn as_bytes(&self) -> Result<&[u8], AsRustError> {
+        if self.size == 0 {
+            Ok(&[])
+        } else if self.data_ptr.is_null() {
+            Err(UnexpectedNullPointerError.into())
+        } else {
+            Ok(unsafe { std::slice::from_raw_parts(self.data_ptr, L::byte_len(self.size)) })
+        }
+    }
+
+    /// Returns the boolean at the given index.
+    ///
+    /// An error is returned if the index is out of bounds or if the data pointer is `null`.
+    ///
+    /// ```
+    /// use ffi_convert::{BitPacked, CBoolArray, CReprOf};
+    ///
+    /// let array = CBoolArray::<BitPacked>::c_repr_of(vec![false, true]).unwrap();
+    ///
+    /// assert!(array.get(1).unwrap());
+    /// assert!(array.get(2).is_err());
+    /// ```
+    pub fn get(&self, index: usize) -> Result<bool, AsRustError> {
+        if index >= self.size {
+            return Err(AsRustError::IndexOutOfBounds {
+                index,
+                size: self.size,
+            });
+        }
+        Ok(L::get(self.as_bytes()?, index))
+    }
+}
+
+impl<L: CBoolLayout> AsRust<Vec<bool>> for CBoolArray<L> {
+    fn as_rust(&self) -> Result<Vec<bool>, AsRustError> {
+        let bytes = self.as_bytes()?;
+        Ok((0..self.size).map(|index| L::get(bytes, index)).collect())
+    }
+}
+
+impl<L: CBoolLayout> CValidate for CBoolArray<L> {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        self.as_bytes().map(|_| ())
+    }
+}
+
+impl<L: CBoolLayout> CReprOf<Vec<bool>> for CBoolArray<L> {
+    fn c_repr_of(input: Vec<bool>) -> Result<Self, CReprOfError> {
+        let data_ptr = if input.is_empty() {
+            ptr::null()
+        } else {
+            reserve_memory(L::byte_len(input.len()))?;
+            Box::into_raw(L::pack(&input).into_boxed_slice()) as *const u8
+        };
+        Ok(Self {
+            data_ptr,
+            size: input.len(),
+            _layout: PhantomData,
+        })
+    }
+}
+
+impl<L: CBoolLayout> CDrop for CBoolArray<L> {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        if !self.data_ptr.is_null() {
+            let _ = unsafe {
+                Box::from_raw(ptr::slice_from_raw_parts_mut(
+                    self.data_ptr as *mut u8,
+                    L::byte_len(self.size),
+                ))
+            };
+            // so that the bytes are not freed twice if the array is dropped again
+            self.data_ptr = ptr::null();
+            self.size = 0;
+        }
+        Ok(())
+    }
+}
+
+impl<L: CBoolLayout> Drop for CBoolArray<L> {
+    fn drop(&mut self) {
+        let _ = self.do_drop();
+    }
+}
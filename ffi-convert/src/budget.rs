@@ -0,0 +1,84 @@
+//! This module contains the [`c_repr_of_with_budget`] function, used to limit the memory allocated
+//! while creating the C representation of a value.
+
+use std::cell::Cell;
+
+use crate::conversions::*;
+
+thread_local! {
+    /// Number of bytes that can still be allocated by the current call to
+    /// [`c_repr_of_with_budget`], `None` outside of such a call.
+    static REMAINING_BUDGET: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Restores the budget of the enclosing call to [`c_repr_of_with_budget`] (if any), minus what was
+/// consumed by this call, when it returns (or panics).
+struct BudgetGuard {
+    previous: Option<usize>,
+    budget: usize,
+}
+
+impl Drop for BudgetGuard {
+    fn drop(&mut self) {
+        let consumed = self.budget - REMAINING_BUDGET.with(Cell::get).unwrap_or(0);
+        REMAINING_BUDGET.with(|remaining| {
+            remaining.set(
+                self.previous
+                    .map(|previous| previous.saturating_sub(consumed)),
+            )
+        });
+    }
+}
+
+/// Creates the C representation of `input`, like [`CReprOf::c_repr_of`], failing with
+/// [`CReprOfError::MemoryBudgetExceeded`] instead of allocating more than `budget` bytes.
+///
+/// This allows to reject a value too big to be converted on a device with limited memory, instead
+/// of running out of memory. The allocations are counted by the implementations of [`CReprOf`]
+/// calling [`reserve_memory`] (this is the case of the strings and arrays of this crate), the
+/// structs themselves are not counted.
+///
+/// When calls are nested, the inner conversion is limited by the smallest of the two budgets and
+/// its allocations are counted in the outer budget.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{c_repr_of_with_budget, CReprOfError, CStringArray};
+///
+/// let names = vec!["Diavola".to_string(), "Margarita".to_string()];
+///
+/// assert!(matches!(
+///     c_repr_of_with_budget::<CStringArray, _>(names.clone(), 4),
+///     Err(CReprOfError::MemoryBudgetExceeded { .. })
+/// ));
+/// assert!(c_repr_of_with_budget::<CStringArray, _>(names, 1024).is_ok());
+/// ```
+pub fn c_repr_of_with_budget<T: CReprOf<U>, U>(input: U, budget: usize) -> Result<T, CReprOfError> {
+    let previous = REMAINING_BUDGET.with(Cell::get);
+    let budget = previous.map_or(budget, |previous| previous.min(budget));
+    REMAINING_BUDGET.with(|remaining| remaining.set(Some(budget)));
+    let _guard = BudgetGuard { previous, budget };
+
+    T::c_repr_of(input)
+}
+
+/// Reserves `bytes` in the budget of the current call to [`c_repr_of_with_budget`], failing with
+/// [`CReprOfError::MemoryBudgetExceeded`] if there is not enough memory left. Does nothing outside
+/// of such a call.
+///
+/// Implementations of [`CReprOf`] should call this function before allocating memory whose size
+/// depends on the input (such as strings or arrays).
+pub fn reserve_memory(bytes: usize) -> Result<(), CReprOfError> {
+    REMAINING_BUDGET.with(|remaining| match remaining.get() {
+        None => Ok(()),
+        Some(available) if bytes <= available => {
+            remaining.set(Some(available - bytes));
+            Ok(())
+        }
+        Some(available) => Err(CReprOfError::MemoryBudgetExceeded {
+            requested: bytes,
+            available,
+        }),
+    })
+}
@@ -0,0 +1,52 @@
+//! This module contains the implementations of the conversion traits for the buffers of the
+//! `bytes` crate, represented as a [`CByteArray`]. Only available with the `bytes` feature.
+//!
+//! A [`Bytes`] or a [`BytesMut`] is converted without copying its content when it is the only
+//! owner of its buffer and the buffer is fully used (e.g. a `Bytes` created from a `Vec<u8>`) :
+//! the C array then takes the ownership of the buffer, which is freed when it is dropped.
+//! Otherwise (e.g. for a slice of a shared `Bytes`), the content is copied.
+//!
+//! # Example
+//!
+//! ```
+//! use bytes::Bytes;
+//! use ffi_convert::{AsRust, CByteArray, CReprOf};
+//!
+//! let payload = Bytes::from(vec![0xca, 0xfe]);
+//! let data_ptr = payload.as_ptr();
+//!
+//! let c_payload = CByteArray::c_repr_of(payload).unwrap();
+//! assert_eq!(c_payload.data_ptr, data_ptr);
+//!
+//! let payload: Bytes = c_payload.as_rust().unwrap();
+//! assert_eq!(payload, Bytes::from_static(&[0xca, 0xfe]));
+//! ```
+
+use bytes::{Bytes, BytesMut};
+
+use crate::conversions::*;
+use crate::types::CByteArray;
+
+impl CReprOf<Bytes> for CByteArray {
+    fn c_repr_of(input: Bytes) -> Result<Self, CReprOfError> {
+        Self::c_repr_of(Vec::from(input))
+    }
+}
+
+impl CReprOf<BytesMut> for CByteArray {
+    fn c_repr_of(input: BytesMut) -> Result<Self, CReprOfError> {
+        Self::c_repr_of(Vec::from(input))
+    }
+}
+
+impl AsRust<Bytes> for CByteArray {
+    fn as_rust(&self) -> Result<Bytes, AsRustError> {
+        Ok(Bytes::copy_from_slice(self.as_slice()?))
+    }
+}
+
+impl AsRust<BytesMut> for CByteArray {
+    fn as_rust(&self) -> Result<BytesMut, AsRustError> {
+        Ok(BytesMut::from(self.as_slice()?))
+    }
+}
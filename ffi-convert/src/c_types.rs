@@ -0,0 +1,207 @@
+//! This module contains the [`export_c_types`](crate::export_c_types) and
+//! [`declare_c_array`](crate::declare_c_array) macros, defining in the caller's crate the C structs
+//! of the containers of this crate (such as [`CArray`](crate::CArray)) so that their definitions
+//! end up in the generated C headers.
+
+/// Defines, in the calling crate, named C structs with the layout of the containers of this
+/// crate, so that header generators such as cbindgen (which can't see the definitions of
+/// `ffi_convert`, nor monomorphize them) write their complete definitions.
+///
+/// The supported containers are `CArray<T>`, `CStringArray` and `CRange<T>`. Each defined struct
+/// has the same fields as its container (its size and alignment are checked at compile time),
+/// dereferences to it, is converted from/into it with `From`, and implements `CReprOf`, `AsRust`,
+/// `CDrop` and `RawPointerConverter` like the container, so that it can be used instead of it in
+/// the fields of the C structs and in the signatures of the exported functions.
+///
+/// cbindgen only sees the structs defined by macros when it expands the crate, with
+/// `[parse.expand] crates = ["<your crate>"]` in its configuration.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{export_c_types, AsRust, CArray, CDrop, CReprOf};
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// pub struct Topping {
+///     pub amount: i32,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(Topping)]
+/// pub struct CTopping {
+///     pub amount: i32,
+/// }
+///
+/// export_c_types! {
+///     /// The toppings of a pizza
+///     pub struct CToppingArray = CArray<CTopping>;
+///     pub struct CNames = CStringArray;
+///     pub struct CIntRange = CRange<i32>;
+/// }
+///
+/// pub struct Pizza {
+///     pub toppings: Vec<Topping>,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(Pizza)]
+/// pub struct CPizza {
+///     pub toppings: *const CToppingArray,
+/// }
+///
+/// let c_pizza = CPizza::c_repr_of(Pizza { toppings: vec![Topping { amount: 2 }] }).unwrap();
+/// let pizza: Pizza = c_pizza.as_rust().unwrap();
+/// assert_eq!(pizza.toppings, vec![Topping { amount: 2 }]);
+///
+/// let names = CNames::c_repr_of(vec!["Regina".to_string()]).unwrap();
+/// assert_eq!(names.get(0).unwrap().to_str().unwrap(), "Regina");
+///
+/// let range = CIntRange::c_repr_of(2..5).unwrap();
+/// assert_eq!((range.start, range.end), (2, 5));
+/// ```
+#[macro_export]
+macro_rules! export_c_types {
+    () => {};
+    ($(#[$attr:meta])* $vis:vis struct $name:ident = CArray<$t:ty>; $($rest:tt)*) => {
+        $crate::export_c_type!(
+            $(#[$attr])* $vis struct $name = $crate::CArray<$t> {
+                /// Pointer to the first element of the array
+                pub data_ptr: *const $t,
+                /// Number of elements in the array
+                pub size: usize,
+            }
+        );
+        $crate::export_c_types!($($rest)*);
+    };
+    ($(#[$attr:meta])* $vis:vis struct $name:ident = CStringArray; $($rest:tt)*) => {
+        $crate::export_c_type!(
+            $(#[$attr])* $vis struct $name = $crate::CStringArray {
+                /// Pointer to the first element of the array
+                pub data: *const *const ::std::os::raw::c_char,
+                /// Number of elements in the array
+                pub size: usize,
+            }
+        );
+        $crate::export_c_types!($($rest)*);
+    };
+    ($(#[$attr:meta])* $vis:vis struct $name:ident = CRange<$t:ty>; $($rest:tt)*) => {
+        $crate::export_c_type!(
+            $(#[$attr])* $vis struct $name = $crate::CRange<$t> {
+                pub start: $t,
+                pub end: $t,
+            }
+        );
+        $crate::export_c_types!($($rest)*);
+    };
+}
+
+/// Defines a public C struct `$name` with the layout of `CArray<$element>`, a shorthand for
+/// `export_c_types! { pub struct $name = CArray<$element>; }` (see
+/// [`export_c_types`](crate::export_c_types)) : header generators write it as a named C struct,
+/// whose `data_ptr` field points to elements of the given type.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{declare_c_array, AsRust, CReprOf};
+///
+/// declare_c_array!(CAmountArray, i32);
+///
+/// let amounts = CAmountArray::c_repr_of(vec![1, 2, 3]).unwrap();
+/// assert_eq!(amounts.size, 3);
+/// assert_eq!(AsRust::<Vec<i32>>::as_rust(&amounts).unwrap(), vec![1, 2, 3]);
+/// ```
+#[macro_export]
+macro_rules! declare_c_array {
+    ($(#[$attr:meta])* $name:ident, $element:ty $(,)?) => {
+        $crate::export_c_types! {
+            $(#[$attr])* pub struct $name = CArray<$element>;
+        }
+    };
+}
+
+/// Defines a C struct with the layout of the given container, see
+/// [`export_c_types`](crate::export_c_types).
+#[doc(hidden)]
+#[macro_export]
+macro_rules! export_c_type {
+    (
+        $(#[$attr:meta])* $vis:vis struct $name:ident = $container:ty {
+            $($(#[$field_attr:meta])* pub $field:ident: $field_type:ty,)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[repr(C)]
+        #[derive($crate::RawPointerConverter)]
+        $vis struct $name {
+            $($(#[$field_attr])* pub $field: $field_type,)*
+        }
+
+        const _: () = assert!(
+            ::std::mem::size_of::<$name>() == ::std::mem::size_of::<$container>()
+                && ::std::mem::align_of::<$name>() == ::std::mem::align_of::<$container>(),
+            concat!("`", stringify!($name), "` doesn't have the layout of its container"),
+        );
+
+        impl ::std::convert::From<$container> for $name {
+            fn from(container: $container) -> Self {
+                let container = ::std::mem::ManuallyDrop::new(container);
+                // the structs have the same fields, in the same order
+                unsafe { ::std::ptr::read(&*container as *const $container as *const Self) }
+            }
+        }
+
+        impl ::std::convert::From<$name> for $container {
+            fn from(value: $name) -> Self {
+                let value = ::std::mem::ManuallyDrop::new(value);
+                unsafe { ::std::ptr::read(&*value as *const $name as *const Self) }
+            }
+        }
+
+        impl ::std::ops::Deref for $name {
+            type Target = $container;
+
+            fn deref(&self) -> &$container {
+                unsafe { &*(self as *const Self as *const $container) }
+            }
+        }
+
+        impl ::std::ops::DerefMut for $name {
+            fn deref_mut(&mut self) -> &mut $container {
+                unsafe { &mut *(self as *mut Self as *mut $container) }
+            }
+        }
+
+        impl<U> $crate::CReprOf<U> for $name
+        where
+            $container: $crate::CReprOf<U>,
+        {
+            fn c_repr_of(input: U) -> Result<Self, $crate::CReprOfError> {
+                <$container as $crate::CReprOf<U>>::c_repr_of(input).map(Self::from)
+            }
+        }
+
+        impl<U> $crate::AsRust<U> for $name
+        where
+            $container: $crate::AsRust<U>,
+        {
+            fn as_rust(&self) -> Result<U, $crate::AsRustError> {
+                <$container as $crate::AsRust<U>>::as_rust(self)
+            }
+        }
+
+        impl $crate::CDrop for $name {
+            fn do_drop(&mut self) -> Result<(), $crate::CDropError> {
+                <$container as $crate::CDrop>::do_drop(self)
+            }
+        }
+
+        impl Drop for $name {
+            fn drop(&mut self) {
+                let _ = $crate::CDrop::do_drop(self);
+            }
+        }
+    };
+}
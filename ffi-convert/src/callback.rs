@@ -0,0 +1,250 @@
+//! This module contains the [`CCallback`] type, a C function pointer with its user data, and the
+//! [`CCallbackArgs`] trait describing its signature.
+
+use ffi_convert_derive::RawPointerConverter;
+
+use std::ptr;
+use std::sync::Arc;
+
+use crate as ffi_convert;
+use crate::conversions::*;
+
+/// A utility type to pass callbacks between C and Rust, as a C function pointer taking an opaque
+/// `user_data` pointer as first argument, followed by the arguments of the callback.
+///
+/// The `user_data` is managed with the `retain` and `release` functions (which can be `null` if
+/// the user data doesn't need to be managed, e.g. if it is a static variable) :
+/// - `retain` takes a new reference on the user data and returns it, so that the callback can be
+///   shared
+/// - `release` releases a reference on the user data, and frees it once the last reference is
+///   released
+///
+/// A Rust closure is converted to a `CCallback` whose user data is the closure itself, and a
+/// `CCallback` is converted to a Rust closure holding its own reference on the user data : the
+/// closure can then outlive the `CCallback`. The `CCallback` releases its reference when it is
+/// dropped.
+///
+/// `Args` is the tuple of the types of the arguments, and `Ret` the return type (`()` by
+/// default). Both are passed as is (they are not converted), so they should have a C
+/// representation.
+///
+/// As panics can't unwind through C code, a panic in a closure called from C aborts the process.
+///
+/// In the custom derives, `CCallback` fields are converted like the other fields, and an optional
+/// closure (`Option<Box<dyn Fn(...)>>`) can be converted to a `null` callback by marking the field
+/// with the `#[callback]` and `#[nullable]` attributes.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, CCallback, CReprOf};
+///
+/// let scale = 3;
+/// let callback =
+///     CCallback::<(i32, i32), i32>::c_repr_of(Box::new(move |a, b| scale * (a + b))).unwrap();
+///
+/// // what C code would do with the callback
+/// let sum = unsafe { (callback.function.unwrap())(callback.user_data, 1, 2) };
+/// assert_eq!(sum, 9);
+///
+/// let closure: Box<dyn Fn(i32, i32) -> i32> = callback.as_rust().unwrap();
+/// drop(callback);
+/// assert_eq!(closure(2, 3), 15);
+/// ```
+#[repr(C)]
+#[derive(Debug, RawPointerConverter)]
+pub struct CCallback<Args: CCallbackArgs<Ret>, Ret = ()> {
+    /// The function pointer, called with the user data followed by the arguments
+    pub function: Option<Args::Function>,
+    /// The user data passed to the function
+    pub user_data: *mut libc::c_void,
+    /// Takes a new reference on the user data, and returns the user data
+    pub retain: Option<unsafe extern "C" fn(user_data: *mut libc::c_void) -> *mut libc::c_void>,
+    /// Releases a reference on the user data
+    pub release: Option<unsafe extern "C" fn(user_data: *mut libc::c_void)>,
+}
+
+impl<Args: CCallbackArgs<Ret>, Ret> CCallback<Args, Ret> {
+    /// Creates a `null` callback, used for the optional callbacks.
+    pub fn null() -> Self {
+        Self {
+            function: None,
+            user_data: ptr::null_mut(),
+            retain: None,
+            release: None,
+        }
+    }
+
+    /// Returns `true` if the function pointer is `null`.
+    pub fn is_null(&self) -> bool {
+        self.function.is_none()
+    }
+
+    /// Calls the callback with the given arguments.
+    ///
+    /// An error is returned if the function pointer is `null`.
+    pub fn call(&self, args: Args) -> Result<Ret, AsRustError> {
+        let function = self.function.ok_or(UnexpectedNullPointerError)?;
+        Ok(unsafe { Args::call(function, self.user_data, args) })
+    }
+}
+
+impl<Args: CCallbackArgs<Ret>, Ret> AsRust<Box<Args::Closure>> for CCallback<Args, Ret> {
+    fn as_rust(&self) -> Result<Box<Args::Closure>, AsRustError> {
+        let function = self.function.ok_or(UnexpectedNullPointerError)?;
+        let user_data = match (self.retain, self.release) {
+            (Some(retain), _) => unsafe { retain(self.user_data) },
+            (None, None) => self.user_data,
+            (None, Some(_)) => {
+                return Err(AsRustError::Other(
+                    "the callback has a `release` function but no `retain` function, \
+                    its user data can't be shared"
+                        .into(),
+                ))
+            }
+        };
+        Ok(Args::closure(CallbackRef {
+            function,
+            user_data,
+            release: self.release,
+        }))
+    }
+}
+
+impl<Args: CCallbackArgs<Ret>, Ret> CReprOf<Box<Args::Closure>> for CCallback<Args, Ret> {
+    fn c_repr_of(input: Box<Args::Closure>) -> Result<Self, CReprOfError> {
+        Ok(Self {
+            function: Some(Args::trampoline()),
+            user_data: Arc::into_raw(Arc::new(input)) as *mut libc::c_void,
+            retain: Some(retain_closure::<Args::Closure>),
+            release: Some(release_closure::<Args::Closure>),
+        })
+    }
+}
+
+impl<Args: CCallbackArgs<Ret>, Ret> CValidate for CCallback<Args, Ret> {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        if self.function.is_none() {
+            return Err(UnexpectedNullPointerError.into());
+        }
+        Ok(())
+    }
+}
+
+impl<Args: CCallbackArgs<Ret>, Ret> CDrop for CCallback<Args, Ret> {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        if let Some(release) = self.release.take() {
+            unsafe { release(self.user_data) };
+        }
+        self.user_data = ptr::null_mut();
+        Ok(())
+    }
+}
+
+impl<Args: CCallbackArgs<Ret>, Ret> Drop for CCallback<Args, Ret> {
+    fn drop(&mut self) {
+        let _ = self.do_drop();
+    }
+}
+
+/// A reference on the user data of a callback, held by the closures created from a
+/// [`CCallback`], and released when they are dropped.
+pub struct CallbackRef<F> {
+    function: F,
+    user_data: *mut libc::c_void,
+    release: Option<unsafe extern "C" fn(user_data: *mut libc::c_void)>,
+}
+
+impl<F: Copy> CallbackRef<F> {
+    /// The function pointer of the callback.
+    pub fn function(&self) -> F {
+        self.function
+    }
+
+    /// The user data of the callback, to be passed to its function.
+    pub fn user_data(&self) -> *mut libc::c_void {
+        self.user_data
+    }
+}
+
+impl<F> Drop for CallbackRef<F> {
+    fn drop(&mut self) {
+        if let Some(release) = self.release {
+            unsafe { release(self.user_data) };
+        }
+    }
+}
+
+/// The signature of a [`CCallback`] : implemented for the tuples (of up to 6 elements) of the
+/// types of its arguments, `Ret` being its return type.
+pub trait CCallbackArgs<Ret>: Sized {
+    /// The C function pointer type, taking the user data followed by the arguments
+    type Function: Copy;
+    /// The Rust closure type, taking the arguments
+    type Closure: ?Sized;
+
+    /// Calls a C function pointer with the given user data and arguments.
+    ///
+    /// # Safety
+    ///
+    /// The user data must be the one expected by the function.
+    unsafe fn call(function: Self::Function, user_data: *mut libc::c_void, args: Self) -> Ret;
+
+    /// Returns the C function calling the Rust closure passed as user data (an
+    /// `Arc<Box<Self::Closure>>`).
+    fn trampoline() -> Self::Function;
+
+    /// Creates a Rust closure calling the function of the callback.
+    fn closure(callback: CallbackRef<Self::Function>) -> Box<Self::Closure>;
+}
+
+unsafe extern "C" fn retain_closure<C: ?Sized>(user_data: *mut libc::c_void) -> *mut libc::c_void {
+    Arc::increment_strong_count(user_data as *const Box<C>);
+    user_data
+}
+
+unsafe extern "C" fn release_closure<C: ?Sized>(user_data: *mut libc::c_void) {
+    Arc::decrement_strong_count(user_data as *const Box<C>);
+}
+
+macro_rules! impl_c_callback_args {
+    ($($arg:ident: $typ:ident),*) => {
+        impl<$($typ: 'static,)* Ret: 'static> CCallbackArgs<Ret> for ($($typ,)*) {
+            type Function = unsafe extern "C" fn(*mut libc::c_void, $($typ),*) -> Ret;
+            type Closure = dyn Fn($($typ),*) -> Ret;
+
+            unsafe fn call(
+                function: Self::Function,
+                user_data: *mut libc::c_void,
+                ($($arg,)*): Self,
+            ) -> Ret {
+                function(user_data, $($arg),*)
+            }
+
+            fn trampoline() -> Self::Function {
+                unsafe extern "C" fn trampoline<$($typ,)* Ret>(
+                    user_data: *mut libc::c_void,
+                    $($arg: $typ),*
+                ) -> Ret {
+                    let closure = &*(user_data as *const Box<dyn Fn($($typ),*) -> Ret>);
+                    closure($($arg),*)
+                }
+                trampoline::<$($typ,)* Ret>
+            }
+
+            fn closure(callback: CallbackRef<Self::Function>) -> Box<Self::Closure> {
+                Box::new(move |$($arg),*| unsafe {
+                    (callback.function())(callback.user_data(), $($arg),*)
+                })
+            }
+        }
+    };
+}
+
+impl_c_callback_args!();
+impl_c_callback_args!(a: A);
+impl_c_callback_args!(a: A, b: B);
+impl_c_callback_args!(a: A, b: B, c: C);
+impl_c_callback_args!(a: A, b: B, c: C, d: D);
+impl_c_callback_args!(a: A, b: B, c: C, d: D, e: E);
+impl_c_callback_args!(a: A, b: B, c: C, d: D, e: E, f: F);
@@ -0,0 +1,121 @@
+//! This module contains the [`ConversionContext`] type and the [`c_repr_of_with`] and
+//! [`as_rust_with`] functions, used to change how values are converted for a single call.
+
+use std::cell::Cell;
+use std::time::Duration;
+
+use crate::budget::c_repr_of_with_budget;
+use crate::conversions::*;
+use crate::deadline::enter_deadline;
+
+/// How strings that are not valid UTF-8 are handled when converting C strings to Rust strings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Utf8Policy {
+    /// The conversion fails with [`AsRustError::Utf8Error`] (this is the default)
+    #[default]
+    Strict,
+    /// The invalid sequences are replaced with `U+FFFD`
+    Lossy,
+}
+
+/// Options of a conversion, applied with [`c_repr_of_with`] and [`as_rust_with`] to all the values
+/// converted during the call (including the fields of derived structs).
+///
+/// The default context gives the same results as [`CReprOf::c_repr_of`] and [`AsRust::as_rust`].
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{as_rust_with, c_repr_of_with, ConversionContext, Utf8Policy};
+/// use std::ffi::{CStr, CString};
+///
+/// let context = ConversionContext {
+///     utf8_policy: Utf8Policy::Lossy,
+///     replace_nul_characters: true,
+///     ..Default::default()
+/// };
+///
+/// let name: CString = c_repr_of_with("Crêpe\0Suzette".to_string(), &context).unwrap();
+/// assert_eq!(name.to_str().unwrap(), "Crêpe\u{FFFD}Suzette");
+///
+/// let invalid = CStr::from_bytes_with_nul(b"Cr\xeape\0").unwrap();
+/// let name: String = as_rust_with(invalid, &context).unwrap();
+/// assert_eq!(name, "Cr\u{FFFD}pe");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConversionContext {
+    /// How C strings that are not valid UTF-8 are converted to Rust strings
+    pub utf8_policy: Utf8Policy,
+    /// Whether nul characters in Rust strings (which can't be represented in C strings) are
+    /// replaced with `U+FFFD`, instead of failing with [`CReprOfError::StringContainsNullBit`]
+    pub replace_nul_characters: bool,
+    /// Whether the checked integer conversions (e.g. from a `usize` to a `u32`) saturate
+    /// instead of failing when the value doesn't fit in the destination type
+    pub saturate_integers: bool,
+    /// Whether the strings too long for their fixed size C buffer (see
+    /// [`CFixedString`](crate::CFixedString)) are truncated instead of failing with
+    /// [`CReprOfError::StringTooLong`]
+    pub truncate_strings: bool,
+    /// Maximum number of bytes allocated by the conversion, see
+    /// [`c_repr_of_with_budget`](crate::c_repr_of_with_budget)
+    pub memory_budget: Option<usize>,
+    /// Maximum duration of the conversion, see
+    /// [`as_rust_with_timeout`](crate::as_rust_with_timeout)
+    pub timeout: Option<Duration>,
+}
+
+thread_local! {
+    /// Context of the current call to [`c_repr_of_with`] or [`as_rust_with`], `None` outside of
+    /// such a call.
+    static CURRENT_CONTEXT: Cell<Option<ConversionContext>> = const { Cell::new(None) };
+}
+
+/// Restores the context of the enclosing call (if any) when a call returns (or panics).
+struct ContextGuard {
+    previous: Option<ConversionContext>,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        CURRENT_CONTEXT.with(|context| context.set(self.previous));
+    }
+}
+
+fn enter(context: &ConversionContext) -> ContextGuard {
+    ContextGuard {
+        previous: CURRENT_CONTEXT.with(|current| current.replace(Some(*context))),
+    }
+}
+
+/// Returns the context of the current call to [`c_repr_of_with`] or [`as_rust_with`], or the
+/// default context outside of such a call.
+///
+/// Implementations of [`CReprOf`] and [`AsRust`] can use it to honor the options of the context.
+pub fn current_context() -> ConversionContext {
+    CURRENT_CONTEXT.with(Cell::get).unwrap_or_default()
+}
+
+/// Creates the C representation of `input`, like [`CReprOf::c_repr_of`], with the options of the
+/// given context.
+pub fn c_repr_of_with<T: CReprOf<U>, U>(
+    input: U,
+    context: &ConversionContext,
+) -> Result<T, CReprOfError> {
+    let _guard = enter(context);
+    let _deadline = context.timeout.map(enter_deadline);
+    match context.memory_budget {
+        Some(budget) => c_repr_of_with_budget(input, budget),
+        None => T::c_repr_of(input),
+    }
+}
+
+/// Converts `input` to its Rust representation, like [`AsRust::as_rust`], with the options of the
+/// given context.
+pub fn as_rust_with<T, U: AsRust<T> + ?Sized>(
+    input: &U,
+    context: &ConversionContext,
+) -> Result<T, AsRustError> {
+    let _guard = enter(context);
+    let _deadline = context.timeout.map(enter_deadline);
+    input.as_rust()
+}
@@ -1,21 +1,32 @@
-use std::ffi::NulError;
+use std::alloc::LayoutError;
+use std::char::CharTryFromError;
+use std::ffi::{CStr, NulError, OsStr, OsString};
+use std::num::{
+    NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8,
+    NonZeroUsize, TryFromIntError,
+};
+use std::path::{Path, PathBuf};
 use std::str::Utf8Error;
+use std::sync::atomic::{
+    AtomicBool, AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicU16, AtomicU32, AtomicU64,
+    AtomicU8, AtomicUsize,
+};
 
 use thiserror::Error;
 
+use crate::budget::reserve_memory;
+use crate::context::{current_context, Utf8Policy};
+use crate::deadline::{check_deadline, DeadlineExceededError};
+
 macro_rules! impl_c_repr_of_for {
     ($typ:ty) => {
         impl CReprOf<$typ> for $typ {
             fn c_repr_of(input: $typ) -> Result<$typ, CReprOfError> {
                 Ok(input)
             }
-        }
-    };
 
-    ($from_typ:ty, $to_typ:ty) => {
-        impl CReprOf<$from_typ> for $to_typ {
-            fn c_repr_of(input: $from_typ) -> Result<$to_typ, CReprOfError> {
-                Ok(input as $to_typ)
+            fn c_repr_of_vec(input: Vec<$typ>) -> Result<Vec<$typ>, CReprOfError> {
+                Ok(input)
             }
         }
     };
@@ -32,19 +43,120 @@ macro_rules! impl_c_drop_for {
     };
 }
 
+/// implements a noop implementation of the CValidate trait for a given type.
+macro_rules! impl_c_validate_for {
+    ($typ:ty) => {
+        impl CValidate for $typ {
+            fn validate_c(&self) -> Result<(), AsRustError> {
+                Ok(())
+            }
+        }
+    };
+}
+
+/// implements the ZeroCheck trait for a given integer type.
+macro_rules! impl_zero_check_for {
+    ($typ:ty) => {
+        impl ZeroCheck for $typ {
+            fn is_zeroed(&self) -> bool {
+                *self == 0
+            }
+        }
+    };
+}
+
 macro_rules! impl_as_rust_for {
     ($typ:ty) => {
         impl AsRust<$typ> for $typ {
             fn as_rust(&self) -> Result<$typ, AsRustError> {
                 Ok(*self)
             }
+
+            fn as_rust_vec(values: &[$typ]) -> Result<Vec<$typ>, AsRustError> {
+                Ok(values.to_vec())
+            }
+        }
+    };
+}
+
+/// implements checked CReprOf and AsRust conversions between a C integer type and a Rust integer
+/// type of a possibly different size or signedness, failing if the value doesn't fit in the
+/// destination type (or saturating, if the `saturate_integers` option of the current context is
+/// set).
+macro_rules! impl_checked_conversions_for {
+    ($c_typ:ty, $rust_typ:ty) => {
+        impl CReprOf<$rust_typ> for $c_typ {
+            fn c_repr_of(input: $rust_typ) -> Result<$c_typ, CReprOfError> {
+                match <$c_typ>::try_from(input) {
+                    Ok(value) => Ok(value),
+                    Err(_) if current_context().saturate_integers => Ok((input as i128)
+                        .clamp(<$c_typ>::MIN as i128, <$c_typ>::MAX as i128)
+                        as $c_typ),
+                    Err(e) => Err(CReprOfError::IntegerOverflow(e)),
+                }
+            }
+        }
+
+        impl AsRust<$rust_typ> for $c_typ {
+            fn as_rust(&self) -> Result<$rust_typ, AsRustError> {
+                match <$rust_typ>::try_from(*self) {
+                    Ok(value) => Ok(value),
+                    Err(_) if current_context().saturate_integers => Ok((*self as i128)
+                        .clamp(<$rust_typ>::MIN as i128, <$rust_typ>::MAX as i128)
+                        as $rust_typ),
+                    Err(e) => Err(AsRustError::IntegerOverflow(e)),
+                }
+            }
         }
     };
+}
+
+/// implements CReprOf and AsRust conversions between a non zero integer type and its primitive
+/// C representation, failing on zero values on the Rust side. `Option`s of non zero integers are
+/// converted too, `0` representing `None`.
+macro_rules! impl_non_zero_conversions_for {
+    ($non_zero_typ:ty, $c_typ:ty) => {
+        impl CReprOf<$non_zero_typ> for $c_typ {
+            fn c_repr_of(input: $non_zero_typ) -> Result<$c_typ, CReprOfError> {
+                Ok(input.get())
+            }
+        }
+
+        impl AsRust<$non_zero_typ> for $c_typ {
+            fn as_rust(&self) -> Result<$non_zero_typ, AsRustError> {
+                <$non_zero_typ>::new(*self).ok_or(AsRustError::UnexpectedZero)
+            }
+        }
 
-    ($from_typ:ty, $to_typ:ty) => {
-        impl AsRust<$to_typ> for $from_typ {
-            fn as_rust(&self) -> Result<$to_typ, AsRustError> {
-                Ok(*self as $to_typ)
+        impl CReprOf<Option<$non_zero_typ>> for $c_typ {
+            fn c_repr_of(input: Option<$non_zero_typ>) -> Result<$c_typ, CReprOfError> {
+                Ok(input.map_or(0, <$non_zero_typ>::get))
+            }
+        }
+
+        impl AsRust<Option<$non_zero_typ>> for $c_typ {
+            fn as_rust(&self) -> Result<Option<$non_zero_typ>, AsRustError> {
+                Ok(<$non_zero_typ>::new(*self))
+            }
+        }
+    };
+}
+
+/// implements CReprOf and AsRust conversions between an atomic type and its primitive C
+/// representation. The atomic is consumed, so its value is read without synchronization : the
+/// `#[atomic_ordering(...)]` field attribute of the `CReprOf` derive loads the value of a shared
+/// atomic (e.g. an `Arc<AtomicU32>`) with the given ordering instead.
+macro_rules! impl_atomic_conversions_for {
+    ($atomic_typ:ty, $c_typ:ty) => {
+        impl CReprOf<$atomic_typ> for $c_typ {
+            fn c_repr_of(input: $atomic_typ) -> Result<$c_typ, CReprOfError> {
+                Ok(input.into_inner())
+            }
+        }
+
+        impl AsRust<$atomic_typ> for $c_typ {
+            fn as_rust(&self) -> Result<$atomic_typ, AsRustError> {
+                Ok(<$atomic_typ>::new(*self))
             }
         }
     };
@@ -77,14 +189,94 @@ macro_rules! impl_rawpointerconverter_for {
 pub enum CReprOfError {
     #[error("A string contains a nul bit")]
     StringContainsNullBit(#[from] NulError),
+    #[error("A cycle was detected in the value to convert")]
+    CycleDetected,
+    #[error(
+        "The memory budget of the conversion is exceeded: {} bytes requested, {} available",
+        .requested,
+        .available
+    )]
+    MemoryBudgetExceeded { requested: usize, available: usize },
+    #[error("An integer does not fit in the C type: {}", .0)]
+    IntegerOverflow(#[from] TryFromIntError),
+    #[error(
+        "A string is too long for its C buffer: {} bytes, {} available",
+        .length,
+        .capacity
+    )]
+    StringTooLong { length: usize, capacity: usize },
+    #[error("The type {} has no variant in the tagged union", .type_name)]
+    UnregisteredType { type_name: &'static str },
+    #[error("The memory to allocate is too large: {}", .0)]
+    AllocationTooLarge(#[from] LayoutError),
+    #[error(transparent)]
+    DeadlineExceeded(#[from] DeadlineExceededError),
+    #[error("element {}: {}", .index, .source)]
+    InElement {
+        index: usize,
+        source: Box<CReprOfError>,
+    },
     #[error("An error occurred during conversion to C repr; {}", .0)]
     Other(#[from] Box<dyn std::error::Error + Send + Sync>),
 }
 
+impl CReprOfError {
+    /// Adds the index of the element of an array whose conversion failed to the error. An exceeded
+    /// deadline is not specific to an element, it is returned as is.
+    pub fn in_element(self, index: usize) -> Self {
+        match self {
+            CReprOfError::DeadlineExceeded(_) => self,
+            _ => CReprOfError::InElement {
+                index,
+                source: Box::new(self),
+            },
+        }
+    }
+}
+
 /// Trait showing that the struct implementing it is a `repr(C)` compatible view of the parametrized
 /// type that can be created from an value of this type.
 pub trait CReprOf<T>: Sized + CDrop {
     fn c_repr_of(input: T) -> Result<Self, CReprOfError>;
+
+    /// Converts the elements of a vector, as done by the `CReprOf<Vec<T>>` implementation of
+    /// `CArray`. The elements are converted one by one, except for the primitive types converted to
+    /// themselves, whose vector is returned as is.
+    fn c_repr_of_vec(input: Vec<T>) -> Result<Vec<Self>, CReprOfError> {
+        reserve_memory(input.len() * std::mem::size_of::<Self>())?;
+        input
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| {
+                check_deadline()?;
+                Self::c_repr_of(value).map_err(|e| e.in_element(index))
+            })
+            .collect()
+    }
+}
+
+/// Creates the C representation of a borrowed value, implemented for all the types implementing
+/// [`CReprOf`] for a `Clone` type : the value is cloned internally, so that callers holding a
+/// reference don't have to clone it themselves at every call site.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{CArray, CReprOfRef};
+///
+/// let values = vec![1.0, 2.0];
+/// let c_values = CArray::<f32>::c_repr_of_ref(&values).unwrap();
+///
+/// assert_eq!(c_values.as_slice().unwrap(), &values[..]);
+/// ```
+pub trait CReprOfRef<T>: Sized {
+    fn c_repr_of_ref(input: &T) -> Result<Self, CReprOfError>;
+}
+
+impl<T: CReprOf<U>, U: Clone> CReprOfRef<U> for T {
+    fn c_repr_of_ref(input: &U) -> Result<Self, CReprOfError> {
+        T::c_repr_of(input.clone())
+    }
 }
 
 #[derive(Error, Debug)]
@@ -105,17 +297,104 @@ pub trait CDrop {
 pub enum AsRustError {
     #[error("unexpected null pointer")]
     NullPointer(#[from] UnexpectedNullPointerError),
+    #[error("unexpected zero value for a non zero integer")]
+    UnexpectedZero,
 
     #[error("could not convert string as it is not UTF-8: {}", .0)]
     Utf8Error(#[from] Utf8Error),
+    #[error("index {} is out of bounds for an array of size {}", .index, .size)]
+    IndexOutOfBounds { index: usize, size: usize },
+    #[error("the string is longer than the {} bytes allowed", .max_len)]
+    StringTooLong { max_len: usize },
+    #[error("the string contains a control character at byte {}", .position)]
+    ControlCharacter { position: usize },
+    #[error("pointer is not aligned on {} bytes", .alignment)]
+    MisalignedPointer { alignment: usize },
+    #[error("An integer does not fit in the Rust type: {}", .0)]
+    IntegerOverflow(#[from] TryFromIntError),
+    #[error("An integer is not a valid char: {}", .0)]
+    InvalidChar(#[from] CharTryFromError),
+    #[error("the tag {} is not a variant of the tagged union", .tag)]
+    UnknownTag { tag: u32 },
+    #[error("the struct has the layout version {}, expected {}", .found, .expected)]
+    VersionMismatch { expected: u32, found: u32 },
+    #[error(transparent)]
+    DeadlineExceeded(#[from] DeadlineExceededError),
+    #[error("element {}: {}", .index, .source)]
+    InElement {
+        index: usize,
+        source: Box<AsRustError>,
+    },
     #[error("An error occurred during conversion to Rust: {}", .0)]
     Other(#[from] Box<dyn std::error::Error + Send + Sync>),
 }
 
+impl AsRustError {
+    /// Adds the index of the element of an array whose conversion failed to the error. An exceeded
+    /// deadline is not specific to an element, it is returned as is.
+    pub fn in_element(self, index: usize) -> Self {
+        match self {
+            AsRustError::DeadlineExceeded(_) => self,
+            _ => AsRustError::InElement {
+                index,
+                source: Box::new(self),
+            },
+        }
+    }
+}
+
 /// Trait showing that the struct implementing it is a `repr(C)` compatible view of the parametrized
 /// type and that an instance of the parametrized type can be created form this struct
 pub trait AsRust<T> {
     fn as_rust(&self) -> Result<T, AsRustError>;
+
+    /// Converts a slice of C values, as done by the `AsRust<Vec<T>>` implementation of `CArray`.
+    /// The elements are converted one by one, except for the primitive types converted to
+    /// themselves, which are copied at once.
+    fn as_rust_vec(values: &[Self]) -> Result<Vec<T>, AsRustError>
+    where
+        Self: Sized,
+    {
+        let mut vec = Vec::with_capacity(values.len());
+        for (index, value) in values.iter().enumerate() {
+            check_deadline()?;
+            vec.push(value.as_rust().map_err(|e| e.in_element(index))?);
+        }
+        Ok(vec)
+    }
+}
+
+/// Trait showing that the struct implementing it is a `repr(C)` compatible view of the parametrized
+/// type and that a borrowed view of the parametrized type can be created from this struct, without
+/// copying the underlying data.
+///
+/// Types that can be converted to an owned value through [`AsRust`] can also be "viewed" as this
+/// owned value, which allows to derive [`AsRustView`] on structs mixing borrowed and owned fields.
+pub trait AsRustView<'a, T> {
+    fn as_rust_view(&'a self) -> Result<T, AsRustError>;
+}
+
+impl<'a, T, U: AsRust<T> + ?Sized> AsRustView<'a, T> for U {
+    fn as_rust_view(&'a self) -> Result<T, AsRustError> {
+        self.as_rust()
+    }
+}
+
+/// Trait showing that the C-like struct implementing it can be checked (null pointers, sizes, UTF-8
+/// strings...) without being converted, i.e. that calling [`AsRust::as_rust`] on it should not
+/// fail because of malformed data.
+pub trait CValidate {
+    fn validate_c(&self) -> Result<(), AsRustError>;
+}
+
+/// Trait showing that the C-like value implementing it can be compared to the value a C caller
+/// gets by zeroing it (e.g. with `memset`). The values are compared field by field, the padding
+/// between the fields, which can't be read, being ignored.
+///
+/// It is implemented for the primitive types, the pointers and the arrays, and for the structs
+/// deriving `AsRust` with the `#[as_rust_zeroed]` attribute, whose fields must implement it.
+pub trait ZeroCheck {
+    fn is_zeroed(&self) -> bool;
 }
 
 #[derive(Error, Debug)]
@@ -194,8 +473,84 @@ pub unsafe fn take_back_from_raw_pointer_mut<T>(
     }
 }
 
+/// The pointer stored in a `#[borrowed_string]` field, taking a `&CStr` so that the string is
+/// borrowed (a `&CString` being coerced), not moved into the conversion and freed with it.
+#[doc(hidden)]
+pub fn borrowed_c_str_ptr(string: &CStr) -> *const libc::c_char {
+    string.as_ptr()
+}
+
+/// Releases a pointer field converted by the `c_repr_of` generated by the `CReprOf` derive if the
+/// conversion of a following field fails, as the struct owning it was not built yet.
+#[doc(hidden)]
+pub struct ReleaseOnError<P: Copy, F: FnMut(P) -> Result<(), CDropError>> {
+    pointer: Option<P>,
+    release: F,
+}
+
+impl<P: Copy, F: FnMut(P) -> Result<(), CDropError>> ReleaseOnError<P, F> {
+    pub fn new(pointer: P, release: F) -> Self {
+        Self {
+            pointer: Some(pointer),
+            release,
+        }
+    }
+
+    /// Takes back the pointer once all the fields are converted, the struct now owning it.
+    pub fn keep(mut self) -> P {
+        self.pointer.take().expect("the pointer is only taken once")
+    }
+}
+
+impl<P: Copy, F: FnMut(P) -> Result<(), CDropError>> Drop for ReleaseOnError<P, F> {
+    fn drop(&mut self) {
+        if let Some(pointer) = self.pointer.take() {
+            let _ = (self.release)(pointer);
+        }
+    }
+}
+
+/// Drops a value passed to C as a raw pointer created with
+/// [`RawPointerConverter::into_raw_pointer_mut`], as the destructors exported to C should : this
+/// returns `0` if the value was dropped, `-1` if the pointer is `null` and `-2` if dropping the
+/// value panicked (the panic doesn't unwind into the C caller).
+///
+/// This is the function called by the destructors generated with the `#[generate_destructor]`
+/// attribute of the `RawPointerConverter` derive.
+///
+/// # Safety
+///
+/// The pointer must be `null` or have been created with
+/// [`RawPointerConverter::into_raw_pointer_mut`], and must not be used anymore.
+pub unsafe fn destroy_raw_pointer<T: RawPointerConverter<T>>(input: *mut T) -> i32 {
+    if input.is_null() {
+        return -1;
+    }
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        T::drop_raw_pointer_mut(input)
+    })) {
+        Ok(_) => 0,
+        Err(_) => -2,
+    }
+}
+
 /// Trait to create borrowed references to type T, from a raw pointer to a T. Note that this is
 /// implemented for all types.
+///
+/// This is how the derived [`AsRust`] implementations read pointer fields, and it can be used in
+/// the same way to read a value received from C without taking its ownership :
+///
+/// ```
+/// use ffi_convert::{AsRust, CReprOf, CStringArray, RawBorrow, RawPointerConverter};
+///
+/// let pointer: *const CStringArray =
+///     CStringArray::c_repr_of(vec!["Diavola".to_string()]).unwrap().into_raw_pointer();
+///
+/// let names: Vec<String> = unsafe { CStringArray::raw_borrow(pointer) }.unwrap().as_rust().unwrap();
+/// assert_eq!(names, vec!["Diavola".to_string()]);
+///
+/// unsafe { CStringArray::drop_raw_pointer(pointer) }.unwrap();
+/// ```
 pub trait RawBorrow<T> {
     /// Get a reference on the value behind the pointer or return an error if the pointer is `null`.
     /// # Safety
@@ -308,6 +663,54 @@ impl_c_drop_for!(f64);
 impl_c_drop_for!(bool);
 impl_c_drop_for!(std::ffi::CString);
 
+impl_zero_check_for!(usize);
+impl_zero_check_for!(isize);
+impl_zero_check_for!(i8);
+impl_zero_check_for!(u8);
+impl_zero_check_for!(i16);
+impl_zero_check_for!(u16);
+impl_zero_check_for!(i32);
+impl_zero_check_for!(u32);
+impl_zero_check_for!(i64);
+impl_zero_check_for!(u64);
+
+impl ZeroCheck for f32 {
+    fn is_zeroed(&self) -> bool {
+        // a negative zero is not all zero bits
+        self.to_bits() == 0
+    }
+}
+
+impl ZeroCheck for f64 {
+    fn is_zeroed(&self) -> bool {
+        self.to_bits() == 0
+    }
+}
+
+impl ZeroCheck for bool {
+    fn is_zeroed(&self) -> bool {
+        !*self
+    }
+}
+
+impl<T: ?Sized> ZeroCheck for *const T {
+    fn is_zeroed(&self) -> bool {
+        self.is_null()
+    }
+}
+
+impl<T: ?Sized> ZeroCheck for *mut T {
+    fn is_zeroed(&self) -> bool {
+        self.is_null()
+    }
+}
+
+impl<T: ZeroCheck, const N: usize> ZeroCheck for [T; N] {
+    fn is_zeroed(&self) -> bool {
+        self.iter().all(T::is_zeroed)
+    }
+}
+
 impl_c_repr_of_for!(usize);
 impl_c_repr_of_for!(i8);
 impl_c_repr_of_for!(u8);
@@ -321,14 +724,55 @@ impl_c_repr_of_for!(f32);
 impl_c_repr_of_for!(f64);
 impl_c_repr_of_for!(bool);
 
-impl_c_repr_of_for!(usize, i32);
-
 impl CReprOf<String> for std::ffi::CString {
     fn c_repr_of(input: String) -> Result<Self, CReprOfError> {
+        let input = if current_context().replace_nul_characters && input.contains('\0') {
+            input.replace('\0', "\u{FFFD}")
+        } else {
+            input
+        };
+        reserve_memory(input.len() + 1)?;
         Ok(std::ffi::CString::new(input)?)
     }
 }
 
+/// Creates a C string from an `OsStr`, from its bytes on Unix (so that any path can be converted),
+/// and from its UTF-8 representation on the other platforms.
+fn os_str_to_c_string(input: &OsStr) -> Result<std::ffi::CString, CReprOfError> {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(input);
+    #[cfg(not(unix))]
+    let bytes = input
+        .to_str()
+        .ok_or_else(|| CReprOfError::Other("the path is not valid UTF-8".into()))?
+        .as_bytes();
+
+    reserve_memory(bytes.len() + 1)?;
+    Ok(std::ffi::CString::new(bytes)?)
+}
+
+/// Borrows an `OsStr` from a C string, see [`os_str_to_c_string`].
+fn c_str_to_os_str(input: &std::ffi::CStr) -> Result<&OsStr, AsRustError> {
+    #[cfg(unix)]
+    let os_str = std::os::unix::ffi::OsStrExt::from_bytes(input.to_bytes());
+    #[cfg(not(unix))]
+    let os_str = OsStr::new(input.to_str()?);
+
+    Ok(os_str)
+}
+
+impl CReprOf<OsString> for std::ffi::CString {
+    fn c_repr_of(input: OsString) -> Result<Self, CReprOfError> {
+        os_str_to_c_string(&input)
+    }
+}
+
+impl CReprOf<PathBuf> for std::ffi::CString {
+    fn c_repr_of(input: PathBuf) -> Result<Self, CReprOfError> {
+        os_str_to_c_string(input.as_os_str())
+    }
+}
+
 impl_as_rust_for!(usize);
 impl_as_rust_for!(i8);
 impl_as_rust_for!(u8);
@@ -342,11 +786,119 @@ impl_as_rust_for!(f32);
 impl_as_rust_for!(f64);
 impl_as_rust_for!(bool);
 
-impl_as_rust_for!(i32, usize);
+impl_checked_conversions_for!(i32, usize);
+impl_checked_conversions_for!(u32, usize);
+impl_checked_conversions_for!(i64, usize);
+impl_checked_conversions_for!(u64, usize);
+impl_checked_conversions_for!(i32, isize);
+impl_checked_conversions_for!(i64, isize);
+
+impl_non_zero_conversions_for!(NonZeroU8, u8);
+impl_non_zero_conversions_for!(NonZeroI8, i8);
+impl_non_zero_conversions_for!(NonZeroU16, u16);
+impl_non_zero_conversions_for!(NonZeroI16, i16);
+impl_non_zero_conversions_for!(NonZeroU32, u32);
+impl_non_zero_conversions_for!(NonZeroI32, i32);
+impl_non_zero_conversions_for!(NonZeroU64, u64);
+impl_non_zero_conversions_for!(NonZeroI64, i64);
+impl_non_zero_conversions_for!(NonZeroUsize, usize);
+
+impl_atomic_conversions_for!(AtomicBool, bool);
+impl_atomic_conversions_for!(AtomicU8, u8);
+impl_atomic_conversions_for!(AtomicI8, i8);
+impl_atomic_conversions_for!(AtomicU16, u16);
+impl_atomic_conversions_for!(AtomicI16, i16);
+impl_atomic_conversions_for!(AtomicU32, u32);
+impl_atomic_conversions_for!(AtomicI32, i32);
+impl_atomic_conversions_for!(AtomicU64, u64);
+impl_atomic_conversions_for!(AtomicI64, i64);
+impl_atomic_conversions_for!(AtomicUsize, usize);
+
+/// A `char` is represented by its code point, so that a `Vec<char>` can be converted to a
+/// `CArray<u32>`.
+impl CReprOf<char> for u32 {
+    fn c_repr_of(input: char) -> Result<Self, CReprOfError> {
+        Ok(input.into())
+    }
+}
+
+impl AsRust<char> for u32 {
+    fn as_rust(&self) -> Result<char, AsRustError> {
+        Ok(char::try_from(*self)?)
+    }
+}
 
 impl AsRust<String> for std::ffi::CStr {
     fn as_rust(&self) -> Result<String, AsRustError> {
-        self.to_str().map(|s| s.to_owned()).map_err(|e| e.into())
+        match current_context().utf8_policy {
+            Utf8Policy::Strict => self.to_str().map(|s| s.to_owned()).map_err(|e| e.into()),
+            Utf8Policy::Lossy => Ok(self.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+impl AsRust<OsString> for std::ffi::CStr {
+    fn as_rust(&self) -> Result<OsString, AsRustError> {
+        Ok(c_str_to_os_str(self)?.to_owned())
+    }
+}
+
+impl AsRust<PathBuf> for std::ffi::CStr {
+    fn as_rust(&self) -> Result<PathBuf, AsRustError> {
+        Ok(PathBuf::from(c_str_to_os_str(self)?))
+    }
+}
+
+impl<'a> AsRustView<'a, &'a Path> for std::ffi::CStr {
+    fn as_rust_view(&'a self) -> Result<&'a Path, AsRustError> {
+        Ok(Path::new(c_str_to_os_str(self)?))
+    }
+}
+
+impl<'a> AsRustView<'a, &'a str> for std::ffi::CStr {
+    fn as_rust_view(&'a self) -> Result<&'a str, AsRustError> {
+        Ok(self.to_str()?)
+    }
+}
+
+impl<'a> AsRustView<'a, std::borrow::Cow<'a, str>> for std::ffi::CStr {
+    fn as_rust_view(&'a self) -> Result<std::borrow::Cow<'a, str>, AsRustError> {
+        match current_context().utf8_policy {
+            Utf8Policy::Strict => Ok(std::borrow::Cow::Borrowed(self.to_str()?)),
+            Utf8Policy::Lossy => Ok(self.to_string_lossy()),
+        }
+    }
+}
+
+impl CReprOf<Box<str>> for std::ffi::CString {
+    fn c_repr_of(input: Box<str>) -> Result<Self, CReprOfError> {
+        Self::c_repr_of(input.into_string())
+    }
+}
+
+impl AsRust<Box<str>> for std::ffi::CStr {
+    fn as_rust(&self) -> Result<Box<str>, AsRustError> {
+        Ok(AsRust::<String>::as_rust(self)?.into_boxed_str())
+    }
+}
+
+impl_c_validate_for!(usize);
+impl_c_validate_for!(i8);
+impl_c_validate_for!(u8);
+impl_c_validate_for!(i16);
+impl_c_validate_for!(u16);
+impl_c_validate_for!(i32);
+impl_c_validate_for!(u32);
+impl_c_validate_for!(i64);
+impl_c_validate_for!(u64);
+impl_c_validate_for!(f32);
+impl_c_validate_for!(f64);
+impl_c_validate_for!(bool);
+
+impl CValidate for std::ffi::CStr {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        self.to_str()?;
+        Ok(())
     }
 }
 
@@ -366,23 +918,16 @@ where
     [T; N]: CDrop,
 {
     fn c_repr_of(input: [U; N]) -> Result<[T; N], CReprOfError> {
-        // TODO passing through a Vec here is a bit ugly, but as the conversion call may fail,
-        // TODO we don't want tobe in the case where we're in the middle of the conversion of the
-        // TODO array and we encounter an error, hence leaving the array partially uninitialised for
-        // TODO rust to try to cleanup. the try_map unstable method on array would be nice here
-        let result_vec: Result<Vec<T>, CReprOfError> =
-            input.into_iter().map(T::c_repr_of).collect();
-        let vec = result_vec?;
-
-        assert_eq!(vec.len(), N);
-
-        let mut result: [T; N] = unsafe { std::mem::zeroed() }; // we'll replace everything so "should" be good
-
-        for (i, t) in vec.into_iter().enumerate() {
-            result[i] = t;
-        }
-
-        Ok(result)
+        // passing through a Vec here is a bit ugly, but as the conversion call may fail, we don't
+        // want to be in the case where we're in the middle of the conversion of the array and we
+        // encounter an error, hence leaving the array partially uninitialised for rust to try to
+        // cleanup. the try_map unstable method on array would be nice here
+        let vec = input
+            .into_iter()
+            .map(T::c_repr_of)
+            .collect::<Result<Vec<T>, CReprOfError>>()?;
+
+        Ok(vec_into_array(vec))
     }
 }
 
@@ -394,23 +939,29 @@ impl<T: CDrop, const N: usize> CDrop for [T; N] {
     }
 }
 
+impl<T: CValidate, const N: usize> CValidate for [T; N] {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        self.iter().try_for_each(T::validate_c)
+    }
+}
+
 impl<U: AsRust<T>, T, const N: usize> AsRust<[T; N]> for [U; N] {
     fn as_rust(&self) -> Result<[T; N], AsRustError> {
-        // TODO passing through a Vec here is a bit ugly, but as the conversion call may fail,
-        // TODO we don't want tobe in the case where we're in the middle of the conversion of the
-        // TODO array and we encounter an error, hence leaving the array partially uninitialised for
-        // TODO rust to try to cleanup. the try_map unstable method on array would be nice here
-        let result_vec: Result<Vec<T>, AsRustError> = self.iter().map(U::as_rust).collect();
-        let vec = result_vec?;
+        // see CReprOf<[U; N]> for [T; N]
+        let vec = self
+            .iter()
+            .map(U::as_rust)
+            .collect::<Result<Vec<T>, AsRustError>>()?;
 
-        assert_eq!(vec.len(), N);
-
-        let mut result: [T; N] = unsafe { std::mem::zeroed() }; // we'll replace everything so "should" be good
-
-        for (i, t) in vec.into_iter().enumerate() {
-            result[i] = t;
-        }
+        Ok(vec_into_array(vec))
+    }
+}
 
-        Ok(result)
+/// Converts a `Vec` created from an array of size `N` back to an array, without requiring the
+/// elements to be `Default` (or to be valid when zeroed).
+fn vec_into_array<T, const N: usize>(vec: Vec<T>) -> [T; N] {
+    match vec.try_into() {
+        Ok(array) => array,
+        Err(vec) => panic!("expected {} elements, got {}", N, vec.len()),
     }
 }
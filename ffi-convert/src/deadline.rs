@@ -0,0 +1,113 @@
+//! This module contains the [`as_rust_with_timeout`] and [`c_repr_of_with_timeout`] functions, used
+//! to stop a conversion taking longer than allowed.
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::conversions::*;
+
+/// The error returned by a conversion that didn't finish before its deadline.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("the conversion took longer than its timeout of {:?}", .timeout)]
+pub struct DeadlineExceededError {
+    /// The timeout given to the call that was stopped
+    pub timeout: Duration,
+}
+
+#[derive(Clone, Copy)]
+struct Deadline {
+    instant: Instant,
+    timeout: Duration,
+}
+
+thread_local! {
+    /// Deadline of the current call to [`as_rust_with_timeout`] or [`c_repr_of_with_timeout`],
+    /// `None` outside of such a call.
+    static DEADLINE: Cell<Option<Deadline>> = const { Cell::new(None) };
+}
+
+/// Restores the deadline of the enclosing call (if any) when a call returns (or panics).
+pub(crate) struct DeadlineGuard {
+    previous: Option<Deadline>,
+}
+
+impl Drop for DeadlineGuard {
+    fn drop(&mut self) {
+        DEADLINE.with(|deadline| deadline.set(self.previous));
+    }
+}
+
+pub(crate) fn enter_deadline(timeout: Duration) -> DeadlineGuard {
+    let previous = DEADLINE.with(Cell::get);
+    let deadline = Instant::now()
+        .checked_add(timeout)
+        .map(|instant| Deadline { instant, timeout })
+        .into_iter()
+        .chain(previous)
+        .min_by_key(|deadline| deadline.instant);
+    DEADLINE.with(|current| current.set(deadline));
+    DeadlineGuard { previous }
+}
+
+/// Converts `input` to its Rust representation, like [`AsRust::as_rust`], failing with
+/// [`AsRustError::DeadlineExceeded`] if the conversion takes longer than `timeout`.
+///
+/// A thread can't be interrupted : the deadline is checked by the implementations of [`AsRust`]
+/// and [`CReprOf`] calling [`check_deadline`] (this is the case of the arrays of this crate,
+/// between two elements), so that a pathological input (e.g. a huge array coming from a plugin)
+/// doesn't stall a real-time thread. The conversions made by other threads (such as
+/// [`par_as_rust_batch`](crate::par_as_rust_batch)) are not stopped.
+///
+/// When calls are nested, the inner conversion stops at the earliest of the two deadlines.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{as_rust_with_timeout, AsRustError, CArray, CReprOf, CStringArray};
+/// use std::time::Duration;
+///
+/// let names = vec!["Diavola".to_string(), "Margarita".to_string()];
+/// let c_names = CArray::<CStringArray>::c_repr_of(vec![names; 1000]).unwrap();
+///
+/// assert!(matches!(
+///     as_rust_with_timeout::<Vec<Vec<String>>, _>(&c_names, Duration::ZERO),
+///     Err(AsRustError::DeadlineExceeded(_))
+/// ));
+/// assert!(as_rust_with_timeout::<Vec<Vec<String>>, _>(&c_names, Duration::from_secs(60)).is_ok());
+/// ```
+pub fn as_rust_with_timeout<T, U: AsRust<T> + ?Sized>(
+    input: &U,
+    timeout: Duration,
+) -> Result<T, AsRustError> {
+    let _guard = enter_deadline(timeout);
+    input.as_rust()
+}
+
+/// Creates the C representation of `input`, like [`CReprOf::c_repr_of`], failing with
+/// [`CReprOfError::DeadlineExceeded`] if the conversion takes longer than `timeout` (see
+/// [`as_rust_with_timeout`]). What was already converted is released.
+pub fn c_repr_of_with_timeout<T: CReprOf<U>, U>(
+    input: U,
+    timeout: Duration,
+) -> Result<T, CReprOfError> {
+    let _guard = enter_deadline(timeout);
+    T::c_repr_of(input)
+}
+
+/// Fails with [`DeadlineExceededError`] if the deadline of the current call to
+/// [`as_rust_with_timeout`] or [`c_repr_of_with_timeout`] (or to a function given a
+/// [`ConversionContext`](crate::ConversionContext) with a timeout) is exceeded. Does nothing
+/// outside of such a call.
+///
+/// Implementations of [`AsRust`] and [`CReprOf`] should call this function regularly while
+/// converting inputs whose size is not bounded (such as arrays).
+pub fn check_deadline() -> Result<(), DeadlineExceededError> {
+    match DEADLINE.with(Cell::get) {
+        Some(deadline) if Instant::now() >= deadline.instant => Err(DeadlineExceededError {
+            timeout: deadline.timeout,
+        }),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,178 @@
+//! This module contains the [`CDynArray`] type, an array keeping the capacity of its allocation so
+//! that C code can append elements in place.
+
+use ffi_convert_derive::RawPointerConverter;
+
+use std::mem::ManuallyDrop;
+use std::ptr;
+
+use crate as ffi_convert;
+use crate::budget::reserve_memory;
+use crate::conversions::*;
+use crate::deadline::check_deadline;
+use crate::types::is_same_primitive;
+
+/// A utility type to represent a `Vec<T>` keeping its capacity : C code can append elements to the
+/// array in place, writing them after the `size` first ones and increasing `size` up to
+/// `capacity`, and Rust can take the buffer back as a `Vec` with [`CDynArray::into_vec`] without
+/// copying it.
+///
+/// Note that the parametrized type should have a C-compatible representation. Unlike a
+/// [`CArray`](crate::CArray), the array must not be reallocated by C code.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{CDynArray, CReprOf};
+///
+/// let mut samples = Vec::with_capacity(8);
+/// samples.extend([1, 2]);
+/// let mut array = CDynArray::<i16>::c_repr_of(samples).unwrap();
+/// assert_eq!(array.capacity, 8);
+///
+/// // what C code appending a sample does
+/// unsafe { array.data.add(array.size).write(3) };
+/// array.size += 1;
+///
+/// let samples = array.into_vec().unwrap();
+/// assert_eq!(samples, vec![1, 2, 3]);
+/// assert_eq!(samples.capacity(), 8);
+/// ```
+#[repr(C)]
+#[derive(Debug, RawPointerConverter)]
+pub struct CDynArray<T> {
+    /// Pointer to the first element of the array
+    pub data: *mut T,
+    /// Number of elements in the array
+    pub size: usize,
+    /// Number of elements that the allocation of the array can hold
+    pub capacity: usize,
+}
+
+impl<T> CDynArray<T> {
+    /// Returns a borrowed slice of the elements of the array, without copying them.
+    ///
+    /// An error is returned if the size of the array exceeds its capacity, or if the array is not
+    /// empty and its data pointer is `null` or not aligned for `T`.
+    pub fn as_slice(&self) -> Result<&[T], AsRustError> {
+        self.check()?;
+        if self.size == 0 {
+            Ok(&[])
+        } else {
+            Ok(unsafe { std::slice::from_raw_parts(self.data, self.size) })
+        }
+    }
+
+    /// Takes the elements back in a `Vec` reusing the allocation of the array, whose capacity is
+    /// kept. An error is returned in the same cases as [`CDynArray::as_slice`].
+    pub fn into_vec(self) -> Result<Vec<T>, AsRustError> {
+        self.check()?;
+        let array = ManuallyDrop::new(self);
+        if array.data.is_null() {
+            Ok(Vec::new())
+        } else {
+            Ok(unsafe { Vec::from_raw_parts(array.data, array.size, array.capacity) })
+        }
+    }
+
+    fn check(&self) -> Result<(), AsRustError> {
+        if self.size > self.capacity {
+            Err(AsRustError::IndexOutOfBounds {
+                index: self.size,
+                size: self.capacity,
+            })
+        } else if self.capacity == 0 {
+            Ok(())
+        } else if self.data.is_null() {
+            Err(UnexpectedNullPointerError.into())
+        } else if !self.data.is_aligned() {
+            Err(AsRustError::MisalignedPointer {
+                alignment: std::mem::align_of::<T>(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for CDynArray<T> {
+    /// Creates an array owning the given (already converted) elements and their allocation.
+    fn from(input: Vec<T>) -> Self {
+        if input.capacity() == 0 {
+            return Self {
+                data: ptr::null_mut(),
+                size: 0,
+                capacity: 0,
+            };
+        }
+        let mut input = ManuallyDrop::new(input);
+        Self {
+            data: input.as_mut_ptr(),
+            size: input.len(),
+            capacity: input.capacity(),
+        }
+    }
+}
+
+impl<U: AsRust<V> + 'static, V: 'static> AsRust<Vec<V>> for CDynArray<U> {
+    fn as_rust(&self) -> Result<Vec<V>, AsRustError> {
+        self.as_slice()?
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                check_deadline()?;
+                value.as_rust().map_err(|e| e.in_element(index))
+            })
+            .collect()
+    }
+}
+
+impl<T: CValidate> CValidate for CDynArray<T> {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        self.as_slice()?.iter().try_for_each(T::validate_c)
+    }
+}
+
+impl<U: CReprOf<V> + CDrop + 'static, V: 'static> CReprOf<Vec<V>> for CDynArray<U> {
+    fn c_repr_of(input: Vec<V>) -> Result<Self, CReprOfError> {
+        if is_same_primitive::<U, V>() {
+            // the allocation is reused, `U` and `V` being the same type
+            let mut input = ManuallyDrop::new(input);
+            return Ok(Self {
+                data: input.as_mut_ptr() as *mut U,
+                size: input.len(),
+                capacity: input.capacity(),
+            });
+        }
+        reserve_memory(input.capacity() * std::mem::size_of::<U>())?;
+        let mut output = Vec::with_capacity(input.capacity());
+        for (index, value) in input.into_iter().enumerate() {
+            check_deadline()?;
+            output.push(U::c_repr_of(value).map_err(|e| e.in_element(index))?);
+        }
+        Ok(Self::from(output))
+    }
+}
+
+impl<T> CDrop for CDynArray<T> {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        if self.size > self.capacity {
+            // the elements can't be dropped without reading out of the allocation
+            return Err(CDropError::Other(Box::new(self.check().unwrap_err())));
+        }
+        if !self.data.is_null() {
+            let _ = unsafe { Vec::from_raw_parts(self.data, self.size, self.capacity) };
+            // so that the elements are not freed twice if the array is dropped again
+            self.data = ptr::null_mut();
+            self.size = 0;
+            self.capacity = 0;
+        }
+        Ok(())
+    }
+}
+
+impl<T> Drop for CDynArray<T> {
+    fn drop(&mut self) {
+        let _ = self.do_drop();
+    }
+}
@@ -0,0 +1,575 @@
+//! This module contains the [`generate_error_handling`](crate::generate_error_handling),
+//! [`wrap`](crate::wrap) and [`catch_panic`](crate::catch_panic) macros, turning the `Result`s
+//! (and the panics) of the functions exported to C into result codes, the message of the last
+//! error being kept for each thread.
+
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::str::Utf8Error;
+
+use crate::conversions::*;
+use crate::error_store::set_last_error;
+use crate::export::CStatus;
+
+/// A C enum of result codes, returned by the functions using [`wrap!`](crate::wrap). It is
+/// implemented for [`CStatus`] and for the enums generated by
+/// [`generate_error_handling!`](crate::generate_error_handling).
+pub trait CResultCode: Sized {
+    /// The code returned on success
+    const OK: Self;
+    /// The code returned on failure, the reason of the failure being the last error
+    const KO: Self;
+
+    /// The code returned on failure for the given kind of error, [`Self::KO`] by default.
+    fn from_error_kind(_kind: ErrorKind) -> Self {
+        Self::KO
+    }
+}
+
+impl CResultCode for CStatus {
+    const OK: Self = CStatus::Ok;
+    const KO: Self = CStatus::Ko;
+}
+
+/// The category of an error, used to return a specific result code on failure, so that C callers
+/// can branch on it (see [`generate_error_handling!`](crate::generate_error_handling)).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// An unexpected null pointer
+    NullPointer,
+    /// A string that is not valid UTF-8
+    Utf8,
+    /// Any other error converting a value to or from its C representation
+    Conversion,
+    /// A panic, caught by [`ffi_guard`]
+    Panic,
+    /// An error of the user code, with a code of its own
+    User(u32),
+    /// An error without category
+    Other,
+}
+
+/// Maps an error type to an [`ErrorKind`]. It is implemented for the errors of this crate, and can
+/// be implemented for the errors of the user code, which are then converted to a [`CodedError`]
+/// to keep their kind (as the errors are type erased by [`wrap!`](crate::wrap)).
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{generate_error_handling, wrap, CodedError, ErrorCode, ErrorKind};
+///
+/// generate_error_handling!(
+///     ffi_get_last_error,
+///     ffi_destroy_error,
+///     FFI_RESULT {
+///         FFI_RESULT_OK,
+///         FFI_RESULT_KO,
+///         FFI_RESULT_NULL_POINTER = NullPointer,
+///         FFI_RESULT_NOT_FOUND = User(404),
+///     }
+/// );
+///
+/// #[derive(Debug, thiserror::Error)]
+/// #[error("no user with id {0}")]
+/// pub struct UnknownUser(u32);
+///
+/// impl ErrorCode for UnknownUser {
+///     fn error_kind(&self) -> ErrorKind {
+///         ErrorKind::User(404)
+///     }
+/// }
+///
+/// fn find_user(id: u32) -> Result<&'static str, CodedError> {
+///     match id {
+///         0 => Ok("root"),
+///         _ => Err(UnknownUser(id).into()),
+///     }
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn ffi_check_user(id: u32) -> FFI_RESULT {
+///     wrap!(find_user(id))
+/// }
+///
+/// assert_eq!(ffi_check_user(0), FFI_RESULT::FFI_RESULT_OK);
+/// assert_eq!(ffi_check_user(7), FFI_RESULT::FFI_RESULT_NOT_FOUND);
+/// assert_eq!(FFI_RESULT::FFI_RESULT_NOT_FOUND as i32, 3);
+/// assert_eq!(
+///     unsafe { ffi_get_last_error(std::ptr::null_mut()) },
+///     FFI_RESULT::FFI_RESULT_NULL_POINTER
+/// );
+/// ```
+pub trait ErrorCode {
+    /// The kind of the error.
+    fn error_kind(&self) -> ErrorKind;
+}
+
+impl ErrorCode for UnexpectedNullPointerError {
+    fn error_kind(&self) -> ErrorKind {
+        ErrorKind::NullPointer
+    }
+}
+
+impl ErrorCode for Utf8Error {
+    fn error_kind(&self) -> ErrorKind {
+        ErrorKind::Utf8
+    }
+}
+
+impl ErrorCode for AsRustError {
+    fn error_kind(&self) -> ErrorKind {
+        match self {
+            AsRustError::NullPointer(_) => ErrorKind::NullPointer,
+            AsRustError::Utf8Error(_) => ErrorKind::Utf8,
+            AsRustError::InElement { source, .. } => source.error_kind(),
+            _ => ErrorKind::Conversion,
+        }
+    }
+}
+
+impl ErrorCode for CReprOfError {
+    fn error_kind(&self) -> ErrorKind {
+        ErrorKind::Conversion
+    }
+}
+
+impl ErrorCode for CDropError {
+    fn error_kind(&self) -> ErrorKind {
+        match self {
+            CDropError::NullPointer(_) => ErrorKind::NullPointer,
+            CDropError::Other(_) => ErrorKind::Other,
+        }
+    }
+}
+
+/// An error with its [`ErrorKind`], created from the errors implementing [`ErrorCode`] with `?`
+/// or `into()`, or with [`CodedError::new`].
+///
+/// The errors boxed by error wrappers which don't expose the wrapped error (such as
+/// `anyhow::Error`) lose their kind : they must be converted to a `CodedError` before being
+/// wrapped.
+#[derive(Debug)]
+pub struct CodedError {
+    kind: ErrorKind,
+    error: Box<dyn Error + Send + Sync>,
+}
+
+impl CodedError {
+    /// Creates an error of the given kind.
+    pub fn new(kind: ErrorKind, error: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        Self {
+            kind,
+            error: error.into(),
+        }
+    }
+
+    /// The kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+// the wrapped error is not a source, the message and the sources are the ones of the wrapped error
+impl Error for CodedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+impl<E: ErrorCode + Error + Send + Sync + 'static> From<E> for CodedError {
+    fn from(error: E) -> Self {
+        Self::new(error.error_kind(), error)
+    }
+}
+
+/// Finds the kind of an error : the kind of the first error of its chain (the error followed by
+/// its sources) that is a [`CodedError`] or an error of this crate, [`ErrorKind::Other`] if there
+/// is none.
+pub fn error_kind(error: &(dyn Error + 'static)) -> ErrorKind {
+    let mut current = Some(error);
+    while let Some(error) = current {
+        let kind = if let Some(error) = error.downcast_ref::<CodedError>() {
+            Some(error.kind())
+        } else if let Some(error) = error.downcast_ref::<AsRustError>() {
+            Some(error.error_kind())
+        } else if let Some(error) = error.downcast_ref::<CReprOfError>() {
+            Some(error.error_kind())
+        } else if let Some(error) = error.downcast_ref::<CDropError>() {
+            Some(error.error_kind())
+        } else if let Some(error) = error.downcast_ref::<UnexpectedNullPointerError>() {
+            Some(error.error_kind())
+        } else {
+            error.downcast_ref::<Utf8Error>().map(ErrorCode::error_kind)
+        };
+        if let Some(kind) = kind {
+            return kind;
+        }
+        current = error.source();
+    }
+    ErrorKind::Other
+}
+
+/// Formats an error with the chain of its sources, one per line.
+pub fn error_message(error: &(dyn Error + 'static)) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(&format!("\nCaused by: {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+/// Converts a result to a result code, recording the error as the last error. Used by
+/// [`wrap!`](crate::wrap).
+#[doc(hidden)]
+pub fn wrap_result<T, E, R>(result: Result<T, E>) -> R
+where
+    E: Into<Box<dyn Error>>,
+    R: CResultCode,
+{
+    match result {
+        Ok(_) => R::OK,
+        Err(error) => {
+            let error = error.into();
+            set_last_error(error_message(&*error));
+            R::from_error_kind(error_kind(&*error))
+        }
+    }
+}
+
+/// Runs the body of a function exported to C, and converts its result to a result code as
+/// [`wrap!`](crate::wrap) does. A panic (which can't unwind into C code) is caught and converted
+/// to the failure code, its message prefixed with `panic: ` being kept as the last error.
+///
+/// The [`catch_panic!`](crate::catch_panic) macro calls this function with its body, and the
+/// functions generated by the `#[ffi_export]` attribute run their conversions and call the
+/// exported function in it.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::error_store::take_last_error;
+/// use ffi_convert::{ffi_guard, CStatus};
+///
+/// #[no_mangle]
+/// pub extern "C" fn ffi_divide(dividend: i32, divisor: i32, quotient: &mut i32) -> CStatus {
+///     ffi_guard(|| -> Result<(), String> {
+///         *quotient = dividend / divisor;
+///         Ok(())
+///     })
+/// }
+///
+/// let mut quotient = 0;
+/// assert_eq!(ffi_divide(7, 2, &mut quotient), CStatus::Ok);
+/// assert_eq!(quotient, 3);
+///
+/// assert_eq!(ffi_divide(7, 0, &mut quotient), CStatus::Ko);
+/// assert_eq!(take_last_error().unwrap(), "panic: attempt to divide by zero");
+/// ```
+pub fn ffi_guard<T, E, R, F>(body: F) -> R
+where
+    F: FnOnce() -> Result<T, E>,
+    E: Into<Box<dyn Error>>,
+    R: CResultCode,
+{
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(result) => wrap_result(result),
+        Err(panic) => {
+            set_last_error(format!("panic: {}", panic_message(&*panic)));
+            R::from_error_kind(ErrorKind::Panic)
+        }
+    }
+}
+
+/// Writes the C representation of `input` in the struct pointed by `dst`, allocated by the caller
+/// (e.g. on its stack) instead of being returned behind a new pointer, and returns the result code
+/// of the conversion as [`ffi_guard`] does. Nothing is written if the conversion fails.
+///
+/// The `CReprOf` custom derive generates a `c_repr_of_at` method calling this function.
+///
+/// # Safety
+///
+/// `dst` must be null (which fails with [`ErrorKind::NullPointer`]) or valid for writes and
+/// aligned. The struct it points to is overwritten without being dropped : the caller takes
+/// ownership of the converted value and must release it (e.g. with `do_drop`).
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{c_repr_of_at, CDrop, CStatus, CStringArray};
+/// use std::mem::MaybeUninit;
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn ffi_list_rooms(rooms: *mut CStringArray) -> CStatus {
+///     c_repr_of_at(rooms, vec!["Kitchen".to_string(), "Office".to_string()])
+/// }
+///
+/// // what C code would do with a struct on its stack
+/// let mut rooms = MaybeUninit::<CStringArray>::uninit();
+/// assert_eq!(unsafe { ffi_list_rooms(rooms.as_mut_ptr()) }, CStatus::Ok);
+/// let mut rooms = unsafe { rooms.assume_init() };
+/// assert_eq!(rooms.get(1).unwrap().to_str().unwrap(), "Office");
+/// rooms.do_drop().unwrap();
+///
+/// assert_eq!(unsafe { ffi_list_rooms(std::ptr::null_mut()) }, CStatus::Ko);
+/// ```
+pub unsafe fn c_repr_of_at<T, U, R>(dst: *mut T, input: U) -> R
+where
+    T: CReprOf<U>,
+    R: CResultCode,
+{
+    ffi_guard(|| -> Result<(), Box<dyn Error>> {
+        if dst.is_null() {
+            return Err(UnexpectedNullPointerError.into());
+        }
+        let value = T::c_repr_of(input)?;
+        std::ptr::write(dst, value);
+        Ok(())
+    })
+}
+
+/// The message given to `panic!`, if any.
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Converts a `Result` whose error can be boxed in a `Box<dyn Error>` (any error type, `String`,
+/// `&str`...) to the result code returned by the enclosing function : a [`CStatus`] or an enum
+/// generated by [`generate_error_handling!`](crate::generate_error_handling). The message of the
+/// error (followed by its sources) is kept as the last error of the current thread.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::error_store::take_last_error;
+/// use ffi_convert::{wrap, CStatus};
+///
+/// #[no_mangle]
+/// pub extern "C" fn ffi_parse_volume(input: u32, volume: &mut u8) -> CStatus {
+///     wrap!(u8::try_from(input).map(|input| *volume = input))
+/// }
+///
+/// let mut volume = 0;
+/// assert_eq!(ffi_parse_volume(42, &mut volume), CStatus::Ok);
+/// assert_eq!(volume, 42);
+/// assert_eq!(ffi_parse_volume(420, &mut volume), CStatus::Ko);
+/// assert!(take_last_error().unwrap().contains("out of range"));
+/// ```
+#[macro_export]
+macro_rules! wrap {
+    ($result:expr) => {
+        $crate::wrap_result($result)
+    };
+}
+
+/// Runs the given statements, returning a `Result`, with [`ffi_guard`] : a panic is caught and
+/// converted to the failure code of the enclosing function instead of unwinding into C code.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::error_store::take_last_error;
+/// use ffi_convert::{catch_panic, CStatus};
+///
+/// #[no_mangle]
+/// pub extern "C" fn ffi_check_index(index: usize) -> CStatus {
+///     catch_panic! {
+///         let thresholds = [10, 20, 30];
+///         if thresholds[index] > 15 {
+///             return Err("the threshold is too high");
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// assert_eq!(ffi_check_index(0), CStatus::Ok);
+/// assert_eq!(ffi_check_index(1), CStatus::Ko);
+/// assert_eq!(take_last_error().unwrap(), "the threshold is too high");
+/// assert_eq!(ffi_check_index(3), CStatus::Ko);
+/// assert!(take_last_error().unwrap().starts_with("panic: index out of bounds"));
+/// ```
+#[macro_export]
+macro_rules! catch_panic {
+    ($($body:tt)*) => {
+        $crate::ffi_guard(|| { $($body)* })
+    };
+}
+
+/// Generates the `extern "C"` functions giving the last error of the current thread to C code :
+/// - `$get_last_error(error: *mut *const c_char) -> R` writes the message of the last error (or
+///   an empty string if there is none) to `error`, and clears it
+/// - `$destroy_error(error: *const c_char) -> R` frees the message
+///
+/// `R` is [`CStatus`], or an enum with the given name and variants (whose values are `0` and `1`)
+/// generated by the macro, which can then be returned by the functions using
+/// [`wrap!`](crate::wrap). The enum can have more failure codes (numbered from `2` in their order)
+/// returned for the errors of a given [`ErrorKind`] (see [`ErrorCode`]), the other errors being
+/// returned as the second variant :
+///
+/// ```
+/// # use ffi_convert::generate_error_handling;
+/// generate_error_handling!(
+///     ffi_get_last_error,
+///     ffi_destroy_error,
+///     FFI_RESULT {
+///         FFI_RESULT_OK,
+///         FFI_RESULT_KO,
+///         FFI_RESULT_NULL_POINTER = NullPointer,
+///         FFI_RESULT_UTF8_ERROR = Utf8,
+///         FFI_RESULT_CONVERSION_ERROR = Conversion,
+///         FFI_RESULT_PANIC = Panic,
+///         FFI_RESULT_TIMEOUT = User(1),
+///     }
+/// );
+/// ```
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{generate_error_handling, wrap};
+/// use std::ffi::CStr;
+///
+/// generate_error_handling!(
+///     ffi_get_last_error,
+///     ffi_destroy_error,
+///     FFI_RESULT { FFI_RESULT_OK, FFI_RESULT_KO }
+/// );
+///
+/// #[no_mangle]
+/// pub extern "C" fn ffi_check_volume(volume: u8) -> FFI_RESULT {
+///     wrap!(if volume <= 100 { Ok(()) } else { Err(format!("volume {} is too loud", volume)) })
+/// }
+///
+/// assert_eq!(ffi_check_volume(11), FFI_RESULT::FFI_RESULT_OK);
+/// assert_eq!(ffi_check_volume(111), FFI_RESULT::FFI_RESULT_KO);
+///
+/// let mut error = std::ptr::null();
+/// assert_eq!(unsafe { ffi_get_last_error(&mut error) }, FFI_RESULT::FFI_RESULT_OK);
+/// assert_eq!(unsafe { CStr::from_ptr(error) }.to_str().unwrap(), "volume 111 is too loud");
+/// assert_eq!(unsafe { ffi_destroy_error(error) }, FFI_RESULT::FFI_RESULT_OK);
+/// ```
+#[macro_export]
+macro_rules! generate_error_handling {
+    ($get_last_error:ident, $destroy_error:ident) => {
+        $crate::generate_error_handling!(
+            @functions $get_last_error, $destroy_error, $crate::CStatus, "CStatus",
+            [(0, "success"), (1, "failure, see the last error")]
+        );
+    };
+    (
+        $get_last_error:ident,
+        $destroy_error:ident,
+        $result:ident {
+            $ok:ident,
+            $ko:ident
+            $(, $variant:ident = $kind:ident $(($code:literal))?)*
+            $(,)?
+        }
+    ) => {
+        /// The result code of the functions exported to C, the reason of a failure being given by
+        #[doc = concat!("`", stringify!($get_last_error), "`.")]
+        #[repr(C)]
+        #[allow(non_camel_case_types)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum $result {
+            $ok = 0,
+            $ko = 1,
+            $($variant,)*
+        }
+
+        impl $crate::CResultCode for $result {
+            const OK: Self = $result::$ok;
+            const KO: Self = $result::$ko;
+
+            fn from_error_kind(kind: $crate::ErrorKind) -> Self {
+                match kind {
+                    $($crate::ErrorKind::$kind $(($code))? => $result::$variant,)*
+                    #[allow(unreachable_patterns)]
+                    _ => $result::$ko,
+                }
+            }
+        }
+
+        $crate::generate_error_handling!(
+            @functions $get_last_error, $destroy_error, $result, stringify!($result),
+            [
+                ($result::$ok as i64, stringify!($ok)),
+                ($result::$ko as i64, stringify!($ko))
+                $(, ($result::$variant as i64, stringify!($variant)))*
+            ]
+        );
+    };
+    (
+        @functions $get_last_error:ident, $destroy_error:ident, $result:ty, $result_name:expr,
+        [$(($code:expr, $meaning:expr)),*]
+    ) => {
+        /// Writes the message of the last error of the current thread (or an empty string if there
+        #[doc = concat!("is none) to `error`, to be freed with `", stringify!($destroy_error), "`.")]
+        ///
+        /// # Safety
+        ///
+        /// `error` must be valid for writes.
+        #[no_mangle]
+        pub unsafe extern "C" fn $get_last_error(
+            error: *mut *const std::os::raw::c_char,
+        ) -> $result {
+            $crate::wrap!($crate::error_store::last_error_to_c(error))
+        }
+
+        /// Frees an error message.
+        ///
+        /// # Safety
+        ///
+        #[doc = concat!("`error` must have been written by `", stringify!($get_last_error), "`.")]
+        #[no_mangle]
+        pub unsafe extern "C" fn $destroy_error(error: *const std::os::raw::c_char) -> $result {
+            $crate::wrap!($crate::error_store::destroy_c_error(error))
+        }
+
+        $crate::register_signature!($crate::CFunctionSignature {
+            name: stringify!($get_last_error),
+            doc: concat!(
+                "Writes the message of the last error of the current thread (or an empty string if \
+                there is none) to `error`, to be freed with `",
+                stringify!($destroy_error),
+                "`."
+            ),
+            parameters: &[$crate::CParameter {
+                name: "error",
+                ty: "*mut *const std::os::raw::c_char",
+                direction: $crate::Direction::Out,
+                ownership: $crate::ParameterOwnership::OwnedByCaller,
+            }],
+            return_type: $result_name,
+            return_ownership: $crate::ParameterOwnership::Value,
+            return_codes: &[$($crate::CReturnCode { value: $code, meaning: $meaning }),*],
+        });
+
+        $crate::register_signature!($crate::CFunctionSignature {
+            name: stringify!($destroy_error),
+            doc: "Frees an error message.",
+            parameters: &[$crate::CParameter {
+                name: "error",
+                ty: "*const std::os::raw::c_char",
+                direction: $crate::Direction::In,
+                ownership: $crate::ParameterOwnership::Released,
+            }],
+            return_type: $result_name,
+            return_ownership: $crate::ParameterOwnership::Value,
+            return_codes: &[$($crate::CReturnCode { value: $code, meaning: $meaning }),*],
+        });
+    };
+}
@@ -0,0 +1,77 @@
+//! The message of the last error of each thread, set by the functions exported to C when they fail
+//! (by [`wrap!`](crate::wrap), [`ffi_guard`](crate::ffi_guard) and the functions generated by the
+//! `#[ffi_export]` attribute) and read by C code.
+//!
+//! The message is stored by this crate, not by the crates using it : the binding crates linked
+//! into a single library (and depending on the same version of this crate) share the last error
+//! of a thread, so that it can be read with any of their functions.
+//!
+//! # Example
+//!
+//! ```
+//! use ffi_convert::error_store::{destroy_c_error, last_error_to_c, set_last_error};
+//! use std::ffi::CStr;
+//!
+//! set_last_error("the device is not ready");
+//!
+//! // what the function giving the last error to C code does
+//! let mut error = std::ptr::null();
+//! unsafe { last_error_to_c(&mut error) }.unwrap();
+//! assert_eq!(unsafe { CStr::from_ptr(error) }.to_str().unwrap(), "the device is not ready");
+//! unsafe { destroy_c_error(error) }.unwrap();
+//! ```
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::fmt::Display;
+
+use crate::conversions::*;
+
+thread_local! {
+    /// The message of the last error that occurred in the current thread.
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Records the message of an error that occurred in the current thread, replacing the previous
+/// one, to be retrieved with [`take_last_error`] or [`last_error_to_c`].
+pub fn set_last_error(error: impl Display) {
+    let message = error.to_string();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// Takes the message of the last error that occurred in the current thread, if any.
+pub fn take_last_error() -> Option<String> {
+    LAST_ERROR.with(|last_error| last_error.borrow_mut().take())
+}
+
+/// Takes the message of the last error of the current thread (or an empty string if there is
+/// none), and writes it to `error` as a C string to be freed with [`destroy_c_error`]. The nul
+/// characters of the message are replaced with `U+FFFD`.
+///
+/// # Safety
+///
+/// `error` must be `null` or valid for writes.
+pub unsafe fn last_error_to_c(
+    error: *mut *const libc::c_char,
+) -> Result<(), UnexpectedNullPointerError> {
+    if error.is_null() {
+        return Err(UnexpectedNullPointerError);
+    }
+    let message = take_last_error()
+        .unwrap_or_default()
+        .replace('\0', "\u{FFFD}");
+    let message = CString::new(message).expect("the nul characters have been replaced");
+    *error = RawPointerConverter::<libc::c_char>::into_raw_pointer(message);
+    Ok(())
+}
+
+/// Frees a message written by [`last_error_to_c`].
+///
+/// # Safety
+///
+/// `error` must be `null` or have been written by [`last_error_to_c`] and not freed yet.
+pub unsafe fn destroy_c_error(
+    error: *const libc::c_char,
+) -> Result<(), UnexpectedNullPointerError> {
+    <CString as RawPointerConverter<libc::c_char>>::drop_raw_pointer(error)
+}
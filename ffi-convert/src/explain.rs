@@ -0,0 +1,145 @@
+//! This module contains the [`explain_as_rust`] function, a debugging helper recording how a C
+//! value is converted, field by field.
+
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::mem;
+
+use crate::conversions::*;
+
+/// Trace of the conversion in progress.
+struct Trace {
+    lines: Vec<String>,
+    depth: usize,
+}
+
+thread_local! {
+    /// Trace of the current call to [`explain_as_rust`], `None` outside of such a call.
+    static TRACE: RefCell<Option<Trace>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous trace when a call to [`explain_as_rust`] returns (or panics).
+struct TraceGuard {
+    previous: Option<Trace>,
+}
+
+impl Drop for TraceGuard {
+    fn drop(&mut self) {
+        TRACE.with(|trace| *trace.borrow_mut() = self.previous.take());
+    }
+}
+
+/// Converts `input` to its Rust representation, like [`AsRust::as_rust`], while recording a human
+/// readable trace of the conversion. This is meant to help finding which field contains invalid
+/// data, for instance when C code fills a struct incorrectly.
+///
+/// The trace contains a line per converted field of the structs deriving `AsRust` (with its type,
+/// size and address, and the value of the pointer for pointer fields), indented according to the
+/// nesting of the structs. The fields whose conversion failed are followed by the error.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{explain_as_rust, AsRust, CDrop, CReprOf};
+///
+/// pub struct Sauce {
+///     pub name: String,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(Sauce)]
+/// pub struct CSauce {
+///     pub name: *const libc::c_char,
+/// }
+///
+/// let c_sauce = CSauce { name: std::ptr::null() };
+/// let (sauce, trace): (Result<Sauce, _>, _) = explain_as_rust(&c_sauce);
+/// assert!(sauce.is_err());
+/// assert!(trace.contains("name: *const"));
+/// assert!(trace.contains("=> error: unexpected null pointer"));
+/// # std::mem::forget(c_sauce);
+/// ```
+pub fn explain_as_rust<T, U: AsRust<T> + ?Sized>(input: &U) -> (Result<T, AsRustError>, String) {
+    let previous = TRACE.with(|trace| {
+        trace.borrow_mut().replace(Trace {
+            lines: vec![format!(
+                "{} ({} bytes at {:p})",
+                std::any::type_name::<U>(),
+                mem::size_of_val(input),
+                input as *const U as *const ()
+            )],
+            depth: 1,
+        })
+    });
+    let guard = TraceGuard { previous };
+
+    let result = input.as_rust();
+
+    let trace = TRACE.with(|trace| trace.borrow_mut().take());
+    drop(guard);
+
+    let mut output = String::new();
+    for line in trace.map(|trace| trace.lines).unwrap_or_default() {
+        let _ = writeln!(output, "{}", line);
+    }
+    if let Err(error) = &result {
+        let _ = writeln!(output, "error: {}", error);
+    }
+    (result, output)
+}
+
+/// Converts a field with `convert`, recording it in the trace of the current call to
+/// [`explain_as_rust`] if any. `pointer` is the value of the field for pointer fields.
+///
+/// This function is used by the code generated by the `AsRust` derive.
+#[doc(hidden)]
+#[inline]
+pub fn explain_field<F, R>(
+    name: &str,
+    field: &F,
+    pointer: Option<*const ()>,
+    convert: impl FnOnce() -> Result<R, AsRustError>,
+) -> Result<R, AsRustError> {
+    let line_index = TRACE.with(|trace| {
+        trace.borrow_mut().as_mut().map(|trace| {
+            let mut line = format!(
+                "{:indent$}{}: {} ({} bytes at {:p})",
+                "",
+                name,
+                std::any::type_name::<F>(),
+                mem::size_of::<F>(),
+                field as *const F,
+                indent = 2 * trace.depth
+            );
+            match pointer {
+                Some(pointer) if pointer.is_null() => line.push_str(" -> null"),
+                Some(pointer) => {
+                    let _ = write!(line, " -> {:p}", pointer);
+                }
+                None => {}
+            }
+            trace.lines.push(line);
+            trace.depth += 1;
+            trace.lines.len() - 1
+        })
+    });
+
+    let line_index = match line_index {
+        Some(line_index) => line_index,
+        // not explaining a conversion
+        None => return convert(),
+    };
+
+    let result = convert();
+
+    TRACE.with(|trace| {
+        if let Some(trace) = trace.borrow_mut().as_mut() {
+            trace.depth -= 1;
+            if let Err(error) = &result {
+                let _ = write!(trace.lines[line_index], " => error: {}", error);
+            }
+        }
+    });
+    result
+}
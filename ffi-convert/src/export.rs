@@ -0,0 +1,40 @@
+//! This module contains the [`CStatus`] type returned by the functions generated with the
+//! `#[ffi_export]` attribute.
+
+/// The status returned by the functions exported to C with the `#[ffi_export]` attribute. When a
+/// function fails, the reason of the failure can be retrieved with
+/// [`take_last_error`](crate::error_store::take_last_error).
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::error_store::take_last_error;
+/// use ffi_convert::{ffi_export, CStatus};
+/// use std::ffi::CStr;
+///
+/// #[ffi_export]
+/// pub fn greet(name: &str, count: u32) -> Result<String, String> {
+///     if name.is_empty() {
+///         return Err("the name can't be empty".to_string());
+///     }
+///     Ok(format!("Hello {}! ", name).repeat(count as usize))
+/// }
+///
+/// let mut greeting = std::ptr::null();
+/// let status = unsafe { ffi_greet(c"Ada".as_ptr(), 2, &mut greeting) };
+/// assert_eq!(status, CStatus::Ok);
+/// assert_eq!(unsafe { CStr::from_ptr(greeting) }.to_str().unwrap(), "Hello Ada! Hello Ada! ");
+/// # unsafe { drop(std::ffi::CString::from_raw(greeting as *mut _)) };
+///
+/// let status = unsafe { ffi_greet(c"".as_ptr(), 2, &mut greeting) };
+/// assert_eq!(status, CStatus::Ko);
+/// assert_eq!(take_last_error().unwrap(), "the name can't be empty");
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CStatus {
+    /// The function succeeded
+    Ok = 0,
+    /// The function failed
+    Ko = 1,
+}
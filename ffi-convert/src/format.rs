@@ -0,0 +1,76 @@
+//! This module contains the [`format_to_c`] function and macro, used to create formatted C
+//! strings (e.g. error or log messages) in one step.
+
+use std::ffi::CString;
+use std::fmt::{self, Write};
+
+use crate::conversions::RawPointerConverter;
+
+/// Buffer of a C string being formatted, nul characters are replaced while writing to it.
+struct CStringBuffer(Vec<u8>);
+
+impl Write for CStringBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut parts = s.split('\0');
+        if let Some(first) = parts.next() {
+            self.0.extend_from_slice(first.as_bytes());
+        }
+        for part in parts {
+            self.0.extend_from_slice("\u{FFFD}".as_bytes());
+            self.0.extend_from_slice(part.as_bytes());
+        }
+        Ok(())
+    }
+}
+
+/// Formats the given arguments into a newly allocated C string, and returns a raw pointer to it.
+///
+/// Contrary to `CString::new(format!(...))`, this never fails : a nul character in the formatted
+/// string (which can't be represented in a C string) is replaced with `U+FFFD`.
+///
+/// The returned string is owned by the caller and can be freed with
+/// `CString::drop_raw_pointer`. See also the [`format_to_c!`](crate::format_to_c!) macro.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{format_to_c, RawPointerConverter};
+/// use std::ffi::{CStr, CString};
+///
+/// let message = format_to_c(format_args!("{} pancakes left", 3));
+/// assert_eq!(unsafe { CStr::from_ptr(message) }.to_str().unwrap(), "3 pancakes left");
+///
+/// unsafe { CString::drop_raw_pointer(message) }.unwrap();
+/// ```
+pub fn format_to_c(args: fmt::Arguments) -> *mut libc::c_char {
+    let mut buffer = CStringBuffer(Vec::new());
+    // writing to the buffer never fails, and Display implementations should not fail either
+    let _ = buffer.write_fmt(args);
+    // SAFETY: nul characters are replaced while writing to the buffer
+    unsafe { CString::from_vec_unchecked(buffer.0) }.into_raw_pointer_mut()
+}
+
+/// Formats the given arguments into a newly allocated C string, like `format!`, and returns a raw
+/// pointer to it. See [`format_to_c`](fn@crate::format_to_c).
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{format_to_c, RawPointerConverter};
+/// use std::ffi::{CStr, CString};
+///
+/// let error = "out of batter";
+/// let message = format_to_c!("Could not make pancakes: {}", error);
+/// assert_eq!(
+///     unsafe { CStr::from_ptr(message) }.to_str().unwrap(),
+///     "Could not make pancakes: out of batter"
+/// );
+///
+/// unsafe { CString::drop_raw_pointer(message) }.unwrap();
+/// ```
+#[macro_export]
+macro_rules! format_to_c {
+    ($($arg:tt)*) => {
+        $crate::format_to_c(format_args!($($arg)*))
+    };
+}
@@ -0,0 +1,164 @@
+//! The canonical C declarations of the types of this crate, to be included by the C (or Go, Swift...)
+//! projects as a stable `ffi_convert.h` header, instead of relying on header generators to
+//! reconstruct them from the Rust definitions.
+//!
+//! The types that are generic on the Rust side (such as [`CArray`](crate::CArray)) are declared
+//! for each element type with the macros of the header, e.g.
+//! `FFI_CONVERT_ARRAY(CToppingArray, CTopping)`.
+//!
+//! # Example
+//!
+//! The header is usually written by the build script of the crate exporting the functions :
+//!
+//! ```no_run
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! ffi_convert::header::write_header(std::path::Path::new(&out_dir).join("ffi_convert.h")).unwrap();
+//! ```
+
+use std::io;
+use std::path::Path;
+
+/// The content of the `ffi_convert.h` header, declaring the types of this crate.
+pub const HEADER: &str = r#"/* The C declarations of the types of the ffi-convert crate */
+
+#ifndef FFI_CONVERT_H
+#define FFI_CONVERT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/** The status returned by the functions exported with `#[ffi_export]` */
+typedef enum CStatus {
+  CStatus_Ok = 0,
+  CStatus_Ko = 1,
+} CStatus;
+
+/** An array of strings */
+typedef struct CStringArray {
+  /** Pointer to the first element of the array */
+  const char *const *data;
+  /** Number of elements in the array */
+  size_t size;
+} CStringArray;
+
+/** An array of strings whose size is an `int` */
+typedef struct CStringArrayInt {
+  /** Pointer to the first element of the array */
+  const char *const *data;
+  /** Number of elements in the array */
+  int size;
+} CStringArrayInt;
+
+/** A duration */
+typedef struct CDuration {
+  /** Number of whole seconds */
+  uint64_t secs;
+  /** Fractional part of the duration in nanoseconds, lower than 1 000 000 000 */
+  uint32_t nanos;
+} CDuration;
+
+/** A point in time */
+typedef struct CTimestamp {
+  /** Number of whole seconds since the UNIX epoch */
+  int64_t secs;
+  /** Number of nanoseconds to add to `secs`, lower than 1 000 000 000 */
+  uint32_t nanos;
+} CTimestamp;
+
+/** Value of `CIpAddr.family` for an IPv4 address */
+#define CIPADDR_FAMILY_V4 4
+/** Value of `CIpAddr.family` for an IPv6 address */
+#define CIPADDR_FAMILY_V6 6
+
+/** An IPv4 or IPv6 address */
+typedef struct CIpAddr {
+  /** Address family, either `CIPADDR_FAMILY_V4` or `CIPADDR_FAMILY_V6` */
+  uint8_t family;
+  /** Bytes of the address in network order */
+  uint8_t addr[16];
+} CIpAddr;
+
+/** A socket address */
+typedef struct CSocketAddr {
+  /** IP address */
+  CIpAddr ip;
+  /** Port number, in native byte order */
+  uint16_t port;
+  /** IPv6 flow information */
+  uint32_t flowinfo;
+  /** IPv6 scope identifier */
+  uint32_t scope_id;
+} CSocketAddr;
+
+/** A UUID */
+typedef struct CUuid {
+  uint8_t bytes[16];
+} CUuid;
+
+/** Declares `name`, the `CArray` of the given element type */
+#define FFI_CONVERT_ARRAY(name, type) \
+  typedef struct name { \
+    /** Pointer to the first element of the array */ \
+    const type *data_ptr; \
+    /** Number of elements in the array */ \
+    size_t size; \
+  } name
+
+/** Declares `name`, the `CRange` of the given integer type */
+#define FFI_CONVERT_RANGE(name, type) \
+  typedef struct name { \
+    type start; \
+    type end; \
+  } name
+
+/** Declares `name`, the `CSpan` of the given integer type */
+#define FFI_CONVERT_SPAN(name, type) \
+  typedef struct name { \
+    type start; \
+    type length; \
+  } name
+
+/** Declares `name`, the `COption` of the given type */
+#define FFI_CONVERT_OPTION(name, type) \
+  typedef struct name { \
+    /** `1` if `value` holds a value, `0` otherwise */ \
+    uint8_t is_some; \
+    /** The value, only meaningful if `is_some` is not `0` */ \
+    type value; \
+  } name
+
+/** Declares `name`, the `CResult` of the given value and error types */
+#define FFI_CONVERT_RESULT(name, ok_type, err_type) \
+  typedef struct name { \
+    /** Pointer to the value in case of success, `NULL` otherwise */ \
+    const ok_type *ok; \
+    /** Pointer to the error in case of failure, `NULL` otherwise */ \
+    const err_type *err; \
+  } name
+
+/** Declares `name`, the `CFixedString` of `size` characters */
+#define FFI_CONVERT_FIXED_STRING(name, size) \
+  typedef struct name { \
+    char chars[size]; \
+  } name
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FFI_CONVERT_H */
+"#;
+
+/// Writes [`HEADER`] to the given path, unless the file already has this content (so that the
+/// projects depending on it are not rebuilt needlessly).
+pub fn write_header(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    match std::fs::read_to_string(path) {
+        Ok(content) if content == HEADER => Ok(()),
+        _ => std::fs::write(path, HEADER),
+    }
+}
@@ -59,6 +59,7 @@
 //! # use ffi_convert::CArray;
 //! # use ffi_convert::RawBorrow;
 //! # struct Topping {};
+//! # #[repr(C)]
 //! # #[derive(CReprOf, AsRust, CDrop)]
 //! # #[target_type(Topping)]
 //! # struct CTopping {};
@@ -72,6 +73,7 @@
 //! use libc::{c_char, c_float};
 //!
 //! struct Sauce {};
+//! #[repr(C)]
 //! #[derive(CReprOf, AsRust, CDrop, RawPointerConverter)]
 //! #[target_type(Sauce)]
 //! struct CSauce {};
@@ -99,6 +101,76 @@
 //!
 //! You can now pass the `CPizza` struct through your FFI boundary !
 //!
+//! The `CReprOf` and `AsRust` derives check that the struct is `#[repr(C)]` (or
+//! `#[repr(transparent)]`), as a struct with the Rust layout can't be read by C code. The
+//! `#[allow_non_repr_c]` attribute disables this check for the structs that are not passed to C.
+//!
+//! The traits and the common types can be imported at once with `use ffi_convert::prelude::*;`.
+//! The code generated by the derives doesn't rely on the user's imports.
+//!
+//! ## Several target types
+//!
+//! A C struct can be converted from/to several Rust types, given by repeated `#[target_type(...)]`
+//! attributes or by `#[target_types(A, B)]` : the `CReprOf` and `AsRust` derives implement their
+//! trait for each of them. A field named differently in one of the targets is renamed for this
+//! target only with `#[target_name(name, for = Type)]`. The other derives use the first target type.
+//!
+//! ```
+//! use ffi_convert::{AsRust, CDrop, CReprOf};
+//!
+//! pub struct OrderRequest {
+//!     pub pizza: String,
+//!     pub quantity: u32,
+//! }
+//!
+//! pub struct StoredOrder {
+//!     pub pizza_name: String,
+//!     pub quantity: u32,
+//! }
+//!
+//! #[repr(C)]
+//! #[derive(CReprOf, AsRust, CDrop)]
+//! #[target_types(OrderRequest, StoredOrder)]
+//! pub struct COrder {
+//!     #[target_name(pizza_name, for = StoredOrder)]
+//!     pub pizza: *const libc::c_char,
+//!     pub quantity: u32,
+//! }
+//!
+//! let order = COrder::c_repr_of(OrderRequest { pizza: "Regina".into(), quantity: 2 }).unwrap();
+//! let stored: StoredOrder = order.as_rust().unwrap();
+//! assert_eq!(stored.pizza_name, "Regina");
+//! ```
+//!
+//! ## Versioned structs
+//!
+//! The layout of a C struct can be versioned with the `#[abi_version(N)]` attribute, the struct
+//! then starting with a `version: u32` field : its C representations are created with the version
+//! `N`, and the conversions to Rust reject the structs of another version, instead of reading
+//! fields that may not be there. See [`CVersioned`] and [`check_version`].
+//!
+//! The `#[expected_size(N)]` and `#[expected_align(N)]` attributes of the `CDrop` derive check at
+//! compile time the size and the alignment of the struct, catching the changes of its layout (a
+//! reordered field, a type of another width...) that would silently break the C ABI :
+//!
+//! ```
+//! # use ffi_convert::CDrop;
+//! #[repr(C)]
+//! #[derive(CDrop)]
+//! #[expected_size(16)]
+//! #[expected_align(8)]
+//! pub struct CRange {
+//!     pub start: u32,
+//!     pub end: u64,
+//! }
+//! ```
+//!
+//! ## C headers
+//!
+//! The [`header`] module contains `ffi_convert.h`, the C declarations of the types of this crate
+//! (with macros declaring the generic ones for an element type), to be included by the C projects.
+//! Header generators such as cbindgen can't see the types of this crate : [`export_c_types`] and
+//! [`declare_c_array`] define named copies of them in the crate exporting the functions.
 
 //! ## Types representations mapping
 //!
@@ -118,6 +190,11 @@
 //!             <td><code>*const libc::c_char</code></td>
 //!         </tr>
 //!         <tr>
+//!             <td><code>const char*</code></td>
+//!             <td><code>PathBuf</code>, <code>OsString</code></td>
+//!             <td><code>*const libc::c_char</code> (raw bytes on Unix, UTF-8 elsewhere)</td>
+//!         </tr>
+//!         <tr>
 //!             <td><code>const T*</code></td>
 //!             <td><code>U</code></td>
 //!             <td><code>*const T</code></td>
@@ -138,20 +215,170 @@
 //!             <td><code>*const T</code> (with <code>#[nullable]</code> field annotation)</td>
 //!         </tr>
 //!         <tr>
+//!             <td><code>const char*</code></td>
+//!             <td><code>Box&lt;str&gt;</code></td>
+//!             <td><code>*const libc::c_char</code></td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>T</code>, <code>const T*</code></td>
+//!             <td><code>Box&lt;U&gt;</code></td>
+//!             <td><code>T</code>, <code>*const T</code> (with <code>#[boxed]</code> field annotation)</td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>uint32_t</code> (and the other integer types)</td>
+//!             <td><code>NonZeroU32</code>, <code>Option&lt;NonZeroU32&gt;</code></td>
+//!             <td><code>u32</code> (<code>0</code> is an error, or <code>None</code>)</td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>uint32_t</code>, <code>bool</code> (and the other integer types)</td>
+//!             <td><code>AtomicU32</code>, <code>AtomicBool</code> (or a shared atomic with the <code>#[atomic_ordering(...)]</code> attribute)</td>
+//!             <td><code>u32</code>, <code>bool</code></td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>COptionT</code></td>
+//!             <td><code>Option&lt;U&gt;</code></td>
+//!             <td><code>COption&lt;T&gt;</code></td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CResultTE</code></td>
+//!             <td><code>Result&lt;U, V&gt;</code></td>
+//!             <td><code>CResult&lt;T, E&gt;</code></td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CDuration</code></td>
+//!             <td><code>Duration</code></td>
+//!             <td><code>CDuration</code></td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CTimestamp</code></td>
+//!             <td><code>SystemTime</code></td>
+//!             <td><code>CTimestamp</code></td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>const uint16_t*</code></td>
+//!             <td><code>String</code></td>
+//!             <td><code>*const u16</code> (with the <code>widestring</code> feature and the <code>#[wide_string]</code> attribute)</td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CIpAddr</code></td>
+//!             <td><code>IpAddr</code></td>
+//!             <td><code>CIpAddr</code></td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CSocketAddr</code></td>
+//!             <td><code>SocketAddr</code></td>
+//!             <td><code>CSocketAddr</code></td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>const T*</code></td>
+//!             <td><code>Arc&lt;U&gt;</code></td>
+//!             <td><code>CShared&lt;T&gt;</code> (reference counted, see [`c_repr_of_shared`])</td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>const void*</code> (opaque)</td>
+//!             <td><code>Arc&lt;T&gt;</code></td>
+//!             <td><code>CArc&lt;T&gt;</code> (reference counted, not converted, see [`export_c_arc`])</td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>void*</code> (opaque)</td>
+//!             <td>any <code>T</code></td>
+//!             <td><code>COpaque&lt;T&gt;</code>, or <code>*mut c_void</code> with the <code>#[opaque]</code> attribute (not converted)</td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CCallback</code> (function pointer and user data)</td>
+//!             <td><code>Box&lt;dyn Fn(A, B...) -&gt; R&gt;</code></td>
+//!             <td><code>CCallback&lt;(A, B...), R&gt;</code></td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CUuid</code></td>
+//!             <td><code>uuid::Uuid</code></td>
+//!             <td><code>CUuid</code> (with the <code>uuid</code> feature)</td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CByteArray</code></td>
+//!             <td><code>bytes::Bytes</code>, <code>bytes::BytesMut</code></td>
+//!             <td><code>CByteArray</code> (with the <code>bytes</code> feature, without copying the buffer when possible)</td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>size_t</code></td>
+//!             <td><code>usize</code>, <code>u64</code>, <code>u32</code></td>
+//!             <td><code>CSize</code></td>
+//!         </tr>
+//!         <tr>
 //!             <td><code>CArrayT</code></td>
 //!             <td><code>Vec&lt;U&gt;</code></td>
 //!             <td><code>CArray&lt;T&gt;</code></td>
 //!         </tr>
 //!         <tr>
+//!             <td><code>CArrayu32</code></td>
+//!             <td><code>Vec&lt;char&gt;</code></td>
+//!             <td><code>CArray&lt;u32&gt;</code></td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>const char*</code></td>
+//!             <td><code>f64</code>, <code>u32</code>... (any number)</td>
+//!             <td><code>*const libc::c_char</code> (with the <code>#[stringify]</code> attribute, see [`CStringifiable`])</td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>const char*</code></td>
+//!             <td><code>&amp;CStr</code></td>
+//!             <td><code>*const libc::c_char</code> (with the <code>#[borrowed_string]</code> attribute, pointing to the borrowed string, which is not freed)</td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CBoolArray</code></td>
+//!             <td><code>Vec&lt;bool&gt;</code></td>
+//!             <td><code>CBoolArray</code> (a byte per boolean) or <code>CBoolArray&lt;BitPacked&gt;</code></td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>char[N]</code></td>
+//!             <td><code>String</code></td>
+//!             <td><code>CFixedString&lt;N&gt;</code></td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CByteArray</code></td>
+//!             <td><code>String</code></td>
+//!             <td><code>CByteArray</code> (the UTF-8 bytes, not nul-terminated)</td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CArrayCArrayT</code></td>
+//!             <td><code>Vec&lt;Vec&lt;U&gt;&gt;</code></td>
+//!             <td><code>CArray&lt;CArray&lt;T&gt;&gt;</code></td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CDynArrayT</code></td>
+//!             <td><code>Vec&lt;U&gt;</code></td>
+//!             <td><code>CDynArray&lt;T&gt;</code> (keeping the capacity, for C code appending elements)</td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CArrayT</code> of tagged unions</td>
+//!             <td><code>Vec&lt;Box&lt;dyn AnyConvertible&gt;&gt;</code></td>
+//!             <td><code>CArray&lt;CTaggedValue&lt;U&gt;&gt;</code> (the variants of the union being listed in a <code>CVariantRegistry</code>)</td>
+//!         </tr>
+//!         <tr>
+//!             <td><code>CSliceViewT</code></td>
+//!             <td><code>&amp;[T]</code></td>
+//!             <td><code>CSliceView&lt;T&gt;</code> (borrowed, see [`AsRustView`])</td>
+//!         </tr>
+//!         <tr>
 //!             <td><code>CStringArray</code></td>
 //!             <td><code>Vec&lt;String&gt;</code></td>
 //!             <td><code>CStringArray</code></td>
 //!         </tr>
 //!         <tr>
+//!             <td><code>CStringArrayInt</code></td>
+//!             <td><code>Vec&lt;String&gt;</code></td>
+//!             <td><code>CStringArrayInt</code> (with an <code>int</code> size)</td>
+//!         </tr>
+//!         <tr>
 //!             <td><code>CRangeT</code></td>
 //!             <td><code>Range&lt;U&gt;</code></td>
 //!             <td><code>CRange&lt;T&gt;</code></td>
 //!         </tr>
+//!         <tr>
+//!             <td><code>CSpanT</code> (start and length)</td>
+//!             <td><code>Range&lt;usize&gt;</code></td>
+//!             <td><code>CSpan&lt;T&gt;</code></td>
+//!         </tr>
 //!     </tbody>
 //! </table>
 //!
@@ -169,12 +396,24 @@
 
 //! This shows that the struct implementing it is a `repr(C)` compatible view of the parametrized
 //! type and can be created from an object of this type.
+//!
+//! The `#[generate_accessors]` attribute of its derive exports to C a `<type_name>_new()`
+//! constructor, creating the C representation of the default value of the target type (or of the
+//! value returned by `#[generate_accessors(new = function)]`), and a
+//! `<type_name>_get_<field>(ptr, value) -> i32` getter per field, writing the field to `value`
+//! (or a pointer to the field, if it is not a primitive type or a pointer).
+//!
+//! The derive also generates a `c_repr_of_at(dst, input)` method, writing the C representation in
+//! a struct allocated by the C caller (see [`c_repr_of_at`]) and returning a result code.
+//!
+//! The [`CReprOfRef`] trait, implemented for all the types implementing `CReprOf` for a `Clone`
+//! type, converts a borrowed value (cloning it internally) : `CPizza::c_repr_of_ref(&pizza)`.
 
 //! ## The AsRust trait
 
 //! > When trying to convert a `repr(C)` struct that originated from C, the philosophy is to immediately convert
 //! > the struct to an **owned** idiomatic representation of the struct via the AsRust trait.
-
+//!
 //! The [`AsRust`] trait allows to create an idiomatic Rust struct from a C-compatible struct :
 
 //! ```
@@ -186,11 +425,114 @@
 
 //! This shows that the struct implementing it is a `repr(C)` compatible view of the parametrized
 //! type and that an instance of the parametrized type can be created from this struct.
+//!
+//! For the structs received from untrusted sources, the `#[string_policy(...)]` attribute bounds
+//! the length of a string field and can reject its control characters, see [`CStrPolicy`].
+//!
+//! When the target type gains fields that a frozen C struct doesn't carry, they are initialized
+//! with `#[as_rust_extra_field(name = expr)]`, with their default value by
+//! `#[as_rust_default_field(name)]`, or all at once by `#[as_rust_fill_defaults]` (the target type
+//! must then implement `Default`) :
+//!
+//! ```
+//! use ffi_convert::{AsRust, CDrop, CReprOf};
+//!
+//! #[derive(Default)]
+//! pub struct Topping {
+//!     pub name: String,
+//!     pub vegan: bool,
+//!     pub allergens: Vec<String>,
+//! }
+//!
+//! #[repr(C)]
+//! #[derive(CReprOf, AsRust, CDrop)]
+//! #[target_type(Topping)]
+//! #[as_rust_fill_defaults]
+//! pub struct CTopping {
+//!     pub name: *const libc::c_char,
+//! }
+//!
+//! let topping: Topping = CTopping::c_repr_of(Topping::default()).unwrap().as_rust().unwrap();
+//! assert!(topping.allergens.is_empty());
+//! ```
+
+//! ## The AsRustView trait
+
+//! When copying the data coming from C is too costly, the [`AsRustView`] trait allows to create an
+//! idiomatic Rust struct that borrows from the C-compatible struct (using `&str`, `&[T]`, `Cow`...)
+//! instead of owning its data. It can be derived similarly to the [`AsRust`] trait.
+
+//! ```
+//! # use ffi_convert::AsRustError;
+//! pub trait AsRustView<'a, T> {
+//!     fn as_rust_view(&'a self) -> Result<T, AsRustError>;
+//! }
+//! ```
 
 //! ## The CDrop trait
 
 //! A Trait showing that the `repr(C)` compatible view implementing it can free up its part of memory that are not
 //! managed by Rust drop mechanism.
+//!
+//! The derived `do_drop` frees the pointer fields (except the `#[skip]` ones) and calls `do_drop`
+//! on the value fields that aren't primitive types, such as an embedded [`CStringArray`] or
+//! [`CArray`], so that a struct that is not dropped by Rust (e.g. allocated by C code) is fully
+//! released. The `#[shallow]` attribute leaves a value field out of `do_drop`. The derived `Drop`
+//! implementation only frees the pointer fields, the value fields being dropped by Rust.
+//!
+//! The derived `do_drop` resets the pointer fields to `null` once they are freed, and the derived
+//! `Drop` skips the `null` ones, so that a struct released by `do_drop` (e.g. as a value field of
+//! another struct) can still be dropped by Rust.
+//!
+//! With the `#[defensive_drop]` struct attribute, the derived `do_drop` skips the `null` pointer
+//! fields too, so that calling it twice (e.g. a C caller releasing the struct twice) is a no-op
+//! instead of an error.
+//!
+//! The `#[thread_safe]` struct attribute asserts that the struct owns the memory its pointer
+//! fields point to, implementing `Send` and `Sync` when the values it owns are `Send` and `Sync`
+//! (its arrays being [`SyncCArray`]s or [`SyncCStringArray`]s, for instance).
+
+//! ## The CValidate trait
+
+//! A trait (that can be derived) to check that a `repr(C)` struct received from C is well-formed
+//! (no unexpected null pointers, valid UTF-8 strings...) without converting it, so that malformed
+//! input can be rejected cheaply.
+
+//! ## Debugging and comparing C structs
+
+//! Deriving `CDebug` implements `Debug` on a C struct by converting it with [`AsRust`] and showing
+//! its Rust value, so that logging it shows its content instead of pointer addresses. If it can't
+//! be converted, the conversion error and the addresses of its pointer fields are shown.
+//!
+//! Likewise, deriving `CPartialEq` compares the Rust values of two C structs (their strings and
+//! arrays, not the addresses of their pointers), so that tests can assert on C structs directly. A
+//! struct that can't be converted is not equal to any struct, not even to itself.
+//!
+//! ```
+//! use ffi_convert::{AsRust, CDebug, CDrop, CPartialEq, CReprOf};
+//!
+//! #[derive(Debug, PartialEq)]
+//! pub struct Sauce {
+//!     pub name: String,
+//! }
+//!
+//! #[repr(C)]
+//! #[derive(CReprOf, AsRust, CDrop, CDebug, CPartialEq)]
+//! #[target_type(Sauce)]
+//! pub struct CSauce {
+//!     name: *const libc::c_char,
+//! }
+//!
+//! let c_sauce = CSauce::c_repr_of(Sauce { name: "tomato".to_string() }).unwrap();
+//! assert_eq!(format!("{:?}", c_sauce), r#"Sauce { name: "tomato" }"#);
+//! assert_eq!(c_sauce, CSauce::c_repr_of(Sauce { name: "tomato".to_string() }).unwrap());
+//! ```
+
+//! ## The CSchema trait
+
+//! A trait (that can be derived) exposing a hash of the layout of a `repr(C)` struct. Combined with
+//! the [`export_schema_hash`] macro, it lets a C host check that its headers match the loaded
+//! library before exchanging structs.
 
 //! ## The RawPointerConverter trait
 
@@ -198,11 +540,158 @@
 //! conversion of a C-like struct to a raw pointer to this struct and conversely.
 //!
 //! This conversion trait comes in handy for C-like struct that have fields that points to other structs.
+//!
+//! The `#[generate_destructor]` attribute of its derive also exports the destructor of the struct
+//! to C, as an `extern "C" fn <type_name>_destroy(ptr: *mut T) -> i32` function (see
+//! [`destroy_raw_pointer`]) whose name can be given with `#[generate_destructor(fn_name)]`.
+
+//! ## Tables of function pointers
+
+//! Plugin interfaces often exchange tables of function pointers (vtables). Deriving `CVTable` on
+//! such a table allows to use it as a field of the derived structs : it is copied as is by the
+//! conversions, and its entries declared as `Option<fn(...)>` are checked to be non null (unless
+//! they are `#[nullable]`) by [`AsRust`] and [`CValidate`].
+//!
+//! Like `CReprOfTransparent`, this derive implements several traits at once, the
+//! `#[impls(CReprOf, AsRust, CDrop)]` attribute restricts it to the listed traits so that the
+//! others (here `CValidate`, to check the table differently) can be implemented manually.
+
+//! ```
+//! use ffi_convert::{AsRust, AsRustError, CDrop, CReprOf, CVTable, CValidate};
+//!
+//! #[repr(C)]
+//! #[derive(Clone, Copy, CVTable)]
+//! pub struct CPluginVTable {
+//!     pub process: Option<extern "C" fn(input: i32) -> i32>,
+//!     #[nullable]
+//!     pub reset: Option<extern "C" fn()>,
+//! }
+//!
+//! pub struct Plugin {
+//!     pub name: String,
+//!     pub vtable: CPluginVTable,
+//! }
+//!
+//! #[repr(C)]
+//! #[derive(CReprOf, AsRust, CDrop)]
+//! #[target_type(Plugin)]
+//! pub struct CPlugin {
+//!     pub name: *const libc::c_char,
+//!     pub vtable: CPluginVTable,
+//! }
+//!
+//! extern "C" fn double(input: i32) -> i32 {
+//!     2 * input
+//! }
+//!
+//! let c_plugin = CPlugin::c_repr_of(Plugin {
+//!     name: "doubler".to_string(),
+//!     vtable: CPluginVTable { process: Some(double), reset: None },
+//! })
+//! .unwrap();
+//! let plugin: Plugin = c_plugin.as_rust().unwrap();
+//! assert_eq!((plugin.vtable.process.unwrap())(21), 42);
+//!
+//! let incomplete = CPluginVTable { process: None, reset: None };
+//! assert!(matches!(incomplete.validate_c(), Err(AsRustError::NullPointer(_))));
+//! ```
+//!
+//! ## Exporting functions
+//!
+//! The `#[ffi_export]` attribute generates an `extern "C"` shim (named `ffi_<fn_name>` unless
+//! given with `#[ffi_export(name = fn_name)]`) for a function taking and returning Rust types. The
+//! arguments are converted with [`AsRust`] from their C type, given with `#[c_type(...)]` unless it
+//! is a string or a primitive type, and the output is converted with [`CReprOf`] and written in a
+//! last `output` argument (whose type is given with `#[ffi_export(output = ...)]`). The shim
+//! returns a [`CStatus`], the errors and panics of the function being available with
+//! [`error_store::take_last_error`].
+//!
+//! The ownership of the arguments follows the signature of the function : an argument taken by
+//! reference (`&T`) is borrowed and converted without being released, its owner keeping it, while
+//! an argument taken by value (`T`) is owned by the function, and released by the shim once
+//! converted.
+//!
+//! For the functions written by hand, the [`wrap!`] macro converts a `Result` to a [`CStatus`] (or
+//! to a result code enum generated by [`generate_error_handling!`]) in the same way, and
+//! [`generate_error_handling!`] exports the functions giving the last error to C. As unwinding
+//! into C code is undefined behavior, [`ffi_guard`] (or the [`catch_panic!`] macro) also catches
+//! the panics of their body, which are converted to the failure code.
 
 pub use ffi_convert_derive::*;
 
+pub mod error_store;
+pub mod header;
+pub mod prelude;
+
+mod allocation;
+mod arc;
+mod arena;
+mod batch;
+mod bool_array;
+mod budget;
+#[cfg(feature = "bytes")]
+mod bytes_buffers;
+mod c_types;
+mod callback;
+mod context;
 mod conversions;
+mod deadline;
+mod dyn_array;
+mod error_handling;
+mod explain;
+mod export;
+mod format;
+mod opaque;
+#[cfg(feature = "proptest")]
+mod property_tests;
+mod schema;
+mod shared;
+mod signatures;
+mod size;
+mod string_policy;
+mod stringify;
+mod sync;
+mod tagged;
 mod types;
+mod versioning;
+#[cfg(feature = "widestring")]
+mod wide_string;
 
+pub use allocation::*;
+pub use arc::*;
+pub use arena::*;
+pub use batch::*;
+pub use bool_array::*;
+pub use budget::*;
+#[cfg(feature = "bytes")]
+pub use bytes;
+pub use callback::*;
+pub use context::*;
 pub use conversions::*;
+pub use deadline::*;
+pub use dyn_array::*;
+pub use error_handling::*;
+pub use explain::*;
+pub use export::*;
+pub use format::*;
+#[cfg(feature = "signatures")]
+#[doc(hidden)]
+pub use inventory;
+pub use opaque::*;
+#[cfg(feature = "proptest")]
+pub use property_tests::*;
+#[cfg(feature = "proptest")]
+#[doc(hidden)]
+pub use proptest;
+pub use schema::*;
+pub use shared::*;
+pub use signatures::*;
+pub use size::*;
+pub use string_policy::*;
+pub use stringify::*;
+pub use sync::*;
+pub use tagged::*;
 pub use types::*;
+pub use versioning::*;
+#[cfg(feature = "widestring")]
+pub use widestring;
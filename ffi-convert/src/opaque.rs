@@ -0,0 +1,180 @@
+//! This module contains the [`COpaque`] type and the functions used by the `#[opaque]` field
+//! attribute, used to carry Rust values that have no C representation through C structs.
+
+use std::any::Any;
+use std::marker::PhantomData;
+use std::ptr;
+
+use crate::conversions::*;
+
+/// A utility type to carry a Rust value that has no sensible C representation (a trait object, a
+/// channel, a closure...) through a C struct. On the C side, this is an opaque `void*` that C code
+/// can only store and pass back.
+///
+/// The value is boxed with its type erased, so that it can be freed without knowing its type, and
+/// getting it back with a wrong type fails instead of reading garbage. As [`AsRust`] doesn't
+/// consume the C struct, it returns a clone of the value : wrap values that can't be cloned in an
+/// `Arc`, or take them back with [`COpaque::into_inner`].
+///
+/// The `#[opaque]` field attribute of the custom derives does the same for `*mut c_void` fields,
+/// so that the type of the value doesn't appear in the C struct.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, CDrop, COpaque, CReprOf};
+/// use std::sync::mpsc::{channel, Sender};
+///
+/// #[derive(Clone)]
+/// pub struct Job {
+///     pub id: u32,
+///     pub done: Sender<u32>,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(Job)]
+/// pub struct CJob {
+///     pub id: u32,
+///     pub done: COpaque<Sender<u32>>,
+/// }
+///
+/// let (done, finished) = channel();
+/// let c_job = CJob::c_repr_of(Job { id: 42, done }).unwrap();
+///
+/// let job = c_job.as_rust().unwrap();
+/// job.done.send(job.id).unwrap();
+/// assert_eq!(finished.recv().unwrap(), 42);
+/// ```
+#[repr(transparent)]
+#[derive(Debug)]
+pub struct COpaque<T> {
+    /// Pointer to the type erased value, created with [`opaque_into_raw`]
+    pub ptr: *mut libc::c_void,
+    _value: PhantomData<T>,
+}
+
+impl<T: 'static> COpaque<T> {
+    /// Returns a borrowed reference to the value, without cloning it.
+    ///
+    /// An error is returned if the pointer is `null` or if the value is not a `T`.
+    pub fn get(&self) -> Result<&T, AsRustError> {
+        unsafe { opaque_borrow(self.ptr) }
+    }
+
+    /// Takes the value back, without cloning it.
+    ///
+    /// An error is returned if the pointer is `null` or if the value is not a `T`, in which case
+    /// the value is freed.
+    pub fn into_inner(mut self) -> Result<T, AsRustError> {
+        let ptr = std::mem::replace(&mut self.ptr, ptr::null_mut());
+        unsafe { opaque_take(ptr) }
+    }
+}
+
+impl<T: Clone + 'static> AsRust<T> for COpaque<T> {
+    fn as_rust(&self) -> Result<T, AsRustError> {
+        unsafe { opaque_as_rust(self.ptr) }
+    }
+}
+
+impl<T: 'static> CReprOf<T> for COpaque<T> {
+    fn c_repr_of(input: T) -> Result<Self, CReprOfError> {
+        Ok(Self {
+            ptr: opaque_into_raw(input),
+            _value: PhantomData,
+        })
+    }
+}
+
+impl<T> CValidate for COpaque<T> {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        if self.ptr.is_null() {
+            return Err(UnexpectedNullPointerError.into());
+        }
+        Ok(())
+    }
+}
+
+impl<T> CDrop for COpaque<T> {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        unsafe { opaque_drop(self.ptr) };
+        self.ptr = ptr::null_mut();
+        Ok(())
+    }
+}
+
+impl<T> Drop for COpaque<T> {
+    fn drop(&mut self) {
+        let _ = self.do_drop();
+    }
+}
+
+/// The boxed value behind an opaque pointer : a thin pointer to a box of the type erased value.
+type OpaqueValue = Box<dyn Any>;
+
+/// Moves a value to the heap with its type erased, and returns an opaque pointer on it, to be
+/// freed with [`opaque_drop`].
+pub fn opaque_into_raw<T: 'static>(value: T) -> *mut libc::c_void {
+    Box::into_raw(Box::new(Box::new(value) as OpaqueValue)) as *mut libc::c_void
+}
+
+/// Borrows the value behind an opaque pointer created with [`opaque_into_raw`].
+///
+/// # Safety
+///
+/// The pointer must be `null` or have been created with [`opaque_into_raw`] and not freed yet.
+pub unsafe fn opaque_borrow<'a, T: 'static>(
+    ptr: *const libc::c_void,
+) -> Result<&'a T, AsRustError> {
+    if ptr.is_null() {
+        return Err(UnexpectedNullPointerError.into());
+    }
+    (*(ptr as *const OpaqueValue))
+        .downcast_ref()
+        .ok_or_else(|| unexpected_type::<T>())
+}
+
+/// Clones the value behind an opaque pointer created with [`opaque_into_raw`].
+///
+/// # Safety
+///
+/// See [`opaque_borrow`].
+pub unsafe fn opaque_as_rust<T: Clone + 'static>(
+    ptr: *const libc::c_void,
+) -> Result<T, AsRustError> {
+    opaque_borrow::<T>(ptr).cloned()
+}
+
+/// Takes back the value behind an opaque pointer created with [`opaque_into_raw`], which must
+/// not be used anymore.
+///
+/// # Safety
+///
+/// See [`opaque_borrow`].
+unsafe fn opaque_take<T: 'static>(ptr: *mut libc::c_void) -> Result<T, AsRustError> {
+    if ptr.is_null() {
+        return Err(UnexpectedNullPointerError.into());
+    }
+    Box::from_raw(ptr as *mut OpaqueValue)
+        .downcast()
+        .map(|value| *value)
+        .map_err(|_| unexpected_type::<T>())
+}
+
+/// Frees the value behind an opaque pointer created with [`opaque_into_raw`], whatever its type.
+/// Does nothing if the pointer is `null`.
+///
+/// # Safety
+///
+/// The pointer must be `null` or have been created with [`opaque_into_raw`], and must not be
+/// used anymore.
+pub unsafe fn opaque_drop(ptr: *mut libc::c_void) {
+    if !ptr.is_null() {
+        drop(Box::from_raw(ptr as *mut OpaqueValue));
+    }
+}
+
+fn unexpected_type<T>() -> AsRustError {
+    AsRustError::Other(format!("the opaque value is not a `{}`", std::any::type_name::<T>()).into())
+}
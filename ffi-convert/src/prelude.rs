@@ -0,0 +1,29 @@
+//! The traits and the most common types of this crate, to be glob imported :
+//!
+//! ```
+//! use ffi_convert::prelude::*;
+//!
+//! pub struct Topping {
+//!     pub name: String,
+//!     pub grams: Vec<u32>,
+//! }
+//!
+//! #[repr(C)]
+//! #[derive(CReprOf, AsRust, CDrop, RawPointerConverter)]
+//! #[target_type(Topping)]
+//! pub struct CTopping {
+//!     pub name: *const libc::c_char,
+//!     pub grams: CArray<u32>,
+//! }
+//! ```
+//!
+//! The code generated by the custom derives only refers to the traits through their full path, it
+//! doesn't depend on the traits imported by the user.
+
+pub use crate::{
+    AnyConvertible, AsRust, AsRustError, AsRustView, CArc, CArray, CBoolArray, CByteArray,
+    CCallback, CDrop, CDropError, CDynArray, CFixedString, COpaque, COption, CRange, CReprOf,
+    CReprOfError, CReprOfRef, CReprOfTransparent, CSchema, CShared, CSliceView, CSpan,
+    CStringArray, CStringArrayInt, CTaggedUnion, CTaggedValue, CVTable, CValidate,
+    CVariantRegistry, RawBorrow, RawBorrowMut, RawPointerConverter, ZeroCheck,
+};
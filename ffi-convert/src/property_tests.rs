@@ -0,0 +1,184 @@
+//! This module contains the [`generate_roundtrip_proptest`](crate::generate_roundtrip_proptest)
+//! macro, generating property tests converting random values to their C representation and back,
+//! and the [`LeakCheckAllocator`] checking that these conversions don't leak.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use proptest::test_runner::TestCaseError;
+
+use crate::conversions::*;
+
+thread_local! {
+    /// Number of blocks allocated by the current thread and not freed yet, counted by the
+    /// [`LeakCheckAllocator`].
+    static LIVE_ALLOCATIONS: Cell<isize> = const { Cell::new(0) };
+}
+
+/// Whether the [`LeakCheckAllocator`] is the global allocator.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// A global allocator counting the blocks allocated by each thread and not freed yet, so that the
+/// property tests generated by
+/// [`generate_roundtrip_proptest`](crate::generate_roundtrip_proptest) check that the conversions
+/// don't leak. The memory is allocated by the system allocator.
+///
+/// The leaks are only checked once it is installed by the test crate :
+///
+/// ```
+/// #[global_allocator]
+/// static ALLOCATOR: ffi_convert::LeakCheckAllocator = ffi_convert::LeakCheckAllocator;
+/// ```
+pub struct LeakCheckAllocator;
+
+fn count_allocations(delta: isize) {
+    INSTALLED.store(true, Ordering::Relaxed);
+    // the counter is not available anymore while the thread is destroyed
+    let _ = LIVE_ALLOCATIONS.try_with(|live| live.set(live.get() + delta));
+}
+
+unsafe impl GlobalAlloc for LeakCheckAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            count_allocations(1);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            count_allocations(1);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        count_allocations(-1);
+    }
+
+    // the number of blocks doesn't change when one of them is reallocated
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// The number of blocks allocated by the current thread and not freed yet, `None` if the
+/// [`LeakCheckAllocator`] is not the global allocator.
+pub fn live_allocations() -> Option<isize> {
+    if INSTALLED.load(Ordering::Relaxed) {
+        Some(LIVE_ALLOCATIONS.with(Cell::get))
+    } else {
+        None
+    }
+}
+
+/// Converts `value` to its C representation `C` and back, failing the property test if the value
+/// changes, or if the conversion leaks (when the [`LeakCheckAllocator`] is installed).
+///
+/// A value that has no C representation (e.g. a string containing a nul byte) must be rejected
+/// by [`CReprOf::c_repr_of`], without leaking what was already converted.
+pub fn check_round_trip<C, T>(value: T) -> Result<(), TestCaseError>
+where
+    C: CReprOf<T> + AsRust<T>,
+    T: Clone + Debug + PartialEq,
+{
+    // the first round trip initializes what is only allocated once (such as thread locals), the
+    // leaks are checked during the second one
+    round_trip::<C, T>(value.clone())?;
+    let before = live_allocations();
+    round_trip::<C, T>(value.clone())?;
+    let after = live_allocations();
+
+    match (before, after) {
+        (Some(before), Some(after)) if after != before => Err(TestCaseError::fail(format!(
+            "the round trip of {:?} leaked {} blocks",
+            value,
+            after - before
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn round_trip<C, T>(value: T) -> Result<(), TestCaseError>
+where
+    C: CReprOf<T> + AsRust<T>,
+    T: Clone + Debug + PartialEq,
+{
+    let c_value = match C::c_repr_of(value.clone()) {
+        Ok(c_value) => c_value,
+        Err(_) => return Ok(()),
+    };
+    let converted = c_value.as_rust().map_err(|e| {
+        TestCaseError::fail(format!(
+            "the C representation of {:?} can't be converted back: {}",
+            value, e
+        ))
+    })?;
+    if converted != value {
+        return Err(TestCaseError::fail(format!(
+            "{:?} became {:?} after the round trip",
+            value, converted
+        )));
+    }
+    Ok(())
+}
+
+/// Generates a property test converting random values of a Rust type to the given C type and
+/// back, checking that they don't change and that the conversions don't leak (see
+/// [`check_round_trip`]). The values are generated by the given `proptest` strategy, or by the
+/// `Arbitrary` implementation of the Rust type.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::*;
+/// use proptest::prelude::*;
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// pub struct Topping {
+///     pub name: String,
+///     pub amount: i32,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(Topping)]
+/// pub struct CTopping {
+///     name: *const libc::c_char,
+///     amount: i32,
+/// }
+///
+/// generate_roundtrip_proptest!(
+///     topping_round_trips,
+///     Topping,
+///     CTopping,
+///     // the names with a nul byte must be rejected
+///     ("[a-z\\x00]{0,8}", any::<i32>()).prop_map(|(name, amount)| Topping { name, amount })
+/// );
+///
+/// generate_roundtrip_proptest!(amounts_round_trip, Vec<i32>, CArray<i32>);
+/// ```
+#[macro_export]
+macro_rules! generate_roundtrip_proptest {
+    ($func_name:ident, $rust_type:ty, $c_type:ty) => {
+        $crate::generate_roundtrip_proptest!(
+            $func_name,
+            $rust_type,
+            $c_type,
+            $crate::proptest::prelude::any::<$rust_type>()
+        );
+    };
+    ($func_name:ident, $rust_type:ty, $c_type:ty, $strategy:expr) => {
+        $crate::proptest::proptest! {
+            #[test]
+            fn $func_name(value in $strategy) {
+                $crate::check_round_trip::<$c_type, $rust_type>(value)?;
+            }
+        }
+    };
+}
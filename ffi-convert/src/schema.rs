@@ -0,0 +1,233 @@
+//! This module contains the [`CSchema`] trait and the [`export_schema_hash`] macro, used to check
+//! that the C headers used by a host match the layout of the structs of the loaded library, and
+//! the [`schema_json`] function describing this layout to binding generators.
+
+use std::fmt::Write;
+
+/// A trait describing the layout of a `repr(C)` struct with a hash, computed at compile time from
+/// the name, size and alignment of the struct and the name, type and offset of each of its
+/// fields.
+///
+/// Exposing this hash with [`export_schema_hash`] allows a C host to check, at startup, that the
+/// headers it was compiled with match the library it loaded (by comparing the hash returned by
+/// the library with the one generated alongside the headers) before exchanging structs.
+///
+/// This trait can be derived, the hash then changes whenever a field is added, removed, renamed,
+/// reordered or has its type changed. The derive also describes the fields of the struct in
+/// [`CSchema::FIELDS`], with their [`Ownership`] : it is deduced from the type of the field and
+/// from its `#[skip]` and `#[callback]` attributes, and can be given explicitly with the
+/// `#[ownership(...)]` attribute (e.g. `#[ownership(c_allocated)]`).
+pub trait CSchema {
+    /// Hash of the layout of the struct
+    const SCHEMA_HASH: u64;
+
+    /// Description of the fields of the struct, in declaration order (the implementations written
+    /// by hand may leave it empty)
+    const FIELDS: &'static [FieldSchema] = &[];
+}
+
+/// Description of a field of a struct implementing [`CSchema`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldSchema {
+    /// Name of the field
+    pub name: &'static str,
+    /// Rust type of the field, as written in the struct
+    pub type_name: &'static str,
+    /// Offset of the field in the struct, in bytes
+    pub offset: usize,
+    /// Who frees the memory referenced by the field
+    pub ownership: Ownership,
+}
+
+/// Who frees the memory referenced by a field, so that the wrappers generated in other languages
+/// know which values they must free.
+///
+/// The ownership is not part of the [`CSchema::SCHEMA_HASH`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ownership {
+    /// The field is stored inline, the memory it references (if any) is described by the schema
+    /// of its type
+    Value,
+    /// The field points to memory allocated by the library, freed when the struct is dropped
+    /// (e.g. by the `CDrop` implementation of the struct or by the function of the library
+    /// freeing it)
+    Owned,
+    /// The field points to memory that the struct doesn't own, which must outlive the struct
+    Borrowed,
+    /// The field points to memory allocated by the C code using the library, which frees it
+    CAllocated,
+    /// The field is a callback whose user data is released with its `release` function (see
+    /// [`CCallback`](crate::CCallback))
+    Callback,
+}
+
+impl Ownership {
+    /// The name of the ownership, as used by [`schema_json`]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Ownership::Value => "value",
+            Ownership::Owned => "owned",
+            Ownership::Borrowed => "borrowed",
+            Ownership::CAllocated => "c_allocated",
+            Ownership::Callback => "callback",
+        }
+    }
+}
+
+/// A hasher that can be used in constant expressions, to implement [`CSchema`] manually.
+///
+/// This is a 64 bits FNV-1a hash, so hashes are stable across builds, platforms and versions of
+/// Rust (as long as the hashed values are).
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{CSchema, SchemaHasher};
+///
+/// #[repr(C)]
+/// pub struct CSauce {
+///     pub volume: f32,
+/// }
+///
+/// impl CSchema for CSauce {
+///     const SCHEMA_HASH: u64 = SchemaHasher::new()
+///         .write_str("CSauce")
+///         .write_usize(std::mem::size_of::<CSauce>())
+///         .write_str("volume")
+///         .write_str("f32")
+///         .write_usize(std::mem::offset_of!(CSauce, volume))
+///         .finish();
+/// }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct SchemaHasher {
+    state: u64,
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl SchemaHasher {
+    /// Creates a new hasher
+    pub const fn new() -> Self {
+        Self {
+            state: FNV_OFFSET_BASIS,
+        }
+    }
+
+    /// Hashes the given bytes
+    pub const fn write_bytes(mut self, bytes: &[u8]) -> Self {
+        let mut i = 0;
+        while i < bytes.len() {
+            self.state ^= bytes[i] as u64;
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+            i += 1;
+        }
+        self
+    }
+
+    /// Hashes a string, followed by a separator so that `("ab", "c")` and `("a", "bc")` have
+    /// different hashes
+    pub const fn write_str(self, value: &str) -> Self {
+        self.write_bytes(value.as_bytes()).write_bytes(&[0xff])
+    }
+
+    /// Hashes an integer, as 8 little-endian bytes whatever the platform
+    pub const fn write_usize(self, value: usize) -> Self {
+        self.write_bytes(&(value as u64).to_le_bytes())
+    }
+
+    /// Returns the hash of the written values
+    pub const fn finish(self) -> u64 {
+        self.state
+    }
+}
+
+impl Default for SchemaHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Describes the layout of a struct implementing [`CSchema`] as a JSON object, with its name, size,
+/// alignment, schema hash and fields (with their type, offset and ownership).
+///
+/// This is meant to be written by a build script or a test next to the generated C headers, for
+/// the tools generating wrappers in other languages.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{schema_json, CSchema};
+///
+/// #[repr(C)]
+/// #[derive(CSchema)]
+/// pub struct CSauce {
+///     pub name: *const libc::c_char,
+///     pub volume: f32,
+///     #[ownership(c_allocated)]
+///     pub user_data: *mut libc::c_void,
+/// }
+///
+/// let json = schema_json::<CSauce>();
+/// assert!(json.contains(r#"{"name":"name","type":"*const libc::c_char","offset":0,"ownership":"owned"}"#));
+/// assert!(json.contains(r#""ownership":"c_allocated""#));
+/// ```
+pub fn schema_json<T: CSchema>() -> String {
+    let type_name = std::any::type_name::<T>();
+    let mut json = String::new();
+    let _ = write!(
+        json,
+        r#"{{"name":"{}","size":{},"align":{},"hash":{},"fields":["#,
+        json_escape(type_name.rsplit("::").next().unwrap_or(type_name)),
+        std::mem::size_of::<T>(),
+        std::mem::align_of::<T>(),
+        T::SCHEMA_HASH,
+    );
+    for (index, field) in T::FIELDS.iter().enumerate() {
+        let _ = write!(
+            json,
+            r#"{}{{"name":"{}","type":"{}","offset":{},"ownership":"{}"}}"#,
+            if index == 0 { "" } else { "," },
+            json_escape(field.name),
+            json_escape(field.type_name),
+            field.offset,
+            field.ownership.as_str(),
+        );
+    }
+    json.push_str("]}");
+    json
+}
+
+/// Escapes the characters of a type name that can't appear in a JSON string.
+fn json_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Generates an `extern "C"` function returning the [`CSchema::SCHEMA_HASH`] of a struct, to be
+/// called by the C host to check its headers.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{export_schema_hash, CSchema};
+///
+/// #[repr(C)]
+/// #[derive(CSchema)]
+/// pub struct CSauce {
+///     pub volume: f32,
+/// }
+///
+/// export_schema_hash!(CSauce, ffi_sauce_schema_hash);
+///
+/// assert_eq!(ffi_sauce_schema_hash(), CSauce::SCHEMA_HASH);
+/// ```
+#[macro_export]
+macro_rules! export_schema_hash {
+    ($typ:ty, $fn_name:ident) => {
+        #[no_mangle]
+        pub extern "C" fn $fn_name() -> u64 {
+            <$typ as $crate::CSchema>::SCHEMA_HASH
+        }
+    };
+}
@@ -0,0 +1,213 @@
+//! This module contains the [`CShared`] type, used to convert `Arc`s, and the
+//! [`c_repr_of_shared`] function that allows to convert each shared `Arc` only once.
+
+use ffi_convert_derive::RawPointerConverter;
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ptr;
+use std::sync::Arc;
+
+use crate as ffi_convert;
+use crate::conversions::*;
+
+/// Converted values, keyed by the address of the original `Arc` and the type of the C value.
+type ConversionsMap = HashMap<(usize, TypeId), Box<dyn Any>>;
+
+thread_local! {
+    /// Identity map of the `Arc`s already converted during the current call to
+    /// [`c_repr_of_shared`], `None` outside of such a call.
+    static SHARED_CONVERSIONS: RefCell<Option<ConversionsMap>> = const { RefCell::new(None) };
+}
+
+thread_local! {
+    /// Addresses of the `Arc`s currently being converted, used to detect cycles.
+    static CONVERSIONS_IN_PROGRESS: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Marks an `Arc` as being converted until it is dropped, fails if it was already being
+/// converted (meaning that the `Arc` is part of a cycle).
+struct InProgressGuard {
+    address: usize,
+}
+
+impl InProgressGuard {
+    fn new(address: usize) -> Result<Self, CReprOfError> {
+        CONVERSIONS_IN_PROGRESS.with(|in_progress| {
+            let mut in_progress = in_progress.borrow_mut();
+            if in_progress.contains(&address) {
+                Err(CReprOfError::CycleDetected)
+            } else {
+                in_progress.push(address);
+                Ok(Self { address })
+            }
+        })
+    }
+}
+
+impl Drop for InProgressGuard {
+    fn drop(&mut self) {
+        CONVERSIONS_IN_PROGRESS.with(|in_progress| {
+            let mut in_progress = in_progress.borrow_mut();
+            if let Some(index) = in_progress.iter().rposition(|it| *it == self.address) {
+                in_progress.remove(index);
+            }
+        });
+    }
+}
+
+/// Resets the identity map when the outermost call to [`c_repr_of_shared`] returns (or panics).
+struct SharedConversionsGuard {
+    is_outermost: bool,
+}
+
+impl Drop for SharedConversionsGuard {
+    fn drop(&mut self) {
+        if self.is_outermost {
+            SHARED_CONVERSIONS.with(|conversions| conversions.borrow_mut().take());
+        }
+    }
+}
+
+/// Creates the C representation of `input`, like [`CReprOf::c_repr_of`], except that an `Arc`
+/// appearing several times in `input` is converted only once : all the [`CShared`] created from
+/// it point to the same C value, which is freed when the last of them is dropped.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{c_repr_of_shared, AsRust, CDrop, CReprOf, CShared, RawPointerConverter};
+/// use std::sync::Arc;
+///
+/// #[derive(Clone)]
+/// pub struct Sauce {
+///     pub volume: f32,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop, RawPointerConverter)]
+/// #[target_type(Sauce)]
+/// pub struct CSauce {
+///     pub volume: f32,
+/// }
+///
+/// pub struct Pizza {
+///     pub base: Arc<Sauce>,
+///     pub topping: Arc<Sauce>,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, CDrop)]
+/// #[target_type(Pizza)]
+/// pub struct CPizza {
+///     pub base: CShared<CSauce>,
+///     pub topping: CShared<CSauce>,
+/// }
+///
+/// let sauce = Arc::new(Sauce { volume: 4.2 });
+/// let pizza = Pizza { base: sauce.clone(), topping: sauce };
+///
+/// let c_pizza: CPizza = c_repr_of_shared(pizza).unwrap();
+/// assert_eq!(c_pizza.base.ptr, c_pizza.topping.ptr);
+/// ```
+pub fn c_repr_of_shared<T: CReprOf<U>, U>(input: U) -> Result<T, CReprOfError> {
+    let is_outermost = SHARED_CONVERSIONS.with(|conversions| {
+        let mut conversions = conversions.borrow_mut();
+        let is_outermost = conversions.is_none();
+        if is_outermost {
+            *conversions = Some(HashMap::new());
+        }
+        is_outermost
+    });
+    let _guard = SharedConversionsGuard { is_outermost };
+
+    T::c_repr_of(input)
+}
+
+/// A utility type to represent a value shared through an `Arc`. On the C side, this is a (non
+/// null) pointer to the C representation of the value.
+///
+/// The pointed value is reference counted : cloning a `CShared` doesn't copy the value and it is
+/// freed when the last `CShared` pointing to it is dropped. Use [`c_repr_of_shared`] to make sure
+/// that an `Arc` appearing several times in the converted value is converted only once.
+///
+/// Converting an `Arc` that (indirectly) contains itself fails with
+/// [`CReprOfError::CycleDetected`] instead of looping forever.
+#[repr(transparent)]
+#[derive(Debug, RawPointerConverter)]
+pub struct CShared<T> {
+    /// Pointer to the shared value, created with `Arc::into_raw`
+    pub ptr: *const T,
+}
+
+impl<T> Clone for CShared<T> {
+    fn clone(&self) -> Self {
+        if !self.ptr.is_null() {
+            unsafe { Arc::increment_strong_count(self.ptr) };
+        }
+        Self { ptr: self.ptr }
+    }
+}
+
+impl<U: AsRust<V>, V> AsRust<Arc<V>> for CShared<U> {
+    fn as_rust(&self) -> Result<Arc<V>, AsRustError> {
+        Ok(Arc::new(unsafe { U::raw_borrow(self.ptr) }?.as_rust()?))
+    }
+}
+
+impl<U: CReprOf<V> + 'static, V: Clone + 'static> CReprOf<Arc<V>> for CShared<U> {
+    fn c_repr_of(input: Arc<V>) -> Result<Self, CReprOfError> {
+        let address = Arc::as_ptr(&input) as *const () as usize;
+        let key = (address, TypeId::of::<U>());
+        let _in_progress = InProgressGuard::new(address)?;
+
+        let already_converted = SHARED_CONVERSIONS.with(|conversions| {
+            conversions.borrow().as_ref().map(|conversions| {
+                conversions
+                    .get(&key)
+                    .and_then(|converted| converted.downcast_ref::<(Arc<V>, Arc<U>)>())
+                    .map(|(_, converted)| converted.clone())
+            })
+        });
+
+        let converted = match already_converted {
+            // not in c_repr_of_shared, the value is converted every time
+            None => Arc::new(U::c_repr_of(
+                Arc::try_unwrap(input).unwrap_or_else(|input| (*input).clone()),
+            )?),
+            Some(Some(converted)) => converted,
+            Some(None) => {
+                let converted = Arc::new(U::c_repr_of((*input).clone())?);
+                // the input is kept alive until the end of the conversion, so that its address
+                // can't be reused by another Arc
+                SHARED_CONVERSIONS.with(|conversions| {
+                    if let Some(conversions) = conversions.borrow_mut().as_mut() {
+                        conversions.insert(key, Box::new((input, converted.clone())));
+                    }
+                });
+                converted
+            }
+        };
+
+        Ok(Self {
+            ptr: Arc::into_raw(converted),
+        })
+    }
+}
+
+impl<T> CDrop for CShared<T> {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        if !self.ptr.is_null() {
+            drop(unsafe { Arc::from_raw(self.ptr) });
+            self.ptr = ptr::null();
+        }
+        Ok(())
+    }
+}
+
+impl<T> Drop for CShared<T> {
+    fn drop(&mut self) {
+        let _ = self.do_drop();
+    }
+}
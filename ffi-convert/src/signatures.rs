@@ -0,0 +1,183 @@
+//! This module contains the [`CFunctionSignature`] type, describing the `extern "C"` functions
+//! generated by this crate (destructors, accessors, `#[ffi_export]` shims, `CArc` and error
+//! handling functions) so that documentation and bindings can be generated from them.
+//!
+//! With the `signatures` feature, the signatures of all the generated functions of the program are
+//! registered and listed by [`registered_signatures`].
+
+use std::fmt::Write;
+
+/// What happens to the ownership of a value passed to (or returned by) an exported function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParameterOwnership {
+    /// The value is copied (a number, or a struct without pointers)
+    Value,
+    /// The pointed value is only borrowed, its owner keeps the ownership
+    Borrowed,
+    /// The pointed value is owned by the caller, who must release it
+    OwnedByCaller,
+    /// The pointed value is released by the function, it must not be used after the call
+    Released,
+}
+
+impl ParameterOwnership {
+    fn description(self) -> &'static str {
+        match self {
+            ParameterOwnership::Value => "passed by value",
+            ParameterOwnership::Borrowed => "borrowed, its owner keeps the ownership",
+            ParameterOwnership::OwnedByCaller => "owned by the caller, who must release it",
+            ParameterOwnership::Released => {
+                "released by the function, it must not be used after the call"
+            }
+        }
+    }
+}
+
+/// Whether a parameter is read by an exported function, or is a pointer where it writes a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+/// A parameter of an exported function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CParameter {
+    pub name: &'static str,
+    /// The Rust type of the parameter (e.g. `*const libc::c_char`)
+    pub ty: &'static str,
+    pub direction: Direction,
+    /// The ownership of the value passed to the function, or written by the function for an
+    /// output parameter
+    pub ownership: ParameterOwnership,
+}
+
+/// A code returned by an exported function, and its meaning.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CReturnCode {
+    pub value: i64,
+    pub meaning: &'static str,
+}
+
+/// The signature of an `extern "C"` function generated by this crate.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{CFunctionSignature, CParameter, CReturnCode, Direction, ParameterOwnership};
+///
+/// let signature = CFunctionSignature {
+///     name: "ffi_pizza_destroy",
+///     doc: "Drops a `CPizza` created by Rust.",
+///     parameters: &[CParameter {
+///         name: "ptr",
+///         ty: "*mut CPizza",
+///         direction: Direction::In,
+///         ownership: ParameterOwnership::Released,
+///     }],
+///     return_type: "i32",
+///     return_ownership: ParameterOwnership::Value,
+///     return_codes: &[
+///         CReturnCode { value: 0, meaning: "success" },
+///         CReturnCode { value: -1, meaning: "null pointer" },
+///     ],
+/// };
+///
+/// assert_eq!(
+///     signature.doxygen_comment(),
+///     "/**
+///  * Drops a `CPizza` created by Rust.
+///  *
+///  * @param[in] ptr `*mut CPizza`, released by the function, it must not be used after the call
+///  * @return `i32` : `0` (success), `-1` (null pointer)
+///  */"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CFunctionSignature {
+    /// The exported name of the function
+    pub name: &'static str,
+    /// The first paragraph of the documentation of the function
+    pub doc: &'static str,
+    pub parameters: &'static [CParameter],
+    /// The Rust type returned by the function, `()` if it returns nothing
+    pub return_type: &'static str,
+    pub return_ownership: ParameterOwnership,
+    /// The result codes returned by the function, empty if it returns a value
+    pub return_codes: &'static [CReturnCode],
+}
+
+impl CFunctionSignature {
+    /// The Doxygen comment documenting the function in a C header.
+    pub fn doxygen_comment(&self) -> String {
+        let mut comment = format!("/**\n * {}\n", self.doc);
+        if !self.parameters.is_empty() || self.return_type != "()" {
+            comment.push_str(" *\n");
+        }
+        for parameter in self.parameters {
+            let direction = match parameter.direction {
+                Direction::In => "in",
+                Direction::Out => "out",
+            };
+            let _ = writeln!(
+                comment,
+                " * @param[{}] {} `{}`, {}",
+                direction,
+                parameter.name,
+                parameter.ty,
+                parameter.ownership.description()
+            );
+        }
+        if self.return_type != "()" {
+            let description = if self.return_codes.is_empty() {
+                self.return_ownership.description().to_string()
+            } else {
+                self.return_codes
+                    .iter()
+                    .map(|code| format!("`{}` ({})", code.value, code.meaning))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let _ = writeln!(
+                comment,
+                " * @return `{}` : {}",
+                self.return_type, description
+            );
+        }
+        comment.push_str(" */");
+        comment
+    }
+}
+
+#[cfg(feature = "signatures")]
+inventory::collect!(CFunctionSignature);
+
+/// The signatures of all the `extern "C"` functions generated by this crate in the program.
+#[cfg(feature = "signatures")]
+pub fn registered_signatures() -> impl Iterator<Item = &'static CFunctionSignature> {
+    inventory::iter::<CFunctionSignature>.into_iter()
+}
+
+/// The signature of the generated `extern "C"` function with the given name, if any.
+#[cfg(feature = "signatures")]
+pub fn registered_signature(name: &str) -> Option<&'static CFunctionSignature> {
+    registered_signatures().find(|signature| signature.name == name)
+}
+
+/// Registers the signature of a generated function, with the `signatures` feature.
+#[doc(hidden)]
+#[cfg(feature = "signatures")]
+#[macro_export]
+macro_rules! register_signature {
+    ($signature:expr) => {
+        $crate::inventory::submit! { $signature }
+    };
+}
+
+/// Registers the signature of a generated function, with the `signatures` feature.
+#[doc(hidden)]
+#[cfg(not(feature = "signatures"))]
+#[macro_export]
+macro_rules! register_signature {
+    ($signature:expr) => {};
+}
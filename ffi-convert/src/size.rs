@@ -0,0 +1,90 @@
+//! This module contains the [`CSize`] type, representing a C `size_t`, and the compile time checks
+//! of the assumptions made by this crate on `size_t`.
+//!
+//! The lengths of the C representations of this crate (e.g. the `size` of a
+//! [`CArray`](crate::CArray)) are `usize`s, declared as `size_t` in C headers. The build fails on
+//! the targets where these types don't have the same layout, rather than mis-sizing the lengths
+//! read and written by C code.
+
+use crate::context::current_context;
+use crate::conversions::*;
+
+const _: () = assert!(
+    std::mem::size_of::<usize>() == std::mem::size_of::<libc::size_t>()
+        && std::mem::align_of::<usize>() == std::mem::align_of::<libc::size_t>(),
+    "`usize` and `size_t` have different layouts on this target, the lengths of ffi_convert types can't be read by C code"
+);
+
+/// A C `size_t`, for the length fields of structs declared by hand whose C declaration uses
+/// `size_t`, independently of the width of `usize`. The values are converted with checks from and
+/// to `usize`, `u64` and `u32`, failing if they don't fit (or saturating, if the
+/// `saturate_integers` option of the current context is set).
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, CDrop, CReprOf, CSize};
+///
+/// pub struct Tensor {
+///     pub rank: u32,
+///     pub len: u64,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(Tensor)]
+/// pub struct CTensor {
+///     pub rank: CSize,
+///     pub len: CSize,
+/// }
+///
+/// let c_tensor = CTensor::c_repr_of(Tensor { rank: 2, len: 64 }).unwrap();
+/// assert_eq!(c_tensor.len, CSize(64));
+/// assert_eq!(c_tensor.as_rust().unwrap().rank, 2);
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CSize(pub libc::size_t);
+
+/// implements checked CReprOf and AsRust conversions between CSize and a Rust integer type.
+macro_rules! impl_c_size_conversions_for {
+    ($rust_typ:ty) => {
+        impl CReprOf<$rust_typ> for CSize {
+            #[allow(clippy::useless_conversion)] // `usize` is `size_t` on most targets
+            fn c_repr_of(input: $rust_typ) -> Result<CSize, CReprOfError> {
+                match libc::size_t::try_from(input) {
+                    Ok(value) => Ok(CSize(value)),
+                    Err(_) if current_context().saturate_integers => Ok(CSize(libc::size_t::MAX)),
+                    Err(e) => Err(CReprOfError::IntegerOverflow(e.into())),
+                }
+            }
+        }
+
+        impl AsRust<$rust_typ> for CSize {
+            #[allow(clippy::useless_conversion)]
+            fn as_rust(&self) -> Result<$rust_typ, AsRustError> {
+                match <$rust_typ>::try_from(self.0) {
+                    Ok(value) => Ok(value),
+                    Err(_) if current_context().saturate_integers => Ok(<$rust_typ>::MAX),
+                    Err(e) => Err(AsRustError::IntegerOverflow(e.into())),
+                }
+            }
+        }
+    };
+}
+
+impl_c_size_conversions_for!(usize);
+impl_c_size_conversions_for!(u64);
+impl_c_size_conversions_for!(u32);
+
+impl CDrop for CSize {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        Ok(())
+    }
+}
+
+impl CValidate for CSize {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        Ok(())
+    }
+}
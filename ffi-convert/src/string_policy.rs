@@ -0,0 +1,85 @@
+//! This module contains the [`CStrPolicy`] type, used to check the C strings received from
+//! untrusted sources before converting them.
+
+use std::ffi::CStr;
+
+use crate::conversions::*;
+
+/// The checks applied to an incoming C string before trusting its terminator, for the structs fed
+/// directly from untrusted sources (e.g. IPC messages).
+///
+/// [`CStr::raw_borrow`](RawBorrow::raw_borrow) reads until the nul terminator, however far it is.
+/// With a maximum length, at most `max_len + 1` bytes are read, and a string whose terminator is
+/// not found within them is rejected with [`AsRustError::StringTooLong`]. The control characters
+/// (`0x01` to `0x1f`, and `0x7f`) can also be rejected with [`AsRustError::ControlCharacter`].
+///
+/// In the custom derives, the policy of a string field is given with the
+/// `#[string_policy(max_len = 64, reject_control_chars)]` attribute (both arguments being
+/// optional), and applied by `AsRust`, `AsRustView` and `CValidate`.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, AsRustError, CDrop, CReprOf};
+///
+/// pub struct Request {
+///     pub method: String,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(Request)]
+/// pub struct CRequest {
+///     #[string_policy(max_len = 8, reject_control_chars)]
+///     pub method: *const libc::c_char,
+/// }
+///
+/// let c_request = CRequest::c_repr_of(Request { method: "GET".to_string() }).unwrap();
+/// assert_eq!(c_request.as_rust().unwrap().method, "GET");
+///
+/// let c_request = CRequest::c_repr_of(Request { method: "GET\r\n".to_string() }).unwrap();
+/// assert!(matches!(
+///     c_request.as_rust(),
+///     Err(AsRustError::ControlCharacter { position: 3 })
+/// ));
+///
+/// let c_request = CRequest::c_repr_of(Request { method: "PROPPATCH".to_string() }).unwrap();
+/// assert!(matches!(c_request.as_rust(), Err(AsRustError::StringTooLong { max_len: 8 })));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CStrPolicy {
+    /// Maximum number of bytes of the string, excluding its terminator
+    pub max_len: Option<usize>,
+    /// Whether the strings containing control characters are rejected
+    pub reject_control_chars: bool,
+}
+
+impl CStrPolicy {
+    /// Borrows the C string behind the pointer, after checking it against the policy.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be `null` or point to a nul terminated string, or to at least
+    /// `max_len + 1` readable bytes if there is a maximum length.
+    pub unsafe fn borrow<'a>(&self, input: *const libc::c_char) -> Result<&'a CStr, AsRustError> {
+        if input.is_null() {
+            return Err(UnexpectedNullPointerError.into());
+        }
+        let mut len = 0;
+        loop {
+            let byte = *input.add(len) as u8;
+            if byte == 0 {
+                break;
+            }
+            if let Some(max_len) = self.max_len.filter(|&max_len| len >= max_len) {
+                return Err(AsRustError::StringTooLong { max_len });
+            }
+            if self.reject_control_chars && (byte < 0x20 || byte == 0x7f) {
+                return Err(AsRustError::ControlCharacter { position: len });
+            }
+            len += 1;
+        }
+        let bytes = std::slice::from_raw_parts(input as *const u8, len + 1);
+        Ok(CStr::from_bytes_with_nul_unchecked(bytes))
+    }
+}
@@ -0,0 +1,93 @@
+//! This module contains the [`CStringifiable`] trait, converting numbers to and from C strings
+//! for the C APIs representing numbers as strings.
+
+use std::ffi::{CStr, CString};
+
+use crate::conversions::*;
+
+/// A number converted to and from a C string, whatever the locale of the process : the decimal
+/// separator is always a `.`, and there is no thousands separator.
+///
+/// The numbers are written with the shortest representation that reads back to the same value
+/// (so `0.1_f64` is written `0.1`), without exponent, and the special floating point values are
+/// written `NaN`, `inf` and `-inf`. Leading and trailing whitespace is not accepted when reading.
+///
+/// In the custom derives, a string field marked with the `#[stringify]` attribute is converted
+/// from and to a number.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, CDrop, CReprOf};
+/// use std::ffi::CStr;
+///
+/// pub struct Setting {
+///     pub gain: f64,
+///     pub channels: u8,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(Setting)]
+/// pub struct CSetting {
+///     #[stringify]
+///     pub gain: *const libc::c_char,
+///     #[stringify]
+///     pub channels: *const libc::c_char,
+/// }
+///
+/// let c_setting = CSetting::c_repr_of(Setting { gain: 0.75, channels: 2 }).unwrap();
+/// assert_eq!(unsafe { CStr::from_ptr(c_setting.gain) }.to_str().unwrap(), "0.75");
+///
+/// let setting = c_setting.as_rust().unwrap();
+/// assert_eq!(setting.gain, 0.75);
+/// assert_eq!(setting.channels, 2);
+/// ```
+pub trait CStringifiable: Sized {
+    /// Writes the number in a C string.
+    fn to_c_string(&self) -> Result<CString, CReprOfError>;
+
+    /// Reads the number from a C string.
+    fn from_c_str(input: &CStr) -> Result<Self, AsRustError>;
+}
+
+macro_rules! impl_c_stringifiable_for {
+    ($typ:ty) => {
+        impl CStringifiable for $typ {
+            fn to_c_string(&self) -> Result<CString, CReprOfError> {
+                // `Display` never depends on the locale, and numbers can't contain nul bytes
+                Ok(CString::new(self.to_string())?)
+            }
+
+            fn from_c_str(input: &CStr) -> Result<Self, AsRustError> {
+                let input = input.to_str()?;
+                input.parse().map_err(|error| {
+                    AsRustError::Other(
+                        format!(
+                            "`{}` is not a valid `{}`: {}",
+                            input,
+                            stringify!($typ),
+                            error
+                        )
+                        .into(),
+                    )
+                })
+            }
+        }
+    };
+}
+
+impl_c_stringifiable_for!(i8);
+impl_c_stringifiable_for!(u8);
+impl_c_stringifiable_for!(i16);
+impl_c_stringifiable_for!(u16);
+impl_c_stringifiable_for!(i32);
+impl_c_stringifiable_for!(u32);
+impl_c_stringifiable_for!(i64);
+impl_c_stringifiable_for!(u64);
+impl_c_stringifiable_for!(i128);
+impl_c_stringifiable_for!(u128);
+impl_c_stringifiable_for!(isize);
+impl_c_stringifiable_for!(usize);
+impl_c_stringifiable_for!(f32);
+impl_c_stringifiable_for!(f64);
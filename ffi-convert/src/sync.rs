@@ -0,0 +1,173 @@
+//! This module contains the [`SyncCArray`] and [`SyncCStringArray`] types, arrays that can be sent
+//! to and shared between threads.
+//!
+//! The C representations contain raw pointers, so they are neither `Send` nor `Sync` : nothing
+//! tells that they own the memory they point to, or that it is not mutated by C code. The arrays
+//! of this module own their elements (as they are converted from Rust values, or freed by Rust),
+//! and are thread safe when their elements are. The derived structs are asserted thread safe in
+//! the same way with the `#[thread_safe]` attribute of the `CDrop` derive.
+//!
+//! # Example
+//!
+//! ```
+//! use ffi_convert::{AsRust, CDrop, CReprOf, SyncCArray, SyncCStringArray};
+//!
+//! pub struct Batch {
+//!     pub ids: Vec<u32>,
+//!     pub names: Vec<String>,
+//! }
+//!
+//! #[repr(C)]
+//! #[derive(CReprOf, AsRust, CDrop)]
+//! #[target_type(Batch)]
+//! #[thread_safe]
+//! pub struct CBatch {
+//!     pub ids: SyncCArray<u32>,
+//!     pub names: SyncCStringArray,
+//! }
+//!
+//! let c_batch = CBatch::c_repr_of(Batch {
+//!     ids: vec![1, 2],
+//!     names: vec!["a".to_string(), "b".to_string()],
+//! })
+//! .unwrap();
+//!
+//! let batch = std::thread::spawn(move || c_batch.as_rust().unwrap()).join().unwrap();
+//! assert_eq!(batch.names, vec!["a".to_string(), "b".to_string()]);
+//! ```
+
+use ffi_convert_derive::RawPointerConverter;
+
+use std::ops::{Deref, DerefMut};
+
+use crate as ffi_convert;
+use crate::conversions::*;
+use crate::types::{CArray, CStringArray};
+
+/// A [`CArray`] owning its elements, which is `Send` and `Sync` when its elements are (as a
+/// `Vec<T>`). It has the same layout as a `CArray<T>`.
+#[repr(transparent)]
+#[derive(Debug, RawPointerConverter)]
+pub struct SyncCArray<T>(pub CArray<T>);
+
+unsafe impl<T: Send> Send for SyncCArray<T> {}
+unsafe impl<T: Sync> Sync for SyncCArray<T> {}
+
+impl<T> SyncCArray<T> {
+    /// Takes the inner array, which is not thread safe anymore.
+    pub fn into_inner(self) -> CArray<T> {
+        self.0
+    }
+}
+
+impl<T> From<CArray<T>> for SyncCArray<T> {
+    /// Asserts that the array owns its elements, as the arrays created by this crate.
+    fn from(array: CArray<T>) -> Self {
+        Self(array)
+    }
+}
+
+impl<T> Deref for SyncCArray<T> {
+    type Target = CArray<T>;
+
+    fn deref(&self) -> &CArray<T> {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for SyncCArray<T> {
+    fn deref_mut(&mut self) -> &mut CArray<T> {
+        &mut self.0
+    }
+}
+
+impl<T, V> CReprOf<V> for SyncCArray<T>
+where
+    CArray<T>: CReprOf<V>,
+{
+    fn c_repr_of(input: V) -> Result<Self, CReprOfError> {
+        Ok(Self(CArray::c_repr_of(input)?))
+    }
+}
+
+impl<T, V> AsRust<V> for SyncCArray<T>
+where
+    CArray<T>: AsRust<V>,
+{
+    fn as_rust(&self) -> Result<V, AsRustError> {
+        self.0.as_rust()
+    }
+}
+
+impl<T: CValidate> CValidate for SyncCArray<T> {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        self.0.validate_c()
+    }
+}
+
+impl<T> CDrop for SyncCArray<T> {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        self.0.do_drop()
+    }
+}
+
+/// A [`CStringArray`] owning its strings, which is `Send` and `Sync`. It has the same layout as a
+/// `CStringArray`.
+#[repr(transparent)]
+#[derive(Debug, RawPointerConverter)]
+pub struct SyncCStringArray(pub CStringArray);
+
+unsafe impl Send for SyncCStringArray {}
+unsafe impl Sync for SyncCStringArray {}
+
+impl SyncCStringArray {
+    /// Takes the inner array, which is not thread safe anymore.
+    pub fn into_inner(self) -> CStringArray {
+        self.0
+    }
+}
+
+impl From<CStringArray> for SyncCStringArray {
+    /// Asserts that the array owns its strings, as the arrays created by this crate.
+    fn from(array: CStringArray) -> Self {
+        Self(array)
+    }
+}
+
+impl Deref for SyncCStringArray {
+    type Target = CStringArray;
+
+    fn deref(&self) -> &CStringArray {
+        &self.0
+    }
+}
+
+impl DerefMut for SyncCStringArray {
+    fn deref_mut(&mut self) -> &mut CStringArray {
+        &mut self.0
+    }
+}
+
+impl CReprOf<Vec<String>> for SyncCStringArray {
+    fn c_repr_of(input: Vec<String>) -> Result<Self, CReprOfError> {
+        Ok(Self(CStringArray::c_repr_of(input)?))
+    }
+}
+
+impl AsRust<Vec<String>> for SyncCStringArray {
+    fn as_rust(&self) -> Result<Vec<String>, AsRustError> {
+        self.0.as_rust()
+    }
+}
+
+impl CValidate for SyncCStringArray {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        self.0.validate_c()
+    }
+}
+
+impl CDrop for SyncCStringArray {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        self.0.do_drop()
+    }
+}
@@ -0,0 +1,309 @@
+//! This module contains the [`CTaggedValue`] type, converting heterogeneous values (boxed as
+//! [`AnyConvertible`] trait objects) to a C tagged union, and the [`CVariantRegistry`] listing the
+//! variants of the union.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::mem::{self, MaybeUninit};
+use std::ptr;
+
+use crate::conversions::*;
+
+/// A value that can be converted to a variant of a C tagged union, implemented for all the
+/// `'static` types. The variants of a union are registered in its [`CVariantRegistry`].
+pub trait AnyConvertible: Any {
+    /// The name of the type of the value, used in the conversion errors
+    fn type_name(&self) -> &'static str;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: Any> AnyConvertible for T {
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl dyn AnyConvertible {
+    /// Whether the boxed value is a `T`.
+    pub fn is<T: Any>(&self) -> bool {
+        self.as_any().is::<T>()
+    }
+
+    /// Borrows the boxed value as a `T`, `None` if it is another type.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    /// Takes back the boxed value as a `T`, or gives the box back if it is another type.
+    pub fn downcast<T: Any>(self: Box<Self>) -> Result<Box<T>, Box<dyn AnyConvertible>> {
+        if self.is::<T>() {
+            Ok(self.into_any().downcast().expect("the type was checked"))
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Debug for dyn AnyConvertible {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AnyConvertible({})", self.type_name())
+    }
+}
+
+/// A C union (usually `#[repr(C)]` with a `ManuallyDrop` field for each variant) whose variants
+/// are listed in a [`CVariantRegistry`], built once for the whole program.
+pub trait CTaggedUnion: Sized + 'static {
+    fn registry() -> &'static CVariantRegistry<Self>;
+}
+
+type CReprOfVariantFn<U> = fn(Box<dyn Any>) -> Result<MaybeUninit<U>, CReprOfError>;
+type AsRustVariantFn<U> =
+    unsafe fn(&MaybeUninit<U>) -> Result<Box<dyn AnyConvertible>, AsRustError>;
+type DropVariantFn<U> = unsafe fn(&mut MaybeUninit<U>) -> Result<(), CDropError>;
+
+/// The functions converting a registered type and its C representation, stored in the union `U`.
+struct CVariant<U> {
+    tag: u32,
+    c_repr_of: CReprOfVariantFn<U>,
+    as_rust: AsRustVariantFn<U>,
+    drop: DropVariantFn<U>,
+}
+
+/// The variants of the C tagged union `U` : each registered Rust type is converted to the given C
+/// representation, stored in the union, with the given tag.
+///
+/// The tag [`CVariantRegistry::RELEASED_TAG`] is reserved.
+pub struct CVariantRegistry<U> {
+    variants: Vec<CVariant<U>>,
+    by_type: HashMap<TypeId, usize>,
+    by_tag: HashMap<u32, usize>,
+}
+
+impl<U> Default for CVariantRegistry<U> {
+    fn default() -> Self {
+        Self {
+            variants: vec![],
+            by_type: HashMap::new(),
+            by_tag: HashMap::new(),
+        }
+    }
+}
+
+impl<U> CVariantRegistry<U> {
+    /// The tag of a [`CTaggedValue`] whose variant was released with `do_drop`.
+    pub const RELEASED_TAG: u32 = u32::MAX;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the type `T`, converted to `C` with the given tag.
+    ///
+    /// # Panics
+    ///
+    /// If the type or the tag is already registered, if the tag is
+    /// [`CVariantRegistry::RELEASED_TAG`], or if `C` doesn't fit in the union `U`.
+    pub fn register<T, C>(mut self, tag: u32) -> Self
+    where
+        T: Any,
+        C: CReprOf<T> + AsRust<T>,
+    {
+        let type_name = std::any::type_name::<T>();
+        assert!(
+            mem::size_of::<C>() <= mem::size_of::<U>()
+                && mem::align_of::<C>() <= mem::align_of::<U>(),
+            "{} doesn't fit in the tagged union {}",
+            std::any::type_name::<C>(),
+            std::any::type_name::<U>(),
+        );
+        assert!(tag != Self::RELEASED_TAG, "the tag {} is reserved", tag);
+        assert!(
+            !self.by_tag.contains_key(&tag),
+            "the tag {} is already registered",
+            tag
+        );
+        assert!(
+            !self.by_type.contains_key(&TypeId::of::<T>()),
+            "{} is already registered",
+            type_name
+        );
+
+        let index = self.variants.len();
+        self.variants.push(CVariant {
+            tag,
+            c_repr_of: c_repr_of_variant::<T, C, U>,
+            as_rust: as_rust_variant::<T, C, U>,
+            drop: drop_variant::<C, U>,
+        });
+        self.by_type.insert(TypeId::of::<T>(), index);
+        self.by_tag.insert(tag, index);
+        self
+    }
+
+    /// The tag of the type `T`, `None` if it is not registered.
+    pub fn tag_of<T: Any>(&self) -> Option<u32> {
+        self.by_type
+            .get(&TypeId::of::<T>())
+            .map(|&index| self.variants[index].tag)
+    }
+
+    fn variant(&self, tag: u32) -> Result<&CVariant<U>, AsRustError> {
+        self.by_tag
+            .get(&tag)
+            .map(|&index| &self.variants[index])
+            .ok_or(AsRustError::UnknownTag { tag })
+    }
+}
+
+fn c_repr_of_variant<T: Any, C: CReprOf<T>, U>(
+    input: Box<dyn Any>,
+) -> Result<MaybeUninit<U>, CReprOfError> {
+    let input = *input.downcast::<T>().expect("the type was checked");
+    let mut value = MaybeUninit::<U>::uninit();
+    // the registry checked that `C` fits in `U`, all the variants of a union start at its address
+    unsafe { ptr::write(value.as_mut_ptr() as *mut C, C::c_repr_of(input)?) };
+    Ok(value)
+}
+
+unsafe fn as_rust_variant<T: Any, C: AsRust<T>, U>(
+    value: &MaybeUninit<U>,
+) -> Result<Box<dyn AnyConvertible>, AsRustError> {
+    let value = &*(value.as_ptr() as *const C);
+    Ok(Box::new(value.as_rust()?))
+}
+
+unsafe fn drop_variant<C: CDrop, U>(value: &mut MaybeUninit<U>) -> Result<(), CDropError> {
+    let mut value = ptr::read(value.as_ptr() as *const C);
+    let result = value.do_drop();
+    // `do_drop` released what the value owns, its destructor would release it again
+    mem::forget(value);
+    result
+}
+
+/// A C tagged union : the tag tells which variant of the union `U` (registered in its
+/// [`CVariantRegistry`]) the value holds. A `CArray<CTaggedValue<U>>` represents a heterogeneous
+/// `Vec<Box<dyn AnyConvertible>>`.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::*;
+/// use std::mem::ManuallyDrop;
+/// use std::sync::OnceLock;
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// pub struct Click {
+///     pub x: i32,
+///     pub y: i32,
+/// }
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// pub struct KeyPress {
+///     pub key: String,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(Click)]
+/// pub struct CClick {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(KeyPress)]
+/// pub struct CKeyPress {
+///     key: *const libc::c_char,
+/// }
+///
+/// #[repr(C)]
+/// pub union CEvent {
+///     click: ManuallyDrop<CClick>,
+///     key_press: ManuallyDrop<CKeyPress>,
+/// }
+///
+/// impl CTaggedUnion for CEvent {
+///     fn registry() -> &'static CVariantRegistry<Self> {
+///         static REGISTRY: OnceLock<CVariantRegistry<CEvent>> = OnceLock::new();
+///         REGISTRY.get_or_init(|| {
+///             CVariantRegistry::new()
+///                 .register::<Click, CClick>(0)
+///                 .register::<KeyPress, CKeyPress>(1)
+///         })
+///     }
+/// }
+///
+/// let events: Vec<Box<dyn AnyConvertible>> = vec![
+///     Box::new(Click { x: 3, y: 4 }),
+///     Box::new(KeyPress { key: "Enter".to_string() }),
+/// ];
+/// let c_events = CArray::<CTaggedValue<CEvent>>::c_repr_of(events).unwrap();
+/// assert_eq!(c_events.get(1).unwrap().tag, 1);
+///
+/// let events: Vec<Box<dyn AnyConvertible>> = c_events.as_rust().unwrap();
+/// assert_eq!(events[0].downcast_ref(), Some(&Click { x: 3, y: 4 }));
+/// ```
+#[repr(C)]
+pub struct CTaggedValue<U: CTaggedUnion> {
+    /// The tag of the variant held by `value`
+    pub tag: u32,
+    /// The C representation of the variant, stored in the union
+    pub value: MaybeUninit<U>,
+}
+
+impl<U: CTaggedUnion> CReprOf<Box<dyn AnyConvertible>> for CTaggedValue<U> {
+    fn c_repr_of(input: Box<dyn AnyConvertible>) -> Result<Self, CReprOfError> {
+        let registry = U::registry();
+        let variant = registry
+            .by_type
+            .get(&(*input).as_any().type_id())
+            .map(|&index| &registry.variants[index])
+            .ok_or(CReprOfError::UnregisteredType {
+                type_name: (*input).type_name(),
+            })?;
+        Ok(Self {
+            tag: variant.tag,
+            value: (variant.c_repr_of)(input.into_any())?,
+        })
+    }
+}
+
+impl<U: CTaggedUnion> AsRust<Box<dyn AnyConvertible>> for CTaggedValue<U> {
+    fn as_rust(&self) -> Result<Box<dyn AnyConvertible>, AsRustError> {
+        let variant = U::registry().variant(self.tag)?;
+        unsafe { (variant.as_rust)(&self.value) }
+    }
+}
+
+impl<U: CTaggedUnion> CDrop for CTaggedValue<U> {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        if self.tag == CVariantRegistry::<U>::RELEASED_TAG {
+            return Ok(());
+        }
+        let variant = U::registry()
+            .variant(self.tag)
+            .map_err(|e| CDropError::Other(Box::new(e)))?;
+        // so that the variant is not released twice if the value is dropped again
+        self.tag = CVariantRegistry::<U>::RELEASED_TAG;
+        unsafe { (variant.drop)(&mut self.value) }
+    }
+}
+
+impl<U: CTaggedUnion> Drop for CTaggedValue<U> {
+    fn drop(&mut self) {
+        let _ = self.do_drop();
+    }
+}
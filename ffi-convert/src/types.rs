@@ -5,11 +5,16 @@ use ffi_convert_derive::RawPointerConverter;
 
 use std::any::TypeId;
 use std::ffi::{CStr, CString};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
 use std::ops::Range;
 use std::ptr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate as ffi_convert;
+use crate::budget::reserve_memory;
+use crate::context::{current_context, Utf8Policy};
 use crate::conversions::*;
+use crate::deadline::check_deadline;
 
 /// A utility type to represent arrays of string
 /// # Example
@@ -29,35 +34,164 @@ pub struct CStringArray {
     pub size: usize,
 }
 
-unsafe impl Sync for CStringArray {}
+impl CStringArray {
+    /// Creates an array from the strings (or bytes) yielded by an iterator, without collecting
+    /// them in a `Vec<String>` first.
+    ///
+    /// An error is returned if one of the strings contains a nul byte, in which case the strings
+    /// already converted are freed.
+    ///
+    /// ```
+    /// use ffi_convert::CStringArray;
+    ///
+    /// let array = CStringArray::from_iter(["Diavola", "Regina"]).unwrap();
+    ///
+    /// assert_eq!(array.size, 2);
+    /// assert_eq!(array.get(1).unwrap().to_str().unwrap(), "Regina");
+    /// ```
+    #[allow(clippy::should_implement_trait)] // the conversion can fail, unlike `FromIterator`
+    pub fn from_iter<I>(iter: I) -> Result<Self, CReprOfError>
+    where
+        I: IntoIterator,
+        I::Item: Into<Vec<u8>>,
+    {
+        let strings = iter
+            .into_iter()
+            .enumerate()
+            .map(|(index, s)| {
+                check_deadline()?;
+                let bytes = s.into();
+                reserve_memory(std::mem::size_of::<*const libc::c_char>() + bytes.len() + 1)
+                    .and_then(|()| Ok(CString::new(bytes)?))
+                    .map_err(|e| e.in_element(index))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            size: strings.len(),
+            data: Box::into_raw(
+                strings
+                    .into_iter()
+                    .map(|s| s.into_raw_pointer())
+                    .collect::<Vec<*const libc::c_char>>()
+                    .into_boxed_slice(),
+            ) as *const *const libc::c_char,
+        })
+    }
+
+    /// Returns a borrowed reference to the string at the given index, without copying it.
+    ///
+    /// An error is returned if the index is out of bounds or if the array or the string is a
+    /// `null` pointer.
+    ///
+    /// ```
+    /// use ffi_convert::{CReprOf, CStringArray};
+    ///
+    /// let array = CStringArray::c_repr_of(vec!["Diavola".to_string()]).unwrap();
+    ///
+    /// assert_eq!(array.get(0).unwrap().to_str().unwrap(), "Diavola");
+    /// assert!(array.get(1).is_err());
+    /// ```
+    pub fn get(&self, index: usize) -> Result<&CStr, AsRustError> {
+        let string = self
+            .as_slice()?
+            .get(index)
+            .ok_or(AsRustError::IndexOutOfBounds {
+                index,
+                size: self.size,
+            })?;
+        Ok(unsafe { CStr::raw_borrow(*string) }?)
+    }
+
+    /// Returns a borrowed slice of the pointers to the strings of the array.
+    ///
+    /// An error is returned if the array is not empty and its data pointer is `null`.
+    pub fn as_slice(&self) -> Result<&[*const libc::c_char], AsRustError> {
+        string_pointers(self.data, self.size)
+    }
+
+    /// Returns an iterator over borrowed references to the strings of the array, see
+    /// [`CStringArray::get`].
+    ///
+    /// ```
+    /// use ffi_convert::{CReprOf, CStringArray};
+    ///
+    /// let array = CStringArray::c_repr_of(vec!["Diavola".to_string(), "Regina".to_string()]).unwrap();
+    /// let lengths = array
+    ///     .iter()
+    ///     .map(|s| s.map(|s| s.to_bytes().len()))
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(lengths, vec![7, 6]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = Result<&CStr, AsRustError>> + '_ {
+        (0..self.size).map(move |index| self.get(index))
+    }
+}
+
+/// The pointers to the strings of an array, borrowed without casting its data pointer to a mutable
+/// one.
+fn string_pointers<'a>(
+    data: *const *const libc::c_char,
+    size: usize,
+) -> Result<&'a [*const libc::c_char], AsRustError> {
+    if size == 0 {
+        Ok(&[])
+    } else if data.is_null() {
+        Err(UnexpectedNullPointerError.into())
+    } else {
+        Ok(unsafe { std::slice::from_raw_parts(data, size) })
+    }
+}
 
 impl AsRust<Vec<String>> for CStringArray {
     fn as_rust(&self) -> Result<Vec<String>, AsRustError> {
-        let mut result = vec![];
-
-        let strings = unsafe {
-            std::slice::from_raw_parts_mut(self.data as *mut *mut libc::c_char, self.size)
-        };
+        self.iter()
+            .enumerate()
+            .map(|(index, s)| {
+                check_deadline()?;
+                s?.as_rust().map_err(|e| e.in_element(index))
+            })
+            .collect()
+    }
+}
 
-        for s in strings {
-            result.push(unsafe { CStr::raw_borrow(*s) }?.as_rust()?)
-        }
+impl<'a> AsRustView<'a, Vec<&'a str>> for CStringArray {
+    fn as_rust_view(&'a self) -> Result<Vec<&'a str>, AsRustError> {
+        self.iter()
+            .enumerate()
+            .map(|(index, s)| s?.as_rust_view().map_err(|e| e.in_element(index)))
+            .collect()
+    }
+}
 
-        Ok(result)
+impl CValidate for CStringArray {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        self.iter().try_for_each(|s| s?.validate_c())
     }
 }
 
 impl CReprOf<Vec<String>> for CStringArray {
     fn c_repr_of(input: Vec<String>) -> Result<Self, CReprOfError> {
+        reserve_memory(input.len() * std::mem::size_of::<*const libc::c_char>())?;
+        // the strings are only turned into raw pointers once all of them are converted, so that
+        // they are freed if one of them fails
+        let strings = input
+            .into_iter()
+            .enumerate()
+            .map(|(index, s)| {
+                check_deadline()?;
+                CString::c_repr_of(s).map_err(|e| e.in_element(index))
+            })
+            .collect::<Result<Vec<_>, CReprOfError>>()?;
         Ok(Self {
-            size: input.len(),
+            size: strings.len(),
             data: Box::into_raw(
-                input
+                strings
                     .into_iter()
-                    .map::<Result<*const libc::c_char, CReprOfError>, _>(|s| {
-                        Ok(CString::c_repr_of(s)?.into_raw_pointer())
-                    })
-                    .collect::<Result<Vec<_>, _>>()?
+                    .map(|s| s.into_raw_pointer())
+                    .collect::<Vec<*const libc::c_char>>()
                     .into_boxed_slice(),
             ) as *const *const libc::c_char,
         })
@@ -66,10 +200,17 @@ impl CReprOf<Vec<String>> for CStringArray {
 
 impl CDrop for CStringArray {
     fn do_drop(&mut self) -> Result<(), CDropError> {
+        if self.data.is_null() {
+            return Ok(());
+        }
+        // the array is emptied first, so that it is not freed twice if dropped again
+        let data = std::mem::replace(&mut self.data, ptr::null());
+        let size = std::mem::take(&mut self.size);
         unsafe {
-            let y = Box::from_raw(std::slice::from_raw_parts_mut(
-                self.data as *mut *mut libc::c_char,
-                self.size,
+            // the array was allocated by `c_repr_of`, which gave up its ownership
+            let y = Box::from_raw(ptr::slice_from_raw_parts_mut(
+                data as *mut *const libc::c_char,
+                size,
             ));
             for p in y.iter() {
                 let _ = CString::from_raw_pointer(*p)?; // let's not panic if we fail here
@@ -85,9 +226,141 @@ impl Drop for CStringArray {
     }
 }
 
+/// An array of strings whose size is a C `int`, for the binding layers expecting this layout
+/// (JNA, or cgo which can't map `size_t` fields), otherwise the same as [`CStringArray`].
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, CReprOf, CStringArrayInt};
+///
+/// let pizza_names = vec!["Diavola".to_string(), "Regina".to_string()];
+/// let c_pizza_names = CStringArrayInt::c_repr_of(pizza_names.clone()).unwrap();
+///
+/// assert_eq!(c_pizza_names.size, 2);
+/// assert_eq!(c_pizza_names.as_rust().unwrap(), pizza_names);
+/// ```
+#[repr(C)]
+#[derive(Debug, RawPointerConverter)]
+pub struct CStringArrayInt {
+    /// Pointer to the first element of the array
+    pub data: *const *const libc::c_char,
+    /// Number of elements in the array
+    pub size: libc::c_int,
+}
+
+impl CStringArrayInt {
+    /// Returns a borrowed reference to the string at the given index, see [`CStringArray::get`].
+    ///
+    /// An error is also returned if the size of the array is negative.
+    pub fn get(&self, index: usize) -> Result<&CStr, AsRustError> {
+        let string = self
+            .as_slice()?
+            .get(index)
+            .ok_or(AsRustError::IndexOutOfBounds {
+                index,
+                size: self.size as usize,
+            })?;
+        Ok(unsafe { CStr::raw_borrow(*string) }?)
+    }
+
+    /// Returns a borrowed slice of the pointers to the strings of the array, see
+    /// [`CStringArray::as_slice`].
+    ///
+    /// An error is also returned if the size of the array is negative.
+    pub fn as_slice(&self) -> Result<&[*const libc::c_char], AsRustError> {
+        string_pointers(self.data, usize::try_from(self.size)?)
+    }
+
+    /// Returns an iterator over borrowed references to the strings of the array, see
+    /// [`CStringArrayInt::get`].
+    ///
+    /// The same errors as [`CStringArrayInt::as_slice`] are returned before iterating, such as for
+    /// a negative size.
+    pub fn iter(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<&CStr, AsRustError>> + '_, AsRustError> {
+        Ok(self
+            .as_slice()?
+            .iter()
+            .map(|string| Ok(unsafe { CStr::raw_borrow(*string) }?)))
+    }
+}
+
+impl AsRust<Vec<String>> for CStringArrayInt {
+    fn as_rust(&self) -> Result<Vec<String>, AsRustError> {
+        self.iter()?
+            .enumerate()
+            .map(|(index, s)| {
+                check_deadline()?;
+                s?.as_rust().map_err(|e| e.in_element(index))
+            })
+            .collect()
+    }
+}
+
+impl<'a> AsRustView<'a, Vec<&'a str>> for CStringArrayInt {
+    fn as_rust_view(&'a self) -> Result<Vec<&'a str>, AsRustError> {
+        self.iter()?
+            .enumerate()
+            .map(|(index, s)| s?.as_rust_view().map_err(|e| e.in_element(index)))
+            .collect()
+    }
+}
+
+impl CValidate for CStringArrayInt {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        self.iter()?.try_for_each(|s| s?.validate_c())
+    }
+}
+
+impl CReprOf<Vec<String>> for CStringArrayInt {
+    fn c_repr_of(input: Vec<String>) -> Result<Self, CReprOfError> {
+        let size = libc::c_int::try_from(input.len())?;
+        // the strings are now owned by this array, freed by its `do_drop`
+        let array = std::mem::ManuallyDrop::new(CStringArray::c_repr_of(input)?);
+        Ok(Self {
+            data: array.data,
+            size,
+        })
+    }
+}
+
+impl CDrop for CStringArrayInt {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        if self.data.is_null() {
+            return Ok(());
+        }
+        let size = usize::try_from(self.size).map_err(|e| CDropError::Other(Box::new(e)))?;
+        let mut array = CStringArray {
+            data: std::mem::replace(&mut self.data, ptr::null()),
+            size,
+        };
+        self.size = 0;
+        array.do_drop()
+    }
+}
+
+impl Drop for CStringArrayInt {
+    fn drop(&mut self) {
+        let _ = self.do_drop();
+    }
+}
+
 /// A utility type to represent arrays of the parametrized type.
 /// Note that the parametrized type should have a C-compatible representation.
 ///
+/// Arrays can be nested, a `Vec<Vec<f32>>` is represented by a `CArray<CArray<f32>>` (and a
+/// `Vec<Vec<String>>` by a `CArray<CStringArray>`).
+///
+/// Arrays of numbers (and of `bool`) whose C and Rust types are the same are converted without
+/// converting each element (see [`CReprOf::c_repr_of_vec`] and [`AsRust::as_rust_vec`]) :
+/// `c_repr_of` reuses the buffer of the `Vec` (which is only reallocated if its capacity is larger
+/// than its length) and `as_rust` copies the elements at once.
+///
+/// When the conversion of an element fails, `c_repr_of` returns the error (with the index of the
+/// element) and releases the elements that were already converted.
+///
 /// # Example
 ///
 /// ```
@@ -98,6 +371,7 @@ impl Drop for CStringArray {
 ///     pub ingredient: String,
 /// }
 ///
+/// #[repr(C)]
 /// #[derive(CDrop, CReprOf, AsRust)]
 /// #[target_type(PizzaTopping)]
 /// pub struct CPizzaTopping {
@@ -120,54 +394,215 @@ pub struct CArray<T> {
     pub size: usize,
 }
 
-impl<U: AsRust<V> + 'static, V> AsRust<Vec<V>> for CArray<U> {
-    fn as_rust(&self) -> Result<Vec<V>, AsRustError> {
-        let mut vec = Vec::with_capacity(self.size);
+impl<T> CArray<T> {
+    /// Creates an array from the C representations of the values yielded by an iterator, without
+    /// collecting them in a `Vec` first.
+    ///
+    /// An error is returned if one of the values can't be converted, in which case the elements
+    /// already converted are dropped.
+    ///
+    /// ```
+    /// use ffi_convert::CArray;
+    ///
+    /// let array = CArray::<i32>::from_iter((0..4).map(|i| i * i)).unwrap();
+    ///
+    /// assert_eq!(array.as_slice().unwrap(), &[0, 1, 4, 9]);
+    /// ```
+    #[allow(clippy::should_implement_trait)] // the conversion can fail, unlike `FromIterator`
+    pub fn from_iter<I, V>(iter: I) -> Result<Self, CReprOfError>
+    where
+        I: IntoIterator<Item = V>,
+        T: CReprOf<V>,
+    {
+        Ok(Self::from(
+            iter.into_iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    check_deadline()?;
+                    reserve_memory(std::mem::size_of::<T>())
+                        .and_then(|()| T::c_repr_of(value))
+                        .map_err(|e| e.in_element(index))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        ))
+    }
 
-        if self.size > 0 {
-            let values =
-                unsafe { std::slice::from_raw_parts_mut(self.data_ptr as *mut U, self.size) };
+    /// Returns a borrowed slice of the elements of the array, without copying them.
+    ///
+    /// An error is returned if the array is not empty and its data pointer is `null`, or is not
+    /// aligned for `T` (which can happen with over-aligned types, e.g. `#[repr(C, align(32))]`
+    /// structs, allocated by C code with `malloc`).
+    pub fn as_slice(&self) -> Result<&[T], AsRustError> {
+        if self.size == 0 {
+            Ok(&[])
+        } else if self.data_ptr.is_null() {
+            Err(UnexpectedNullPointerError.into())
+        } else if !self.data_ptr.is_aligned() {
+            Err(AsRustError::MisalignedPointer {
+                alignment: std::mem::align_of::<T>(),
+            })
+        } else {
+            Ok(unsafe { std::slice::from_raw_parts(self.data_ptr, self.size) })
+        }
+    }
 
-            if is_primitive(TypeId::of::<U>()) {
-                unsafe {
-                    ptr::copy(values.as_ptr() as *const V, vec.as_mut_ptr(), self.size);
-                    vec.set_len(self.size);
-                }
-            } else {
-                for value in values {
-                    vec.push(value.as_rust()?);
-                }
-            }
+    /// Returns a mutable slice of the elements of the array, to update them in place.
+    ///
+    /// The same errors as [`CArray::as_slice`] are returned. The elements must be writable : the
+    /// array must have been created by this crate (or its buffer allocated by C code), not point
+    /// to constant data.
+    ///
+    /// ```
+    /// use ffi_convert::{CArray, CReprOf};
+    ///
+    /// let mut array = CArray::<i32>::c_repr_of(vec![1, 2, 3]).unwrap();
+    /// array.as_mut_slice().unwrap()[1] = 4;
+    ///
+    /// assert_eq!(array.as_slice().unwrap(), &[1, 4, 3]);
+    /// ```
+    pub fn as_mut_slice(&mut self) -> Result<&mut [T], AsRustError> {
+        // the checks of `as_slice` apply to the same pointer
+        self.as_slice()?;
+        if self.size == 0 {
+            Ok(&mut [])
+        } else {
+            // the array owns its elements, borrowing it mutably makes this slice the only access
+            Ok(unsafe { std::slice::from_raw_parts_mut(self.data_ptr as *mut T, self.size) })
+        }
+    }
+
+    /// Returns a borrowed reference to the element at the given index.
+    ///
+    /// An error is returned if the index is out of bounds or if the data pointer is `null`.
+    ///
+    /// ```
+    /// use ffi_convert::{CArray, CReprOf};
+    ///
+    /// let array = CArray::<i32>::c_repr_of(vec![4, 2]).unwrap();
+    ///
+    /// assert_eq!(*array.get(1).unwrap(), 2);
+    /// assert!(array.get(2).is_err());
+    /// ```
+    pub fn get(&self, index: usize) -> Result<&T, AsRustError> {
+        self.as_slice()?
+            .get(index)
+            .ok_or(AsRustError::IndexOutOfBounds {
+                index,
+                size: self.size,
+            })
+    }
+
+    /// Returns an iterator over borrowed references to the elements of the array, see
+    /// [`CArray::as_slice`].
+    pub fn iter(&self) -> Result<std::slice::Iter<'_, T>, AsRustError> {
+        Ok(self.as_slice()?.iter())
+    }
+
+    /// Shortens the array, keeping its first `len` elements and dropping the others. Does nothing
+    /// if the array has `len` elements or less.
+    ///
+    /// The elements are moved to a new allocation (the size of the allocation of an array can't
+    /// change), so `data_ptr` changes : C code must not keep the data pointer, or pointers to the
+    /// elements, across this call. The array must have been created by this crate, as for
+    /// [`CDrop`].
+    ///
+    /// ```
+    /// use ffi_convert::{CArray, CReprOf};
+    ///
+    /// let mut array = CArray::<i32>::c_repr_of(vec![1, 2, 3, 4]).unwrap();
+    /// array.truncate(2);
+    ///
+    /// assert_eq!(array.as_slice().unwrap(), &[1, 2]);
+    /// ```
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.size {
+            self.update(|elements| elements.truncate(len));
         }
-        Ok(vec)
+    }
+
+    /// Keeps only the elements for which `keep` returns `true`, dropping the others, in place of
+    /// rebuilding the array (and swapping the pointers to it) from Rust values.
+    ///
+    /// As with [`CArray::truncate`], the elements are moved to a new allocation : C code must not
+    /// keep the data pointer, or pointers to the elements, across this call.
+    ///
+    /// ```
+    /// use ffi_convert::{CArray, CReprOf};
+    ///
+    /// let mut array = CArray::<i32>::c_repr_of(vec![1, 2, 3, 4]).unwrap();
+    /// array.retain(|value| value % 2 == 0);
+    ///
+    /// assert_eq!(array.as_slice().unwrap(), &[2, 4]);
+    /// ```
+    pub fn retain(&mut self, keep: impl FnMut(&T) -> bool) {
+        self.update(|elements| elements.retain(keep));
+    }
+
+    /// Takes the elements of the array back in a `Vec`, to update them with `f` and move them
+    /// to a new allocation of the right size.
+    fn update(&mut self, f: impl FnOnce(&mut Vec<T>)) {
+        // the array is left empty while the elements are updated, in case `f` panics
+        let data_ptr = std::mem::replace(&mut self.data_ptr, ptr::null());
+        let size = std::mem::take(&mut self.size);
+        let mut elements = if data_ptr.is_null() {
+            vec![]
+        } else {
+            unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(data_ptr as *mut T, size)) }
+                .into_vec()
+        };
+        f(&mut elements);
+        *self = Self::from(elements);
     }
 }
 
-impl<U: CReprOf<V> + CDrop, V: 'static> CReprOf<Vec<V>> for CArray<U> {
-    fn c_repr_of(input: Vec<V>) -> Result<Self, CReprOfError> {
-        let input_size = input.len();
-        let mut output: CArray<U> = CArray {
+impl<T> Default for CArray<T> {
+    /// Creates an empty array, with a `null` data pointer.
+    fn default() -> Self {
+        Self {
             data_ptr: ptr::null(),
-            size: input_size,
-        };
+            size: 0,
+        }
+    }
+}
 
-        if input_size > 0 {
-            if is_primitive(TypeId::of::<V>()) {
-                output.data_ptr = Box::into_raw(input.into_boxed_slice()) as *const U;
-            } else {
-                output.data_ptr = Box::into_raw(
-                    input
-                        .into_iter()
-                        .map(U::c_repr_of)
-                        .collect::<Result<Vec<_>, CReprOfError>>()
-                        .expect("Could not convert to C representation")
-                        .into_boxed_slice(),
-                ) as *const U;
+impl<T> From<Vec<T>> for CArray<T> {
+    /// Creates an array owning the given (already converted) elements.
+    fn from(input: Vec<T>) -> Self {
+        if input.is_empty() {
+            Self {
+                data_ptr: ptr::null(),
+                size: 0,
             }
         } else {
-            output.data_ptr = ptr::null();
+            Self {
+                size: input.len(),
+                data_ptr: Box::into_raw(input.into_boxed_slice()) as *const T,
+            }
         }
-        Ok(output)
+    }
+}
+
+impl<'a, T> AsRustView<'a, &'a [T]> for CArray<T> {
+    fn as_rust_view(&'a self) -> Result<&'a [T], AsRustError> {
+        self.as_slice()
+    }
+}
+
+impl<U: AsRust<V>, V> AsRust<Vec<V>> for CArray<U> {
+    fn as_rust(&self) -> Result<Vec<V>, AsRustError> {
+        U::as_rust_vec(self.as_slice()?)
+    }
+}
+
+impl<T: CValidate> CValidate for CArray<T> {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        self.as_slice()?.iter().try_for_each(T::validate_c)
+    }
+}
+
+impl<U: CReprOf<V> + CDrop, V> CReprOf<Vec<V>> for CArray<U> {
+    fn c_repr_of(input: Vec<V>) -> Result<Self, CReprOfError> {
+        Ok(Self::from(U::c_repr_of_vec(input)?))
     }
 }
 
@@ -175,11 +610,14 @@ impl<T> CDrop for CArray<T> {
     fn do_drop(&mut self) -> Result<(), CDropError> {
         if !self.data_ptr.is_null() {
             let _ = unsafe {
-                Box::from_raw(std::slice::from_raw_parts_mut(
+                Box::from_raw(ptr::slice_from_raw_parts_mut(
                     self.data_ptr as *mut T,
                     self.size,
                 ))
             };
+            // so that the elements are not freed twice if the array is dropped again
+            self.data_ptr = ptr::null();
+            self.size = 0;
         }
         Ok(())
     }
@@ -213,6 +651,281 @@ impl<T> RawPointerConverter<CArray<T>> for CArray<T> {
     }
 }
 
+/// An array of bytes, which can represent a `String` by its UTF-8 bytes instead of a
+/// nul-terminated C string (for code working on the encoded text, or for strings that can
+/// contain nul characters).
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, CByteArray, CReprOf};
+///
+/// let bytes = CByteArray::c_repr_of("crêpe\0suzette".to_string()).unwrap();
+/// assert_eq!(bytes.size, 14);
+///
+/// let text: String = bytes.as_rust().unwrap();
+/// assert_eq!(text, "crêpe\0suzette");
+/// ```
+pub type CByteArray = CArray<u8>;
+
+impl CReprOf<String> for CArray<u8> {
+    fn c_repr_of(input: String) -> Result<Self, CReprOfError> {
+        Self::c_repr_of(input.into_bytes())
+    }
+}
+
+impl AsRust<String> for CArray<u8> {
+    fn as_rust(&self) -> Result<String, AsRustError> {
+        let bytes = self.as_slice()?;
+        match current_context().utf8_policy {
+            Utf8Policy::Strict => Ok(std::str::from_utf8(bytes)?.to_owned()),
+            Utf8Policy::Lossy => Ok(String::from_utf8_lossy(bytes).into_owned()),
+        }
+    }
+}
+
+impl<'a> AsRustView<'a, &'a str> for CArray<u8> {
+    fn as_rust_view(&'a self) -> Result<&'a str, AsRustError> {
+        Ok(std::str::from_utf8(self.as_slice()?)?)
+    }
+}
+
+/// A utility type to represent a string stored inline in a fixed size buffer (`char name[N]` on
+/// the C side), as used by many embedded C APIs instead of a pointer to a heap allocated string.
+///
+/// The strings converted from Rust are always nul-terminated, so they can hold at most `N - 1`
+/// bytes (a buffer filled by C code without a nul terminator is read entirely). A string that
+/// doesn't fit in the buffer fails to be converted with
+/// [`CReprOfError::StringTooLong`], or is truncated on a character boundary if the
+/// `truncate_strings` option of the current [`ConversionContext`](crate::ConversionContext) is
+/// set.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, CDrop, CFixedString, CReprOf};
+///
+/// pub struct Device {
+///     pub name: String,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(Device)]
+/// pub struct CDevice {
+///     pub name: CFixedString<16>,
+/// }
+///
+/// let c_device = CDevice::c_repr_of(Device { name: "sensor-0".to_string() }).unwrap();
+/// assert_eq!(c_device.name.to_bytes(), b"sensor-0");
+///
+/// let device = c_device.as_rust().unwrap();
+/// assert_eq!(device.name, "sensor-0");
+///
+/// assert!(CDevice::c_repr_of(Device { name: "a sensor with a long name".to_string() }).is_err());
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, RawPointerConverter)]
+pub struct CFixedString<const N: usize>(pub [libc::c_char; N]);
+
+impl<const N: usize> CFixedString<N> {
+    /// Returns the bytes of the string, without its nul terminator.
+    pub fn to_bytes(&self) -> &[u8] {
+        let bytes = unsafe { std::slice::from_raw_parts(self.0.as_ptr() as *const u8, N) };
+        let length = bytes.iter().position(|byte| *byte == 0).unwrap_or(N);
+        &bytes[..length]
+    }
+}
+
+impl<const N: usize> Default for CFixedString<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> CReprOf<String> for CFixedString<N> {
+    fn c_repr_of(input: String) -> Result<Self, CReprOfError> {
+        let context = current_context();
+        let input = if context.replace_nul_characters && input.contains('\0') {
+            input.replace('\0', "\u{FFFD}")
+        } else {
+            input
+        };
+        if input.contains('\0') {
+            return Err(CString::new(input).unwrap_err().into());
+        }
+
+        // room is left for the nul terminator
+        let capacity = N.saturating_sub(1);
+        let mut length = input.len();
+        if length > capacity {
+            if !context.truncate_strings {
+                return Err(CReprOfError::StringTooLong { length, capacity });
+            }
+            length = capacity;
+            while !input.is_char_boundary(length) {
+                length -= 1;
+            }
+        }
+
+        let mut output = Self::default();
+        for (c_char, byte) in output.0.iter_mut().zip(&input.as_bytes()[..length]) {
+            *c_char = *byte as libc::c_char;
+        }
+        Ok(output)
+    }
+}
+
+impl<const N: usize> AsRust<String> for CFixedString<N> {
+    fn as_rust(&self) -> Result<String, AsRustError> {
+        let bytes = self.to_bytes();
+        match current_context().utf8_policy {
+            Utf8Policy::Strict => Ok(std::str::from_utf8(bytes)?.to_owned()),
+            Utf8Policy::Lossy => Ok(String::from_utf8_lossy(bytes).into_owned()),
+        }
+    }
+}
+
+impl<const N: usize> CDrop for CFixedString<N> {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        Ok(())
+    }
+}
+
+impl<const N: usize> CValidate for CFixedString<N> {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        std::str::from_utf8(self.to_bytes())?;
+        Ok(())
+    }
+}
+
+/// A utility type to represent a borrowed, read-only view on an array of the parametrized type.
+///
+/// Contrary to [`CArray`], this type doesn't own the memory it points to : it is created from a
+/// borrowed slice (`&[T]`) without copying it, and nothing is freed when it is dropped. It is up
+/// to the user to make sure the slice outlives the view. This makes it suited to large read-only
+/// buffers (audio samples, tensors) passed to C code which doesn't take their ownership.
+///
+/// Its [`CDrop`] implementation does nothing, it only exists so that views can be fields of
+/// structs deriving `CDrop`.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRustView, CDrop, CReprOf, CSliceView};
+///
+/// pub struct Samples<'a> {
+///     pub values: &'a [f32],
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CDrop, CReprOf)]
+/// #[target_type(Samples<'a>)]
+/// pub struct CSamples {
+///     pub values: CSliceView<f32>,
+/// }
+///
+/// let values = vec![1.0, 2.0, 3.0];
+/// let c_samples = CSamples::c_repr_of(Samples { values: &values }).unwrap();
+///
+/// let values_view: &[f32] = c_samples.values.as_rust_view().unwrap();
+/// assert_eq!(values_view, &[1.0, 2.0, 3.0]);
+/// ```
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CSliceView<T> {
+    /// Pointer to the first element of the array
+    pub data_ptr: *const T,
+    /// Number of elements in the array
+    pub size: usize,
+}
+
+impl<'a, T> AsRustView<'a, &'a [T]> for CSliceView<T> {
+    fn as_rust_view(&'a self) -> Result<&'a [T], AsRustError> {
+        if self.size == 0 {
+            Ok(&[])
+        } else if self.data_ptr.is_null() {
+            Err(UnexpectedNullPointerError.into())
+        } else if !self.data_ptr.is_aligned() {
+            Err(AsRustError::MisalignedPointer {
+                alignment: std::mem::align_of::<T>(),
+            })
+        } else {
+            Ok(unsafe { std::slice::from_raw_parts(self.data_ptr, self.size) })
+        }
+    }
+}
+
+impl<U: AsRust<V>, V> AsRust<Vec<V>> for CSliceView<U> {
+    fn as_rust(&self) -> Result<Vec<V>, AsRustError> {
+        let values: &[U] = self.as_rust_view()?;
+        values.iter().map(U::as_rust).collect()
+    }
+}
+
+impl<T: CValidate> CValidate for CSliceView<T> {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        let values: &[T] = self.as_rust_view()?;
+        values.iter().try_for_each(T::validate_c)
+    }
+}
+
+impl<'a, T> From<&'a [T]> for CSliceView<T> {
+    /// Creates a view on the given slice, which must outlive it.
+    fn from(input: &'a [T]) -> Self {
+        Self {
+            data_ptr: if input.is_empty() {
+                ptr::null()
+            } else {
+                input.as_ptr()
+            },
+            size: input.len(),
+        }
+    }
+}
+
+impl<'a, T> CReprOf<&'a [T]> for CSliceView<T> {
+    fn c_repr_of(input: &'a [T]) -> Result<Self, CReprOfError> {
+        Ok(Self::from(input))
+    }
+}
+
+impl<T> CDrop for CSliceView<T> {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        // the view doesn't own the memory it points to
+        Ok(())
+    }
+}
+
+impl<T> RawPointerConverter<CSliceView<T>> for CSliceView<T> {
+    fn into_raw_pointer(self) -> *const CSliceView<T> {
+        convert_into_raw_pointer(self)
+    }
+
+    fn into_raw_pointer_mut(self) -> *mut CSliceView<T> {
+        convert_into_raw_pointer_mut(self)
+    }
+
+    unsafe fn from_raw_pointer(
+        input: *const CSliceView<T>,
+    ) -> Result<Self, UnexpectedNullPointerError> {
+        take_back_from_raw_pointer(input)
+    }
+
+    unsafe fn from_raw_pointer_mut(
+        input: *mut CSliceView<T>,
+    ) -> Result<Self, UnexpectedNullPointerError> {
+        take_back_from_raw_pointer_mut(input)
+    }
+}
+
+/// Whether the C and Rust types are the same primitive type, in which case arrays can be converted
+/// by copying (or reusing) their memory.
+pub(crate) fn is_same_primitive<U: 'static, V: 'static>() -> bool {
+    let id = TypeId::of::<U>();
+    id == TypeId::of::<V>() && is_primitive(id)
+}
+
 fn is_primitive(id: TypeId) -> bool {
     id == TypeId::of::<u8>()
         || id == TypeId::of::<i8>()
@@ -220,8 +933,12 @@ fn is_primitive(id: TypeId) -> bool {
         || id == TypeId::of::<i16>()
         || id == TypeId::of::<u32>()
         || id == TypeId::of::<i32>()
+        || id == TypeId::of::<u64>()
+        || id == TypeId::of::<i64>()
+        || id == TypeId::of::<usize>()
         || id == TypeId::of::<f32>()
         || id == TypeId::of::<f64>()
+        || id == TypeId::of::<bool>()
 }
 
 /// A utility type to represent range.
@@ -238,6 +955,7 @@ fn is_primitive(id: TypeId) -> bool {
 ///     pub range: Range<i32>
 /// }
 ///
+/// #[repr(C)]
 /// #[derive(AsRust, CDrop, CReprOf, Debug, PartialEq)]
 /// #[target_type(Foo)]
 /// pub struct CFoo {
@@ -265,7 +983,7 @@ fn is_primitive(id: TypeId) -> bool {
 /// assert_eq!(foo_converted, foo);
 /// ```
 #[repr(C)]
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, RawPointerConverter)]
 pub struct CRange<T> {
     pub start: T,
     pub end: T,
@@ -280,6 +998,13 @@ impl<U: AsRust<V>, V: PartialOrd + PartialEq> AsRust<Range<V>> for CRange<U> {
     }
 }
 
+impl<T: CValidate> CValidate for CRange<T> {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        self.start.validate_c()?;
+        self.end.validate_c()
+    }
+}
+
 impl<U: CReprOf<V> + CDrop, V: PartialOrd + PartialEq> CReprOf<Range<V>> for CRange<U> {
     fn c_repr_of(input: Range<V>) -> Result<Self, CReprOfError> {
         Ok(Self {
@@ -300,3 +1025,663 @@ impl<T> Drop for CRange<T> {
         let _ = self.do_drop();
     }
 }
+
+/// A utility type to represent a `Range<usize>` by its start and its length, as many C APIs do,
+/// instead of its start and its end like [`CRange`].
+/// The parametrized type T is the integer type used on the C side (`usize` by default), and
+/// should be convertible to and from `usize`.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{CReprOf, AsRust, CDrop, CSpan};
+/// use std::ops::Range;
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// pub struct Selection {
+///     pub bytes: Range<usize>,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(AsRust, CDrop, CReprOf)]
+/// #[target_type(Selection)]
+/// pub struct CSelection {
+///     pub bytes: CSpan<u32>,
+/// }
+///
+/// let c_selection = CSelection::c_repr_of(Selection { bytes: 20..30 }).unwrap();
+/// assert_eq!(c_selection.bytes, CSpan { start: 20, length: 10 });
+///
+/// assert_eq!(c_selection.as_rust().unwrap(), Selection { bytes: 20..30 });
+/// ```
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq, Eq, RawPointerConverter)]
+pub struct CSpan<T = usize> {
+    pub start: T,
+    pub length: T,
+}
+
+impl<T: AsRust<usize>> AsRust<Range<usize>> for CSpan<T> {
+    fn as_rust(&self) -> Result<Range<usize>, AsRustError> {
+        let start = self.start.as_rust()?;
+        let end = start
+            .checked_add(self.length.as_rust()?)
+            .ok_or_else(|| AsRustError::Other("the end of the span overflows".into()))?;
+        Ok(start..end)
+    }
+}
+
+impl<T: AsRust<usize>> CValidate for CSpan<T> {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        AsRust::<Range<usize>>::as_rust(self).map(|_| ())
+    }
+}
+
+impl<T: CReprOf<usize>> CReprOf<Range<usize>> for CSpan<T> {
+    fn c_repr_of(input: Range<usize>) -> Result<Self, CReprOfError> {
+        let length = input.end.checked_sub(input.start).ok_or_else(|| {
+            CReprOfError::Other("the end of the range is before its start".into())
+        })?;
+        Ok(Self {
+            start: T::c_repr_of(input.start)?,
+            length: T::c_repr_of(length)?,
+        })
+    }
+}
+
+impl<T> CDrop for CSpan<T> {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        Ok(())
+    }
+}
+
+impl<T> Drop for CSpan<T> {
+    fn drop(&mut self) {
+        let _ = self.do_drop();
+    }
+}
+
+/// A utility type to represent an optional value inline, without going through a nullable pointer.
+/// Note that the parametrized type T should have `CReprOf`, `AsRust` and `Default` implemented,
+/// the default value being used to fill the `value` field when there is no value.
+///
+/// This is mostly useful for optional scalars, where using `#[nullable]` would require an
+/// allocation.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{CReprOf, AsRust, CDrop, COption};
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// pub struct Foo {
+///     pub weight: Option<f32>
+/// }
+///
+/// #[repr(C)]
+/// #[derive(AsRust, CDrop, CReprOf, Debug, PartialEq)]
+/// #[target_type(Foo)]
+/// pub struct CFoo {
+///     pub weight: COption<f32>
+/// }
+///
+/// let foo = Foo { weight: Some(4.2) };
+///
+/// let c_foo = CFoo::c_repr_of(foo.clone()).unwrap();
+/// assert_eq!(c_foo.weight, COption { is_some: 1, value: 4.2 });
+///
+/// let foo_converted = c_foo.as_rust().unwrap();
+/// assert_eq!(foo_converted, foo);
+/// ```
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq, Eq, RawPointerConverter)]
+pub struct COption<T> {
+    /// `1` if `value` holds a value, `0` otherwise
+    pub is_some: u8,
+    /// The value, only meaningful if `is_some` is not `0`
+    pub value: T,
+}
+
+impl<U: AsRust<V>, V> AsRust<Option<V>> for COption<U> {
+    fn as_rust(&self) -> Result<Option<V>, AsRustError> {
+        if self.is_some != 0 {
+            Ok(Some(self.value.as_rust()?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<T: CValidate> CValidate for COption<T> {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        if self.is_some != 0 {
+            self.value.validate_c()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<U: CReprOf<V> + CDrop + Default, V> CReprOf<Option<V>> for COption<U> {
+    fn c_repr_of(input: Option<V>) -> Result<Self, CReprOfError> {
+        Ok(match input {
+            Some(value) => Self {
+                is_some: 1,
+                value: U::c_repr_of(value)?,
+            },
+            None => Self {
+                is_some: 0,
+                value: U::default(),
+            },
+        })
+    }
+}
+
+impl<T: CDrop> CDrop for COption<T> {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        if self.is_some != 0 {
+            self.value.do_drop()?;
+            // so that the value is not released twice if the option is dropped again
+            self.is_some = 0;
+        }
+        Ok(())
+    }
+}
+
+/// A utility type to represent a `Result` : exactly one of the `ok` and `err` pointers is not
+/// `null`.
+/// Note that the parametrized types T and E should have `CReprOf`, `AsRust` and
+/// `RawPointerConverter` implemented.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{CReprOf, AsRust, CDrop, CResult, RawPointerConverter};
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// pub struct Pizza {
+///     pub weight: f32,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop, RawPointerConverter)]
+/// #[target_type(Pizza)]
+/// pub struct CPizza {
+///     pub weight: f32,
+/// }
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// pub struct Burnt {
+///     pub temperature: f32,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop, RawPointerConverter)]
+/// #[target_type(Burnt)]
+/// pub struct CBurnt {
+///     pub temperature: f32,
+/// }
+///
+/// let result: Result<Pizza, Burnt> = Err(Burnt { temperature: 451.0 });
+/// let c_result = CResult::<CPizza, CBurnt>::c_repr_of(result.clone()).unwrap();
+///
+/// assert!(c_result.ok.is_null());
+/// assert_eq!(c_result.as_rust().unwrap(), result);
+/// ```
+#[repr(C)]
+#[derive(Debug)]
+pub struct CResult<T: RawPointerConverter<T>, E: RawPointerConverter<E>> {
+    /// Pointer to the value in case of success, `null` otherwise
+    pub ok: *const T,
+    /// Pointer to the error in case of failure, `null` otherwise
+    pub err: *const E,
+}
+
+impl<T, U, E, V> AsRust<Result<U, V>> for CResult<T, E>
+where
+    T: AsRust<U> + RawPointerConverter<T>,
+    E: AsRust<V> + RawPointerConverter<E>,
+{
+    fn as_rust(&self) -> Result<Result<U, V>, AsRustError> {
+        if !self.ok.is_null() {
+            Ok(Ok(unsafe { T::raw_borrow(self.ok) }?.as_rust()?))
+        } else {
+            Ok(Err(unsafe { E::raw_borrow(self.err) }?.as_rust()?))
+        }
+    }
+}
+
+impl<T, E> CValidate for CResult<T, E>
+where
+    T: CValidate + RawPointerConverter<T>,
+    E: CValidate + RawPointerConverter<E>,
+{
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        if !self.ok.is_null() {
+            unsafe { T::raw_borrow(self.ok) }?.validate_c()
+        } else {
+            unsafe { E::raw_borrow(self.err) }?.validate_c()
+        }
+    }
+}
+
+impl<T, U, E, V> CReprOf<Result<U, V>> for CResult<T, E>
+where
+    T: CReprOf<U> + RawPointerConverter<T>,
+    E: CReprOf<V> + RawPointerConverter<E>,
+{
+    fn c_repr_of(input: Result<U, V>) -> Result<Self, CReprOfError> {
+        Ok(match input {
+            Ok(value) => Self {
+                ok: T::c_repr_of(value)?.into_raw_pointer(),
+                err: ptr::null(),
+            },
+            Err(error) => Self {
+                ok: ptr::null(),
+                err: E::c_repr_of(error)?.into_raw_pointer(),
+            },
+        })
+    }
+}
+
+impl<T: RawPointerConverter<T>, E: RawPointerConverter<E>> CDrop for CResult<T, E> {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        if !self.ok.is_null() {
+            unsafe { T::drop_raw_pointer(self.ok) }?;
+            self.ok = ptr::null();
+        }
+        if !self.err.is_null() {
+            unsafe { E::drop_raw_pointer(self.err) }?;
+            self.err = ptr::null();
+        }
+        Ok(())
+    }
+}
+
+impl<T: RawPointerConverter<T>, E: RawPointerConverter<E>> Drop for CResult<T, E> {
+    fn drop(&mut self) {
+        let _ = self.do_drop();
+    }
+}
+
+impl<T: RawPointerConverter<T>, E: RawPointerConverter<E>> RawPointerConverter<CResult<T, E>>
+    for CResult<T, E>
+{
+    fn into_raw_pointer(self) -> *const CResult<T, E> {
+        convert_into_raw_pointer(self)
+    }
+
+    fn into_raw_pointer_mut(self) -> *mut CResult<T, E> {
+        convert_into_raw_pointer_mut(self)
+    }
+
+    unsafe fn from_raw_pointer(
+        input: *const CResult<T, E>,
+    ) -> Result<Self, UnexpectedNullPointerError> {
+        take_back_from_raw_pointer(input)
+    }
+
+    unsafe fn from_raw_pointer_mut(
+        input: *mut CResult<T, E>,
+    ) -> Result<Self, UnexpectedNullPointerError> {
+        take_back_from_raw_pointer_mut(input)
+    }
+}
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/// A utility type to represent a `Duration`.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, CDuration, CReprOf};
+/// use std::time::Duration;
+///
+/// let c_duration = CDuration::c_repr_of(Duration::from_millis(1500)).unwrap();
+/// assert_eq!(c_duration, CDuration { secs: 1, nanos: 500_000_000 });
+///
+/// let duration: Duration = c_duration.as_rust().unwrap();
+/// assert_eq!(duration, Duration::from_millis(1500));
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, RawPointerConverter)]
+pub struct CDuration {
+    /// Number of whole seconds
+    pub secs: u64,
+    /// Fractional part of the duration in nanoseconds, must be lower than 1 000 000 000
+    pub nanos: u32,
+}
+
+impl CValidate for CDuration {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        if self.nanos >= NANOS_PER_SEC {
+            Err(AsRustError::Other(
+                format!("invalid duration: {} nanoseconds", self.nanos).into(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl AsRust<Duration> for CDuration {
+    fn as_rust(&self) -> Result<Duration, AsRustError> {
+        self.validate_c()?;
+        Ok(Duration::new(self.secs, self.nanos))
+    }
+}
+
+impl CReprOf<Duration> for CDuration {
+    fn c_repr_of(input: Duration) -> Result<Self, CReprOfError> {
+        Ok(Self {
+            secs: input.as_secs(),
+            nanos: input.subsec_nanos(),
+        })
+    }
+}
+
+impl CDrop for CDuration {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        Ok(())
+    }
+}
+
+/// A utility type to represent a `SystemTime`, as a signed offset to the UNIX epoch.
+///
+/// Dates before the epoch have a negative number of seconds, the nanoseconds are always counted
+/// forward (e.g. 1.5 seconds before the epoch is represented as `{ secs: -2, nanos: 500_000_000 }`).
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, CReprOf, CTimestamp};
+/// use std::time::{Duration, SystemTime, UNIX_EPOCH};
+///
+/// let time = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+///
+/// let c_timestamp = CTimestamp::c_repr_of(time).unwrap();
+/// assert_eq!(c_timestamp, CTimestamp { secs: 1_600_000_000, nanos: 0 });
+///
+/// let time_converted: SystemTime = c_timestamp.as_rust().unwrap();
+/// assert_eq!(time_converted, time);
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, RawPointerConverter)]
+pub struct CTimestamp {
+    /// Number of whole seconds since the UNIX epoch
+    pub secs: i64,
+    /// Number of nanoseconds to add to `secs`, must be lower than 1 000 000 000
+    pub nanos: u32,
+}
+
+impl CValidate for CTimestamp {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        if self.nanos >= NANOS_PER_SEC {
+            Err(AsRustError::Other(
+                format!("invalid timestamp: {} nanoseconds", self.nanos).into(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl AsRust<SystemTime> for CTimestamp {
+    fn as_rust(&self) -> Result<SystemTime, AsRustError> {
+        self.validate_c()?;
+
+        let seconds = if self.secs >= 0 {
+            UNIX_EPOCH.checked_add(Duration::from_secs(self.secs as u64))
+        } else {
+            UNIX_EPOCH.checked_sub(Duration::from_secs(self.secs.unsigned_abs()))
+        };
+
+        seconds
+            .and_then(|time| time.checked_add(Duration::from_nanos(self.nanos as u64)))
+            .ok_or_else(|| AsRustError::Other("timestamp out of range".into()))
+    }
+}
+
+impl CReprOf<SystemTime> for CTimestamp {
+    fn c_repr_of(input: SystemTime) -> Result<Self, CReprOfError> {
+        let out_of_range = || CReprOfError::Other("timestamp out of range".into());
+
+        match input.duration_since(UNIX_EPOCH) {
+            Ok(since_epoch) => Ok(Self {
+                secs: i64::try_from(since_epoch.as_secs()).map_err(|_| out_of_range())?,
+                nanos: since_epoch.subsec_nanos(),
+            }),
+            Err(error) => {
+                let before_epoch = error.duration();
+                let secs = i64::try_from(before_epoch.as_secs()).map_err(|_| out_of_range())?;
+
+                if before_epoch.subsec_nanos() == 0 {
+                    Ok(Self {
+                        secs: -secs,
+                        nanos: 0,
+                    })
+                } else {
+                    Ok(Self {
+                        secs: -secs - 1,
+                        nanos: NANOS_PER_SEC - before_epoch.subsec_nanos(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl CDrop for CTimestamp {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        Ok(())
+    }
+}
+
+/// A utility type to represent an `IpAddr`, as an address family and 16 bytes of address.
+///
+/// For IPv4 addresses, only the first 4 bytes of `addr` are used and the others are set to 0.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, CIpAddr, CReprOf};
+/// use std::net::{IpAddr, Ipv4Addr};
+///
+/// let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+///
+/// let c_ip = CIpAddr::c_repr_of(ip).unwrap();
+/// assert_eq!(c_ip.family, CIpAddr::FAMILY_V4);
+/// assert_eq!(c_ip.addr[..4], [192, 168, 0, 1]);
+///
+/// let ip_converted: IpAddr = c_ip.as_rust().unwrap();
+/// assert_eq!(ip_converted, ip);
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, RawPointerConverter)]
+pub struct CIpAddr {
+    /// Address family, either [`CIpAddr::FAMILY_V4`] or [`CIpAddr::FAMILY_V6`]
+    pub family: u8,
+    /// Bytes of the address in network order
+    pub addr: [u8; 16],
+}
+
+impl CIpAddr {
+    /// Value of `family` for an IPv4 address
+    pub const FAMILY_V4: u8 = 4;
+    /// Value of `family` for an IPv6 address
+    pub const FAMILY_V6: u8 = 6;
+}
+
+impl CValidate for CIpAddr {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        match self.family {
+            Self::FAMILY_V4 | Self::FAMILY_V6 => Ok(()),
+            family => Err(AsRustError::Other(
+                format!("invalid address family: {}", family).into(),
+            )),
+        }
+    }
+}
+
+impl AsRust<IpAddr> for CIpAddr {
+    fn as_rust(&self) -> Result<IpAddr, AsRustError> {
+        self.validate_c()?;
+
+        if self.family == Self::FAMILY_V4 {
+            let [a, b, c, d, ..] = self.addr;
+            Ok(IpAddr::V4(Ipv4Addr::new(a, b, c, d)))
+        } else {
+            Ok(IpAddr::V6(Ipv6Addr::from(self.addr)))
+        }
+    }
+}
+
+impl CReprOf<IpAddr> for CIpAddr {
+    fn c_repr_of(input: IpAddr) -> Result<Self, CReprOfError> {
+        match input {
+            IpAddr::V4(ip) => {
+                let mut addr = [0; 16];
+                addr[..4].copy_from_slice(&ip.octets());
+                Ok(Self {
+                    family: Self::FAMILY_V4,
+                    addr,
+                })
+            }
+            IpAddr::V6(ip) => Ok(Self {
+                family: Self::FAMILY_V6,
+                addr: ip.octets(),
+            }),
+        }
+    }
+}
+
+impl CDrop for CIpAddr {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        Ok(())
+    }
+}
+
+/// A utility type to represent a `SocketAddr`.
+///
+/// `flowinfo` and `scope_id` are only meaningful for IPv6 addresses and are set to 0 for IPv4
+/// addresses.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, CIpAddr, CReprOf, CSocketAddr};
+/// use std::net::SocketAddr;
+///
+/// let socket_addr: SocketAddr = "[::1]:8080".parse().unwrap();
+///
+/// let c_socket_addr = CSocketAddr::c_repr_of(socket_addr).unwrap();
+/// assert_eq!(c_socket_addr.ip.family, CIpAddr::FAMILY_V6);
+/// assert_eq!(c_socket_addr.port, 8080);
+///
+/// let socket_addr_converted: SocketAddr = c_socket_addr.as_rust().unwrap();
+/// assert_eq!(socket_addr_converted, socket_addr);
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, RawPointerConverter)]
+pub struct CSocketAddr {
+    /// IP address
+    pub ip: CIpAddr,
+    /// Port number, in native byte order
+    pub port: u16,
+    /// IPv6 flow information
+    pub flowinfo: u32,
+    /// IPv6 scope identifier
+    pub scope_id: u32,
+}
+
+impl CValidate for CSocketAddr {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        self.ip.validate_c()
+    }
+}
+
+impl AsRust<SocketAddr> for CSocketAddr {
+    fn as_rust(&self) -> Result<SocketAddr, AsRustError> {
+        match self.ip.as_rust()? {
+            IpAddr::V4(ip) => Ok(SocketAddr::V4(SocketAddrV4::new(ip, self.port))),
+            IpAddr::V6(ip) => Ok(SocketAddr::V6(SocketAddrV6::new(
+                ip,
+                self.port,
+                self.flowinfo,
+                self.scope_id,
+            ))),
+        }
+    }
+}
+
+impl CReprOf<SocketAddr> for CSocketAddr {
+    fn c_repr_of(input: SocketAddr) -> Result<Self, CReprOfError> {
+        let (flowinfo, scope_id) = match input {
+            SocketAddr::V4(_) => (0, 0),
+            SocketAddr::V6(socket_addr) => (socket_addr.flowinfo(), socket_addr.scope_id()),
+        };
+        Ok(Self {
+            ip: CIpAddr::c_repr_of(input.ip())?,
+            port: input.port(),
+            flowinfo,
+            scope_id,
+        })
+    }
+}
+
+impl CDrop for CSocketAddr {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        Ok(())
+    }
+}
+
+/// A utility type to represent a `Uuid` by its 16 bytes (in big-endian order, as returned by
+/// `Uuid::as_bytes`). Only available with the `uuid` feature.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{AsRust, CReprOf, CUuid};
+/// use uuid::Uuid;
+///
+/// let uuid = Uuid::from_u128(0x936da01f9abd4d9d80c702af85c822a8);
+///
+/// let c_uuid = CUuid::c_repr_of(uuid).unwrap();
+/// assert_eq!(c_uuid.0[0], 0x93);
+///
+/// let uuid_converted: Uuid = c_uuid.as_rust().unwrap();
+/// assert_eq!(uuid_converted, uuid);
+/// ```
+#[cfg(feature = "uuid")]
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, RawPointerConverter)]
+pub struct CUuid(pub [u8; 16]);
+
+#[cfg(feature = "uuid")]
+impl AsRust<uuid::Uuid> for CUuid {
+    fn as_rust(&self) -> Result<uuid::Uuid, AsRustError> {
+        Ok(uuid::Uuid::from_bytes(self.0))
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl CReprOf<uuid::Uuid> for CUuid {
+    fn c_repr_of(input: uuid::Uuid) -> Result<Self, CReprOfError> {
+        Ok(Self(input.into_bytes()))
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl CDrop for CUuid {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl CValidate for CUuid {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+//! This module contains the [`CVersioned`] trait, implemented by the C structs starting with a
+//! `version` field, and the [`check_version`] function, detecting the structs whose layout doesn't
+//! match the one of the Rust library.
+
+use crate::conversions::AsRustError;
+
+/// A C struct whose layout is versioned : it starts with a `version: u32` field, set by its creator
+/// to the version of the layout it was compiled with.
+///
+/// This trait is implemented by the `CDrop` derive for the structs with the
+/// `#[abi_version(...)]` attribute. The `CReprOf` derive then writes [`Self::ABI_VERSION`] in the
+/// `version` field, and the `AsRust` derive fails with [`AsRustError::VersionMismatch`] for a
+/// struct of another version, before reading any other field.
+///
+/// # Example
+///
+/// ```
+/// use ffi_convert::{check_version, AsRust, AsRustError, CDrop, CReprOf, CVersioned};
+///
+/// pub struct Sauce {
+///     pub volume: f32,
+/// }
+///
+/// #[repr(C)]
+/// #[derive(CReprOf, AsRust, CDrop)]
+/// #[target_type(Sauce)]
+/// #[abi_version(2)]
+/// pub struct CSauce {
+///     pub version: u32,
+///     pub volume: f32,
+/// }
+///
+/// let mut sauce = CSauce::c_repr_of(Sauce { volume: 4.2 }).unwrap();
+/// assert_eq!(sauce.version, CSauce::ABI_VERSION);
+/// assert!(check_version(&sauce).is_ok());
+///
+/// // written by a C caller built with the headers of the first version
+/// sauce.version = 1;
+/// assert!(matches!(
+///     AsRust::<Sauce>::as_rust(&sauce),
+///     Err(AsRustError::VersionMismatch { expected: 2, found: 1 })
+/// ));
+/// ```
+pub trait CVersioned {
+    /// The version of the layout of the struct, given by the `#[abi_version(...)]` attribute
+    const ABI_VERSION: u32;
+
+    /// The version written in the `version` field of this struct
+    fn version(&self) -> u32;
+}
+
+/// Checks that the given struct has the layout of the Rust library, failing with
+/// [`AsRustError::VersionMismatch`] otherwise.
+///
+/// Only the `version` field is read, so that a struct of another version is rejected without
+/// reading fields that may not exist (or not be at the same place) in its layout.
+pub fn check_version<T: CVersioned>(value: &T) -> Result<(), AsRustError> {
+    let found = value.version();
+    if found == T::ABI_VERSION {
+        Ok(())
+    } else {
+        Err(AsRustError::VersionMismatch {
+            expected: T::ABI_VERSION,
+            found,
+        })
+    }
+}
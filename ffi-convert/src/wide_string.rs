@@ -0,0 +1,105 @@
+//! This module contains the implementations of the conversion traits for the UTF-16 strings of the
+//! `widestring` crate, used to represent `String`s as `*const u16` (such as a `wchar_t*` on
+//! Windows). Only available with the `widestring` feature.
+//!
+//! In derived structs, a `*const u16` field is converted as a wide string when annotated with the
+//! `#[wide_string]` attribute.
+//!
+//! # Example
+//!
+//! ```
+//! use ffi_convert::{AsRust, CDrop, CReprOf};
+//!
+//! #[derive(Clone, Debug, PartialEq)]
+//! pub struct Sauce {
+//!     pub name: String,
+//! }
+//!
+//! #[repr(C)]
+//! #[derive(CReprOf, AsRust, CDrop)]
+//! #[target_type(Sauce)]
+//! pub struct CSauce {
+//!     #[wide_string]
+//!     pub name: *const u16,
+//! }
+//!
+//! let sauce = Sauce { name: "Béchamel".to_string() };
+//! let c_sauce = CSauce::c_repr_of(sauce.clone()).unwrap();
+//! assert_eq!(unsafe { *c_sauce.name.add(1) }, 'é' as u16);
+//!
+//! let sauce_converted: Sauce = c_sauce.as_rust().unwrap();
+//! assert_eq!(sauce_converted, sauce);
+//! ```
+
+use widestring::{U16CStr, U16CString};
+
+use crate::budget::reserve_memory;
+use crate::context::{current_context, Utf8Policy};
+use crate::conversions::*;
+
+impl RawPointerConverter<u16> for U16CString {
+    fn into_raw_pointer(self) -> *const u16 {
+        self.into_raw() as _
+    }
+
+    fn into_raw_pointer_mut(self) -> *mut u16 {
+        self.into_raw()
+    }
+
+    unsafe fn from_raw_pointer(input: *const u16) -> Result<Self, UnexpectedNullPointerError> {
+        Self::from_raw_pointer_mut(input as *mut u16)
+    }
+
+    unsafe fn from_raw_pointer_mut(input: *mut u16) -> Result<Self, UnexpectedNullPointerError> {
+        if input.is_null() {
+            Err(UnexpectedNullPointerError)
+        } else {
+            Ok(U16CString::from_raw(input))
+        }
+    }
+}
+
+impl RawBorrow<u16> for U16CStr {
+    unsafe fn raw_borrow<'a>(input: *const u16) -> Result<&'a Self, UnexpectedNullPointerError> {
+        if input.is_null() {
+            Err(UnexpectedNullPointerError)
+        } else {
+            Ok(Self::from_ptr_str(input))
+        }
+    }
+}
+
+impl CDrop for U16CString {
+    fn do_drop(&mut self) -> Result<(), CDropError> {
+        Ok(())
+    }
+}
+
+impl CReprOf<String> for U16CString {
+    fn c_repr_of(input: String) -> Result<Self, CReprOfError> {
+        let input = if current_context().replace_nul_characters && input.contains('\0') {
+            input.replace('\0', "\u{FFFD}")
+        } else {
+            input
+        };
+        reserve_memory((input.encode_utf16().count() + 1) * std::mem::size_of::<u16>())?;
+        U16CString::from_str(input).map_err(|error| CReprOfError::Other(error.into()))
+    }
+}
+
+impl AsRust<String> for U16CStr {
+    fn as_rust(&self) -> Result<String, AsRustError> {
+        match current_context().utf8_policy {
+            Utf8Policy::Strict => self
+                .to_string()
+                .map_err(|error| AsRustError::Other(error.into())),
+            Utf8Policy::Lossy => Ok(self.to_string_lossy()),
+        }
+    }
+}
+
+impl CValidate for U16CStr {
+    fn validate_c(&self) -> Result<(), AsRustError> {
+        self.as_rust().map(|_: String| ())
+    }
+}